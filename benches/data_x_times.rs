@@ -0,0 +1,80 @@
+//! Compares filling the RAM of a panel with `clear_frame`, which sends the fill in chunks,
+//! to sending the same bytes one transfer per byte
+//!
+//! The mock SPI spends a fixed amount of work per transfer, like the HALs which set up a DMA
+//! transfer or wait for the peripheral on every `write`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use embedded_hal::{
+    blocking::{delay::DelayMs, spi::Write},
+    digital::v2::{InputPin, OutputPin},
+};
+use epd_waveshare::{epd2in9_v2, epd2in9_v2::Epd2in9, prelude::*};
+use std::convert::Infallible;
+
+/// Counts the bytes and spends some work on every transfer
+#[derive(Default)]
+struct MockSpi {
+    bytes: usize,
+}
+
+impl Write<u8> for MockSpi {
+    type Error = Infallible;
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        for i in 0..64u32 {
+            black_box(i);
+        }
+        self.bytes += black_box(words).len();
+        Ok(())
+    }
+}
+
+struct Pin;
+impl OutputPin for Pin {
+    type Error = Infallible;
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+impl InputPin for Pin {
+    type Error = Infallible;
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+}
+
+struct NoDelay;
+impl DelayMs<u8> for NoDelay {
+    fn delay_ms(&mut self, _ms: u8) {}
+}
+
+fn fill(c: &mut Criterion) {
+    let mut spi = MockSpi::default();
+    let mut epd = Epd2in9::new(&mut spi, Pin, Pin, Pin, Pin, &mut NoDelay).unwrap();
+    let frame_bytes = epd2in9_v2::WIDTH / 8 * epd2in9_v2::HEIGHT;
+
+    let mut group = c.benchmark_group("fill both RAM banks");
+    group.bench_function("chunked (clear_frame)", |b| {
+        b.iter(|| epd.clear_frame(&mut spi, &mut NoDelay).unwrap())
+    });
+    group.bench_function("one transfer per byte", |b| {
+        b.iter(|| {
+            for command in [0x24, 0x26] {
+                epd.send_command_raw(&mut spi, command).unwrap();
+                for _ in 0..frame_bytes {
+                    epd.send_data_raw(&mut spi, &[0xFF]).unwrap();
+                }
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, fill);
+criterion_main!(benches);
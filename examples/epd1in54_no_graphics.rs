@@ -12,7 +12,7 @@ use linux_embedded_hal::{
 // needs to be run with sudo because of some sysfs_gpio permission problems and follow-up timing problems
 // see https://github.com/rust-embedded/rust-sysfs-gpio/issues/5 and follow-up issues
 
-fn main() -> Result<(), std::io::Error> {
+fn main() -> Result<(), epd_waveshare::error::Error<std::io::Error>> {
     // Configure SPI
     // SPI settings are from eink-waveshare-rs documenation
     let mut spi = Spidev::open("/dev/spidev0.0")?;
@@ -70,7 +70,15 @@ fn main() -> Result<(), std::io::Error> {
     let number_of_runs = 1;
     for i in 0..number_of_runs {
         let offset = i * 8 % 150;
-        epd.update_partial_frame(&mut spi, &small_buffer, 25 + offset, 25 + offset, 16, 16)?;
+        epd.update_partial_frame(
+            &mut spi,
+            &small_buffer,
+            24 + offset,
+            24 + offset,
+            16,
+            16,
+            &mut delay,
+        )?;
         epd.display_frame(&mut spi, &mut delay)?;
     }
 
@@ -78,15 +86,15 @@ fn main() -> Result<(), std::io::Error> {
     epd.clear_frame(&mut spi, &mut delay)?;
     epd.display_frame(&mut spi, &mut delay)?;
 
-    // Draw some squares
+    // Draw some squares, x and the widths are multiples of 8 to cover whole bytes
     let small_buffer = [Color::Black.get_byte_value(); 3200]; //160x160
-    epd.update_partial_frame(&mut spi, &small_buffer, 20, 20, 160, 160)?;
+    epd.update_partial_frame(&mut spi, &small_buffer, 24, 24, 160, 160, &mut delay)?;
 
     let small_buffer = [Color::White.get_byte_value(); 800]; //80x80
-    epd.update_partial_frame(&mut spi, &small_buffer, 60, 60, 80, 80)?;
+    epd.update_partial_frame(&mut spi, &small_buffer, 64, 64, 80, 80, &mut delay)?;
 
     let small_buffer = [Color::Black.get_byte_value(); 8]; //8x8
-    epd.update_partial_frame(&mut spi, &small_buffer, 96, 96, 8, 8)?;
+    epd.update_partial_frame(&mut spi, &small_buffer, 96, 100, 8, 8, &mut delay)?;
 
     // Display updated frame
     epd.display_frame(&mut spi, &mut delay)?;
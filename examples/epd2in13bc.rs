@@ -34,7 +34,7 @@ use linux_embedded_hal::{
 //
 // after finishing, put the display to sleep
 
-fn main() -> Result<(), std::io::Error> {
+fn main() -> Result<(), epd_waveshare::error::Error<std::io::Error>> {
     let busy = Pin::new(24); // GPIO 24, board J-18
     busy.export().expect("busy export");
     while !busy.is_exported() {}
@@ -1,5 +1,4 @@
 #![deny(warnings)]
-#![deny(warnings)]
 
 use embedded_graphics::{
     mono_font::MonoTextStyleBuilder,
@@ -24,7 +23,7 @@ use linux_embedded_hal::{
 // needs to be run with sudo because of some sysfs_gpio permission problems and follow-up timing problems
 // see https://github.com/rust-embedded/rust-sysfs-gpio/issues/5 and follow-up issues
 
-fn main() -> Result<(), std::io::Error> {
+fn main() -> Result<(), epd_waveshare::error::Error<std::io::Error>> {
     // Configure SPI
     // Settings are taken from
     let mut spi = Spidev::open("/dev/spidev0.0").expect("spidev directory");
@@ -67,7 +66,8 @@ fn main() -> Result<(), std::io::Error> {
 
     println!("Test all the rotations");
 
-    let (x, y, width, height) = (50, 50, 250, 250);
+    // x and width of partial windows are multiples of 8
+    let (x, y, width, height) = (48, 50, 248, 250);
 
     let mut buffer = [epd4in2::DEFAULT_BACKGROUND_COLOR.get_byte_value(); 62500]; //250*250
     let mut display = VarDisplay::new(width, height, &mut buffer);
@@ -84,7 +84,7 @@ fn main() -> Result<(), std::io::Error> {
     draw_text(&mut display, "Rotate 270!", 5, 50);
 
     epd4in2
-        .update_partial_frame(&mut spi, display.buffer(), x, y, width, height)
+        .update_partial_frame(&mut spi, display.buffer(), x, y, width, height, &mut delay)
         .unwrap();
     epd4in2
         .display_frame(&mut spi, &mut delay)
@@ -140,7 +140,7 @@ fn main() -> Result<(), std::io::Error> {
         draw_text(&mut display, "  Hello World! ", 5 + i * 12, 50);
 
         epd4in2
-            .update_partial_frame(&mut spi, display.buffer(), x, y, width, height)
+            .update_partial_frame(&mut spi, display.buffer(), x, y, width, height, &mut delay)
             .unwrap();
         epd4in2
             .display_frame(&mut spi, &mut delay)
@@ -0,0 +1,75 @@
+#![deny(warnings)]
+
+use epd_waveshare::{epd7in5_v3::Epd7in5, prelude::*};
+use linux_embedded_hal::{
+    spidev::{self, SpidevOptions},
+    sysfs_gpio::Direction,
+    Delay, Pin, Spidev,
+};
+
+// Reads the LM75 compatible temperature sensor which is connected to the I2C master of the
+// controller. Only works on boards which connect the data line of the controller to MISO.
+//
+// activate spi, gpio in raspi-config
+// needs to be run with sudo because of some sysfs_gpio permission problems and follow-up timing problems
+// see https://github.com/rust-embedded/rust-sysfs-gpio/issues/5 and follow-up issues
+
+/// Temperature register of the LM75
+const LM75_TEMPERATURE: u8 = 0x00;
+/// Configuration register of the LM75
+const LM75_CONFIGURATION: u8 = 0x01;
+
+fn main() -> Result<(), epd_waveshare::error::Error<std::io::Error>> {
+    // Configure SPI
+    let mut spi = Spidev::open("/dev/spidev0.0")?;
+    let options = SpidevOptions::new()
+        .bits_per_word(8)
+        .max_speed_hz(4_000_000)
+        .mode(spidev::SpiModeFlags::SPI_MODE_0)
+        .build();
+    spi.configure(&options).expect("spi configuration");
+
+    // Configure Digital I/O Pin to be used as Chip Select for SPI
+    let cs_pin = Pin::new(26); //BCM7 CE0
+    cs_pin.export().expect("cs_pin export");
+    while !cs_pin.is_exported() {}
+    cs_pin
+        .set_direction(Direction::Out)
+        .expect("cs_pin Direction");
+    cs_pin.set_value(1).expect("cs_pin Value set to 1");
+
+    // Configure Busy Input Pin
+    let busy = Pin::new(5); //pin 29
+    busy.export().expect("busy export");
+    while !busy.is_exported() {}
+    busy.set_direction(Direction::In).expect("busy Direction");
+
+    // Configure Data/Command OutputPin
+    let dc = Pin::new(6); //pin 31 //bcm6
+    dc.export().expect("dc export");
+    while !dc.is_exported() {}
+    dc.set_direction(Direction::Out).expect("dc Direction");
+    dc.set_value(1).expect("dc Value set to 1");
+
+    // Configure Reset OutputPin
+    let rst = Pin::new(16); //pin 36 //bcm16
+    rst.export().expect("rst export");
+    while !rst.is_exported() {}
+    rst.set_direction(Direction::Out).expect("rst Direction");
+    rst.set_value(1).expect("rst Value set to 1");
+
+    let mut delay = Delay {};
+
+    let mut epd = Epd7in5::new(&mut spi, cs_pin, busy, dc, rst, &mut delay)?;
+
+    // Wake the sensor up from its shutdown mode
+    epd.external_sensor_write(&mut spi, LM75_CONFIGURATION, 0x00)?;
+
+    // The first byte of the temperature register holds the whole degrees
+    let temperature = epd.external_sensor_read(&mut spi, LM75_TEMPERATURE)? as i8;
+    println!("Temperature: {} °C", temperature);
+
+    epd.sleep(&mut spi, &mut delay)?;
+
+    Ok(())
+}
@@ -0,0 +1,92 @@
+#![deny(warnings)]
+
+use embedded_hal::prelude::*;
+use epd_waveshare::{
+    epd1in54::{self, Epd1in54},
+    epd2in9_v2::{self, Epd2in9},
+    multi::{DisplayIndex, MultiDisplay},
+    prelude::*,
+};
+use linux_embedded_hal::{
+    spidev::{self, SpidevOptions},
+    sysfs_gpio::Direction,
+    Delay, Pin, Spidev,
+};
+
+// activate spi, gpio in raspi-config
+// needs to be run with sudo because of some sysfs_gpio permission problems and follow-up timing problems
+// see https://github.com/rust-embedded/rust-sysfs-gpio/issues/5 and follow-up issues
+//
+// Both displays share MOSI and CLK, every other line has its own pin.
+
+fn pin(number: u64, direction: Direction) -> Pin {
+    let pin = Pin::new(number);
+    pin.export().expect("pin export");
+    while !pin.is_exported() {}
+    pin.set_direction(direction).expect("pin Direction");
+    if direction == Direction::Out {
+        pin.set_value(1).expect("pin Value set to 1");
+    }
+    pin
+}
+
+fn main() -> Result<(), epd_waveshare::error::Error<std::io::Error>> {
+    // Configure SPI, the chip selects are driven as gpios
+    let mut spi = Spidev::open("/dev/spidev0.0")?;
+    let options = SpidevOptions::new()
+        .bits_per_word(8)
+        .max_speed_hz(4_000_000)
+        .mode(spidev::SpiModeFlags::SPI_MODE_0)
+        .build();
+    spi.configure(&options).expect("spi configuration");
+
+    let mut delay = Delay {};
+
+    // cs, busy, dc and rst of the 2.9" display
+    let epd2in9 = Epd2in9::new(
+        &mut spi,
+        pin(26, Direction::Out),
+        pin(5, Direction::In),
+        pin(6, Direction::Out),
+        pin(16, Direction::Out),
+        &mut delay,
+    )?;
+    // cs, busy, dc and rst of the 1.54" display
+    let epd1in54 = Epd1in54::new(
+        &mut spi,
+        pin(22, Direction::Out),
+        pin(23, Direction::In),
+        pin(24, Direction::Out),
+        pin(25, Direction::Out),
+        &mut delay,
+    )?;
+    let mut displays = MultiDisplay::new(epd2in9, epd1in54);
+
+    // Clear both screens
+    displays.clear_frame(&mut spi, &mut delay)?;
+    displays.display_frame(DisplayIndex::First, &mut spi, &mut delay)?;
+    displays.display_frame(DisplayIndex::Second, &mut spi, &mut delay)?;
+
+    // Fill the 1.54" display only, the 2.9" display keeps its white frame
+    let buffer =
+        [Color::Black.get_byte_value(); epd1in54::WIDTH as usize / 8 * epd1in54::HEIGHT as usize];
+    displays.update_and_display_frame(DisplayIndex::Second, &mut spi, &buffer, &mut delay)?;
+    delay.delay_ms(5000u16);
+
+    // Stripes on the 2.9" display
+    let mut buffer = [Color::White.get_byte_value();
+        epd2in9_v2::WIDTH as usize / 8 * epd2in9_v2::HEIGHT as usize];
+    for row in buffer
+        .chunks_mut(epd2in9_v2::WIDTH as usize / 8)
+        .step_by(16)
+    {
+        row.fill(Color::Black.get_byte_value());
+    }
+    displays.update_and_display_frame(DisplayIndex::First, &mut spi, &buffer, &mut delay)?;
+    delay.delay_ms(5000u16);
+
+    // Set both displays to sleep
+    displays.sleep(&mut spi, &mut delay)?;
+
+    Ok(())
+}
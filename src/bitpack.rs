@@ -0,0 +1,128 @@
+//! Packing of single pixels into the frame layout of the displays
+//!
+//! The drivers take frames with one bit per pixel, 8 pixels per byte with the leftmost pixel
+//! in the most significant bit. Every row starts with a new byte, the unused low bits of the
+//! last byte of a row are padding. [pack_row()] and [pack_frame()] build these bytes from one
+//! `bool` per pixel, `true` sets the bit. On most panels a set bit is white, see
+//! [Color::get_bit_value()](crate::color::Color::get_bit_value()).
+//!
+//! ```rust
+//! use epd_waveshare::bitpack::pack_frame;
+//! use epd_waveshare::buffer_len;
+//!
+//! // a 10 x 2 checkerboard
+//! const WIDTH: usize = 10;
+//! let pixels = (0..WIDTH * 2).map(|i| (i % WIDTH + i / WIDTH) % 2 == 0);
+//! let mut frame = [0u8; buffer_len(WIDTH, 2)];
+//! for (byte, packed) in frame.iter_mut().zip(pack_frame(pixels, WIDTH)) {
+//!     *byte = packed;
+//! }
+//! assert_eq!(frame, [0xAA, 0x80, 0x55, 0x40]);
+//! ```
+
+/// Iterator over the packed bytes of [pack_row()] and [pack_frame()]
+#[derive(Debug, Clone)]
+pub struct Packed<I> {
+    pixels: I,
+    width: usize,
+    x: usize,
+}
+
+impl<I: Iterator<Item = bool>> Iterator for Packed<I> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        if self.x == self.width {
+            self.x = 0;
+        }
+        let mut byte = 0;
+        let mut bits = 0;
+        while bits < 8 && self.x < self.width {
+            match self.pixels.next() {
+                Some(pixel) => {
+                    if pixel {
+                        byte |= 0x80 >> bits;
+                    }
+                    bits += 1;
+                    self.x += 1;
+                }
+                None => break,
+            }
+        }
+        if bits == 0 {
+            return None;
+        }
+        Some(byte)
+    }
+}
+
+/// Packs the pixels of one row, 8 pixels per byte with the first one in the highest bit
+///
+/// The unused bits of the last byte are cleared.
+pub fn pack_row<I>(pixels: I) -> Packed<I::IntoIter>
+where
+    I: IntoIterator<Item = bool>,
+{
+    pack_frame(pixels, usize::MAX)
+}
+
+/// Packs the row-major pixels of a frame `width` pixels wide
+///
+/// Every row starts with a new byte like in the buffers of the drivers, a full frame gives
+/// [buffer_len()](crate::buffer_len()) bytes. The unused bits at the end of the rows are
+/// cleared, an incomplete last row ends with the last pixel.
+pub fn pack_frame<I>(pixels: I, width: usize) -> Packed<I::IntoIter>
+where
+    I: IntoIterator<Item = bool>,
+{
+    Packed {
+        pixels: pixels.into_iter(),
+        width,
+        x: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer_len;
+    use crate::color::Color;
+    extern crate std;
+    use std::vec;
+    use std::vec::Vec;
+
+    #[test]
+    fn first_pixel_is_the_highest_bit() {
+        let mut first = [false; 8];
+        first[0] = true;
+        assert_eq!(pack_row(first.iter().copied()).collect::<Vec<_>>(), [0x80]);
+        let mut last = [false; 8];
+        last[7] = true;
+        assert_eq!(pack_row(last.iter().copied()).collect::<Vec<_>>(), [0x01]);
+
+        // the bit of a white pixel is set, like in the buffers of the drivers
+        let white = Color::White.get_bit_value() == 1;
+        assert_eq!(pack_row(vec![white; 8]).collect::<Vec<_>>(), [0xFF]);
+        assert_eq!(
+            pack_row(vec![white; 8]).next(),
+            Some(Color::White.get_byte_value())
+        );
+    }
+
+    #[test]
+    fn rows_are_padded_to_whole_bytes() {
+        assert_eq!(pack_row(vec![true; 11]).collect::<Vec<_>>(), [0xFF, 0xE0]);
+        assert_eq!(pack_row(Vec::new()).count(), 0);
+
+        let width = 11;
+        let frame: Vec<u8> = pack_frame(vec![true; width * 3], width).collect();
+        assert_eq!(frame.len(), buffer_len(width, 3));
+        assert_eq!(frame, [0xFF, 0xE0, 0xFF, 0xE0, 0xFF, 0xE0]);
+
+        // an incomplete last row isn't padded to the full width
+        let frame: Vec<u8> = pack_frame(vec![true; width + 2], width).collect();
+        assert_eq!(frame, [0xFF, 0xE0, 0xC0]);
+
+        assert_eq!(pack_frame(vec![true; 8], 0).count(), 0);
+    }
+}
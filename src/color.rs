@@ -26,7 +26,10 @@ impl OutOfColorRangeParseError {
 }
 
 /// Only for the Black/White-Displays
-#[derive(Clone, Copy, PartialEq, Debug)]
+///
+/// Colors are ordered like the variants, black before white.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Color {
     /// Black color
     Black,
@@ -35,7 +38,10 @@ pub enum Color {
 }
 
 /// Only for the Black/White/Color-Displays
-#[derive(Clone, Copy, PartialEq, Debug)]
+///
+/// Colors are ordered like the variants: black, white, chromatic, yellow.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum TriColor {
     /// Black color
     Black,
@@ -43,6 +49,10 @@ pub enum TriColor {
     White,
     /// Chromatic color
     Chromatic,
+    /// Yellow, the chromatic color of the yellow panels like the 1.54" (C)
+    ///
+    /// Sent like [TriColor::Chromatic], pixels read back from a buffer are `Chromatic`.
+    Yellow,
 }
 
 /// For the 5in65 7 Color Display
@@ -182,9 +192,165 @@ impl OctColor {
         }
     }
 }
+/// For the 4 color (G) Displays with 2 bits per pixel
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum QuadColor {
+    /// Black Color
+    Black = 0x00,
+    /// White Color
+    White = 0x01,
+    /// Yellow Color
+    Yellow = 0x02,
+    /// Red Color
+    Red = 0x03,
+}
+
+#[cfg(feature = "graphics")]
+impl From<BinaryColor> for QuadColor {
+    fn from(b: BinaryColor) -> QuadColor {
+        match b {
+            BinaryColor::On => QuadColor::Black,
+            BinaryColor::Off => QuadColor::White,
+        }
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl From<QuadColor> for embedded_graphics_core::pixelcolor::Rgb888 {
+    fn from(b: QuadColor) -> Self {
+        let (r, g, b) = b.rgb();
+        Self::new(r, g, b)
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl From<embedded_graphics_core::pixelcolor::Rgb888> for QuadColor {
+    fn from(p: embedded_graphics_core::pixelcolor::Rgb888) -> QuadColor {
+        use embedded_graphics_core::prelude::RgbColor;
+        // pick the nearest color
+        *QuadColor::ALL
+            .iter()
+            .min_by_key(|c| {
+                let (r, g, b) = c.rgb();
+                (i32::from(r) - i32::from(p.r())).pow(2)
+                    + (i32::from(g) - i32::from(p.g())).pow(2)
+                    + (i32::from(b) - i32::from(p.b())).pow(2)
+            })
+            .unwrap_or(&QuadColor::White)
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl From<embedded_graphics_core::pixelcolor::raw::RawU2> for QuadColor {
+    fn from(b: embedded_graphics_core::pixelcolor::raw::RawU2) -> Self {
+        use embedded_graphics_core::prelude::RawData;
+        QuadColor::from_bits(b.into_inner())
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl PixelColor for QuadColor {
+    type Raw = embedded_graphics_core::pixelcolor::raw::RawU2;
+}
+
+impl QuadColor {
+    /// All colors, in the order of their bits
+    pub const ALL: [QuadColor; 4] = [
+        QuadColor::Black,
+        QuadColor::White,
+        QuadColor::Yellow,
+        QuadColor::Red,
+    ];
+
+    /// Gets the 2 bits of the Color as needed by the display
+    pub fn get_bits(self) -> u8 {
+        self as u8
+    }
+
+    /// Gets a full byte of 4 pixels in this color
+    pub fn get_byte_value(self) -> u8 {
+        self.get_bits() * 0x55
+    }
+
+    /// Converts four colors into a single byte for the Display, the first one in the upper bits
+    pub fn colors_byte(colors: [QuadColor; 4]) -> u8 {
+        colors
+            .iter()
+            .fold(0, |byte, color| byte << 2 | color.get_bits())
+    }
+
+    /// Takes the lower 2 bits and converts them to a QuadColor
+    pub fn from_bits(bits: u8) -> QuadColor {
+        QuadColor::ALL[usize::from(bits & 0x03)]
+    }
+
+    /// Splits a byte into the colors of its 4 pixels, the first one from the upper bits
+    pub fn split_byte(byte: u8) -> [QuadColor; 4] {
+        [
+            QuadColor::from_bits(byte >> 6),
+            QuadColor::from_bits(byte >> 4),
+            QuadColor::from_bits(byte >> 2),
+            QuadColor::from_bits(byte),
+        ]
+    }
+
+    /// Converts to limited range of RGB values.
+    pub fn rgb(self) -> (u8, u8, u8) {
+        match self {
+            QuadColor::White => (0xff, 0xff, 0xff),
+            QuadColor::Black => (0x00, 0x00, 0x00),
+            QuadColor::Yellow => (0xff, 0xff, 0x00),
+            QuadColor::Red => (0xff, 0x00, 0x00),
+        }
+    }
+}
+
+/// One of the two RAM planes of the controllers with 4 gray levels
+///
+/// Buffers with 2 bits per pixel, like the ones of the `GrayDisplay` graphics buffers, hold
+/// the level of a pixel as the `Gray2` color of embedded-graphics: `0b00` is black and `0b11`
+/// white.
+/// The controllers take the high and the low bit of the levels in two separate planes with
+/// 1 bit per pixel.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GrayPlane {
+    /// The high bits of the levels
+    High,
+    /// The low bits of the levels
+    Low,
+}
+
+impl GrayPlane {
+    /// Gets the bit of this plane from a 2 bit gray level
+    pub fn bit(self, level: u8) -> u8 {
+        match self {
+            GrayPlane::High => (level >> 1) & 1,
+            GrayPlane::Low => level & 1,
+        }
+    }
+
+    /// Converts two bytes with the levels of 8 pixels into one byte of this plane
+    ///
+    /// Missing pixels of a shorter slice are 0.
+    pub fn byte(self, pixels: &[u8]) -> u8 {
+        let mut byte = 0;
+        for (i, levels) in pixels.iter().take(2).enumerate() {
+            for pixel in 0..4 {
+                byte |= self.bit(levels >> (6 - 2 * pixel)) << (7 - 4 * i - pixel);
+            }
+        }
+        byte
+    }
+}
+
 //TODO: Rename get_bit_value to bit() and get_byte_value to byte() ?
 
 impl Color {
+    /// All colors, in their order
+    pub fn all() -> &'static [Color] {
+        &[Color::Black, Color::White]
+    }
+
     /// Get the color encoding of the color for one bit
     pub fn get_bit_value(self) -> u8 {
         match self {
@@ -222,6 +388,21 @@ impl Color {
             Color::Black => Color::White,
         }
     }
+
+    /// Thresholds a brightness, `value`s of at least `threshold` are White
+    ///
+    /// ```rust
+    /// # use epd_waveshare::color::Color;
+    /// assert_eq!(Color::from_luma(127, 128), Color::Black);
+    /// assert_eq!(Color::from_luma(128, 128), Color::White);
+    /// ```
+    pub fn from_luma(value: u8, threshold: u8) -> Color {
+        if value >= threshold {
+            Color::White
+        } else {
+            Color::Black
+        }
+    }
 }
 
 impl From<u8> for Color {
@@ -256,11 +437,24 @@ impl From<embedded_graphics_core::pixelcolor::Rgb888> for Color {
 }
 
 impl TriColor {
+    /// All colors, in their order
+    ///
+    /// [TriColor::Yellow] is included, skip it for a palette of the red panels, where it would
+    /// show up as a second chromatic color.
+    pub fn all() -> &'static [TriColor] {
+        &[
+            TriColor::Black,
+            TriColor::White,
+            TriColor::Chromatic,
+            TriColor::Yellow,
+        ]
+    }
+
     /// Get the color encoding of the color for one bit
     pub fn get_bit_value(self) -> u8 {
         match self {
             TriColor::White => 1u8,
-            TriColor::Black | TriColor::Chromatic => 0u8,
+            TriColor::Black | TriColor::Chromatic | TriColor::Yellow => 0u8,
         }
     }
 
@@ -268,11 +462,38 @@ impl TriColor {
     pub fn get_byte_value(self) -> u8 {
         match self {
             TriColor::White => 0xff,
-            TriColor::Black | TriColor::Chromatic => 0x00,
+            TriColor::Black | TriColor::Chromatic | TriColor::Yellow => 0x00,
+        }
+    }
+
+    /// Picks the nearest of black, white and the chromatic color
+    ///
+    /// The chromatic color is taken as pure red, the color of most of the (B) displays. Ties go
+    /// to black, then to white.
+    pub fn from_rgb(r: u8, g: u8, b: u8) -> TriColor {
+        *[TriColor::Black, TriColor::White, TriColor::Chromatic]
+            .iter()
+            .min_by_key(|color| {
+                let (cr, cg, cb) = color.rgb();
+                (i32::from(cr) - i32::from(r)).pow(2)
+                    + (i32::from(cg) - i32::from(g)).pow(2)
+                    + (i32::from(cb) - i32::from(b)).pow(2)
+            })
+            .unwrap_or(&TriColor::White)
+    }
+
+    /// Converts to RGB values, the chromatic color as pure red
+    pub fn rgb(self) -> (u8, u8, u8) {
+        match self {
+            TriColor::Black => (0x00, 0x00, 0x00),
+            TriColor::White => (0xFF, 0xFF, 0xFF),
+            TriColor::Chromatic => (0xFF, 0x00, 0x00),
+            TriColor::Yellow => (0xFF, 0xFF, 0x00),
         }
     }
 
     /// Parses from u8 to Color
+    #[cfg(any(feature = "graphics", test))]
     fn from_u8(val: u8) -> Self {
         match val & 0xf {
             0x00 => TriColor::Black,
@@ -320,7 +541,9 @@ impl From<TriColor> for embedded_graphics_core::pixelcolor::Gray8 {
         match tri_color {
             TriColor::Black => embedded_graphics_core::pixelcolor::Gray8::new(0),
             TriColor::White => embedded_graphics_core::pixelcolor::Gray8::new(255),
-            TriColor::Chromatic => embedded_graphics_core::pixelcolor::Gray8::new(127),
+            TriColor::Chromatic | TriColor::Yellow => {
+                embedded_graphics_core::pixelcolor::Gray8::new(127)
+            }
         }
     }
 }
@@ -345,6 +568,7 @@ impl From<TriColor> for embedded_graphics_core::pixelcolor::Rgb555 {
             TriColor::Black => embedded_graphics_core::pixelcolor::Rgb555::BLACK,
             TriColor::White => embedded_graphics_core::pixelcolor::Rgb555::WHITE,
             TriColor::Chromatic => embedded_graphics_core::pixelcolor::Rgb555::new(255, 0, 0),
+            TriColor::Yellow => embedded_graphics_core::pixelcolor::Rgb555::new(255, 255, 0),
         }
     }
 }
@@ -369,6 +593,7 @@ impl From<TriColor> for embedded_graphics_core::pixelcolor::Rgb565 {
             TriColor::Black => embedded_graphics_core::pixelcolor::Rgb565::BLACK,
             TriColor::White => embedded_graphics_core::pixelcolor::Rgb565::WHITE,
             TriColor::Chromatic => embedded_graphics_core::pixelcolor::Rgb565::new(255, 0, 0),
+            TriColor::Yellow => embedded_graphics_core::pixelcolor::Rgb565::new(255, 255, 0),
         }
     }
 }
@@ -393,6 +618,7 @@ impl From<TriColor> for embedded_graphics_core::pixelcolor::Rgb666 {
             TriColor::Black => embedded_graphics_core::pixelcolor::Rgb666::BLACK,
             TriColor::White => embedded_graphics_core::pixelcolor::Rgb666::WHITE,
             TriColor::Chromatic => embedded_graphics_core::pixelcolor::Rgb666::new(255, 0, 0),
+            TriColor::Yellow => embedded_graphics_core::pixelcolor::Rgb666::new(255, 255, 0),
         }
     }
 }
@@ -417,6 +643,7 @@ impl From<TriColor> for embedded_graphics_core::pixelcolor::Rgb888 {
             TriColor::Black => embedded_graphics_core::pixelcolor::Rgb888::BLACK,
             TriColor::White => embedded_graphics_core::pixelcolor::Rgb888::WHITE,
             TriColor::Chromatic => embedded_graphics_core::pixelcolor::Rgb888::new(255, 0, 0),
+            TriColor::Yellow => embedded_graphics_core::pixelcolor::Rgb888::new(255, 255, 0),
         }
     }
 }
@@ -434,7 +661,7 @@ mod tests {
     // test all values aside from 0 and 1 which all should panic
     #[test]
     fn from_u8_panic() {
-        for val in 2..=u8::max_value() {
+        for val in 2..=u8::MAX {
             extern crate std;
             let result = std::panic::catch_unwind(|| Color::from(val));
             assert!(result.is_err());
@@ -462,4 +689,139 @@ mod tests {
             Ok((left, right))
         );
     }
+
+    #[test]
+    fn test_quad() {
+        assert_eq!(QuadColor::White.get_byte_value(), 0x55);
+        assert_eq!(QuadColor::Red.get_byte_value(), 0xFF);
+        let colors = [
+            QuadColor::Red,
+            QuadColor::Black,
+            QuadColor::Yellow,
+            QuadColor::White,
+        ];
+        assert_eq!(QuadColor::colors_byte(colors), 0b11_00_10_01);
+        assert_eq!(QuadColor::split_byte(0b11_00_10_01), colors);
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn quad_from_rgb() {
+        use embedded_graphics_core::pixelcolor::Rgb888;
+        assert_eq!(
+            QuadColor::from(Rgb888::new(0xF0, 0x10, 0x20)),
+            QuadColor::Red
+        );
+        assert_eq!(
+            QuadColor::from(Rgb888::new(0xE0, 0xD0, 0x30)),
+            QuadColor::Yellow
+        );
+        assert_eq!(
+            QuadColor::from(Rgb888::new(0x20, 0x20, 0x20)),
+            QuadColor::Black
+        );
+        for color in QuadColor::ALL {
+            assert_eq!(QuadColor::from(Rgb888::from(color)), color);
+        }
+    }
+
+    #[test]
+    fn luma_threshold_boundary() {
+        assert_eq!(Color::from_luma(99, 100), Color::Black);
+        assert_eq!(Color::from_luma(100, 100), Color::White);
+        assert_eq!(Color::from_luma(101, 100), Color::White);
+        // every value is at least 0, nothing is above 255
+        assert_eq!(Color::from_luma(0, 0), Color::White);
+        assert_eq!(Color::from_luma(254, 255), Color::Black);
+        assert_eq!(Color::from_luma(255, 255), Color::White);
+    }
+
+    #[test]
+    fn all_colors_are_sorted() {
+        assert_eq!(Color::all(), [Color::Black, Color::White]);
+        assert!(Color::all().windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(TriColor::all().len(), 4);
+        assert!(TriColor::all().windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(TriColor::all().iter().max(), Some(&TriColor::Yellow));
+    }
+
+    #[test]
+    fn yellow_is_sent_like_chromatic() {
+        assert_eq!(
+            TriColor::Yellow.get_byte_value(),
+            TriColor::Chromatic.get_byte_value()
+        );
+        assert_eq!(
+            TriColor::Yellow.get_bit_value(),
+            TriColor::Chromatic.get_bit_value()
+        );
+        assert_eq!(TriColor::Yellow.rgb(), (0xFF, 0xFF, 0x00));
+    }
+
+    #[test]
+    fn tri_color_from_rgb() {
+        assert_eq!(TriColor::from_rgb(0, 0, 0), TriColor::Black);
+        assert_eq!(TriColor::from_rgb(0xFF, 0xFF, 0xFF), TriColor::White);
+        assert_eq!(TriColor::from_rgb(0xFF, 0, 0), TriColor::Chromatic);
+        assert_eq!(TriColor::from_rgb(0xD0, 0x30, 0x20), TriColor::Chromatic);
+        assert_eq!(TriColor::from_rgb(0x60, 0x50, 0x50), TriColor::Black);
+        assert_eq!(TriColor::from_rgb(0xF0, 0xC0, 0xC0), TriColor::White);
+        // halfway between black and red
+        assert_eq!(TriColor::from_rgb(0x7F, 0, 0), TriColor::Black);
+        assert_eq!(TriColor::from_rgb(0x80, 0, 0), TriColor::Chromatic);
+    }
+
+    #[test]
+    fn gray_levels_are_split_into_planes() {
+        let pixels = [0b00_01_10_11, 0b11_10_01_00];
+        assert_eq!(GrayPlane::High.byte(&pixels), 0b0011_1100);
+        assert_eq!(GrayPlane::Low.byte(&pixels), 0b0101_1010);
+        assert_eq!(GrayPlane::High.byte(&pixels[..1]), 0b0011_0000);
+    }
+
+    fn oct_color() -> impl proptest::strategy::Strategy<Value = OctColor> {
+        proptest::sample::select(
+            &[
+                OctColor::Black,
+                OctColor::White,
+                OctColor::Green,
+                OctColor::Blue,
+                OctColor::Red,
+                OctColor::Yellow,
+                OctColor::Orange,
+                OctColor::HiZ,
+            ][..],
+        )
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn oct_byte_round_trip(a in oct_color(), b in oct_color()) {
+            let byte = OctColor::colors_byte(a, b);
+            proptest::prop_assert_eq!(OctColor::split_byte(byte), Ok((a, b)));
+            proptest::prop_assert_eq!(OctColor::from_nibble(a.get_nibble()), Ok(a));
+        }
+
+        #[test]
+        fn oct_nibble_out_of_range(nibble in 0x08u8..=0x0F) {
+            proptest::prop_assert!(OctColor::from_nibble(nibble).is_err());
+        }
+
+        #[test]
+        fn bit_and_byte_values_agree(white in proptest::bool::ANY, chromatic in proptest::bool::ANY) {
+            let color = if white { Color::White } else { Color::Black };
+            proptest::prop_assert_eq!(Color::from(color.get_bit_value()), color);
+            proptest::prop_assert_eq!(color.get_byte_value(), 0u8.wrapping_sub(color.get_bit_value()));
+
+            let tri = match (white, chromatic) {
+                (true, _) => TriColor::White,
+                (false, false) => TriColor::Black,
+                (false, true) => TriColor::Chromatic,
+            };
+            proptest::prop_assert_eq!(tri.get_byte_value(), 0u8.wrapping_sub(tri.get_bit_value()));
+            if tri != TriColor::Chromatic {
+                proptest::prop_assert_eq!(TriColor::from_u8(tri.get_bit_value()), tri);
+            }
+        }
+    }
 }
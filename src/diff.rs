@@ -0,0 +1,163 @@
+//! Dirty windows between two frames
+//!
+//! [diff_regions()] compares the frame on the panel with the next one and yields the windows
+//! which changed, ready to be cut out of the new frame and sent with `update_partial_frame`.
+//! The windows start and end on byte boundaries, like the x addresses of the controllers which
+//! count bytes, e.g. [epd1in54_v2](crate::epd1in54_v2).
+//!
+//! ```rust
+//! use epd_waveshare::diff::diff_regions;
+//! use embedded_graphics_core::{prelude::*, primitives::Rectangle};
+//!
+//! let old = [0xFF; 32 / 8 * 40];
+//! let mut new = old;
+//! // pixel (17, 3) turns black
+//! new[3 * 4 + 2] = 0b1011_1111;
+//!
+//! let mut windows = diff_regions(&old, &new, 32, 40);
+//! assert_eq!(windows.next(), Some(Rectangle::new(Point::new(16, 3), Size::new(8, 1))));
+//! assert_eq!(windows.next(), None);
+//! ```
+
+use crate::buffer_len;
+use embedded_graphics_core::prelude::*;
+use embedded_graphics_core::primitives::Rectangle;
+
+/// Dirty rows closer than this many clean rows end up in the same window
+///
+/// Sending a few clean rows again is cheaper than the commands and the refresh of another
+/// window.
+pub const MERGE_ROWS: u32 = 8;
+
+/// Finds the windows in which two frames with 1 bit per pixel differ
+///
+/// Dirty rows are grouped into bands, rows which are at most [MERGE_ROWS] apart share a band.
+/// Each band yields the bounding box of its dirty bytes, from top to bottom. The x coordinate
+/// and the width are multiples of 8, except that the last window column is clipped to `width`.
+///
+/// # Panics
+///
+/// If `old` or `new` aren't `buffer_len(width, height)` bytes long.
+pub fn diff_regions<'a>(old: &'a [u8], new: &'a [u8], width: u32, height: u32) -> DiffRegions<'a> {
+    let len = buffer_len(width as usize, height as usize);
+    assert!(
+        old.len() == len && new.len() == len,
+        "both frames need buffer_len(width, height) bytes"
+    );
+    DiffRegions {
+        old,
+        new,
+        width,
+        height,
+        row: 0,
+    }
+}
+
+/// Iterator over the dirty windows of two frames, see [diff_regions()]
+#[derive(Debug, Clone)]
+pub struct DiffRegions<'a> {
+    old: &'a [u8],
+    new: &'a [u8],
+    width: u32,
+    height: u32,
+    /// First row which wasn't looked at yet
+    row: u32,
+}
+
+impl DiffRegions<'_> {
+    /// First and last dirty byte of a row
+    fn dirty_bytes(&self, row: u32) -> Option<(usize, usize)> {
+        let row_len = self.width.div_ceil(8) as usize;
+        let start = row as usize * row_len;
+        let old = &self.old[start..start + row_len];
+        let new = &self.new[start..start + row_len];
+        let changed = |(_, (old, new)): &(usize, (&u8, &u8))| old != new;
+        let (first, _) = old.iter().zip(new).enumerate().find(changed)?;
+        let (last, _) = old.iter().zip(new).enumerate().rev().find(changed)?;
+        Some((first, last))
+    }
+}
+
+impl Iterator for DiffRegions<'_> {
+    type Item = Rectangle;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (top, (mut first, mut last)) =
+            (self.row..self.height).find_map(|row| Some((row, self.dirty_bytes(row)?)))?;
+
+        let mut bottom = top;
+        let mut row = top + 1;
+        while row < self.height && row - bottom - 1 <= MERGE_ROWS {
+            if let Some((row_first, row_last)) = self.dirty_bytes(row) {
+                first = first.min(row_first);
+                last = last.max(row_last);
+                bottom = row;
+            }
+            row += 1;
+        }
+        self.row = bottom + 1;
+
+        let x = first as u32 * 8;
+        let end_x = ((last as u32 + 1) * 8).min(self.width);
+        Some(Rectangle::new(
+            Point::new(x as i32, top as i32),
+            Size::new(end_x - x, bottom - top + 1),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    extern crate std;
+    use std::vec;
+    use std::vec::Vec;
+
+    fn window(x: i32, y: i32, width: u32, height: u32) -> Rectangle {
+        Rectangle::new(Point::new(x, y), Size::new(width, height))
+    }
+
+    #[test]
+    fn equal_frames_have_no_windows() {
+        let frame = [0xAA; 4 * 10];
+        assert_eq!(diff_regions(&frame, &frame, 32, 10).next(), None);
+    }
+
+    #[test]
+    fn windows_are_byte_aligned_and_clipped_to_the_width() {
+        // 21 pixels: 3 bytes per row, the last one with 5 pixels
+        let old = [0xFF; 3 * 4];
+        let mut new = old;
+        new[3 + 1] = 0xFE;
+        new[2 * 3 + 2] = 0x7F;
+
+        let windows: Vec<_> = diff_regions(&old, &new, 21, 4).collect();
+        assert_eq!(windows, [window(8, 1, 13, 2)]);
+    }
+
+    #[test]
+    fn distant_bands_get_their_own_windows() {
+        let old = vec![0xFF; 4 * 40];
+        let mut new = old.clone();
+        new[0] = 0x00;
+        // MERGE_ROWS clean rows in between still merge
+        new[(1 + MERGE_ROWS as usize) * 4 + 3] = 0x00;
+        // one more doesn't
+        new[(2 + 2 * MERGE_ROWS as usize + 1) * 4 + 1] = 0x00;
+
+        let windows: Vec<_> = diff_regions(&old, &new, 32, 40).collect();
+        assert_eq!(
+            windows,
+            [
+                window(0, 0, 32, 1 + MERGE_ROWS + 1),
+                window(8, 2 + 2 * MERGE_ROWS as i32 + 1, 8, 1),
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn frames_of_the_wrong_size_panic() {
+        let _ = diff_regions(&[0; 4], &[0; 5], 8, 4);
+    }
+}
@@ -0,0 +1,241 @@
+//! Tracking of the area which changed since the last update
+//!
+//! [DirtyRegionTracker] wraps a display buffer and remembers the bounding box of everything
+//! drawn into it. Displays which only change a small area between updates, like a clock, send
+//! just this window with `update_partial_frame` instead of the full frame.
+//!
+//! The region is in the coordinates of the [DrawTarget], after the rotation of the display.
+//! Most controllers address x in bytes, so round the window out to multiples of 8 before
+//! cutting it out of the frame, see [diff](crate::diff) for a byte aligned alternative which
+//! compares two frames.
+//!
+//! ```rust
+//! use embedded_graphics::{prelude::*, primitives::{PrimitiveStyle, Rectangle}};
+//! use embedded_graphics::pixelcolor::BinaryColor;
+//! use epd_waveshare::{dirty_region::DirtyRegionTracker, epd2in9::Display2in9};
+//!
+//! let mut display = DirtyRegionTracker::new(Display2in9::default());
+//! Rectangle::new(Point::new(10, 20), Size::new(30, 5))
+//!     .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+//!     .draw(&mut display)
+//!     .unwrap();
+//!
+//! assert_eq!(
+//!     display.take_dirty_region(),
+//!     Some(Rectangle::new(Point::new(10, 20), Size::new(30, 5)))
+//! );
+//! assert_eq!(display.take_dirty_region(), None);
+//! ```
+
+use crate::graphics::FrameBuffer;
+use embedded_graphics_core::prelude::*;
+use embedded_graphics_core::primitives::Rectangle;
+
+/// Display buffer which tracks the bounding box of the drawn pixels, see the
+/// [module documentation](self)
+#[derive(Debug, Clone)]
+pub struct DirtyRegionTracker<D> {
+    inner: D,
+    dirty: Option<Rectangle>,
+}
+
+impl<D> DirtyRegionTracker<D> {
+    /// Wraps `inner`, nothing is dirty yet
+    pub fn new(inner: D) -> Self {
+        DirtyRegionTracker { inner, dirty: None }
+    }
+
+    /// The wrapped display
+    pub fn inner(&self) -> &D {
+        &self.inner
+    }
+
+    /// The wrapped display, changes made through it aren't tracked
+    pub fn inner_mut(&mut self) -> &mut D {
+        &mut self.inner
+    }
+
+    /// Unwraps the display
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+
+    /// Bounding box of the pixels drawn since the last
+    /// [take_dirty_region()](Self::take_dirty_region()), `None` if nothing was drawn
+    pub fn dirty_region(&self) -> Option<Rectangle> {
+        self.dirty
+    }
+
+    /// Returns the dirty region and starts tracking from scratch
+    pub fn take_dirty_region(&mut self) -> Option<Rectangle> {
+        self.dirty.take()
+    }
+}
+
+impl<D: Dimensions> DirtyRegionTracker<D> {
+    /// Adds the visible part of `area` to the dirty region
+    fn mark(&mut self, area: &Rectangle) {
+        let area = area.intersection(&self.inner.bounding_box());
+        self.dirty = envelope(self.dirty, area);
+    }
+}
+
+/// Smallest rectangle around `dirty` and `area`, empty areas are skipped
+fn envelope(dirty: Option<Rectangle>, area: Rectangle) -> Option<Rectangle> {
+    if area.is_zero_sized() {
+        return dirty;
+    }
+    let dirty = match dirty {
+        Some(dirty) => dirty,
+        None => return Some(area),
+    };
+    let top_left = dirty.top_left.component_min(area.top_left);
+    let bottom_right = (dirty.top_left + dirty.size).component_max(area.top_left + area.size);
+    Some(Rectangle::new(
+        top_left,
+        Size::new(
+            (bottom_right.x - top_left.x) as u32,
+            (bottom_right.y - top_left.y) as u32,
+        ),
+    ))
+}
+
+impl<D: Dimensions> Dimensions for DirtyRegionTracker<D> {
+    fn bounding_box(&self) -> Rectangle {
+        self.inner.bounding_box()
+    }
+}
+
+impl<D: DrawTarget + FrameBuffer> DrawTarget for DirtyRegionTracker<D> {
+    type Color = <D as DrawTarget>::Color;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let bounds = self.inner.bounding_box();
+        let dirty = &mut self.dirty;
+        self.inner
+            .draw_iter(pixels.into_iter().inspect(|Pixel(point, _)| {
+                if bounds.contains(*point) {
+                    *dirty = envelope(*dirty, Rectangle::new(*point, Size::new(1, 1)));
+                }
+            }))
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        self.mark(area);
+        self.inner.fill_contiguous(area, colors)
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.mark(area);
+        self.inner.fill_solid(area, color)
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.mark(&self.inner.bounding_box());
+        DrawTarget::clear(&mut self.inner, color)
+    }
+}
+
+/// Changes through the bytes mark the whole display as dirty
+impl<D: Dimensions + FrameBuffer> FrameBuffer for DirtyRegionTracker<D> {
+    type Color = <D as FrameBuffer>::Color;
+
+    fn as_bytes(&self) -> &[u8] {
+        self.inner.as_bytes()
+    }
+
+    fn as_bytes_mut(&mut self) -> &mut [u8] {
+        self.mark(&self.inner.bounding_box());
+        self.inner.as_bytes_mut()
+    }
+
+    fn clear(&mut self, color: <D as FrameBuffer>::Color) {
+        self.mark(&self.inner.bounding_box());
+        FrameBuffer::clear(&mut self.inner, color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epd2in9::{Display2in9, HEIGHT, WIDTH};
+    use embedded_graphics_core::pixelcolor::BinaryColor;
+
+    #[test]
+    fn pixels_grow_the_region() {
+        let mut display = DirtyRegionTracker::new(Display2in9::default());
+        assert_eq!(display.dirty_region(), None);
+
+        Pixel(Point::new(5, 7), BinaryColor::On)
+            .draw(&mut display)
+            .unwrap();
+        assert_eq!(
+            display.dirty_region(),
+            Some(Rectangle::new(Point::new(5, 7), Size::new(1, 1)))
+        );
+
+        // pixels outside of the display are dropped and don't count
+        display
+            .draw_iter([
+                Pixel(Point::new(20, 3), BinaryColor::Off),
+                Pixel(Point::new(-1, 200), BinaryColor::On),
+                Pixel(Point::new(1000, 0), BinaryColor::On),
+            ])
+            .unwrap();
+        assert_eq!(
+            display.take_dirty_region(),
+            Some(Rectangle::new(Point::new(5, 3), Size::new(16, 5)))
+        );
+        assert_eq!(display.take_dirty_region(), None);
+        // the pixel was drawn into the wrapped buffer, row 7 byte 0, bit 5
+        assert_eq!(display.as_bytes()[7 * 16] & 0x04, 0);
+    }
+
+    #[test]
+    fn fills_are_clipped_to_the_display() {
+        let mut display = DirtyRegionTracker::new(Display2in9::default());
+        display
+            .fill_solid(
+                &Rectangle::new(Point::new(120, -4), Size::new(20, 10)),
+                BinaryColor::On,
+            )
+            .unwrap();
+        assert_eq!(
+            display.take_dirty_region(),
+            Some(Rectangle::new(Point::new(120, 0), Size::new(8, 6)))
+        );
+
+        display
+            .fill_solid(
+                &Rectangle::new(Point::new(200, 0), Size::new(8, 8)),
+                BinaryColor::On,
+            )
+            .unwrap();
+        assert_eq!(display.take_dirty_region(), None);
+    }
+
+    #[test]
+    fn clears_and_byte_access_mark_everything() {
+        let full = Rectangle::new(Point::zero(), Size::new(WIDTH, HEIGHT));
+        let mut display = DirtyRegionTracker::new(Display2in9::default());
+
+        DrawTarget::clear(&mut display, BinaryColor::Off).unwrap();
+        assert_eq!(display.take_dirty_region(), Some(full));
+
+        FrameBuffer::clear(&mut display, crate::color::Color::Black);
+        assert_eq!(display.take_dirty_region(), Some(full));
+
+        display.as_bytes_mut()[0] = 0;
+        assert_eq!(display.take_dirty_region(), Some(full));
+
+        assert_eq!(display.as_bytes().len(), display.inner().as_bytes().len());
+        assert_eq!(display.take_dirty_region(), None);
+    }
+}
@@ -0,0 +1,455 @@
+//! Error diffusion dithering of images into the frame layout of the displays
+//!
+//! A flat threshold like [Color::from_luma()] turns the gradients of photos into large black
+//! and white areas. The functions here diffuse the error of every pixel onto its neighbours
+//! instead. [floyd_steinberg_dither()] passes on all of it, 7/16 to the right and 3/16, 5/16
+//! and 1/16 to the row below. [atkinson_dither()] passes on only 6/8 to six neighbours, which
+//! loses some detail in the shadows and highlights but keeps the contrast higher. The output
+//! has the layout of the full frame buffers, rows of MSB first bytes with the white bit set,
+//! and can be sent with `update_frame` as is.
+//!
+//! Both keep the error of the next rows in an array of [STACK_ERROR_BYTES] on the stack, sized
+//! for images up to [MAX_WIDTH] pixels wide. Small targets need a stack with room for it. With
+//! the `alloc` feature `dither_gray` and `dither_rgb_tri_color` keep the error on the heap
+//! instead, only as much as the width needs, and take images of any width.
+//!
+//! Buffers of the wrong size return [Error::BufferSize], images wider than [MAX_WIDTH]
+//! [Error::WindowTooLarge].
+//!
+//! ```rust
+//! use epd_waveshare::dither::floyd_steinberg_dither;
+//! use epd_waveshare::buffer_len;
+//!
+//! // a horizontal gradient from black to white
+//! const WIDTH: usize = 256;
+//! const HEIGHT: usize = 10;
+//! let mut gray = [0u8; WIDTH * HEIGHT];
+//! for (i, pixel) in gray.iter_mut().enumerate() {
+//!     *pixel = (i % WIDTH) as u8;
+//! }
+//! let mut frame = [0u8; buffer_len(WIDTH, HEIGHT)];
+//! floyd_steinberg_dither::<()>(&gray, &mut frame, WIDTH, HEIGHT)?;
+//! # Ok::<(), epd_waveshare::error::Error<()>>(())
+//! ```
+
+use crate::buffer_len;
+use crate::color::Color;
+#[cfg(all(feature = "alloc", feature = "graphics"))]
+use crate::color::TriColor;
+use crate::error::Error;
+#[cfg(all(feature = "alloc", feature = "graphics"))]
+use crate::graphics::{set_tri_color_bit, DisplayColorRendering};
+#[cfg(feature = "alloc")]
+use alloc::vec;
+
+/// Widest image [floyd_steinberg_dither()] and [atkinson_dither()] take
+pub const MAX_WIDTH: usize = 1024;
+
+/// Stack [floyd_steinberg_dither()] and [atkinson_dither()] use for the error, about 6 KiB
+pub const STACK_ERROR_BYTES: usize = error_len(MAX_WIDTH, 1) * core::mem::size_of::<i16>();
+
+/// Rows of errors kept, the current one and the two below it
+const ROWS: usize = 3;
+/// Columns of padding on both sides, saves the bounds checks at the edges
+const PAD: usize = 2;
+
+/// How the error of a pixel is passed on to its neighbours
+struct Kernel {
+    /// Column offset, row offset and weight of every neighbour
+    weights: &'static [(isize, usize, i16)],
+    /// Sum of the weights if all of the error is passed on
+    divisor: i16,
+}
+
+const FLOYD_STEINBERG: Kernel = Kernel {
+    weights: &[(1, 0, 7), (-1, 1, 3), (0, 1, 5), (1, 1, 1)],
+    divisor: 16,
+};
+
+const ATKINSON: Kernel = Kernel {
+    weights: &[
+        (1, 0, 1),
+        (2, 0, 1),
+        (-1, 1, 1),
+        (0, 1, 1),
+        (1, 1, 1),
+        (0, 2, 1),
+    ],
+    divisor: 8,
+};
+
+/// Number of error values [ErrorRows] needs for an image `width` pixels wide
+const fn error_len(width: usize, channels: usize) -> usize {
+    ROWS * (width + 2 * PAD) * channels
+}
+
+/// Accumulated error of the current and the next rows, in units of `1 / kernel.divisor`
+struct ErrorRows<'a> {
+    kernel: &'a Kernel,
+    channels: usize,
+    /// Values per row
+    stride: usize,
+    /// [ROWS] rows used as a ring, `current` is the one of the pixels being dithered
+    errors: &'a mut [i16],
+    current: usize,
+}
+
+impl<'a> ErrorRows<'a> {
+    fn new(kernel: &'a Kernel, width: usize, channels: usize, errors: &'a mut [i16]) -> Self {
+        let len = error_len(width, channels);
+        errors[..len].iter_mut().for_each(|e| *e = 0);
+        ErrorRows {
+            kernel,
+            channels,
+            stride: (width + 2 * PAD) * channels,
+            errors: &mut errors[..len],
+            current: 0,
+        }
+    }
+
+    fn index(&self, x: usize, dx: isize, dy: usize, c: usize) -> usize {
+        let row = (self.current + dy) % ROWS;
+        let column = ((x + PAD) as isize + dx) as usize;
+        row * self.stride + column * self.channels + c
+    }
+
+    /// `value` of channel `c` of pixel `x` with the diffused error added
+    fn adjusted(&self, x: usize, c: usize, value: u8) -> i32 {
+        let error = self.errors[self.index(x, 0, 0, c)];
+        i32::from(value) + i32::from(error / self.kernel.divisor)
+    }
+
+    /// Diffuses the quantization `error` of channel `c` of pixel `x` onto its neighbours
+    fn spread(&mut self, x: usize, c: usize, error: i32) {
+        for &(dx, dy, weight) in self.kernel.weights {
+            let index = self.index(x, dx, dy, c);
+            let share = (error * i32::from(weight)).clamp(i16::MIN.into(), i16::MAX.into());
+            self.errors[index] = self.errors[index].saturating_add(share as i16);
+        }
+    }
+
+    fn next_row(&mut self) {
+        let start = self.current * self.stride;
+        self.errors[start..start + self.stride]
+            .iter_mut()
+            .for_each(|e| *e = 0);
+        self.current = (self.current + 1) % ROWS;
+    }
+}
+
+/// Byte and bit of pixel `x` in row `y` of a frame with 1 bit per pixel
+fn position(width: usize, x: usize, y: usize) -> (usize, u8) {
+    (y * width.div_ceil(8) + x / 8, 0x80 >> (x % 8))
+}
+
+/// Returns [Error::BufferSize] unless `buffer` is `expected` bytes long
+fn check_len<E>(buffer: &[u8], expected: usize) -> Result<(), Error<E>> {
+    if buffer.len() != expected {
+        return Err(Error::BufferSize {
+            expected,
+            got: buffer.len(),
+        });
+    }
+    Ok(())
+}
+
+/// Dithers `gray` into the b/w `frame` with the error kept in `errors`
+fn dither<E>(
+    kernel: &Kernel,
+    gray: &[u8],
+    frame: &mut [u8],
+    width: usize,
+    height: usize,
+    errors: &mut [i16],
+) -> Result<(), Error<E>> {
+    check_len(gray, width * height)?;
+    check_len(frame, buffer_len(width, height))?;
+
+    let mut errors = ErrorRows::new(kernel, width, 1, errors);
+    for (y, row) in gray.chunks_exact(width.max(1)).enumerate() {
+        for (x, &value) in row.iter().enumerate() {
+            let value = errors.adjusted(x, 0, value);
+            let color = Color::from_luma(value.clamp(0, 255) as u8, 128);
+            errors.spread(x, 0, value - i32::from(color.get_byte_value()));
+
+            let (index, bit) = position(width, x, y);
+            match color {
+                Color::White => frame[index] |= bit,
+                Color::Black => frame[index] &= !bit,
+            }
+        }
+        errors.next_row();
+    }
+    Ok(())
+}
+
+/// Dithers with the error in an array on the stack, see [STACK_ERROR_BYTES]
+fn dither_on_stack<E>(
+    kernel: &Kernel,
+    src: &[u8],
+    dst: &mut [u8],
+    width: usize,
+    height: usize,
+) -> Result<(), Error<E>> {
+    if width > MAX_WIDTH {
+        return Err(Error::WindowTooLarge);
+    }
+    let mut errors = [0; error_len(MAX_WIDTH, 1)];
+    dither(kernel, src, dst, width, height, &mut errors)
+}
+
+/// Dithers a grayscale image with one byte per pixel into a b/w frame with Floyd–Steinberg
+///
+/// `src` holds the rows of the image, `0` is black and `255` white. Nothing is allocated, the
+/// error takes [STACK_ERROR_BYTES] of stack.
+///
+/// Returns [Error::WindowTooLarge] if `width` is larger than [MAX_WIDTH] and
+/// [Error::BufferSize] if `src` isn't `width * height` bytes or `dst` isn't
+/// `buffer_len(width, height)` bytes long.
+pub fn floyd_steinberg_dither<E>(
+    src: &[u8],
+    dst: &mut [u8],
+    width: usize,
+    height: usize,
+) -> Result<(), Error<E>> {
+    dither_on_stack(&FLOYD_STEINBERG, src, dst, width, height)
+}
+
+/// Dithers a grayscale image with one byte per pixel into a b/w frame with Atkinson's
+/// algorithm
+///
+/// Same as [floyd_steinberg_dither()] otherwise.
+pub fn atkinson_dither<E>(
+    src: &[u8],
+    dst: &mut [u8],
+    width: usize,
+    height: usize,
+) -> Result<(), Error<E>> {
+    dither_on_stack(&ATKINSON, src, dst, width, height)
+}
+
+/// Dithers a grayscale image with one byte per pixel into a b/w frame
+///
+/// [floyd_steinberg_dither()] for images of any width, the error is kept on the heap.
+///
+/// Returns [Error::BufferSize] if `gray` isn't `width * height` bytes or `frame` isn't
+/// `buffer_len(width, height)` bytes long.
+#[cfg(feature = "alloc")]
+pub fn dither_gray<E>(
+    gray: &[u8],
+    width: u32,
+    height: u32,
+    frame: &mut [u8],
+) -> Result<(), Error<E>> {
+    let (width, height) = (width as usize, height as usize);
+    let mut errors = vec![0; error_len(width, 1)];
+    dither(&FLOYD_STEINBERG, gray, frame, width, height, &mut errors)
+}
+
+/// Dithers an RGB image with three bytes per pixel into the b/w and chromatic frame of a
+/// tri-color display
+///
+/// The chromatic color is taken as red, see [TriColor::from_rgb()]. `rendering` is the one of
+/// the display, see [DisplayColorRendering].
+///
+/// Returns [Error::BufferSize] if `rgb` isn't `3 * width * height` bytes or one of the
+/// frames isn't `buffer_len(width, height)` bytes long.
+#[cfg(all(feature = "alloc", feature = "graphics"))]
+pub fn dither_rgb_tri_color<E>(
+    rgb: &[u8],
+    width: u32,
+    height: u32,
+    rendering: DisplayColorRendering,
+    bw: &mut [u8],
+    chromatic: &mut [u8],
+) -> Result<(), Error<E>> {
+    let (width, height) = (width as usize, height as usize);
+    check_len(rgb, 3 * width * height)?;
+    let len = buffer_len(width, height);
+    check_len(bw, len)?;
+    check_len(chromatic, len)?;
+
+    let mut errors = vec![0; error_len(width, 3)];
+    let mut errors = ErrorRows::new(&FLOYD_STEINBERG, width, 3, &mut errors);
+    for (y, row) in rgb.chunks_exact(3 * width.max(1)).enumerate() {
+        for (x, pixel) in row.chunks_exact(3).enumerate() {
+            let mut adjusted = [0i32; 3];
+            for (c, value) in pixel.iter().enumerate() {
+                adjusted[c] = errors.adjusted(x, c, *value);
+            }
+            let [r, g, b] = adjusted.map(|v| v.clamp(0, 255) as u8);
+            let color = TriColor::from_rgb(r, g, b);
+            let (cr, cg, cb) = color.rgb();
+            for (c, &palette) in [cr, cg, cb].iter().enumerate() {
+                errors.spread(x, c, adjusted[c] - i32::from(palette));
+            }
+
+            let (index, bit) = position(width, x, y);
+            set_tri_color_bit(&mut bw[index], &mut chromatic[index], bit, color, rendering);
+        }
+        errors.next_row();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    extern crate std;
+    use std::vec;
+    use std::vec::Vec;
+
+    fn white_pixels(frame: &[u8]) -> u32 {
+        frame.iter().map(|b| b.count_ones()).sum()
+    }
+
+    /// 8 x 4 pixels, every row goes from black to white
+    fn small_gradient() -> Vec<u8> {
+        (0..8 * 4).map(|i| (i % 8 * 255 / 7) as u8).collect()
+    }
+
+    #[test]
+    fn flat_gray_levels() {
+        for dither in [floyd_steinberg_dither::<()>, atkinson_dither::<()>] {
+            let mut frame = [0x55; 4 * 16];
+            dither(&[0; 32 * 16], &mut frame, 32, 16).unwrap();
+            assert_eq!(white_pixels(&frame), 0);
+
+            dither(&[255; 32 * 16], &mut frame, 32, 16).unwrap();
+            assert_eq!(white_pixels(&frame), 32 * 16);
+        }
+
+        // 50% gray is a checkerboard
+        let mut frame = [0; 4 * 16];
+        floyd_steinberg_dither::<()>(&[128; 32 * 16], &mut frame, 32, 16).unwrap();
+        assert_eq!(white_pixels(&frame), 32 * 16 / 2);
+        assert!(frame
+            .chunks(4)
+            .all(|row| row == [0xAA; 4] || row == [0x55; 4]));
+    }
+
+    #[test]
+    fn known_gradient_output() {
+        let gray = small_gradient();
+        let mut frame = [0; 4];
+
+        floyd_steinberg_dither::<()>(&gray, &mut frame, 8, 4).unwrap();
+        assert_eq!(frame, [0x17, 0x17, 0x17, 0x2F]);
+        // 36 and 72 stay black, the first error pushes 109 over the threshold
+        let pixel = |frame: &[u8], x: usize, y: usize| frame[y] & (0x80 >> x) != 0;
+        assert!(!pixel(&frame, 1, 0) && !pixel(&frame, 2, 0) && pixel(&frame, 3, 0));
+        assert!(!pixel(&frame, 4, 0) && pixel(&frame, 7, 0));
+
+        atkinson_dither::<()>(&gray, &mut frame, 8, 4).unwrap();
+        assert_eq!(frame, [0x0F, 0x1F, 0x17, 0x0F]);
+        // only 6/8 of the error is passed on, 109 stays black in the first row
+        assert!(!pixel(&frame, 3, 0) && pixel(&frame, 4, 0));
+    }
+
+    #[test]
+    fn gradient_gets_brighter() {
+        let (width, height) = (64, 32);
+        let gray: Vec<u8> = (0..width * height).map(|i| (i % width * 4) as u8).collect();
+        for dither in [floyd_steinberg_dither::<()>, atkinson_dither::<()>] {
+            let mut frame = vec![0; buffer_len(width, height)];
+            dither(&gray, &mut frame, width, height).unwrap();
+
+            // share of white pixels per 8 column wide stripe follows the gray level
+            let stripes: Vec<u32> = (0..width / 8)
+                .map(|column| {
+                    frame
+                        .iter()
+                        .skip(column)
+                        .step_by(8)
+                        .map(|b| b.count_ones())
+                        .sum()
+                })
+                .collect();
+            assert!(stripes.windows(2).all(|pair| pair[0] <= pair[1]));
+            for (column, &white) in stripes.iter().enumerate() {
+                let mean = (column * 8 * 4 + 14) as f32 / 255.0;
+                let share = white as f32 / (8 * height) as f32;
+                assert!((share - mean).abs() < 0.1, "{} {}", column, share);
+            }
+        }
+    }
+
+    #[test]
+    fn partial_bytes_at_the_end_of_the_rows() {
+        let mut frame = [0; 2 * 3];
+        floyd_steinberg_dither::<()>(&[255; 10 * 3], &mut frame, 10, 3).unwrap();
+        assert_eq!(frame, [0xFF, 0xC0, 0xFF, 0xC0, 0xFF, 0xC0]);
+    }
+
+    #[test]
+    fn buffers_of_the_wrong_size() {
+        assert_eq!(
+            floyd_steinberg_dither::<()>(&[0; 16], &mut [0; 3], 4, 4),
+            Err(Error::BufferSize {
+                expected: 4,
+                got: 3
+            })
+        );
+        assert_eq!(
+            atkinson_dither::<()>(&[0; 15], &mut [0; 4], 4, 4),
+            Err(Error::BufferSize {
+                expected: 16,
+                got: 15
+            })
+        );
+    }
+
+    #[test]
+    fn wider_than_max_width() {
+        let width = MAX_WIDTH + 8;
+        assert_eq!(
+            floyd_steinberg_dither::<()>(&vec![0; width], &mut vec![0; width / 8], width, 1),
+            Err(Error::WindowTooLarge)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn heap_version_takes_any_width() {
+        let gray = small_gradient();
+        let mut frame = [0; 4];
+        dither_gray::<()>(&gray, 8, 4, &mut frame).unwrap();
+        assert_eq!(frame, [0x17, 0x17, 0x17, 0x2F]);
+
+        let width = MAX_WIDTH + 8;
+        let mut frame = vec![0; width / 8];
+        dither_gray::<()>(&vec![255; width], width as u32, 1, &mut frame).unwrap();
+        assert!(frame.iter().all(|&b| b == 0xFF));
+    }
+
+    #[test]
+    #[cfg(all(feature = "alloc", feature = "graphics"))]
+    fn tri_color_planes() {
+        let mut rgb = Vec::new();
+        for _ in 0..8 {
+            rgb.extend_from_slice(&[0xFF, 0xFF, 0xFF]);
+        }
+        for _ in 0..8 {
+            rgb.extend_from_slice(&[0xE0, 0x10, 0x10]);
+        }
+        for rendering in [
+            DisplayColorRendering::Positive,
+            DisplayColorRendering::Negative,
+        ] {
+            let (mut bw, mut chromatic) = ([0; 2], [0; 2]);
+            dither_rgb_tri_color::<()>(&rgb, 16, 1, rendering, &mut bw, &mut chromatic).unwrap();
+
+            let (mut expected_bw, mut expected_chromatic) = ([0; 2], [0; 2]);
+            for (i, &color) in [TriColor::White, TriColor::Chromatic].iter().enumerate() {
+                set_tri_color_bit(
+                    &mut expected_bw[i],
+                    &mut expected_chromatic[i],
+                    0xFF,
+                    color,
+                    rendering,
+                );
+            }
+            assert_eq!((bw, chromatic), (expected_bw, expected_chromatic));
+        }
+    }
+}
@@ -0,0 +1,481 @@
+//! A simple Driver for the Waveshare 13.3" (K) E-Ink Display via SPI
+//!
+//! The panel uses a SSD1677 with the command set of the [type A displays](crate::epd1in54_v2).
+//! Unlike the smaller controllers its RAM addresses are 10 bit wide and the x addresses count
+//! pixels instead of bytes, so all address registers take two bytes.
+//!
+//! A full frame has 81600 bytes. If that doesn't fit into the memory of the microcontroller,
+//! the frame can be sent in bands of whole rows with
+//! [update_partial_frame()](WaveshareDisplay::update_partial_frame()) and refreshed once with
+//! [display_frame()](WaveshareDisplay::display_frame()). The full size `Display13in3k` is
+//! only available with the `large-buffers` feature.
+//!
+//! # References
+//!
+//! - [Waveshare wiki](https://www.waveshare.com/wiki/13.3inch_e-Paper_HAT_(K))
+//! - [Waveshare C driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_13in3k.c)
+
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::{InputPin, OutputPin},
+};
+
+use crate::check_buffer_len;
+use crate::color::Color;
+use crate::error::Error;
+use crate::interface::DisplayInterface;
+use crate::power::{PowerState, Transition};
+use crate::timing::ResetTiming;
+use crate::traits::{InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::type_a::command::Command;
+use crate::window::WindowLimits;
+use crate::{AutoCs, CsStrategy, ResetPin};
+
+#[cfg(feature = "large-buffers")]
+mod graphics;
+#[cfg(feature = "large-buffers")]
+pub use self::graphics::Display13in3k;
+
+/// Width of the display
+pub const WIDTH: u32 = 960;
+/// Height of the display
+pub const HEIGHT: u32 = 680;
+/// Limits of the partial window registers: 10 bit x and y addresses, the whole panel fits
+pub const WINDOW_LIMITS: WindowLimits = WindowLimits::new(WIDTH, HEIGHT, 1023, 1023);
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+const IS_BUSY_LOW: bool = false;
+/// Longest time in milliseconds to wait for the busy pin before giving up with
+/// [Error::Timeout]
+pub const BUSY_TIMEOUT_MS: u32 = 30_000;
+/// Fastest SPI clock of the controller in Hz, see
+/// [check_spi_frequency()](crate::check_spi_frequency())
+pub const MAX_SPI_FREQ_HZ: u32 = 20_000_000;
+
+/// Epd13in3k driver
+///
+pub struct Epd13in3k<SPI, CS, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+    /// Background Color
+    color: Color,
+    /// Mode of the controller
+    state: PowerState,
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd13in3k<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state = self.state.check(Transition::Init)?;
+        self.interface.reset(delay, 10, 10)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.interface.cmd(spi, Command::SwReset)?;
+        self.wait_until_idle_with_timeout(delay)?;
+
+        // soft start phases and duration of the booster, values from the vendor code
+        self.interface.cmd_with_data(
+            spi,
+            Command::BoosterSoftStartControl,
+            &[0xAE, 0xC7, 0xC3, 0xC0, 0x80],
+        )?;
+
+        // 680 gate lines: A[9:0] = 0x2A7, GD, SM and TB = 0
+        self.interface.cmd_with_data(
+            spi,
+            Command::DriverOutputControl,
+            &[(HEIGHT - 1) as u8, ((HEIGHT - 1) >> 8) as u8, 0x00],
+        )?;
+
+        // x and y increment, the address counter is updated in x direction
+        self.interface
+            .cmd_with_data(spi, Command::DataEntryModeSetting, &[0x03])?;
+
+        self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
+
+        // white border
+        self.interface
+            .cmd_with_data(spi, Command::BorderWaveformControl, &[0x01])?;
+
+        self.interface.cmd_with_data(
+            spi,
+            Command::TemperatureSensorSelection,
+            &[0x80], // 0x80: internal temperature sensor
+        )?;
+
+        self.set_ram_counter(spi, 0, 0)?;
+
+        self.wait_until_idle_with_timeout(delay)?;
+        Ok(())
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd13in3k<SPI, AutoCs, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Creates the driver for a board without a CS GPIO, see [AutoCs]
+    pub fn new_without_cs(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new(spi, AutoCs, busy, dc, rst, delay)
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd13in3k<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    type DisplayColor = Color;
+    fn new(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_background(spi, cs, busy, dc, rst, delay, DEFAULT_BACKGROUND_COLOR)
+    }
+
+    fn new_with_background(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        color: Self::DisplayColor,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(cs, busy, dc, rst);
+
+        let mut epd = Epd13in3k {
+            interface,
+            color,
+            state: PowerState::Active,
+        };
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.check_reset_pin()?;
+        self.init(spi, delay)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        let next = self.state.check(Transition::Sleep)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        // deep sleep mode 2, the RAM isn't retained
+        self.interface
+            .cmd_with_data(spi, Command::DeepSleepMode, &[0x03])?;
+        self.state = next;
+        Ok(())
+    }
+
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer, WIDTH, HEIGHT)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.use_full_frame(spi)?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteRam, buffer)?;
+        Ok(())
+    }
+
+    /// Updates a window of the frame
+    ///
+    /// Windows with the full width and a part of the rows can be used to send a whole frame
+    /// in several bands.
+    fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        WINDOW_LIMITS.validate_aligned(x, y, width, height)?;
+        check_buffer_len(buffer, width, height)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)?;
+        self.set_ram_counter(spi, x, y)?;
+
+        self.interface
+            .cmd_with_data(spi, Command::WriteRam, buffer)?;
+        Ok(())
+    }
+
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Refresh)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        // load the temperature and the LUT from the OTP, display and power off the analog part
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xF7])?;
+        self.interface.start_measurement(RefreshLut::Full.into());
+        self.interface.cmd(spi, Command::MasterActivation)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        Ok(())
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)?;
+        Ok(())
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.use_full_frame(spi)?;
+
+        let color = self.color.get_byte_value();
+        self.interface.cmd(spi, Command::WriteRam)?;
+        self.interface
+            .data_x_times(spi, color, WIDTH / 8 * HEIGHT)?;
+        Ok(())
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn full_refresh_ms(&self) -> u32 {
+        4000
+    }
+
+    /// The waveform of the OTP is the only one, anything but [RefreshLut::Full] returns
+    /// [Error::UnsupportedLut]
+    fn set_lut(
+        &mut self,
+        _spi: &mut SPI,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        match refresh_rate {
+            None | Some(RefreshLut::Full) => Ok(()),
+            Some(_) => Err(Error::UnsupportedLut),
+        }
+    }
+
+    fn is_busy(&self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
+    fn send_command_raw(&mut self, spi: &mut SPI, command: u8) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn send_data_raw(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        self.interface.data(spi, data)
+    }
+
+    fn set_spi_chunk_size(&mut self, chunk_size: usize) {
+        self.interface.set_chunk_size(chunk_size);
+    }
+
+    fn set_reset_timing(&mut self, timing: Option<ResetTiming>) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    #[cfg(feature = "test-patterns")]
+    fn set_test_pattern(&mut self, enable: bool) {
+        let pattern = crate::test_pattern::FillPattern::new(self.width(), 1);
+        self.interface.set_fill_pattern(enable.then_some(pattern));
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> Epd13in3k<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    fn wait_until_idle_with_timeout(&mut self, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .wait_until_idle_with_timeout(delay, IS_BUSY_LOW, BUSY_TIMEOUT_MS)
+    }
+
+    /// Current mode of the controller
+    pub fn power_state(&self) -> PowerState {
+        self.state
+    }
+
+    fn use_full_frame(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        // choose full frame/ram
+        self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
+
+        // start from the beginning
+        self.set_ram_counter(spi, 0, 0)
+    }
+
+    /// Sets the RAM window, `end_x` and `end_y` are inclusive
+    fn set_ram_area(
+        &mut self,
+        spi: &mut SPI,
+        start_x: u32,
+        start_y: u32,
+        end_x: u32,
+        end_y: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        if start_x > end_x || start_y > end_y {
+            return Err(Error::InvalidRegion);
+        }
+
+        // 2 Databytes: A[7:0] & 0..A[9:8] for each - start and end, x is in pixels
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamXAddressStartEndPosition,
+            &[
+                start_x as u8,
+                (start_x >> 8) as u8,
+                end_x as u8,
+                (end_x >> 8) as u8,
+            ],
+        )?;
+
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamYAddressStartEndPosition,
+            &[
+                start_y as u8,
+                (start_y >> 8) as u8,
+                end_y as u8,
+                (end_y >> 8) as u8,
+            ],
+        )
+    }
+
+    fn set_ram_counter(&mut self, spi: &mut SPI, x: u32, y: u32) -> Result<(), Error<SPI::Error>> {
+        // 2 Databytes: A[7:0] & 0..A[9:8], x is in pixels
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamXAddressCounter,
+            &[x as u8, (x >> 8) as u8],
+        )?;
+
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamYAddressCounter,
+            &[y as u8, (y >> 8) as u8],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{cmd, test_epd, DcPin, LowPin, NoDelay, RecordingSpi};
+
+    type TestEpd = Epd13in3k<RecordingSpi, LowPin, LowPin, DcPin, LowPin, NoDelay>;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 960);
+        assert_eq!(HEIGHT, 680);
+        assert_eq!(crate::buffer_len(WIDTH as usize, HEIGHT as usize), 81600);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+    }
+
+    #[test]
+    fn init_sequence() {
+        let (spi, _) = test_epd::<TestEpd, _>();
+        let expected = [
+            cmd(0x12, &[]),
+            cmd(0x0C, &[0xAE, 0xC7, 0xC3, 0xC0, 0x80]),
+            cmd(0x01, &[0xA7, 0x02, 0x00]),
+            cmd(0x11, &[0x03]),
+            cmd(0x44, &[0x00, 0x00, 0xBF, 0x03]),
+            cmd(0x45, &[0x00, 0x00, 0xA7, 0x02]),
+            cmd(0x3C, &[0x01]),
+            cmd(0x18, &[0x80]),
+            cmd(0x4E, &[0x00, 0x00]),
+            cmd(0x4F, &[0x00, 0x00]),
+        ]
+        .concat();
+        assert_eq!(spi.sent, expected);
+    }
+
+    #[test]
+    fn partial_frame_uses_ten_bit_pixel_addresses() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+
+        epd.update_partial_frame(&mut spi, &[0xAA, 0x55], 944, 679, 16, 1, &mut NoDelay)
+            .unwrap();
+
+        let expected = [
+            cmd(0x44, &[0xB0, 0x03, 0xBF, 0x03]),
+            cmd(0x45, &[0xA7, 0x02, 0xA7, 0x02]),
+            cmd(0x4E, &[0xB0, 0x03]),
+            cmd(0x4F, &[0xA7, 0x02]),
+            cmd(0x24, &[0xAA, 0x55]),
+        ]
+        .concat();
+        assert_eq!(spi.sent, expected);
+    }
+
+    #[test]
+    fn only_the_otp_lut_is_accepted() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+
+        assert_eq!(epd.set_lut(&mut spi, None), Ok(()));
+        assert_eq!(epd.set_lut(&mut spi, Some(RefreshLut::Full)), Ok(()));
+        assert_eq!(
+            epd.set_lut(&mut spi, Some(RefreshLut::Quick)),
+            Err(Error::UnsupportedLut)
+        );
+        assert!(spi.sent.is_empty());
+    }
+}
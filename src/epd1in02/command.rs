@@ -0,0 +1,91 @@
+//! SPI Commands for the Waveshare 1.02" E-Ink Display
+use crate::traits;
+
+/// EPD1IN02 commands
+///
+/// Should rarely (never?) be needed directly.
+///
+/// The controller is UC8175 like: it shares the data transmission and partial window commands
+/// with the UC8176 of the 4.2", but it only has two LUT registers and some power registers of
+/// its own.
+#[allow(dead_code)]
+#[derive(Copy, Clone)]
+pub(crate) enum Command {
+    /// Resolution, LUT source, gate scan and source shift direction, booster switch, soft reset
+    ///
+    /// 0x6F: LUT from register, scan up, shift right, booster on, no soft reset
+    PanelSetting = 0x00,
+    /// Selecting internal and external power, VGH/VGL, VDH and VDL
+    PowerSetting = 0x01,
+    /// Turns off the charge pump, T-con, source and gate driver and VCOM
+    PowerOff = 0x02,
+    /// Setting Power OFF sequence
+    PowerOffSequenceSetting = 0x03,
+    /// Turning On the Power
+    PowerOn = 0x04,
+    /// Charge pump setting, one byte for the driving strength and the clock of the pump
+    ChargePumpSetting = 0x06,
+    /// Deep sleep, returns to standby only with a hardware reset
+    ///
+    /// The only parameter is a check code, the command is executed if it is 0xA5.
+    DeepSleep = 0x07,
+    /// Writes the "OLD" data to SRAM
+    DataStartTransmission1 = 0x10,
+    /// Stopping data transmission
+    DataStop = 0x11,
+    /// Refreshes the panel according to the SRAM data and the LUTs
+    DisplayRefresh = 0x12,
+    /// Writes the "NEW" data to SRAM
+    DataStartTransmission2 = 0x13,
+    /// LUT for the white pixels of the new data, 42 bytes
+    LutWhite = 0x23,
+    /// LUT for the black pixels of the new data, 42 bytes
+    LutBlack = 0x24,
+    /// Sets XON and the options of the LUTs
+    LutOption = 0x2A,
+    /// Frame rate of the waveform
+    PllControl = 0x30,
+    /// Border output and the interval between the VCOM and the data output
+    VcomAndDataIntervalSetting = 0x50,
+    /// Non overlapping period of the gate and source outputs
+    TconSetting = 0x60,
+    /// Horizontal (80) and vertical (128) resolution, one byte each
+    ResolutionSetting = 0x61,
+    /// VCOM DC level
+    VcmDcSetting = 0x82,
+    /// Window of the partial mode
+    ///
+    /// Horizontal start and end are in pixels, but the lower three bits are ignored: the
+    /// window always starts at a multiple of 8 and ends one pixel before one.
+    PartialWindow = 0x90,
+    /// Enters the partial mode
+    PartialIn = 0x91,
+    /// Leaves the partial mode
+    PartialOut = 0x92,
+    /// Undocumented power optimization register of the vendor init sequence
+    PowerOptimization = 0xD2,
+    /// Power saving during the refresh, active while VCOM or the sources change polarity
+    PowerSaving = 0xE3,
+}
+
+impl traits::Command for Command {
+    /// Returns the address of the command
+    fn address(self) -> u8 {
+        self as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::Command as CommandTrait;
+
+    #[test]
+    fn command_addr() {
+        assert_eq!(Command::PowerOptimization.address(), 0xD2);
+
+        assert_eq!(Command::LutWhite.address(), 0x23);
+
+        assert_eq!(Command::DisplayRefresh.address(), 0x12);
+    }
+}
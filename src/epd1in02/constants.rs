@@ -0,0 +1,55 @@
+//! Look-up tables and driving voltages of the 1.02"
+//!
+//! The controller only has a LUT for the white and one for the black pixels of the new data,
+//! both 7 groups of 6 bytes. The old data doesn't select a waveform, so a partial refresh only
+//! needs the new data of the window.
+
+/// Power setting for the full refresh: VDS_EN/VDG_EN, VGH/VGL (gate), VDH (source), VDL (source)
+pub(crate) const POWER_SETTING_FULL: [u8; 4] = [0x03, 0x00, 0x2B, 0x2B];
+
+/// Power setting for the partial LUTs, with lower source voltages for the short waveform
+pub(crate) const POWER_SETTING_QUICK: [u8; 4] = [0x03, 0x00, 0x26, 0x26];
+
+#[rustfmt::skip]
+pub(crate) const LUT_WHITE_FULL: [u8; 42] = [
+    0x60, 0x5A, 0x5A, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+#[rustfmt::skip]
+pub(crate) const LUT_BLACK_FULL: [u8; 42] = [
+    0x90, 0x5A, 0x5A, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+#[rustfmt::skip]
+pub(crate) const LUT_WHITE_QUICK: [u8; 42] = [
+    0x60, 0x01, 0x01, 0x00, 0x00, 0x01,
+    0x80, 0x0F, 0x00, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+#[rustfmt::skip]
+pub(crate) const LUT_BLACK_QUICK: [u8; 42] = [
+    0x90, 0x01, 0x01, 0x00, 0x00, 0x01,
+    0x40, 0x0F, 0x00, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
@@ -0,0 +1,480 @@
+//! A simple Driver for the Waveshare 1.02" E-Ink Display (GDEW0102T4) via SPI
+//!
+//! The tiny 80x128 panel uses an UC8175 like controller. It shares the data transmission
+//! commands with the UC8176 of the [4.2" V2](crate::epd4in2_v2), but has its own power registers
+//! and only two LUTs, one for the white and one for the black pixels of the new data. The full
+//! and the quick refresh both use LUTs from the registers.
+//!
+//! Partial windows aren't supported: the waveform of the vendor driver for them hasn't been
+//! checked on a panel, so [update_partial_frame()](Epd1in02::update_partial_frame()) returns
+//! [Error::PartialUpdateUnsupported].
+//!
+//! # References
+//!
+//! - [Waveshare wiki](https://www.waveshare.com/wiki/1.02inch_e-Paper_Module)
+//! - [Waveshare C driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_1in02d.c)
+
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::*,
+};
+
+use crate::check_buffer_len;
+use crate::color::Color;
+use crate::error::Error;
+use crate::interface::DisplayInterface;
+use crate::power::{PowerState, Transition};
+use crate::timing::ResetTiming;
+use crate::traits::{InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::{AutoCs, CsStrategy, ResetPin};
+
+pub(crate) mod command;
+use self::command::Command;
+
+//The Lookup Tables for the Display
+mod constants;
+use crate::epd1in02::constants::*;
+
+#[cfg(feature = "graphics")]
+mod graphics;
+#[cfg(feature = "graphics")]
+pub use self::graphics::Display1in02;
+
+/// Width of the display
+pub const WIDTH: u32 = 80;
+/// Height of the display
+pub const HEIGHT: u32 = 128;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+const IS_BUSY_LOW: bool = true;
+/// Longest time in milliseconds to wait for the busy pin before giving up with
+/// [Error::Timeout]
+pub const BUSY_TIMEOUT_MS: u32 = 10_000;
+/// Fastest SPI clock of the controller in Hz, see
+/// [check_spi_frequency()](crate::check_spi_frequency())
+pub const MAX_SPI_FREQ_HZ: u32 = 10_000_000;
+
+/// Epd1in02 driver
+///
+pub struct Epd1in02<SPI, CS, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+    /// Background Color
+    color: Color,
+    /// Refresh LUT
+    refresh: RefreshLut,
+    /// Mode of the controller
+    state: PowerState,
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd1in02<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        // reset the device
+        self.state = self.state.check(Transition::Init)?;
+        self.interface.reset(delay, 10, 10)?;
+
+        self.cmd_with_data(spi, Command::PowerOptimization, &[0x3F])?;
+
+        // LUT from register, booster on
+        self.cmd_with_data(spi, Command::PanelSetting, &[0x6F])?;
+
+        // the driving voltages have to be set before the power is switched on
+        self.set_driving_voltages(spi)?;
+
+        self.cmd_with_data(spi, Command::ChargePumpSetting, &[0x3F])?;
+
+        // XON and the LUT options
+        self.cmd_with_data(spi, Command::LutOption, &[0x00, 0x00])?;
+
+        // 50Hz
+        self.cmd_with_data(spi, Command::PllControl, &[0x13])?;
+
+        self.cmd_with_data(spi, Command::TconSetting, &[0x22])?;
+
+        self.cmd_with_data(
+            spi,
+            Command::ResolutionSetting,
+            &[WIDTH as u8, HEIGHT as u8],
+        )?;
+
+        // -1V
+        self.cmd_with_data(spi, Command::VcmDcSetting, &[0x12])?;
+
+        self.cmd_with_data(spi, Command::PowerSaving, &[0x33])?;
+
+        self.load_lut(spi)?;
+
+        // power on
+        self.command(spi, Command::PowerOn)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        Ok(())
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd1in02<SPI, AutoCs, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Creates the driver for a board without a CS GPIO, see [AutoCs]
+    pub fn new_without_cs(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new(spi, AutoCs, busy, dc, rst, delay)
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd1in02<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    type DisplayColor = Color;
+    fn new(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_background(spi, cs, busy, dc, rst, delay, DEFAULT_BACKGROUND_COLOR)
+    }
+
+    fn new_with_background(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        color: Self::DisplayColor,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(cs, busy, dc, rst);
+
+        let mut epd = Epd1in02 {
+            interface,
+            color,
+            refresh: RefreshLut::Full,
+            state: PowerState::Active,
+        };
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.check_reset_pin()?;
+        self.init(spi, delay)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        let next = self.state.check(Transition::Sleep)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        // border floating
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0xF7])?;
+        self.command(spi, Command::PowerOff)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
+        self.state = next;
+        Ok(())
+    }
+
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer, WIDTH, HEIGHT)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        let color_value = self.color.get_byte_value();
+
+        self.command(spi, Command::DataStartTransmission1)?;
+        self.interface
+            .data_x_times(spi, color_value, WIDTH / 8 * HEIGHT)?;
+
+        self.cmd_with_data(spi, Command::DataStartTransmission2, buffer)
+    }
+
+    /// Partial windows aren't supported, returns [Error::PartialUpdateUnsupported]
+    fn update_partial_frame(
+        &mut self,
+        _spi: &mut SPI,
+        _buffer: &[u8],
+        _x: u32,
+        _y: u32,
+        _width: u32,
+        _height: u32,
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        Err(Error::PartialUpdateUnsupported)
+    }
+
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Refresh)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.interface.start_measurement(self.refresh.into());
+        self.command(spi, Command::DisplayRefresh)?;
+        Ok(())
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+
+        let color_value = self.color.get_byte_value();
+
+        self.command(spi, Command::DataStartTransmission1)?;
+        self.interface
+            .data_x_times(spi, color_value, WIDTH / 8 * HEIGHT)?;
+
+        self.command(spi, Command::DataStartTransmission2)?;
+        self.interface
+            .data_x_times(spi, color_value, WIDTH / 8 * HEIGHT)
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn full_refresh_ms(&self) -> u32 {
+        2000
+    }
+
+    fn quick_refresh_ms(&self) -> u32 {
+        500
+    }
+
+    /// Switches between the LUTs of the full and the partial (`Quick`) refresh
+    ///
+    /// The driving voltages and the border setting are written together with the LUTs.
+    fn set_lut(
+        &mut self,
+        spi: &mut SPI,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Update)?;
+        if let Some(RefreshLut::Custom(_)) = refresh_rate {
+            return Err(Error::UnsupportedLut);
+        }
+        if let Some(refresh_lut) = refresh_rate {
+            self.refresh = refresh_lut;
+        }
+        self.wait_until_idle();
+        self.set_driving_voltages(spi)?;
+        self.load_lut(spi)
+    }
+
+    fn is_busy(&self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
+    fn send_command_raw(&mut self, spi: &mut SPI, command: u8) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn send_data_raw(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        self.interface.data(spi, data)
+    }
+
+    fn set_spi_chunk_size(&mut self, chunk_size: usize) {
+        self.interface.set_chunk_size(chunk_size);
+    }
+
+    fn set_reset_timing(&mut self, timing: Option<ResetTiming>) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    #[cfg(feature = "test-patterns")]
+    fn set_test_pattern(&mut self, enable: bool) {
+        let pattern = crate::test_pattern::FillPattern::new(self.width(), 1);
+        self.interface.set_fill_pattern(enable.then_some(pattern));
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> Epd1in02<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Current mode of the controller
+    pub fn power_state(&self) -> PowerState {
+        self.state
+    }
+
+    /// Power setting with the gate (VGH/VGL) and source (VDH/VDL) driving voltages of the
+    /// current LUT
+    fn set_driving_voltages(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        let voltages = match self.refresh {
+            RefreshLut::Full => &POWER_SETTING_FULL,
+            RefreshLut::Quick => &POWER_SETTING_QUICK,
+            RefreshLut::Custom(_) => return Err(Error::UnsupportedLut),
+        };
+        self.cmd_with_data(spi, Command::PowerSetting, voltages)
+    }
+
+    /// Border and data interval setting and the LUT registers
+    fn load_lut(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        let (interval, white, black) = match self.refresh {
+            // white border
+            RefreshLut::Full => (0x57, &LUT_WHITE_FULL, &LUT_BLACK_FULL),
+            // floating border, so the partial refresh doesn't flash it
+            RefreshLut::Quick => (0xF2, &LUT_WHITE_QUICK, &LUT_BLACK_QUICK),
+            RefreshLut::Custom(_) => return Err(Error::UnsupportedLut),
+        };
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[interval])?;
+        self.cmd_with_data(spi, Command::LutWhite, white)?;
+        self.cmd_with_data(spi, Command::LutBlack, black)
+    }
+
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    fn wait_until_idle(&mut self) {
+        self.interface.wait_until_idle(IS_BUSY_LOW);
+    }
+
+    fn wait_until_idle_with_timeout(&mut self, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .wait_until_idle_with_timeout(delay, IS_BUSY_LOW, BUSY_TIMEOUT_MS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{cmd, test_epd, DcPin, HighPin, NoDelay, RecordingSpi};
+
+    type TestEpd = Epd1in02<RecordingSpi, HighPin, HighPin, DcPin, HighPin, NoDelay>;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 80);
+        assert_eq!(HEIGHT, 128);
+        assert_eq!(crate::buffer_len(WIDTH as usize, HEIGHT as usize), 1280);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+    }
+
+    #[test]
+    fn init_sequence() {
+        let (spi, epd) = test_epd::<TestEpd, _>();
+        let expected = [
+            cmd(0xD2, &[0x3F]),
+            cmd(0x00, &[0x6F]),
+            cmd(0x01, &POWER_SETTING_FULL),
+            cmd(0x06, &[0x3F]),
+            cmd(0x2A, &[0x00, 0x00]),
+            cmd(0x30, &[0x13]),
+            cmd(0x60, &[0x22]),
+            cmd(0x61, &[0x50, 0x80]),
+            cmd(0x82, &[0x12]),
+            cmd(0xE3, &[0x33]),
+            cmd(0x50, &[0x57]),
+            cmd(0x23, &LUT_WHITE_FULL),
+            cmd(0x24, &LUT_BLACK_FULL),
+            cmd(0x04, &[]),
+        ]
+        .concat();
+        assert_eq!(spi.sent, expected);
+        assert_eq!(epd.power_state(), PowerState::Active);
+    }
+
+    #[test]
+    fn quick_lut_switches_voltages_and_border() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+
+        spi.sent.clear();
+        epd.set_lut(&mut spi, Some(RefreshLut::Quick)).unwrap();
+        let expected = [
+            cmd(0x01, &POWER_SETTING_QUICK),
+            cmd(0x50, &[0xF2]),
+            cmd(0x23, &LUT_WHITE_QUICK),
+            cmd(0x24, &LUT_BLACK_QUICK),
+        ]
+        .concat();
+        assert_eq!(spi.sent, expected);
+    }
+
+    #[test]
+    fn partial_frames_are_rejected() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+
+        assert_eq!(
+            epd.update_partial_frame(&mut spi, &[0xAA, 0x55], 72, 126, 8, 2, &mut NoDelay),
+            Err(Error::PartialUpdateUnsupported)
+        );
+        assert!(spi.sent.is_empty());
+        assert_eq!(epd.power_state(), PowerState::Active);
+    }
+
+    #[test]
+    fn raw_commands_and_data_are_sent_unchanged() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+
+        epd.send_command_raw(&mut spi, 0x40).unwrap();
+        epd.send_data_raw(&mut spi, &[0x01, 0x02]).unwrap();
+        assert_eq!(spi.sent, cmd(0x40, &[0x01, 0x02]));
+    }
+}
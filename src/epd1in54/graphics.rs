@@ -1,14 +1,26 @@
 //! Embedded graphics display module for 1in54 display
 //!
+use crate::color::Color;
 use crate::epd1in54::{DEFAULT_BACKGROUND_COLOR, HEIGHT, WIDTH};
 use crate::graphics::{Display, DisplayRotation};
+use crate::traits::FrameBuffer;
 use embedded_graphics_core::pixelcolor::BinaryColor;
 use embedded_graphics_core::prelude::*;
+use embedded_graphics_core::primitives::Rectangle;
 
 /// Full size buffer for use with the 1in54 EPD
 ///
 /// Can also be manuall constructed:
 /// `buffer: [DEFAULT_BACKGROUND_COLOR.get_byte_value(); WIDTH / 8 * HEIGHT]`
+///
+/// The drawing coordinates follow the rotation, the buffer layout always stays the one of the
+/// unrotated panel:
+///
+///```rust
+///# use epd_waveshare::{epd1in54::Display1in54, graphics::{Display, DisplayRotation}};
+///let mut display = Display1in54::with_rotation(DisplayRotation::Rotate90);
+///display.set_rotation(DisplayRotation::Rotate180);
+///```
 pub struct Display1in54 {
     buffer: [u8; WIDTH as usize * HEIGHT as usize / 8],
     rotation: DisplayRotation,
@@ -37,11 +49,18 @@ impl DrawTarget for Display1in54 {
         }
         Ok(())
     }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.fill_solid_helper(WIDTH, HEIGHT, area, color)
+    }
 }
 
 impl OriginDimensions for Display1in54 {
     fn size(&self) -> Size {
-        Size::new(WIDTH, HEIGHT)
+        match self.rotation {
+            DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => Size::new(WIDTH, HEIGHT),
+            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => Size::new(HEIGHT, WIDTH),
+        }
     }
 }
 
@@ -63,6 +82,22 @@ impl Display for Display1in54 {
     }
 }
 
+impl FrameBuffer for Display1in54 {
+    type Color = Color;
+
+    fn as_bytes(&self) -> &[u8] {
+        self.buffer()
+    }
+
+    fn as_bytes_mut(&mut self) -> &mut [u8] {
+        self.get_mut_buffer()
+    }
+
+    fn clear(&mut self, color: Color) {
+        self.clear_buffer(color);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
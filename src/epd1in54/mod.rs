@@ -4,7 +4,7 @@
 //!
 //!```rust, no_run
 //!# use embedded_hal_mock::*;
-//!# fn main() -> Result<(), MockError> {
+//!# fn main() -> Result<(), epd_waveshare::error::Error<MockError>> {
 //!use embedded_graphics::{
 //!    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyleBuilder},
 //!};
@@ -48,26 +48,42 @@
 pub const WIDTH: u32 = 200;
 /// Height of the display
 pub const HEIGHT: u32 = 200;
+/// Limits of the partial window registers: 6 bit x byte address and 9 bit y address, the whole panel fits
+pub const WINDOW_LIMITS: WindowLimits = WindowLimits::new(WIDTH, HEIGHT, 511, 511);
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
 //const DPI: u16 = 184;
 const IS_BUSY_LOW: bool = false;
+/// Longest time in milliseconds to wait for the busy pin before giving up with
+/// [Error::Timeout]
+pub const BUSY_TIMEOUT_MS: u32 = 10_000;
+/// Fastest SPI clock of the controller in Hz, see
+/// [check_spi_frequency()](crate::check_spi_frequency())
+pub const MAX_SPI_FREQ_HZ: u32 = 20_000_000;
 
 use embedded_hal::{
     blocking::{delay::*, spi::Write},
     digital::v2::*,
 };
 
+use crate::error::Error;
 use crate::type_a::{
     command::Command,
     constants::{LUT_FULL_UPDATE, LUT_PARTIAL_UPDATE},
+    Mirror,
 };
+use crate::window::WindowLimits;
+use crate::{buffer_len, check_buffer_len};
+use crate::{AutoCs, CsStrategy, ResetPin};
 
 use crate::color::Color;
 
-use crate::traits::{RefreshLut, WaveshareDisplay};
+use crate::timing::{Clock, Operation, ResetTiming, Timings};
+
+use crate::traits::{QuickRefresh, RefreshLut, WaveshareDisplay};
 
 use crate::interface::DisplayInterface;
+use crate::power::{PowerState, Transition};
 
 #[cfg(feature = "graphics")]
 pub mod graphics;
@@ -82,19 +98,29 @@ pub struct Epd1in54<SPI, CS, BUSY, DC, RST, DELAY> {
     background_color: Color,
     /// Refresh LUT
     refresh: RefreshLut,
+    /// Mode of the controller
+    state: PowerState,
+    /// Mirroring of the frames
+    mirror: Mirror,
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> Epd1in54<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.reset(delay, 10, 10);
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state = self.state.check(Transition::Init)?;
+        if !self.interface.reset(delay, 10, 10)? {
+            // no reset pin
+            self.wait_until_idle_with_timeout(delay)?;
+            self.interface.cmd(spi, Command::SwReset)?;
+            self.wait_until_idle_with_timeout(delay)?;
+        }
 
         // 3 Databytes:
         // A[7:0]
@@ -127,26 +153,43 @@ where
         self.interface
             .cmd_with_data(spi, Command::SetGateLineWidth, &[0x08])?;
 
-        // One Databyte with default value 0x03
-        //  -> address: x increment, y increment, address counter is updated in x direction
-        self.interface
-            .cmd_with_data(spi, Command::DataEntryModeSetting, &[0x03])?;
+        self.set_data_entry_mode(spi)?;
 
         self.set_lut(spi, None)?;
 
-        self.wait_until_idle();
+        self.wait_until_idle_with_timeout(delay)?;
         Ok(())
     }
 }
 
+impl<SPI, BUSY, DC, RST, E, DELAY> Epd1in54<SPI, AutoCs, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8, Error = E>,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Creates the driver for a board without a CS GPIO, see [AutoCs]
+    pub fn new_without_cs(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new(spi, AutoCs, busy, dc, rst, delay)
+    }
+}
+
 impl<SPI, CS, BUSY, DC, RST, E, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
     for Epd1in54<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8, Error = E>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
     type DisplayColor = Color;
@@ -158,6 +201,14 @@ where
         HEIGHT
     }
 
+    fn full_refresh_ms(&self) -> u32 {
+        2000
+    }
+
+    fn quick_refresh_ms(&self) -> u32 {
+        300
+    }
+
     fn new(
         spi: &mut SPI,
         cs: CS,
@@ -165,13 +216,27 @@ where
         dc: DC,
         rst: RST,
         delay: &mut DELAY,
-    ) -> Result<Self, SPI::Error> {
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_background(spi, cs, busy, dc, rst, delay, DEFAULT_BACKGROUND_COLOR)
+    }
+
+    fn new_with_background(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        color: Self::DisplayColor,
+    ) -> Result<Self, Error<SPI::Error>> {
         let interface = DisplayInterface::new(cs, busy, dc, rst);
 
         let mut epd = Epd1in54 {
             interface,
-            background_color: DEFAULT_BACKGROUND_COLOR,
+            background_color: color,
             refresh: RefreshLut::Full,
+            state: PowerState::Active,
+            mirror: Mirror::default(),
         };
 
         epd.init(spi, delay)?;
@@ -179,16 +244,19 @@ where
         Ok(epd)
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.check_reset_pin()?;
         self.init(spi, delay)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        let next = self.state.check(Transition::Sleep)?;
+        self.wait_until_idle_with_timeout(delay)?;
         // 0x00 for Normal mode (Power on Reset), 0x01 for Deep Sleep Mode
         //TODO: is 0x00 needed here or would 0x01 be even more efficient?
         self.interface
             .cmd_with_data(spi, Command::DeepSleepMode, &[0x00])?;
+        self.state = next;
         Ok(())
     }
 
@@ -196,16 +264,16 @@ where
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
-        _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer, WIDTH, HEIGHT)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
         self.use_full_frame(spi)?;
-        self.interface
-            .cmd_with_data(spi, Command::WriteRam, buffer)?;
+        self.write_ram(spi, Command::WriteRam, buffer)?;
         Ok(())
     }
 
-    //TODO: update description: last 3 bits will be ignored for width and x_pos
     fn update_partial_frame(
         &mut self,
         spi: &mut SPI,
@@ -214,28 +282,34 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
-        self.set_ram_area(spi, x, y, x + width, y + height)?;
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        WINDOW_LIMITS.validate_aligned(x, y, width, height)?;
+        check_buffer_len(buffer, width, height)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)?;
         self.set_ram_counter(spi, x, y)?;
 
-        self.interface
-            .cmd_with_data(spi, Command::WriteRam, buffer)?;
+        self.write_ram(spi, Command::WriteRam, buffer)?;
         Ok(())
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Refresh)?;
+        self.wait_until_idle_with_timeout(delay)?;
         // enable clock signal, enable cp, display pattern -> 0xC4 (tested with the arduino version)
         //TODO: test control_1 or control_2 with default value 0xFF (from the datasheet)
         self.interface
             .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xC4])?;
 
+        self.interface.start_measurement(self.refresh.into());
         self.interface.cmd(spi, Command::MasterActivation)?;
         // MASTER Activation should not be interupted to avoid currption of panel images
         // therefore a terminate command is send
         self.interface.cmd(spi, Command::Nop)?;
-        Ok(())
+        self.interface
+            .measure_busy_period(delay, IS_BUSY_LOW, BUSY_TIMEOUT_MS)
     }
 
     fn update_and_display_frame(
@@ -243,15 +317,17 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
         self.use_full_frame(spi)?;
+        self.interface.start_measurement(Operation::Clear);
 
         // clear the ram with the background color
         let color = self.background_color.get_byte_value();
@@ -259,7 +335,8 @@ where
         self.interface.cmd(spi, Command::WriteRam)?;
         self.interface
             .data_x_times(spi, color, WIDTH / 8 * HEIGHT)?;
-        Ok(())
+        self.interface
+            .measure_busy_period(delay, IS_BUSY_LOW, BUSY_TIMEOUT_MS)
     }
 
     fn set_background_color(&mut self, background_color: Color) {
@@ -274,35 +351,137 @@ where
         &mut self,
         spi: &mut SPI,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
-        if let Some(refresh_lut) = refresh_rate {
-            self.refresh = refresh_lut;
-        }
-        match self.refresh {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Update)?;
+        let refresh = refresh_rate.unwrap_or(self.refresh);
+        match refresh {
             RefreshLut::Full => self.set_lut_helper(spi, &LUT_FULL_UPDATE),
             RefreshLut::Quick => self.set_lut_helper(spi, &LUT_PARTIAL_UPDATE),
-        }
+            RefreshLut::Custom(lut) => self.set_lut_helper(spi, lut),
+        }?;
+        self.refresh = refresh;
+        Ok(())
     }
 
     fn is_busy(&self) -> bool {
         self.interface.is_busy(IS_BUSY_LOW)
     }
+
+    fn send_command_raw(&mut self, spi: &mut SPI, command: u8) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn send_data_raw(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        self.interface.data(spi, data)
+    }
+
+    fn set_spi_chunk_size(&mut self, chunk_size: usize) {
+        self.interface.set_chunk_size(chunk_size);
+    }
+
+    fn set_reset_timing(&mut self, timing: Option<ResetTiming>) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    #[cfg(feature = "test-patterns")]
+    fn set_test_pattern(&mut self, enable: bool) {
+        let pattern = crate::test_pattern::FillPattern::new(self.width(), 1);
+        self.interface.set_fill_pattern(enable.then_some(pattern));
+    }
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> Epd1in54<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
     fn wait_until_idle(&mut self) {
         self.interface.wait_until_idle(IS_BUSY_LOW);
     }
 
-    pub(crate) fn use_full_frame(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    fn wait_until_idle_with_timeout(&mut self, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .wait_until_idle_with_timeout(delay, IS_BUSY_LOW, BUSY_TIMEOUT_MS)
+    }
+
+    /// Current mode of the controller
+    pub fn power_state(&self) -> PowerState {
+        self.state
+    }
+
+    /// Sets the clock used to measure the durations of refreshes and clears
+    ///
+    /// The measurements can be read with [last_timings()](Self::last_timings()),
+    /// `None` disables them. While a clock is set, `display_frame` only returns after the
+    /// refresh, so its duration can be measured.
+    pub fn set_clock(&mut self, clock: Option<&'static dyn Clock>) {
+        self.interface.set_clock(clock);
+    }
+
+    /// Last measured durations, measured until the display wasn't busy anymore
+    pub fn last_timings(&self) -> Timings {
+        self.interface.timings()
+    }
+
+    /// Mirrors the following frames horizontally
+    ///
+    /// The X address counter of the data entry mode runs backwards and the bits of every
+    /// frame byte are reversed. The RAM isn't rewritten, the setting applies from the next
+    /// update and is kept over [sleep()](WaveshareDisplay::sleep()) and
+    /// [wake_up()](WaveshareDisplay::wake_up()).
+    ///
+    /// Mirroring happens after the rotation of the graphics buffer, the buffer is drawn with its
+    /// `DisplayRotation` as before and the controller flips the result. Mirroring both axes
+    /// turns the frame by 180° like `DisplayRotation::Rotate180`, without costing CPU time.
+    pub fn set_mirror_horizontal(
+        &mut self,
+        spi: &mut SPI,
+        enable: bool,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.mirror.horizontal = enable;
+        self.set_data_entry_mode(spi)
+    }
+
+    /// Mirrors the following frames vertically
+    ///
+    /// The Y address counter of the data entry mode runs backwards, see
+    /// [set_mirror_horizontal()](Self::set_mirror_horizontal()).
+    pub fn set_mirror_vertical(
+        &mut self,
+        spi: &mut SPI,
+        enable: bool,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.mirror.vertical = enable;
+        self.set_data_entry_mode(spi)
+    }
+
+    fn set_data_entry_mode(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        // the counter is updated in x direction, x and y decrement on the mirrored axes
+        self.interface.cmd_with_data(
+            spi,
+            Command::DataEntryModeSetting,
+            &[self.mirror.data_entry_mode()],
+        )
+    }
+
+    /// Writes frame bytes to the RAM, with reversed bits if mirrored horizontally
+    fn write_ram(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        buffer: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        let mirror = self.mirror;
+        self.interface.cmd(spi, command)?;
+        self.interface
+            .data_mapped(spi, buffer, |byte| mirror.byte(byte))
+    }
+
+    pub(crate) fn use_full_frame(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
         // choose full frame/ram
         self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
 
@@ -310,6 +489,11 @@ where
         self.set_ram_counter(spi, 0, 0)
     }
 
+    /// Selects the RAM window from `start` to `end`, both inclusive like in the registers
+    ///
+    /// A window of a single row or column is valid. The X registers count bytes, so x is
+    /// rounded down to a multiple of 8. A window which ends before it starts or outside of the
+    /// panel returns [Error::InvalidRegion].
     pub(crate) fn set_ram_area(
         &mut self,
         spi: &mut SPI,
@@ -317,10 +501,12 @@ where
         start_y: u32,
         end_x: u32,
         end_y: u32,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
-        assert!(start_x < end_x);
-        assert!(start_y < end_y);
+    ) -> Result<(), Error<SPI::Error>> {
+        if start_x > end_x || start_y > end_y || end_x >= WIDTH || end_y >= HEIGHT {
+            return Err(Error::InvalidRegion);
+        }
+        let (start_x, end_x) = (self.mirror.x(start_x, WIDTH), self.mirror.x(end_x, WIDTH));
+        let (start_y, end_y) = (self.mirror.y(start_y, HEIGHT), self.mirror.y(end_y, HEIGHT));
 
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
@@ -349,8 +535,8 @@ where
         spi: &mut SPI,
         x: u32,
         y: u32,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    ) -> Result<(), Error<SPI::Error>> {
+        let (x, y) = (self.mirror.x(x, WIDTH), self.mirror.y(y, HEIGHT));
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
         self.interface
@@ -365,19 +551,182 @@ where
         Ok(())
     }
 
-    fn set_lut_helper(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), SPI::Error> {
+    /// Writes the LUT register, also used for custom LUTs
+    ///
+    /// Returns [Error::BufferSize] without sending anything if the LUT doesn't have 30 bytes.
+    fn set_lut_helper(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Error<SPI::Error>> {
+        if buffer.len() != 30 {
+            return Err(Error::BufferSize {
+                expected: 30,
+                got: buffer.len(),
+            });
+        }
         self.wait_until_idle();
-        assert!(buffer.len() == 30);
-
         self.interface
             .cmd_with_data(spi, Command::WriteLutRegister, buffer)?;
         Ok(())
     }
 }
 
+impl<SPI, CS, BUSY, DC, RST, DELAY> QuickRefresh<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd1in54<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Writes the frame the panel currently shows into the old RAM (`WriteRam2`)
+    fn update_old_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer, WIDTH, HEIGHT)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.use_full_frame(spi)?;
+        self.write_ram(spi, Command::WriteRam2, buffer)
+    }
+
+    /// Writes the next frame into the new RAM, the same as
+    /// [update_frame()](WaveshareDisplay::update_frame())
+    fn update_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)
+    }
+
+    /// Refreshes the panel with the quick LUT, which only drives the pixels that differ
+    /// between the old and the new RAM
+    ///
+    /// The quick LUT stays selected afterwards.
+    fn display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        if self.refresh != RefreshLut::Quick {
+            self.set_lut(spi, Some(RefreshLut::Quick))?;
+        }
+        self.display_frame(spi, delay)
+    }
+
+    fn update_and_display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_new_frame(spi, buffer, delay)?;
+        self.display_new_frame(spi, delay)
+    }
+
+    fn update_partial_old_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        WINDOW_LIMITS.validate_aligned(x, y, width, height)?;
+        check_buffer_len(buffer, width, height)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)?;
+        self.set_ram_counter(spi, x, y)?;
+        self.write_ram(spi, Command::WriteRam2, buffer)
+    }
+
+    fn update_partial_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_partial_frame(spi, buffer, x, y, width, height, delay)
+    }
+
+    /// Fills the window of the new RAM with the background color
+    fn clear_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        WINDOW_LIMITS.validate_aligned(x, y, width, height)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)?;
+        self.set_ram_counter(spi, x, y)?;
+
+        let color = self.background_color.get_byte_value();
+        self.interface.cmd(spi, Command::WriteRam)?;
+        self.interface.data_x_times(
+            spi,
+            color,
+            buffer_len(width as usize, height as usize) as u32,
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::{cmd, test_epd, DcPin, LowPin, NoDelay, RecordingSpi};
+    use crate::NoReset;
+
+    type TestEpd = Epd1in54<RecordingSpi, LowPin, LowPin, DcPin, LowPin, NoDelay>;
+
+    #[test]
+    fn new_without_cs_sends_the_same_init() {
+        let (spi, _) = test_epd::<TestEpd, _>();
+
+        let (mut spi_without_cs, dc) = RecordingSpi::new();
+        let _epd = Epd1in54::new_without_cs(&mut spi_without_cs, LowPin, dc, LowPin, &mut NoDelay)
+            .unwrap();
+        assert_eq!(spi_without_cs.sent, spi.sent);
+    }
+
+    #[test]
+    fn software_reset_without_reset_pin() {
+        let (spi, _) = test_epd::<TestEpd, _>();
+
+        let (mut spi_without_rst, dc) = RecordingSpi::new();
+        let mut epd = Epd1in54::new(
+            &mut spi_without_rst,
+            LowPin,
+            LowPin,
+            dc,
+            NoReset,
+            &mut NoDelay,
+        )
+        .unwrap();
+        assert_eq!(spi_without_rst.sent[0], (true, 0x12));
+        assert_eq!(spi_without_rst.sent[1..], spi.sent[..]);
+
+        epd.sleep(&mut spi_without_rst, &mut NoDelay).unwrap();
+        assert_eq!(
+            epd.wake_up(&mut spi_without_rst, &mut NoDelay),
+            Err(Error::NoResetPin)
+        );
+    }
 
     #[test]
     fn epd_size() {
@@ -385,4 +734,103 @@ mod tests {
         assert_eq!(HEIGHT, 200);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn old_and_new_frames_go_to_their_rams() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        let frame = [0x0F; WIDTH as usize / 8 * HEIGHT as usize];
+
+        spi.sent.clear();
+        epd.update_old_frame(&mut spi, &frame, &mut NoDelay)
+            .unwrap();
+        assert!(spi.sent.ends_with(&cmd(0x26, &frame)));
+
+        spi.sent.clear();
+        epd.update_and_display_new_frame(&mut spi, &frame, &mut NoDelay)
+            .unwrap();
+        let expected = [
+            cmd(0x24, &frame),
+            cmd(0x32, &LUT_PARTIAL_UPDATE),
+            cmd(0x22, &[0xC4]),
+            cmd(0x20, &[]),
+            cmd(0xFF, &[]),
+        ]
+        .concat();
+        assert!(spi.sent.ends_with(&expected));
+
+        // the quick LUT is only sent once
+        spi.sent.clear();
+        epd.display_new_frame(&mut spi, &mut NoDelay).unwrap();
+        assert_eq!(
+            spi.sent,
+            [cmd(0x22, &[0xC4]), cmd(0x20, &[]), cmd(0xFF, &[])].concat()
+        );
+    }
+
+    #[test]
+    fn partial_old_frame() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+
+        epd.update_partial_old_frame(&mut spi, &[0xAA; 2], 8, 4, 16, 1, &mut NoDelay)
+            .unwrap();
+        assert!(spi.sent.ends_with(&cmd(0x26, &[0xAA; 2])));
+
+        spi.sent.clear();
+        epd.clear_partial_frame(&mut spi, 8, 4, 16, 2, &mut NoDelay)
+            .unwrap();
+        assert!(spi.sent.ends_with(&cmd(0x24, &[0xFF; 4])));
+    }
+
+    #[test]
+    fn partial_windows_end_inclusive() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+
+        // a single row of the second byte column doesn't reach into the third one
+        spi.sent.clear();
+        epd.update_partial_frame(&mut spi, &[0x80], 8, 3, 8, 1, &mut NoDelay)
+            .unwrap();
+        let expected = [cmd(0x44, &[1, 1]), cmd(0x45, &[3, 0, 3, 0])].concat();
+        assert_eq!(&spi.sent[..8], &expected[..]);
+
+        // the last row of the last byte column
+        spi.sent.clear();
+        epd.update_partial_frame(&mut spi, &[0x01], WIDTH - 8, HEIGHT - 1, 8, 1, &mut NoDelay)
+            .unwrap();
+        let expected = [cmd(0x44, &[24, 24]), cmd(0x45, &[199, 0, 199, 0])].concat();
+        assert_eq!(&spi.sent[..8], &expected[..]);
+
+        spi.sent.clear();
+        for (start_x, start_y, end_x, end_y) in [(9, 0, 8, 0), (0, 1, 0, 0), (0, 0, WIDTH, 0)] {
+            assert_eq!(
+                epd.set_ram_area(&mut spi, start_x, start_y, end_x, end_y),
+                Err(Error::InvalidRegion)
+            );
+        }
+        assert!(spi.sent.is_empty());
+    }
+
+    #[test]
+    fn timings_cover_the_busy_periods() {
+        use crate::test_utils::{busy_after, busy_for, BusyPin, TEST_CLOCK};
+        type TimedEpd = Epd1in54<RecordingSpi, BusyPin, BusyPin, DcPin, BusyPin, NoDelay>;
+        busy_for(0);
+        let (mut spi, mut epd) = test_epd::<TimedEpd, _>();
+        epd.set_clock(Some(&TEST_CLOCK));
+
+        // 30 busy reads and the idle one
+        busy_after(Command::MasterActivation as u8, 30);
+        epd.display_frame(&mut spi, &mut NoDelay).unwrap();
+        assert_eq!(epd.last_timings().full_refresh_ms, Some(31));
+
+        epd.set_lut(&mut spi, Some(RefreshLut::Quick)).unwrap();
+        busy_after(Command::MasterActivation as u8, 12);
+        epd.display_frame(&mut spi, &mut NoDelay).unwrap();
+        assert_eq!(epd.last_timings().quick_refresh_ms, Some(13));
+        assert_eq!(epd.last_timings().full_refresh_ms, Some(31));
+
+        busy_after(Command::WriteRam as u8, 7);
+        epd.clear_frame(&mut spi, &mut NoDelay).unwrap();
+        assert_eq!(epd.last_timings().clear_ms, Some(8));
+    }
 }
@@ -0,0 +1,142 @@
+use crate::epd1in54_v2::{HEIGHT, WIDTH};
+use crate::graphics::{DisplayRotation, GrayDisplay};
+use crate::traits::FrameBuffer;
+use embedded_graphics_core::pixelcolor::{Gray2, GrayColor};
+use embedded_graphics_core::prelude::*;
+
+/// Full size buffer with 4 gray levels for use with the 1in54 v2 EPD
+///
+/// Send it with [update_gray_frame()](crate::traits::WaveshareGrayscaleDisplay::update_gray_frame()).
+///
+/// Can also be manually constructed:
+/// `buffer: [0xFF; WIDTH / 4 * HEIGHT]`
+pub struct Display1in54Gray {
+    buffer: [u8; WIDTH as usize * HEIGHT as usize / 4],
+    rotation: DisplayRotation,
+}
+
+impl Default for Display1in54Gray {
+    fn default() -> Self {
+        Display1in54Gray {
+            buffer: [Gray2::WHITE.luma() * 0x55; WIDTH as usize * HEIGHT as usize / 4],
+            rotation: DisplayRotation::default(),
+        }
+    }
+}
+
+impl DrawTarget for Display1in54Gray {
+    type Color = Gray2;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for pixel in pixels {
+            self.draw_helper(WIDTH, HEIGHT, pixel)?;
+        }
+        Ok(())
+    }
+}
+
+impl OriginDimensions for Display1in54Gray {
+    fn size(&self) -> Size {
+        match self.rotation {
+            DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => Size::new(WIDTH, HEIGHT),
+            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => Size::new(HEIGHT, WIDTH),
+        }
+    }
+}
+
+impl GrayDisplay for Display1in54Gray {
+    fn buffer(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    fn get_mut_buffer(&mut self) -> &mut [u8] {
+        &mut self.buffer
+    }
+
+    fn set_rotation(&mut self, rotation: DisplayRotation) {
+        self.rotation = rotation;
+    }
+
+    fn rotation(&self) -> DisplayRotation {
+        self.rotation
+    }
+}
+
+impl FrameBuffer for Display1in54Gray {
+    type Color = Gray2;
+
+    fn as_bytes(&self) -> &[u8] {
+        self.buffer()
+    }
+
+    fn as_bytes_mut(&mut self) -> &mut [u8] {
+        self.get_mut_buffer()
+    }
+
+    fn clear(&mut self, color: Gray2) {
+        self.clear_buffer(color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::GrayPlane;
+    use embedded_graphics::{
+        prelude::*,
+        primitives::{Line, PrimitiveStyle},
+    };
+
+    #[test]
+    fn graphics_size() {
+        let display = Display1in54Gray::default();
+        assert_eq!(display.buffer().len(), 200 * 200 / 4);
+        assert!(display.buffer().iter().all(|&b| b == 0xFF));
+    }
+
+    #[test]
+    fn graphics_rotation_0() {
+        let mut display = Display1in54Gray::default();
+
+        let _ = Line::new(Point::new(0, 0), Point::new(1, 0))
+            .into_styled(PrimitiveStyle::with_stroke(Gray2::new(1), 1))
+            .draw(&mut display);
+
+        let buffer = display.buffer();
+        assert_eq!(buffer[0], 0b01_01_11_11);
+        assert!(buffer.iter().skip(1).all(|&b| b == 0xFF));
+
+        // dark gray: high bit cleared, low bit set
+        assert_eq!(GrayPlane::High.byte(&buffer[..2]), 0b0011_1111);
+        assert_eq!(GrayPlane::Low.byte(&buffer[..2]), 0xFF);
+    }
+
+    #[test]
+    fn graphics_rotation_90() {
+        let mut display = Display1in54Gray::with_rotation(DisplayRotation::Rotate90);
+
+        let _ = Line::new(
+            Point::new(0, WIDTH as i32 - 4),
+            Point::new(0, WIDTH as i32 - 1),
+        )
+        .into_styled(PrimitiveStyle::with_stroke(Gray2::BLACK, 1))
+        .draw(&mut display);
+
+        let buffer = display.buffer();
+        assert_eq!(buffer[0], 0x00);
+        assert!(buffer.iter().skip(1).all(|&b| b == 0xFF));
+    }
+
+    #[test]
+    fn clear_with_each_level() {
+        let mut display = Display1in54Gray::default();
+        for (luma, byte) in [(0, 0x00), (1, 0x55), (2, 0xAA), (3, 0xFF)] {
+            display.clear_buffer(Gray2::new(luma));
+            assert!(display.buffer().iter().all(|&b| b == byte));
+        }
+    }
+}
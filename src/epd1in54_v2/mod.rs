@@ -6,28 +6,78 @@
 pub const WIDTH: u32 = 200;
 /// Height of the display
 pub const HEIGHT: u32 = 200;
+/// Limits of the partial window registers: 6 bit x byte address and 9 bit y address, the whole panel fits
+pub const WINDOW_LIMITS: WindowLimits = WindowLimits::new(WIDTH, HEIGHT, 511, 511);
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
 const IS_BUSY_LOW: bool = false;
+/// Longest time in milliseconds to wait for the busy pin before giving up with
+/// [Error::Timeout]
+pub const BUSY_TIMEOUT_MS: u32 = 10_000;
+/// Fastest SPI clock of the controller in Hz, see
+/// [check_spi_frequency()](crate::check_spi_frequency())
+pub const MAX_SPI_FREQ_HZ: u32 = 20_000_000;
 
 use embedded_hal::{
-    blocking::{delay::*, spi::Write},
+    blocking::{
+        delay::*,
+        spi::{Transfer, Write},
+    },
     digital::v2::*,
 };
 
-use crate::type_a::command::Command;
+use crate::check_buffer_len;
+use crate::error::Error;
+use crate::type_a::{border_waveform, command::Command, vcom_register, Mirror};
+use crate::window::WindowLimits;
+use crate::{AutoCs, CsStrategy, ResetPin};
 
 mod constants;
-use crate::epd1in54_v2::constants::{LUT_FULL_UPDATE, LUT_PARTIAL_UPDATE};
+use crate::epd1in54_v2::constants::{
+    LUT_FULL_UPDATE_COLD, LUT_FULL_UPDATE_HOT, LUT_FULL_UPDATE_NORMAL, LUT_GRAY4,
+    LUT_PARTIAL_UPDATE,
+};
+
+use crate::color::{Color, GrayPlane};
 
-use crate::color::Color;
+use crate::timing::{Clock, Operation, ResetTiming, Timings};
 
-use crate::traits::{RefreshLut, WaveshareDisplay};
+use crate::traits::{BorderColor, RefreshLut, WaveshareDisplay, WaveshareGrayscaleDisplay};
 
 use crate::interface::DisplayInterface;
+use crate::power::{PowerState, Transition};
 
 #[cfg(feature = "graphics")]
 pub use crate::epd1in54::graphics::Display1in54;
+#[cfg(feature = "graphics")]
+mod graphics;
+#[cfg(feature = "graphics")]
+pub use self::graphics::Display1in54Gray;
+
+/// Deep sleep modes of the controller
+///
+/// The controller only wakes up from deep sleep through a hardware reset,
+/// which [wake_up()](WaveshareDisplay::wake_up()) already does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DeepSleepMode {
+    /// Normal mode, the controller is not put to sleep (POR state)
+    Mode0,
+    /// Deep sleep which retains the RAM, so an unchanged frame doesn't need to be sent again
+    #[default]
+    Mode1,
+    /// Deep sleep which doesn't retain the RAM and draws the least current
+    Mode2,
+}
+
+impl DeepSleepMode {
+    fn value(self) -> u8 {
+        match self {
+            DeepSleepMode::Mode0 => 0x00,
+            DeepSleepMode::Mode1 => 0x01,
+            DeepSleepMode::Mode2 => 0x03,
+        }
+    }
+}
 
 /// Epd1in54 driver
 pub struct Epd1in54<SPI, CS, BUSY, DC, RST, DELAY> {
@@ -38,22 +88,43 @@ pub struct Epd1in54<SPI, CS, BUSY, DC, RST, DELAY> {
 
     /// Refresh LUT
     refresh: RefreshLut,
+    /// Mode of the controller
+    state: PowerState,
+    /// Mirroring of the frames
+    mirror: Mirror,
+    /// Partial updates after which the next refresh uses the full LUT
+    partial_refresh_limit: Option<u32>,
+    /// Partial updates since the last refresh with the full LUT
+    partial_refreshes: u32,
+    /// The LUT registers hold the gray LUT instead of the one of `refresh`
+    gray_lut: bool,
+    /// LUT of [RefreshLut::Full], selected by the temperature
+    full_lut: &'static [u8; 159],
+    /// Border color set with `set_border_color()`, `None` for the waveforms of the vendor
+    border: Option<BorderColor>,
+    /// Write shown partial windows into the base map as well
+    sync_base_map: bool,
+    /// VCOM register set with `set_vcom()`, `None` for the value of the LUT
+    vcom: Option<u8>,
+    /// Gate voltage register set with `set_gate_voltage()`, `None` for the value of the LUT
+    gate_voltage: Option<u8>,
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> Epd1in54<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.reset(delay, 10, 10);
-        self.wait_until_idle();
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state = self.state.check(Transition::Init)?;
+        self.interface.reset(delay, 10, 10)?;
+        self.wait_until_idle_with_timeout(delay)?;
         self.interface.cmd(spi, Command::SwReset)?;
-        self.wait_until_idle();
+        self.wait_until_idle_with_timeout(delay)?;
 
         // 3 Databytes:
         // A[7:0]
@@ -66,13 +137,13 @@ where
             &[(HEIGHT - 1) as u8, 0x0, 0x00],
         )?;
 
-        self.interface
-            .cmd_with_data(spi, Command::DataEntryModeSetting, &[0x3])?;
+        self.set_data_entry_mode(spi)?;
 
         self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
 
+        let border = self.border.map_or(0x01, border_waveform);
         self.interface
-            .cmd_with_data(spi, Command::BorderWaveformControl, &[0x1])?;
+            .cmd_with_data(spi, Command::BorderWaveformControl, &[border])?;
 
         self.interface.cmd_with_data(
             spi,
@@ -85,19 +156,39 @@ where
 
         self.set_ram_counter(spi, 0, 0)?;
 
-        self.wait_until_idle();
+        self.wait_until_idle_with_timeout(delay)?;
         Ok(())
     }
 }
 
+impl<SPI, BUSY, DC, RST, E, DELAY> Epd1in54<SPI, AutoCs, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8, Error = E>,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Creates the driver for a board without a CS GPIO, see [AutoCs]
+    pub fn new_without_cs(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new(spi, AutoCs, busy, dc, rst, delay)
+    }
+}
+
 impl<SPI, CS, BUSY, DC, RST, E, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
     for Epd1in54<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8, Error = E>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
     type DisplayColor = Color;
@@ -109,6 +200,14 @@ where
         HEIGHT
     }
 
+    fn full_refresh_ms(&self) -> u32 {
+        2000
+    }
+
+    fn quick_refresh_ms(&self) -> u32 {
+        300
+    }
+
     fn new(
         spi: &mut SPI,
         cs: CS,
@@ -116,13 +215,35 @@ where
         dc: DC,
         rst: RST,
         delay: &mut DELAY,
-    ) -> Result<Self, SPI::Error> {
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_background(spi, cs, busy, dc, rst, delay, DEFAULT_BACKGROUND_COLOR)
+    }
+
+    fn new_with_background(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        color: Self::DisplayColor,
+    ) -> Result<Self, Error<SPI::Error>> {
         let interface = DisplayInterface::new(cs, busy, dc, rst);
 
         let mut epd = Epd1in54 {
             interface,
-            background_color: DEFAULT_BACKGROUND_COLOR,
+            background_color: color,
             refresh: RefreshLut::Full,
+            state: PowerState::Active,
+            mirror: Mirror::default(),
+            partial_refresh_limit: None,
+            partial_refreshes: 0,
+            gray_lut: false,
+            full_lut: &LUT_FULL_UPDATE_NORMAL,
+            border: None,
+            sync_base_map: false,
+            vcom: None,
+            gate_voltage: None,
         };
 
         epd.init(spi, delay)?;
@@ -130,33 +251,30 @@ where
         Ok(epd)
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.check_reset_pin()?;
         self.init(spi, delay)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
-        // 0x00 for Normal mode (Power on Reset), 0x01 for Deep Sleep Mode
-        //TODO: is 0x00 needed here or would 0x01 be even more efficient?
-        self.interface
-            .cmd_with_data(spi, Command::DeepSleepMode, &[0x00])?;
-        Ok(())
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.sleep_mode(spi, delay, DeepSleepMode::default())
     }
 
     fn update_frame(
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
-        _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer, WIDTH, HEIGHT)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.leave_gray_mode(spi)?;
         self.use_full_frame(spi)?;
-        self.interface
-            .cmd_with_data(spi, Command::WriteRam, buffer)?;
+        self.write_ram(spi, Command::WriteRam, buffer)?;
         Ok(())
     }
 
-    //TODO: update description: last 3 bits will be ignored for width and x_pos
     fn update_partial_frame(
         &mut self,
         spi: &mut SPI,
@@ -165,30 +283,58 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
-        self.set_ram_area(spi, x, y, x + width, y + height)?;
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        WINDOW_LIMITS.validate_aligned(x, y, width, height)?;
+        check_buffer_len(buffer, width, height)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.leave_gray_mode(spi)?;
+        self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)?;
         self.set_ram_counter(spi, x, y)?;
 
-        self.interface
-            .cmd_with_data(spi, Command::WriteRam, buffer)?;
+        self.write_ram(spi, Command::WriteRam, buffer)?;
+        self.partial_refreshes = self.partial_refreshes.saturating_add(1);
         Ok(())
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
-        if self.refresh == RefreshLut::Full {
-            self.interface
-                .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xC7])?;
-        } else if self.refresh == RefreshLut::Quick {
+    /// Refreshes the display with the current LUT
+    ///
+    /// If the [partial refresh limit](Epd1in54::set_partial_refresh_limit()) is reached, the
+    /// full LUT is used for this refresh instead. The current LUT is loaded again afterwards,
+    /// so this call then waits until the full refresh is finished.
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Refresh)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        let forced_full = self.full_refresh_due();
+        let refresh = self.refresh;
+        if forced_full {
+            self.set_lut(spi, Some(RefreshLut::Full))?;
+        }
+
+        // custom LUTs are shown with display mode 1 like the full LUT
+        if self.refresh == RefreshLut::Quick && !self.gray_lut {
             self.interface
                 .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xCF])?;
+        } else {
+            self.interface
+                .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xC7])?;
         }
 
+        self.interface.start_measurement(self.refresh.into());
         self.interface.cmd(spi, Command::MasterActivation)?;
         // MASTER Activation should not be interupted to avoid currption of panel images
         // therefore a terminate command is send
         self.interface.cmd(spi, Command::Nop)?;
+        self.interface
+            .measure_busy_period(delay, IS_BUSY_LOW, BUSY_TIMEOUT_MS)?;
+
+        if self.refresh == RefreshLut::Full {
+            self.partial_refreshes = 0;
+        }
+        if forced_full {
+            self.set_lut(spi, Some(refresh))?;
+        }
         Ok(())
     }
 
@@ -197,15 +343,17 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
         self.use_full_frame(spi)?;
+        self.interface.start_measurement(Operation::Clear);
 
         // clear the ram with the background color
         let color = self.background_color.get_byte_value();
@@ -216,7 +364,8 @@ where
         self.interface.cmd(spi, Command::WriteRam2)?;
         self.interface
             .data_x_times(spi, color, WIDTH / 8 * HEIGHT)?;
-        Ok(())
+        self.interface
+            .measure_busy_period(delay, IS_BUSY_LOW, BUSY_TIMEOUT_MS)
     }
 
     fn set_background_color(&mut self, background_color: Color) {
@@ -231,14 +380,16 @@ where
         &mut self,
         spi: &mut SPI,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
-        if let Some(refresh_lut) = refresh_rate {
-            self.refresh = refresh_lut;
-        }
-        match self.refresh {
-            RefreshLut::Full => self.set_lut_helper(spi, &LUT_FULL_UPDATE),
+    ) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Update)?;
+        let refresh = refresh_rate.unwrap_or(self.refresh);
+        match refresh {
+            RefreshLut::Full => self.set_lut_helper(spi, self.full_lut),
             RefreshLut::Quick => self.set_lut_helper(spi, &LUT_PARTIAL_UPDATE),
+            RefreshLut::Custom(lut) => self.set_lut_helper(spi, lut),
         }?;
+        self.refresh = refresh;
+        self.gray_lut = false;
 
         // Additional configuration required only for partial updates
         if self.refresh == RefreshLut::Quick {
@@ -247,8 +398,9 @@ where
                 Command::WriteOtpSelection,
                 &[0x0, 0x0, 0x0, 0x0, 0x0, 0x40, 0x0, 0x0, 0x0, 0x0],
             )?;
+            let border = self.border.map_or(0x80, border_waveform);
             self.interface
-                .cmd_with_data(spi, Command::BorderWaveformControl, &[0x80])?;
+                .cmd_with_data(spi, Command::BorderWaveformControl, &[border])?;
             self.interface
                 .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xc0])?;
             self.interface.cmd(spi, Command::MasterActivation)?;
@@ -262,22 +414,386 @@ where
     fn is_busy(&self) -> bool {
         self.interface.is_busy(IS_BUSY_LOW)
     }
+
+    fn send_command_raw(&mut self, spi: &mut SPI, command: u8) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn send_data_raw(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        self.interface.data(spi, data)
+    }
+
+    fn set_spi_chunk_size(&mut self, chunk_size: usize) {
+        self.interface.set_chunk_size(chunk_size);
+    }
+
+    fn set_reset_timing(&mut self, timing: Option<ResetTiming>) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    #[cfg(feature = "test-patterns")]
+    fn set_test_pattern(&mut self, enable: bool) {
+        let pattern = crate::test_pattern::FillPattern::new(self.width(), 1);
+        self.interface.set_fill_pattern(enable.then_some(pattern));
+    }
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> Epd1in54<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
     fn wait_until_idle(&mut self) {
         self.interface.wait_until_idle(IS_BUSY_LOW);
     }
 
-    pub(crate) fn use_full_frame(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    fn wait_until_idle_with_timeout(&mut self, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .wait_until_idle_with_timeout(delay, IS_BUSY_LOW, BUSY_TIMEOUT_MS)
+    }
+
+    /// Puts the display to sleep with the given [DeepSleepMode]
+    ///
+    /// [sleep()](WaveshareDisplay::sleep()) uses [DeepSleepMode::Mode1].
+    pub fn sleep_mode(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        mode: DeepSleepMode,
+    ) -> Result<(), Error<SPI::Error>> {
+        let next = match mode {
+            DeepSleepMode::Mode0 => self.state.check(Transition::Update)?,
+            DeepSleepMode::Mode1 | DeepSleepMode::Mode2 => self.state.check(Transition::Sleep)?,
+        };
+        self.wait_until_idle_with_timeout(delay)?;
+        self.interface
+            .cmd_with_data(spi, Command::DeepSleepMode, &[mode.value()])?;
+        self.state = next;
+        Ok(())
+    }
+
+    /// Sets the color of the border and keeps it for the following refreshes
+    ///
+    /// By default the border follows the waveforms of the vendor: white for the full refresh and
+    /// the VCOM level for the quick refresh.
+    pub fn set_border_color(
+        &mut self,
+        spi: &mut SPI,
+        color: BorderColor,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle();
+        self.interface.cmd_with_data(
+            spi,
+            Command::BorderWaveformControl,
+            &[border_waveform(color)],
+        )?;
+        self.border = Some(color);
+        Ok(())
+    }
+
+    /// Sets VCOM to `mv` millivolts and keeps it for the following LUTs
+    ///
+    /// By default every LUT brings its own VCOM. A slightly more negative VCOM darkens panels
+    /// with faded contrast. The controller takes -200 mV to -3000 mV in steps of 100 mV, other
+    /// values return [Error::InvalidVoltage] without sending anything.
+    pub fn set_vcom(&mut self, spi: &mut SPI, mv: i16) -> Result<(), Error<SPI::Error>> {
+        let vcom = vcom_register(mv).ok_or(Error::InvalidVoltage)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle();
+        self.interface
+            .cmd_with_data(spi, Command::WriteVcomRegister, &[vcom])?;
+        self.vcom = Some(vcom);
+        Ok(())
+    }
+
+    /// Sets the gate driving voltage VGH to `mv` millivolts and keeps it for the following
+    /// LUTs
+    ///
+    /// By default every LUT brings its own, 20 V for the LUTs of the driver. The controller
+    /// takes 12000 mV to 20000 mV in steps of 500 mV, other values return
+    /// [Error::InvalidVoltage] without sending anything.
+    pub fn set_gate_voltage(&mut self, spi: &mut SPI, mv: u16) -> Result<(), Error<SPI::Error>> {
+        if !(12_000..=20_000).contains(&mv) || mv % 500 != 0 {
+            return Err(Error::InvalidVoltage);
+        }
+        let voltage = ((mv - 12_000) / 500 + 0x07) as u8;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle();
+        self.interface
+            .cmd_with_data(spi, Command::GateDrivingVoltage, &[voltage])?;
+        self.gate_voltage = Some(voltage);
+        Ok(())
+    }
+
+    /// Loads a LUT from a buffer which doesn't have to be `'static`, for trying out waveforms
+    ///
+    /// The 159 bytes have the layout of the LUTs of the driver:
+    ///
+    /// | Bytes      | Content                                                 | Command |
+    /// |------------|---------------------------------------------------------|---------|
+    /// | `0..60`    | voltage selection VS, 12 bytes for each of the 5 LUTs   | `0x32`  |
+    /// | `60..144`  | 12 groups of 7 bytes with the phase lengths and repeats | `0x32`  |
+    /// | `144..150` | frame rate of each group pair                           | `0x32`  |
+    /// | `150..153` | gate scan selection XON                                 | `0x32`  |
+    /// | `153`      | end option EOPT                                         | `0x3F`  |
+    /// | `154`      | gate driving voltage VGH                                | `0x03`  |
+    /// | `155..158` | source driving voltages VSH1, VSH2 and VSL              | `0x04`  |
+    /// | `158`      | VCOM                                                    | `0x2C`  |
+    ///
+    /// Values set with [set_vcom()](Epd1in54::set_vcom()) and
+    /// [set_gate_voltage()](Epd1in54::set_gate_voltage()) replace the ones of the LUT. Other
+    /// lengths return [Error::BufferSize] without sending anything.
+    ///
+    /// Refreshes use display mode 1 like [RefreshLut::Full]. The LUT isn't kept by the driver,
+    /// [RefreshLut::Full] is selected afterwards and loaded again by
+    /// [set_lut()](WaveshareDisplay::set_lut()) without a LUT, the partial refresh limit and the
+    /// first black/white frame after a gray frame. Use [RefreshLut::Custom] for a `'static` LUT
+    /// which stays selected.
+    pub fn set_custom_lut(&mut self, spi: &mut SPI, lut: &[u8]) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Update)?;
+        self.set_lut_helper(spi, lut)?;
+        self.refresh = RefreshLut::Full;
+        self.gray_lut = false;
+        Ok(())
+    }
+
+    /// Current mode of the controller
+    pub fn power_state(&self) -> PowerState {
+        self.state
+    }
+
+    /// Forces a refresh with the full LUT after `limit` partial updates
+    ///
+    /// Many partial refreshes in a row leave ghosting on the panel. With a limit, the driver
+    /// counts the calls of [update_partial_frame()](WaveshareDisplay::update_partial_frame())
+    /// and the first [display_frame()](WaveshareDisplay::display_frame()) after the limit is
+    /// reached uses [RefreshLut::Full], even if the quick LUT is selected. Every refresh with
+    /// the full LUT restarts the count. `None` (the default) disables the limit.
+    pub fn set_partial_refresh_limit(&mut self, limit: Option<u32>) {
+        self.partial_refresh_limit = limit;
+    }
+
+    /// Writes a window of the frame and shows it with the quick LUT
+    ///
+    /// The quick LUT is loaded first if another LUT is selected and stays selected afterwards.
+    /// The window is checked like in
+    /// [update_partial_frame()](WaveshareDisplay::update_partial_frame()) before anything is
+    /// sent, and the [partial refresh limit](Self::set_partial_refresh_limit()) still forces a
+    /// full refresh from time to time.
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_and_display_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        WINDOW_LIMITS.validate_aligned(x, y, width, height)?;
+        check_buffer_len(buffer, width, height)?;
+        if self.refresh != RefreshLut::Quick {
+            self.set_lut(spi, Some(RefreshLut::Quick))?;
+        }
+        self.update_partial_frame(spi, buffer, x, y, width, height, delay)?;
+        self.display_frame(spi, delay)?;
+
+        if self.sync_base_map {
+            self.wait_until_idle_with_timeout(delay)?;
+            self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)?;
+            self.set_ram_counter(spi, x, y)?;
+            self.write_ram(spi, Command::WriteRam2, buffer)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a full frame into the base map, the RAM the quick LUT compares the new frame to
+    ///
+    /// Like `EPD_1IN54_V2_DisplayPartBaseImage` of the vendor, this should hold the frame on
+    /// the panel before the first partial refresh, e.g. the buffer of the last full refresh.
+    /// Only [clear_frame()](WaveshareDisplay::clear_frame()) writes the base map otherwise.
+    pub fn set_partial_base_buffer(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer, WIDTH, HEIGHT)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle();
+        self.leave_gray_mode(spi)?;
+        self.use_full_frame(spi)?;
+        self.write_ram(spi, Command::WriteRam2, buffer)
+    }
+
+    /// Lets [update_and_display_partial_frame()](Self::update_and_display_partial_frame())
+    /// write the window into the base map after the refresh
+    ///
+    /// The next partial refresh then starts from the frame on the panel instead of an old base
+    /// map, which avoids the ghosting of repeated partial refreshes. Off by default.
+    pub fn set_base_map_sync(&mut self, enable: bool) {
+        self.sync_base_map = enable;
+    }
+
+    /// Loads the full LUT for the panel temperature `temp_celsius` and selects [RefreshLut::Full]
+    ///
+    /// The particles move slower in the cold and faster in the heat, so the driver has a
+    /// waveform with longer phases below 10 °C and one with shorter phases above 40 °C. The
+    /// temperature can come from [read_temperature()](Self::read_temperature()) or from a
+    /// sensor of the board. The LUT is also used by later calls of
+    /// [set_lut()](WaveshareDisplay::set_lut()) with the full refresh.
+    pub fn set_lut_for_temperature(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        temp_celsius: i8,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.full_lut = full_lut_for_temperature(temp_celsius);
+        self.set_lut(spi, Some(RefreshLut::Full))
+    }
+
+    fn full_refresh_due(&self) -> bool {
+        match self.partial_refresh_limit {
+            Some(limit) => {
+                !self.gray_lut
+                    && self.refresh != RefreshLut::Full
+                    && self.partial_refreshes >= limit
+            }
+            None => false,
+        }
+    }
+
+    /// Sets the clock used to measure the durations of refreshes and clears
+    ///
+    /// The measurements can be read with [last_timings()](Self::last_timings()),
+    /// `None` disables them. While a clock is set, `display_frame` only returns after the
+    /// refresh, so its duration can be measured.
+    pub fn set_clock(&mut self, clock: Option<&'static dyn Clock>) {
+        self.interface.set_clock(clock);
+    }
+
+    /// Last measured durations, measured until the display wasn't busy anymore
+    pub fn last_timings(&self) -> Timings {
+        self.interface.timings()
+    }
+
+    /// Mirrors the following frames horizontally
+    ///
+    /// The X address counter of the data entry mode runs backwards and the bits of every
+    /// frame byte are reversed. The RAM isn't rewritten, the setting applies from the next
+    /// update and is kept over [sleep()](WaveshareDisplay::sleep()) and
+    /// [wake_up()](WaveshareDisplay::wake_up()).
+    ///
+    /// Mirroring happens after the rotation of the graphics buffer, the buffer is drawn with its
+    /// `DisplayRotation` as before and the controller flips the result. Mirroring both axes
+    /// turns the frame by 180° like `DisplayRotation::Rotate180`, without costing CPU time.
+    pub fn set_mirror_horizontal(
+        &mut self,
+        spi: &mut SPI,
+        enable: bool,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.mirror.horizontal = enable;
+        self.set_data_entry_mode(spi)
+    }
+
+    /// Mirrors the following frames vertically
+    ///
+    /// The Y address counter of the data entry mode runs backwards, see
+    /// [set_mirror_horizontal()](Self::set_mirror_horizontal()).
+    pub fn set_mirror_vertical(
+        &mut self,
+        spi: &mut SPI,
+        enable: bool,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.mirror.vertical = enable;
+        self.set_data_entry_mode(spi)
+    }
+
+    fn set_data_entry_mode(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        // the counter is updated in x direction, x and y decrement on the mirrored axes
+        self.interface.cmd_with_data(
+            spi,
+            Command::DataEntryModeSetting,
+            &[self.mirror.data_entry_mode()],
+        )
+    }
+
+    /// Writes frame bytes to the RAM, with reversed bits if mirrored horizontally
+    fn write_ram(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        buffer: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        let mirror = self.mirror;
+        self.interface.cmd(spi, command)?;
+        self.interface
+            .data_mapped(spi, buffer, |byte| mirror.byte(byte))
+    }
+
+    /// Writes a frame with 4 gray levels given as two planes with 1 bit per pixel
+    ///
+    /// `high` holds the high bits of the levels and goes to `WriteRam`, `low` the low bits and
+    /// goes to `WriteRam2`, see [GrayPlane]. Each plane has 5000 bytes like a black/white
+    /// frame. The gray LUT is loaded like by
+    /// [update_gray_frame()](WaveshareGrayscaleDisplay::update_gray_frame()).
+    pub fn update_gray_planes(
+        &mut self,
+        spi: &mut SPI,
+        high: &[u8],
+        low: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(high, WIDTH, HEIGHT)?;
+        check_buffer_len(low, WIDTH, HEIGHT)?;
+        self.enter_gray_mode(spi)?;
+        self.write_ram(spi, Command::WriteRam, high)?;
+        self.write_ram(spi, Command::WriteRam2, low)
+    }
+
+    /// Loads the gray LUT and selects the full RAM for the two planes of a gray frame
+    fn enter_gray_mode(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle();
+        self.set_lut_helper(spi, &LUT_GRAY4)?;
+        self.gray_lut = true;
+        self.use_full_frame(spi)
+    }
+
+    /// Loads the LUT of `refresh` again after a gray frame
+    fn leave_gray_mode(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        if self.gray_lut {
+            self.set_lut(spi, None)?;
+        }
+        Ok(())
+    }
+
+    /// Sends one bit of the gray levels in `buffer` as a RAM plane
+    fn send_gray_plane(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        buffer: &[u8],
+        gray_plane: GrayPlane,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)?;
+        let mut plane = [0u8; 64];
+        for chunk in buffer.chunks(2 * plane.len()) {
+            for (dst, pair) in plane.iter_mut().zip(chunk.chunks(2)) {
+                *dst = self.mirror.byte(gray_plane.byte(pair));
+            }
+            self.interface.data(spi, &plane[..chunk.len() / 2])?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn use_full_frame(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
         // choose full frame/ram
         self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
 
@@ -285,6 +801,11 @@ where
         self.set_ram_counter(spi, 0, 0)
     }
 
+    /// Selects the RAM window from `start` to `end`, both inclusive like in the registers
+    ///
+    /// A window of a single row or column is valid. The X registers count bytes, so x is
+    /// rounded down to a multiple of 8. A window which ends before it starts or outside of the
+    /// panel returns [Error::InvalidRegion].
     pub(crate) fn set_ram_area(
         &mut self,
         spi: &mut SPI,
@@ -292,10 +813,12 @@ where
         start_y: u32,
         end_x: u32,
         end_y: u32,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
-        assert!(start_x < end_x);
-        assert!(start_y < end_y);
+    ) -> Result<(), Error<SPI::Error>> {
+        if start_x > end_x || start_y > end_y || end_x >= WIDTH || end_y >= HEIGHT {
+            return Err(Error::InvalidRegion);
+        }
+        let (start_x, end_x) = (self.mirror.x(start_x, WIDTH), self.mirror.x(end_x, WIDTH));
+        let (start_y, end_y) = (self.mirror.y(start_y, HEIGHT), self.mirror.y(end_y, HEIGHT));
 
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
@@ -324,8 +847,8 @@ where
         spi: &mut SPI,
         x: u32,
         y: u32,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    ) -> Result<(), Error<SPI::Error>> {
+        let (x, y) = (self.mirror.x(x, WIDTH), self.mirror.y(y, HEIGHT));
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
         self.interface
@@ -340,9 +863,18 @@ where
         Ok(())
     }
 
-    fn set_lut_helper(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), SPI::Error> {
+    /// Writes the waveform and the voltages which follow it in the LUT, also used for custom
+    /// LUTs
+    ///
+    /// Returns [Error::BufferSize] without sending anything if the LUT doesn't have 159 bytes.
+    fn set_lut_helper(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Error<SPI::Error>> {
+        if buffer.len() != 159 {
+            return Err(Error::BufferSize {
+                expected: 159,
+                got: buffer.len(),
+            });
+        }
         self.wait_until_idle();
-        assert!(buffer.len() == 159);
 
         self.interface
             .cmd_with_data(spi, Command::WriteLutRegister, &buffer[0..153])?;
@@ -352,24 +884,105 @@ where
 
         self.wait_until_idle();
 
+        let gate_voltage = self.gate_voltage.unwrap_or(buffer[154]);
         self.interface
-            .cmd_with_data(spi, Command::GateDrivingVoltage, &[buffer[154]])?;
+            .cmd_with_data(spi, Command::GateDrivingVoltage, &[gate_voltage])?;
 
         self.interface.cmd_with_data(
             spi,
             Command::SourceDrivingVoltage,
             &[buffer[155], buffer[156], buffer[157]],
         )?;
+        let vcom = self.vcom.unwrap_or(buffer[158]);
         self.interface
-            .cmd_with_data(spi, Command::WriteVcomRegister, &[buffer[158]])?;
+            .cmd_with_data(spi, Command::WriteVcomRegister, &[vcom])?;
 
         Ok(())
     }
 }
 
+impl<SPI, CS, BUSY, DC, RST, DELAY> Epd1in54<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8> + Transfer<u8, Error = <SPI as Write<u8>>::Error>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Measures the temperature with the internal sensor, in whole °C
+    ///
+    /// Needs a board which connects the data line of the controller to MISO. The measurement
+    /// doesn't change the RAM or the LUTs.
+    pub fn read_temperature(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<i8, Error<<SPI as Write<u8>>::Error>> {
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle();
+        // clock on, load the temperature, clock off
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xB1])?;
+        self.interface.cmd(spi, Command::MasterActivation)?;
+        self.wait_until_idle();
+
+        // 12 bit two's complement in 1/16 °C, the first byte holds the whole degrees
+        let mut value = [0u8; 2];
+        self.interface
+            .cmd_with_read(spi, Command::TemperatureSensorRead, &mut value)?;
+        Ok(value[0] as i8)
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareGrayscaleDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd1in54<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Writes the high bit of the gray levels to `WriteRam` and the low bit to `WriteRam2`
+    ///
+    /// The gray LUT stays loaded until the next [set_lut()](WaveshareDisplay::set_lut()) or
+    /// black/white update, which load the LUT of the current [RefreshLut] again.
+    fn update_gray_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Error<SPI::Error>> {
+        // two bits per pixel
+        check_buffer_len(buffer, 2 * WIDTH, HEIGHT)?;
+        self.enter_gray_mode(spi)?;
+        self.send_gray_plane(spi, Command::WriteRam, buffer, GrayPlane::High)?;
+        self.send_gray_plane(spi, Command::WriteRam2, buffer, GrayPlane::Low)
+    }
+}
+
+/// Full LUT for the panel temperature
+fn full_lut_for_temperature(temp_celsius: i8) -> &'static [u8; 159] {
+    match temp_celsius {
+        i8::MIN..=9 => &LUT_FULL_UPDATE_COLD,
+        10..=40 => &LUT_FULL_UPDATE_NORMAL,
+        41..=i8::MAX => &LUT_FULL_UPDATE_HOT,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::{cmd, test_epd, DcPin, LowPin, NoDelay, RecordingSpi, Sent};
+    extern crate std;
+    use std::vec;
+    use std::vec::Vec;
+
+    type TestEpd = Epd1in54<RecordingSpi, LowPin, LowPin, DcPin, LowPin, NoDelay>;
+
+    /// Data bytes of all DisplayUpdateControl2 commands
+    fn update_sequences(sent: &[Sent]) -> Vec<u8> {
+        sent.windows(2)
+            .filter(|pair| pair[0] == (true, 0x22))
+            .map(|pair| pair[1].1)
+            .collect()
+    }
 
     #[test]
     fn epd_size() {
@@ -377,4 +990,397 @@ mod tests {
         assert_eq!(HEIGHT, 200);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn border_color_replaces_the_vendor_values() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        let has = |sent: &[Sent], border: u8| {
+            let border = cmd(0x3C, &[border]);
+            sent.windows(border.len()).any(|sent| sent == border)
+        };
+        assert!(has(&spi.sent, 0x01));
+
+        spi.sent.clear();
+        epd.set_lut(&mut spi, Some(RefreshLut::Quick)).unwrap();
+        assert!(has(&spi.sent, 0x80));
+
+        epd.set_border_color(&mut spi, BorderColor::Float).unwrap();
+        spi.sent.clear();
+        epd.set_lut(&mut spi, Some(RefreshLut::Quick)).unwrap();
+        assert!(has(&spi.sent, 0xC0));
+        assert!(!has(&spi.sent, 0x80));
+
+        spi.sent.clear();
+        epd.wake_up(&mut spi, &mut NoDelay).unwrap();
+        assert!(has(&spi.sent, 0xC0));
+        assert!(!has(&spi.sent, 0x01));
+    }
+
+    #[test]
+    fn mirrored_frames_are_written_backwards() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+        epd.set_mirror_horizontal(&mut spi, true).unwrap();
+        assert_eq!(spi.sent, cmd(0x11, &[0x02]));
+
+        spi.sent.clear();
+        epd.update_partial_frame(&mut spi, &[0x80, 0x0F], 8, 10, 8, 2, &mut NoDelay)
+            .unwrap();
+        let mut expected = cmd(0x44, &[23, 23]);
+        expected.extend(cmd(0x45, &[10, 0, 11, 0]));
+        expected.extend(cmd(0x4E, &[23]));
+        expected.extend(cmd(0x4F, &[10, 0]));
+        expected.extend(cmd(0x24, &[0x01, 0xF0]));
+        assert_eq!(spi.sent, expected);
+
+        epd.set_mirror_vertical(&mut spi, true).unwrap();
+        epd.set_mirror_horizontal(&mut spi, false).unwrap();
+        epd.sleep(&mut spi, &mut NoDelay).unwrap();
+        spi.sent.clear();
+        epd.wake_up(&mut spi, &mut NoDelay).unwrap();
+        let mode = cmd(0x11, &[0x01]);
+        assert!(spi.sent.windows(mode.len()).any(|sent| sent == mode));
+        let counter = cmd(0x4F, &[(HEIGHT - 1) as u8, 0]);
+        assert!(spi.sent.windows(counter.len()).any(|sent| sent == counter));
+    }
+
+    #[test]
+    fn partial_windows_end_inclusive() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+
+        // a single row of the first byte column
+        spi.sent.clear();
+        epd.update_partial_frame(&mut spi, &[0x80], 0, 0, 8, 1, &mut NoDelay)
+            .unwrap();
+        assert_eq!(
+            &spi.sent[..8],
+            &[cmd(0x44, &[0, 0]), cmd(0x45, &[0, 0, 0, 0])].concat()[..]
+        );
+
+        // the last row of the last byte column
+        spi.sent.clear();
+        epd.update_partial_frame(&mut spi, &[0x01], WIDTH - 8, HEIGHT - 1, 8, 1, &mut NoDelay)
+            .unwrap();
+        let expected = [cmd(0x44, &[24, 24]), cmd(0x45, &[199, 0, 199, 0])].concat();
+        assert_eq!(&spi.sent[..8], &expected[..]);
+
+        spi.sent.clear();
+        assert_eq!(
+            epd.set_ram_area(&mut spi, 9, 0, 8, 0),
+            Err(Error::InvalidRegion)
+        );
+        assert_eq!(
+            epd.set_ram_area(&mut spi, 0, 1, 0, 0),
+            Err(Error::InvalidRegion)
+        );
+        assert_eq!(
+            epd.set_ram_area(&mut spi, 0, 0, 0, HEIGHT),
+            Err(Error::InvalidRegion)
+        );
+        assert!(spi.sent.is_empty());
+    }
+
+    #[test]
+    fn refresh_duration_hints() {
+        let (_spi, epd) = test_epd::<TestEpd, _>();
+        assert!(epd.quick_refresh_ms() < epd.full_refresh_ms());
+        assert!(epd.full_refresh_ms() < BUSY_TIMEOUT_MS);
+    }
+
+    #[test]
+    fn deep_sleep_mode_values() {
+        assert_eq!(DeepSleepMode::default(), DeepSleepMode::Mode1);
+        assert_eq!(DeepSleepMode::Mode0.value(), 0x00);
+        assert_eq!(DeepSleepMode::Mode1.value(), 0x01);
+        assert_eq!(DeepSleepMode::Mode2.value(), 0x03);
+    }
+
+    #[test]
+    fn partial_refresh_limit_forces_a_full_refresh() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        epd.set_lut(&mut spi, Some(RefreshLut::Quick)).unwrap();
+        epd.set_partial_refresh_limit(Some(2));
+        spi.sent.clear();
+
+        for _ in 0..3 {
+            epd.update_partial_frame(&mut spi, &[0x00; 2], 0, 0, 16, 1, &mut NoDelay)
+                .unwrap();
+            epd.display_frame(&mut spi, &mut NoDelay).unwrap();
+        }
+
+        // quick, full and the quick LUT loaded again, then quick with a new count
+        assert_eq!(update_sequences(&spi.sent), vec![0xCF, 0xC7, 0xC0, 0xCF]);
+        assert_eq!(epd.refresh, RefreshLut::Quick);
+        assert_eq!(epd.partial_refreshes, 1);
+    }
+
+    #[test]
+    fn partial_frame_is_shown_with_the_quick_lut() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+
+        assert_eq!(
+            epd.update_and_display_partial_frame(&mut spi, &[0x00; 3], 0, 0, 16, 1, &mut NoDelay),
+            Err(Error::BufferSize {
+                expected: 2,
+                got: 3
+            })
+        );
+        assert!(spi.sent.is_empty());
+
+        for _ in 0..2 {
+            epd.update_and_display_partial_frame(&mut spi, &[0x00; 2], 8, 0, 16, 1, &mut NoDelay)
+                .unwrap();
+        }
+        // the quick LUT is loaded once, both windows are refreshed with it
+        assert_eq!(update_sequences(&spi.sent), vec![0xC0, 0xCF, 0xCF]);
+        assert_eq!(epd.refresh, RefreshLut::Quick);
+        assert_eq!(epd.partial_refreshes, 2);
+    }
+
+    #[test]
+    fn shown_windows_are_written_into_the_base_map() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+        epd.set_partial_base_buffer(&mut spi, &[0xAA; 5000])
+            .unwrap();
+        let mut expected = cmd(0x44, &[0, 24]);
+        expected.extend(cmd(0x45, &[0, 0, 199, 0]));
+        expected.extend(cmd(0x4E, &[0]));
+        expected.extend(cmd(0x4F, &[0, 0]));
+        expected.extend(cmd(0x26, &[0xAA; 5000]));
+        assert_eq!(spi.sent, expected);
+
+        let window = |spi: &RecordingSpi| {
+            let mut ram2 = cmd(0x4F, &[0, 0]);
+            ram2.extend(cmd(0x26, &[0x0F; 2]));
+            spi.sent.windows(ram2.len()).any(|sent| sent == ram2)
+        };
+        epd.update_and_display_partial_frame(&mut spi, &[0x0F; 2], 8, 0, 16, 1, &mut NoDelay)
+            .unwrap();
+        assert!(!window(&spi));
+
+        epd.set_base_map_sync(true);
+        spi.sent.clear();
+        epd.update_and_display_partial_frame(&mut spi, &[0x0F; 2], 8, 0, 16, 1, &mut NoDelay)
+            .unwrap();
+        assert!(window(&spi));
+    }
+
+    #[test]
+    fn gray_planes_go_to_both_rams() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        let plane = WIDTH as usize / 8 * HEIGHT as usize;
+        assert_eq!(
+            epd.update_gray_planes(&mut spi, &[0x00; 5000], &[0xFF; 4999]),
+            Err(Error::BufferSize {
+                expected: plane,
+                got: 4999
+            })
+        );
+
+        spi.sent.clear();
+        epd.update_gray_planes(&mut spi, &[0x0F; 5000], &[0xF0; 5000])
+            .unwrap();
+        assert!(epd.gray_lut);
+        let sent = &spi.sent;
+        assert_eq!(sent[0], (true, 0x32));
+        let ram = sent.iter().position(|&s| s == (true, 0x24)).unwrap();
+        assert_eq!(
+            sent[ram + 1..ram + 1 + plane],
+            vec![(false, 0x0F); plane][..]
+        );
+        assert_eq!(sent[ram + 1 + plane], (true, 0x26));
+        assert_eq!(sent[ram + 2 + plane..], vec![(false, 0xF0); plane][..]);
+    }
+
+    #[test]
+    fn gray_frame_loads_the_gray_lut_and_both_rams() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+
+        // dark gray: low bit set, high bit cleared
+        let buffer = [0x55; 2 * WIDTH as usize / 8 * HEIGHT as usize];
+        epd.update_gray_frame(&mut spi, &buffer).unwrap();
+
+        let plane = WIDTH as usize / 8 * HEIGHT as usize;
+        let sent = &spi.sent;
+        assert_eq!(sent[0], (true, 0x32));
+        assert_eq!(&sent[1..154], &cmd(0, &LUT_GRAY4[..153])[1..]);
+        let ram = sent.iter().position(|&s| s == (true, 0x24)).unwrap();
+        assert_eq!(
+            sent[ram + 1..ram + 1 + plane],
+            vec![(false, 0x00); plane][..]
+        );
+        assert_eq!(sent[ram + 1 + plane], (true, 0x26));
+        assert_eq!(sent[ram + 2 + plane..], vec![(false, 0xFF); plane][..]);
+
+        // a black/white frame loads the full LUT again
+        spi.sent.clear();
+        epd.update_frame(&mut spi, &[0xFF; 5000], &mut NoDelay)
+            .unwrap();
+        assert_eq!(
+            &spi.sent[1..154],
+            &cmd(0, &LUT_FULL_UPDATE_NORMAL[..153])[1..]
+        );
+    }
+
+    #[test]
+    fn full_lut_follows_the_temperature() {
+        assert_eq!(full_lut_for_temperature(-20), &LUT_FULL_UPDATE_COLD);
+        assert_eq!(full_lut_for_temperature(9), &LUT_FULL_UPDATE_COLD);
+        assert_eq!(full_lut_for_temperature(10), &LUT_FULL_UPDATE_NORMAL);
+        assert_eq!(full_lut_for_temperature(40), &LUT_FULL_UPDATE_NORMAL);
+        assert_eq!(full_lut_for_temperature(41), &LUT_FULL_UPDATE_HOT);
+
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        epd.set_lut(&mut spi, Some(RefreshLut::Quick)).unwrap();
+        spi.sent.clear();
+        epd.set_lut_for_temperature(&mut spi, &mut NoDelay, 0)
+            .unwrap();
+        assert_eq!(epd.refresh, RefreshLut::Full);
+        assert_eq!(
+            &spi.sent[1..154],
+            &cmd(0, &LUT_FULL_UPDATE_COLD[..153])[1..]
+        );
+
+        // the full LUT stays selected for the later refreshes
+        spi.sent.clear();
+        epd.set_lut(&mut spi, None).unwrap();
+        assert_eq!(
+            &spi.sent[1..154],
+            &cmd(0, &LUT_FULL_UPDATE_COLD[..153])[1..]
+        );
+    }
+
+    #[test]
+    fn vcom_and_gate_voltage_replace_the_lut_values() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        let has = |sent: &[Sent], command: u8, value: u8| {
+            let register = cmd(command, &[value]);
+            sent.windows(register.len()).any(|sent| sent == register)
+        };
+        epd.set_lut(&mut spi, Some(RefreshLut::Full)).unwrap();
+        assert!(has(&spi.sent, 0x03, 0x17));
+
+        spi.sent.clear();
+        assert_eq!(epd.set_vcom(&mut spi, -250), Err(Error::InvalidVoltage));
+        assert_eq!(epd.set_vcom(&mut spi, 100), Err(Error::InvalidVoltage));
+        assert_eq!(
+            epd.set_gate_voltage(&mut spi, 21_000),
+            Err(Error::InvalidVoltage)
+        );
+        assert!(spi.sent.is_empty());
+
+        epd.set_vcom(&mut spi, -2100).unwrap();
+        epd.set_gate_voltage(&mut spi, 15_000).unwrap();
+        assert_eq!(spi.sent, [cmd(0x2C, &[0x54]), cmd(0x03, &[0x0D])].concat());
+
+        for lut in [RefreshLut::Quick, RefreshLut::Full] {
+            spi.sent.clear();
+            epd.set_lut(&mut spi, Some(lut)).unwrap();
+            assert!(has(&spi.sent, 0x2C, 0x54) && has(&spi.sent, 0x03, 0x0D));
+        }
+    }
+
+    #[test]
+    fn custom_lut_is_checked_and_loaded() {
+        static TOO_SHORT: [u8; 153] = [0x11; 153];
+        static CUSTOM: [u8; 159] = [0x22; 159];
+
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+        assert_eq!(
+            epd.set_lut(&mut spi, Some(RefreshLut::Custom(&TOO_SHORT))),
+            Err(Error::BufferSize {
+                expected: 159,
+                got: 153
+            })
+        );
+        assert!(spi.sent.is_empty());
+        assert_eq!(epd.refresh, RefreshLut::Full);
+
+        epd.set_lut(&mut spi, Some(RefreshLut::Custom(&CUSTOM)))
+            .unwrap();
+        assert_eq!(epd.refresh, RefreshLut::Custom(&CUSTOM));
+        assert_eq!(&spi.sent[..154], &cmd(0x32, &CUSTOM[..153])[..]);
+
+        spi.sent.clear();
+        epd.display_frame(&mut spi, &mut NoDelay).unwrap();
+        assert_eq!(&spi.sent[..2], &cmd(0x22, &[0xC7])[..]);
+    }
+
+    #[test]
+    fn custom_lut_from_a_local_buffer() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        epd.set_lut(&mut spi, Some(RefreshLut::Quick)).unwrap();
+        spi.sent.clear();
+
+        let mut lut = LUT_FULL_UPDATE_NORMAL;
+        assert_eq!(
+            epd.set_custom_lut(&mut spi, &lut[..158]),
+            Err(Error::BufferSize {
+                expected: 159,
+                got: 158
+            })
+        );
+        assert!(spi.sent.is_empty());
+
+        lut[144] = 0x11;
+        lut[158] = 0x30;
+        epd.set_custom_lut(&mut spi, &lut).unwrap();
+        let expected = [
+            cmd(0x32, &lut[..153]),
+            cmd(0x3F, &[lut[153]]),
+            cmd(0x03, &[lut[154]]),
+            cmd(0x04, &lut[155..158]),
+            cmd(0x2C, &[0x30]),
+        ]
+        .concat();
+        assert_eq!(spi.sent, expected);
+        assert_eq!(epd.refresh, RefreshLut::Full);
+
+        spi.sent.clear();
+        epd.display_frame(&mut spi, &mut NoDelay).unwrap();
+        assert_eq!(&spi.sent[..2], &cmd(0x22, &[0xC7])[..]);
+    }
+
+    #[test]
+    fn read_temperature_loads_and_reads_the_register() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+        // -5.5 °C, the whole degrees are rounded down
+        spi.answers = vec![0xFA, 0x80];
+
+        assert_eq!(epd.read_temperature(&mut spi), Ok(-6));
+        let mut expected = cmd(0x22, &[0xB1]);
+        expected.extend(cmd(0x20, &[]));
+        expected.extend(cmd(0x1B, &[]));
+        assert_eq!(spi.sent, expected);
+        assert!(spi.answers.is_empty());
+    }
+
+    #[test]
+    fn timings_cover_the_busy_periods() {
+        use crate::test_utils::{busy_after, busy_for, BusyPin, TEST_CLOCK};
+        type TimedEpd = Epd1in54<RecordingSpi, BusyPin, BusyPin, DcPin, BusyPin, NoDelay>;
+        busy_for(0);
+        let (mut spi, mut epd) = test_epd::<TimedEpd, _>();
+        epd.set_clock(Some(&TEST_CLOCK));
+
+        // 30 busy reads and the idle one
+        busy_after(Command::MasterActivation as u8, 30);
+        epd.display_frame(&mut spi, &mut NoDelay).unwrap();
+        assert_eq!(epd.last_timings().full_refresh_ms, Some(31));
+
+        epd.set_lut(&mut spi, Some(RefreshLut::Quick)).unwrap();
+        busy_after(Command::MasterActivation as u8, 12);
+        epd.display_frame(&mut spi, &mut NoDelay).unwrap();
+        assert_eq!(epd.last_timings().quick_refresh_ms, Some(13));
+        assert_eq!(epd.last_timings().full_refresh_ms, Some(31));
+
+        busy_after(Command::WriteRam as u8, 7);
+        epd.clear_frame(&mut spi, &mut NoDelay).unwrap();
+        assert_eq!(epd.last_timings().clear_ms, Some(8));
+    }
 }
@@ -5,10 +5,15 @@ use embedded_hal::{
     digital::v2::*,
 };
 
+use crate::check_buffer_len;
+use crate::error::Error;
 use crate::interface::DisplayInterface;
+use crate::timing::ResetTiming;
 use crate::traits::{
     InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
 };
+use crate::window::WindowLimits;
+use crate::{AutoCs, CsStrategy, ResetPin};
 
 //The Lookup Tables for the Display
 mod constants;
@@ -18,9 +23,19 @@ use crate::epd1in54b::constants::*;
 pub const WIDTH: u32 = 200;
 /// Height of epd1in54 in pixels
 pub const HEIGHT: u32 = 200;
+/// Limits of the partial window registers: 8 bit x and y registers, the whole panel fits
+pub const WINDOW_LIMITS: WindowLimits = WindowLimits::new(WIDTH, HEIGHT, 255, 255);
 /// Default Background Color (white)
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
 const IS_BUSY_LOW: bool = true;
+/// Longest time in milliseconds to wait for the busy pin before giving up with
+/// [Error::Timeout]
+pub const BUSY_TIMEOUT_MS: u32 = 60_000;
+/// Fastest SPI clock of the controller in Hz, see
+/// [check_spi_frequency()](crate::check_spi_frequency())
+pub const MAX_SPI_FREQ_HZ: u32 = 10_000_000;
+/// Byte of the red plane without red pixels, a cleared bit is red
+const NO_RED: u8 = 0xFF;
 
 use crate::color::Color;
 
@@ -42,14 +57,14 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST,
     for Epd1in54b<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.reset(delay, 10, 10);
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.reset(delay, 10, 10)?;
 
         // set the power settings
         self.interface
@@ -62,7 +77,7 @@ where
         // power on
         self.command(spi, Command::PowerOn)?;
         delay.delay_ms(5);
-        self.wait_until_idle();
+        self.wait_until_idle_with_timeout(delay)?;
 
         // set the panel settings
         self.cmd_with_data(spi, Command::PanelSetting, &[0xCF])?;
@@ -79,7 +94,7 @@ where
 
         self.set_lut(spi, None)?;
 
-        self.wait_until_idle();
+        self.wait_until_idle_with_timeout(delay)?;
 
         Ok(())
     }
@@ -89,10 +104,10 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareThreeColorDisplay<SPI, CS, BUSY, DC
     for Epd1in54b<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
     fn update_color_frame(
@@ -100,12 +115,17 @@ where
         spi: &mut SPI,
         black: &[u8],
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_achromatic_frame(spi, black)?;
         self.update_chromatic_frame(spi, chromatic)
     }
 
-    fn update_achromatic_frame(&mut self, spi: &mut SPI, black: &[u8]) -> Result<(), SPI::Error> {
+    fn update_achromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        black: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(black, WIDTH, HEIGHT)?;
         self.wait_until_idle();
         self.send_resolution(spi)?;
 
@@ -122,21 +142,44 @@ where
         &mut self,
         spi: &mut SPI,
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(chromatic, WIDTH, HEIGHT)?;
+        // the chromatic plane can be written alone, wait for the refresh of the last one
+        self.wait_until_idle();
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
         self.interface.data(spi, chromatic)?;
         Ok(())
     }
 }
 
+impl<SPI, BUSY, DC, RST, DELAY> Epd1in54b<SPI, AutoCs, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Creates the driver for a board without a CS GPIO, see [AutoCs]
+    pub fn new_without_cs(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new(spi, AutoCs, busy, dc, rst, delay)
+    }
+}
+
 impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
     for Epd1in54b<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
     type DisplayColor = Color;
@@ -147,10 +190,20 @@ where
         dc: DC,
         rst: RST,
         delay: &mut DELAY,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(cs, busy, dc, rst);
-        let color = DEFAULT_BACKGROUND_COLOR;
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_background(spi, cs, busy, dc, rst, delay, DEFAULT_BACKGROUND_COLOR)
+    }
 
+    fn new_with_background(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        color: Self::DisplayColor,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(cs, busy, dc, rst);
         let mut epd = Epd1in54b { interface, color };
 
         epd.init(spi, delay)?;
@@ -158,8 +211,8 @@ where
         Ok(epd)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle_with_timeout(delay)?;
         self.interface
             .cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x17])?; //border floating
 
@@ -169,7 +222,7 @@ where
         self.interface
             .cmd_with_data(spi, Command::PowerSetting, &[0x02, 0x00, 0x00, 0x00])?; //VG&VS to 0V fast
 
-        self.wait_until_idle();
+        self.wait_until_idle_with_timeout(delay)?;
 
         //NOTE: The example code has a 1s delay here
 
@@ -178,7 +231,8 @@ where
         Ok(())
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.check_reset_pin()?;
         self.init(spi, delay)
     }
 
@@ -198,51 +252,60 @@ where
         HEIGHT
     }
 
+    fn full_refresh_ms(&self) -> u32 {
+        8000
+    }
+
     fn update_frame(
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
-        _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer, WIDTH, HEIGHT)?;
+        self.wait_until_idle_with_timeout(delay)?;
         self.send_resolution(spi)?;
 
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
 
-        for b in buffer {
-            // Two bits per pixel
-            let expanded = expand_bits(*b);
-            self.interface.data(spi, &expanded)?;
+        // Two bits per pixel, expanded in chunks so that not every byte is a transfer on its own
+        let mut expanded = [0u8; 2 * 64];
+        for chunk in buffer.chunks(expanded.len() / 2) {
+            for (dst, b) in expanded.chunks_exact_mut(2).zip(chunk) {
+                dst.copy_from_slice(&expand_bits(*b));
+            }
+            self.interface
+                .data_block(spi, &expanded[..2 * chunk.len()])?;
         }
 
         //NOTE: Example code has a delay here
 
-        // Clear the read layer
-        let color = self.color.get_byte_value();
+        // Clear the red layer
         let nbits = WIDTH * (HEIGHT / 8);
 
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
-        self.interface.data_x_times(spi, color, nbits)?;
+        self.interface.data_x_times(spi, NO_RED, nbits)?;
 
         //NOTE: Example code has a delay here
         Ok(())
     }
 
-    #[allow(unused)]
+    /// Partial windows aren't supported, returns [Error::PartialUpdateUnsupported]
     fn update_partial_frame(
         &mut self,
-        spi: &mut SPI,
-        buffer: &[u8],
-        x: u32,
-        y: u32,
-        width: u32,
-        height: u32,
-    ) -> Result<(), SPI::Error> {
-        unimplemented!()
+        _spi: &mut SPI,
+        _buffer: &[u8],
+        _x: u32,
+        _y: u32,
+        _width: u32,
+        _height: u32,
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        Err(Error::PartialUpdateUnsupported)
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle_with_timeout(delay)?;
         self.command(spi, Command::DisplayRefresh)?;
         Ok(())
     }
@@ -252,17 +315,19 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    /// Clears the black/white plane to the background color and the red plane to no red
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle_with_timeout(delay)?;
         self.send_resolution(spi)?;
 
-        let color = DEFAULT_BACKGROUND_COLOR.get_byte_value();
+        // a full byte expands to two equal bytes, see expand_bits()
+        let color = self.color.get_byte_value();
 
         // Clear the black
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
@@ -274,52 +339,77 @@ where
         // Clear the red
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
         self.interface
-            .data_x_times(spi, color, WIDTH * HEIGHT / 8)?;
+            .data_x_times(spi, NO_RED, WIDTH * HEIGHT / 8)?;
         Ok(())
     }
 
+    /// Loads the full LUT, also for [RefreshLut::Quick] as no quick LUT is known for this panel
+    ///
+    /// Custom LUTs return [Error::UnsupportedLut].
     fn set_lut(
         &mut self,
         spi: &mut SPI,
-        _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
-        self.interface
-            .cmd_with_data(spi, Command::LutForVcom, LUT_VCOM0)?;
-        self.interface
-            .cmd_with_data(spi, Command::LutWhiteToWhite, LUT_WHITE_TO_WHITE)?;
-        self.interface
-            .cmd_with_data(spi, Command::LutBlackToWhite, LUT_BLACK_TO_WHITE)?;
-        self.interface.cmd_with_data(spi, Command::LutG0, LUT_G1)?;
-        self.interface.cmd_with_data(spi, Command::LutG1, LUT_G2)?;
-        self.interface
-            .cmd_with_data(spi, Command::LutRedVcom, LUT_RED_VCOM)?;
-        self.interface
-            .cmd_with_data(spi, Command::LutRed0, LUT_RED0)?;
-        self.interface
-            .cmd_with_data(spi, Command::LutRed1, LUT_RED1)?;
-
-        Ok(())
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        match refresh_rate {
+            None | Some(RefreshLut::Full) | Some(RefreshLut::Quick) => self.set_lut_helper(
+                spi,
+                [
+                    LUT_VCOM0,
+                    LUT_WHITE_TO_WHITE,
+                    LUT_BLACK_TO_WHITE,
+                    LUT_G1,
+                    LUT_G2,
+                    LUT_RED_VCOM,
+                    LUT_RED0,
+                    LUT_RED1,
+                ],
+            ),
+            Some(_) => Err(Error::UnsupportedLut),
+        }
     }
 
     fn is_busy(&self) -> bool {
         self.interface.is_busy(IS_BUSY_LOW)
     }
+
+    fn send_command_raw(&mut self, spi: &mut SPI, command: u8) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn send_data_raw(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        self.interface.data(spi, data)
+    }
+
+    fn set_spi_chunk_size(&mut self, chunk_size: usize) {
+        self.interface.set_chunk_size(chunk_size);
+    }
+
+    fn set_reset_timing(&mut self, timing: Option<ResetTiming>) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    #[cfg(feature = "test-patterns")]
+    fn set_test_pattern(&mut self, enable: bool) {
+        let pattern = crate::test_pattern::FillPattern::new(self.width(), 1);
+        self.interface.set_fill_pattern(enable.then_some(pattern));
+    }
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> Epd1in54b<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd(spi, command)
     }
 
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
         self.interface.data(spi, data)
     }
 
@@ -328,7 +418,7 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
@@ -336,7 +426,31 @@ where
         self.interface.wait_until_idle(IS_BUSY_LOW);
     }
 
-    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    fn wait_until_idle_with_timeout(&mut self, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .wait_until_idle_with_timeout(delay, IS_BUSY_LOW, BUSY_TIMEOUT_MS)
+    }
+
+    /// Sends the LUTs in the order vcom, white to white, black to white, g0, g1,
+    /// red vcom, red0 and red1
+    fn set_lut_helper(&mut self, spi: &mut SPI, luts: [&[u8]; 8]) -> Result<(), Error<SPI::Error>> {
+        let commands = [
+            Command::LutForVcom,
+            Command::LutWhiteToWhite,
+            Command::LutBlackToWhite,
+            Command::LutG0,
+            Command::LutG1,
+            Command::LutRedVcom,
+            Command::LutRed0,
+            Command::LutRed1,
+        ];
+        for (command, lut) in commands.iter().zip(luts.iter()) {
+            self.interface.cmd_with_data(spi, *command, lut)?;
+        }
+        Ok(())
+    }
+
+    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
         let w = self.width();
         let h = self.height();
 
@@ -362,6 +476,122 @@ fn expand_bits(bits: u8) -> [u8; 2] {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::{DcPin, HighPin, NoDelay, RecordingSpi};
+    extern crate std;
+    use crate::buffer_len;
+    use std::vec::Vec;
+
+    type TestEpd = Epd1in54b<RecordingSpi, HighPin, HighPin, DcPin, HighPin, NoDelay>;
+
+    fn test_epd(color: Color) -> (RecordingSpi, TestEpd) {
+        let (spi, dc) = RecordingSpi::new();
+        let epd = Epd1in54b {
+            interface: DisplayInterface::new(HighPin, HighPin, dc, HighPin),
+            color,
+        };
+        (spi, epd)
+    }
+
+    /// The recorded bytes split into the single transfers
+    fn transfers(spi: &RecordingSpi) -> Vec<Vec<u8>> {
+        let bytes = spi.bytes();
+        let mut rest = &bytes[..];
+        spi.writes
+            .iter()
+            .map(|&len| {
+                let (transfer, tail) = rest.split_at(len);
+                rest = tail;
+                transfer.to_vec()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn update_frame_sends_expanded_chunks() {
+        let (mut spi, mut epd) = test_epd(DEFAULT_BACKGROUND_COLOR);
+        let buffer: Vec<u8> = (0..buffer_len(WIDTH as usize, HEIGHT as usize))
+            .map(|i| i as u8)
+            .collect();
+        epd.update_frame(&mut spi, &buffer, &mut NoDelay).unwrap();
+        let sent = transfers(&spi);
+
+        // resolution command and its 3 data bytes, then DataStartTransmission1
+        let start = 5;
+        assert_eq!(sent[start - 1], [Command::DataStartTransmission1 as u8]);
+        // 5000 source bytes expand to 10000 bytes: 78 full chunks and a rest of 16
+        let chunks = &sent[start..start + 79];
+        assert!(chunks[..78].iter().all(|t| t.len() == 128));
+        assert_eq!(chunks[78].len(), 16);
+
+        let expected: Vec<u8> = buffer.iter().flat_map(|&b| expand_bits(b)).collect();
+        assert_eq!(chunks.concat(), expected);
+        assert_eq!(sent[start + 79], [Command::DataStartTransmission2 as u8]);
+    }
+
+    #[test]
+    fn clear_frame_uses_the_background_color_and_no_red() {
+        for color in [Color::Black, Color::White] {
+            let (mut spi, mut epd) = test_epd(color);
+            epd.clear_frame(&mut spi, &mut NoDelay).unwrap();
+            let sent = transfers(&spi);
+
+            let red_start = sent
+                .iter()
+                .position(|t| *t == [Command::DataStartTransmission2 as u8])
+                .unwrap();
+            // resolution command and its 3 data bytes, then DataStartTransmission1
+            assert_eq!(sent[4], [Command::DataStartTransmission1 as u8]);
+            let black = sent[5..red_start].concat();
+            let frame = [color.get_byte_value(); WIDTH as usize * HEIGHT as usize / 8];
+            let expanded: Vec<u8> = frame.iter().flat_map(|&b| expand_bits(b)).collect();
+            assert_eq!(black, expanded);
+
+            let red = sent[red_start + 1..].concat();
+            assert_eq!(red.len(), frame.len());
+            assert!(red.iter().all(|&b| b == NO_RED));
+        }
+    }
+
+    #[test]
+    fn chromatic_plane_alone() {
+        let (mut spi, mut epd) = test_epd(DEFAULT_BACKGROUND_COLOR);
+        let plane = [0xF0; WIDTH as usize * HEIGHT as usize / 8];
+        epd.update_chromatic_frame(&mut spi, &plane).unwrap();
+        epd.display_frame(&mut spi, &mut NoDelay).unwrap();
+        let sent = transfers(&spi);
+
+        // the black/white plane in the RAM isn't touched
+        assert_eq!(sent[0], [Command::DataStartTransmission2 as u8]);
+        let last = sent.len() - 1;
+        assert_eq!(sent[1..last].concat(), plane);
+        assert_eq!(sent[last], [Command::DisplayRefresh as u8]);
+    }
+
+    #[cfg(feature = "test-patterns")]
+    #[test]
+    fn test_pattern_fills_both_planes() {
+        let (mut spi, mut epd) = test_epd(Color::White);
+        epd.display_test_pattern(&mut spi, &mut NoDelay).unwrap();
+        let sent = transfers(&spi);
+
+        let red_start = sent
+            .iter()
+            .position(|t| *t == [Command::DataStartTransmission2 as u8])
+            .unwrap();
+        let red = sent[red_start + 1..].concat();
+        // the red plane is shifted by half a square
+        assert_eq!(red[..4], [NO_RED, !NO_RED, !NO_RED, NO_RED]);
+
+        // the pattern is only used once
+        spi.clear();
+        epd.clear_frame(&mut spi, &mut NoDelay).unwrap();
+        let sent = transfers(&spi);
+        let red_start = sent
+            .iter()
+            .position(|t| *t == [Command::DataStartTransmission2 as u8])
+            .unwrap();
+        assert!(sent[red_start + 1..].concat().iter().all(|&b| b == NO_RED));
+    }
 
     #[test]
     fn epd_size() {
@@ -369,4 +599,58 @@ mod tests {
         assert_eq!(HEIGHT, 200);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    proptest::proptest! {
+        #[test]
+        fn expand_bits_doubles_every_bit(bits in proptest::num::u8::ANY) {
+            let mut expected = 0u16;
+            for i in 0..8 {
+                if bits & (0x80 >> i) != 0 {
+                    expected |= 0xC000 >> (2 * i);
+                }
+            }
+            proptest::prop_assert_eq!(expand_bits(bits), expected.to_be_bytes());
+        }
+    }
+
+    #[test]
+    fn set_lut_sends_the_full_tables() {
+        let (mut spi, mut epd) = test_epd(DEFAULT_BACKGROUND_COLOR);
+        for refresh in [None, Some(RefreshLut::Full), Some(RefreshLut::Quick)] {
+            spi.clear();
+            epd.set_lut(&mut spi, refresh).unwrap();
+            let expected: Vec<u8> = [
+                (Command::LutForVcom, LUT_VCOM0),
+                (Command::LutWhiteToWhite, LUT_WHITE_TO_WHITE),
+                (Command::LutBlackToWhite, LUT_BLACK_TO_WHITE),
+                (Command::LutG0, LUT_G1),
+                (Command::LutG1, LUT_G2),
+                (Command::LutRedVcom, LUT_RED_VCOM),
+                (Command::LutRed0, LUT_RED0),
+                (Command::LutRed1, LUT_RED1),
+            ]
+            .iter()
+            .flat_map(|&(command, lut)| [&[command as u8][..], lut].concat())
+            .collect();
+            assert_eq!(spi.bytes(), expected);
+        }
+
+        spi.clear();
+        assert!(matches!(
+            epd.set_lut(&mut spi, Some(RefreshLut::Custom(&[0; 8]))),
+            Err(Error::UnsupportedLut)
+        ));
+        assert!(spi.sent.is_empty());
+    }
+
+    #[test]
+    fn partial_frames_are_rejected() {
+        let (mut spi, mut epd) = test_epd(DEFAULT_BACKGROUND_COLOR);
+
+        assert_eq!(
+            epd.update_partial_frame(&mut spi, &[0; 8], 0, 0, 8, 8, &mut NoDelay),
+            Err(Error::PartialUpdateUnsupported)
+        );
+        assert!(spi.sent.is_empty());
+    }
 }
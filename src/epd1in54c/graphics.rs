@@ -1,27 +1,34 @@
+use crate::color::TriColor;
 use crate::epd1in54c::{DEFAULT_BACKGROUND_COLOR, HEIGHT, NUM_DISPLAY_BITS, WIDTH};
-use crate::graphics::{Display, DisplayRotation};
-use embedded_graphics_core::pixelcolor::BinaryColor;
+use crate::graphics::{DisplayColorRendering, DisplayRotation, TriDisplay};
+use crate::traits::FrameBuffer;
 use embedded_graphics_core::prelude::*;
 
 /// Full size buffer for use with the 1in54c EPD
 ///
+/// Draw the yellow parts with [TriColor::Yellow] or [TriColor::Chromatic], both end up in the
+/// chromatic plane.
+///
 /// Can also be manually constructed and be used together with VarDisplay
 pub struct Display1in54c {
-    buffer: [u8; NUM_DISPLAY_BITS as usize],
+    // one buffer for both b/w and for chromatic:
+    // * &buffer[0..NUM_DISPLAY_BITS] for b/w buffer and
+    // * &buffer[NUM_DISPLAY_BITS..2*NUM_DISPLAY_BITS] for chromatic buffer
+    buffer: [u8; 2 * NUM_DISPLAY_BITS as usize],
     rotation: DisplayRotation,
 }
 
 impl Default for Display1in54c {
     fn default() -> Self {
         Display1in54c {
-            buffer: [DEFAULT_BACKGROUND_COLOR.get_byte_value(); NUM_DISPLAY_BITS as usize],
+            buffer: [DEFAULT_BACKGROUND_COLOR.get_byte_value(); 2 * NUM_DISPLAY_BITS as usize],
             rotation: DisplayRotation::default(),
         }
     }
 }
 
 impl DrawTarget for Display1in54c {
-    type Color = BinaryColor;
+    type Color = TriColor;
     type Error = core::convert::Infallible;
 
     fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
@@ -29,7 +36,8 @@ impl DrawTarget for Display1in54c {
         I: IntoIterator<Item = Pixel<Self::Color>>,
     {
         for pixel in pixels {
-            self.draw_helper(WIDTH, HEIGHT, pixel)?;
+            // a cleared bit in the chromatic plane is yellow
+            self.draw_helper_tri(WIDTH, HEIGHT, pixel, DisplayColorRendering::Positive)?;
         }
         Ok(())
     }
@@ -37,11 +45,14 @@ impl DrawTarget for Display1in54c {
 
 impl OriginDimensions for Display1in54c {
     fn size(&self) -> Size {
-        Size::new(WIDTH, HEIGHT)
+        match self.rotation {
+            DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => Size::new(WIDTH, HEIGHT),
+            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => Size::new(HEIGHT, WIDTH),
+        }
     }
 }
 
-impl Display for Display1in54c {
+impl TriDisplay for Display1in54c {
     fn buffer(&self) -> &[u8] {
         &self.buffer
     }
@@ -57,4 +68,32 @@ impl Display for Display1in54c {
     fn rotation(&self) -> DisplayRotation {
         self.rotation
     }
+
+    fn chromatic_offset(&self) -> usize {
+        NUM_DISPLAY_BITS as usize
+    }
+
+    fn bw_buffer(&self) -> &[u8] {
+        &self.buffer[0..self.chromatic_offset()]
+    }
+
+    fn chromatic_buffer(&self) -> &[u8] {
+        &self.buffer[self.chromatic_offset()..]
+    }
+}
+
+impl FrameBuffer for Display1in54c {
+    type Color = TriColor;
+
+    fn as_bytes(&self) -> &[u8] {
+        self.buffer()
+    }
+
+    fn as_bytes_mut(&mut self) -> &mut [u8] {
+        self.get_mut_buffer()
+    }
+
+    fn clear(&mut self, color: TriColor) {
+        self.clear_buffer(color);
+    }
 }
@@ -1,25 +1,42 @@
 //! A simple Driver for the Waveshare 1.54" (C) E-Ink Display via SPI
+//!
+//! The chromatic color of this panel is yellow, [TriColor::Yellow] and [TriColor::Chromatic] are
+//! sent the same way. The chromatic plane uses a cleared bit for yellow, so an empty chromatic
+//! plane and the white clear are `0xFF`. The 152 columns fill whole bytes, a row is 19 bytes without padding.
 
 use embedded_hal::{
     blocking::{delay::*, spi::Write},
     digital::v2::*,
 };
 
+use crate::check_buffer_len;
+use crate::error::Error;
 use crate::interface::DisplayInterface;
+use crate::timing::ResetTiming;
 use crate::traits::{
     InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
 };
+use crate::window::WindowLimits;
+use crate::{AutoCs, CsStrategy, ResetPin};
 
 /// Width of epd1in54 in pixels
 pub const WIDTH: u32 = 152;
 /// Height of epd1in54 in pixels
 pub const HEIGHT: u32 = 152;
+/// Limits of the partial window registers: 8 bit x and y registers, the whole panel fits
+pub const WINDOW_LIMITS: WindowLimits = WindowLimits::new(WIDTH, HEIGHT, 255, 255);
 /// Default Background Color (white)
-pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+pub const DEFAULT_BACKGROUND_COLOR: TriColor = TriColor::White;
 const IS_BUSY_LOW: bool = true;
+/// Longest time in milliseconds to wait for the busy pin before giving up with
+/// [Error::Timeout]
+pub const BUSY_TIMEOUT_MS: u32 = 60_000;
+/// Fastest SPI clock of the controller in Hz, see
+/// [check_spi_frequency()](crate::check_spi_frequency())
+pub const MAX_SPI_FREQ_HZ: u32 = 10_000_000;
 const NUM_DISPLAY_BITS: u32 = WIDTH * HEIGHT / 8;
 
-use crate::color::Color;
+use crate::color::TriColor;
 
 pub(crate) mod command;
 use self::command::Command;
@@ -33,25 +50,25 @@ pub use self::graphics::Display1in54c;
 /// Epd1in54c driver
 pub struct Epd1in54c<SPI, CS, BUSY, DC, RST, DELAY> {
     interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
-    color: Color,
+    color: TriColor,
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
     for Epd1in54c<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         // Based on Reference Program Code from:
         // https://www.waveshare.com/w/upload/a/ac/1.54inch_e-Paper_Module_C_Specification.pdf
         // and:
         // https://github.com/waveshare/e-Paper/blob/master/STM32/STM32-F103ZET6/User/e-Paper/EPD_1in54c.c
-        self.interface.reset(delay, 10, 2);
+        self.interface.reset(delay, 10, 2)?;
 
         // start the booster
         self.cmd_with_data(spi, Command::BoosterSoftStart, &[0x17, 0x17, 0x17])?;
@@ -59,7 +76,7 @@ where
         // power on
         self.command(spi, Command::PowerOn)?;
         delay.delay_ms(5);
-        self.wait_until_idle();
+        self.wait_until_idle_with_timeout(delay)?;
 
         // set the panel settings
         self.cmd_with_data(spi, Command::PanelSetting, &[0x0f, 0x0d])?;
@@ -77,10 +94,10 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareThreeColorDisplay<SPI, CS, BUSY, DC
     for Epd1in54c<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
     fn update_color_frame(
@@ -88,12 +105,17 @@ where
         spi: &mut SPI,
         black: &[u8],
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_achromatic_frame(spi, black)?;
         self.update_chromatic_frame(spi, chromatic)
     }
 
-    fn update_achromatic_frame(&mut self, spi: &mut SPI, black: &[u8]) -> Result<(), SPI::Error> {
+    fn update_achromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        black: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(black, WIDTH, HEIGHT)?;
         self.wait_until_idle();
         self.cmd_with_data(spi, Command::DataStartTransmission1, black)?;
 
@@ -104,7 +126,8 @@ where
         &mut self,
         spi: &mut SPI,
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(chromatic, WIDTH, HEIGHT)?;
         self.wait_until_idle();
         self.cmd_with_data(spi, Command::DataStartTransmission2, chromatic)?;
 
@@ -112,17 +135,37 @@ where
     }
 }
 
+impl<SPI, BUSY, DC, RST, DELAY> Epd1in54c<SPI, AutoCs, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Creates the driver for a board without a CS GPIO, see [AutoCs]
+    pub fn new_without_cs(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new(spi, AutoCs, busy, dc, rst, delay)
+    }
+}
+
 impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
     for Epd1in54c<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
-    type DisplayColor = Color;
+    type DisplayColor = TriColor;
     fn new(
         spi: &mut SPI,
         cs: CS,
@@ -130,10 +173,20 @@ where
         dc: DC,
         rst: RST,
         delay: &mut DELAY,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(cs, busy, dc, rst);
-        let color = DEFAULT_BACKGROUND_COLOR;
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_background(spi, cs, busy, dc, rst, delay, DEFAULT_BACKGROUND_COLOR)
+    }
 
+    fn new_with_background(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        color: Self::DisplayColor,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(cs, busy, dc, rst);
         let mut epd = Epd1in54c { interface, color };
 
         epd.init(spi, delay)?;
@@ -141,25 +194,26 @@ where
         Ok(epd)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle_with_timeout(delay)?;
 
         self.command(spi, Command::PowerOff)?;
-        self.wait_until_idle();
+        self.wait_until_idle_with_timeout(delay)?;
         self.cmd_with_data(spi, Command::DeepSleep, &[0xa5])?;
 
         Ok(())
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.check_reset_pin()?;
         self.init(spi, delay)
     }
 
-    fn set_background_color(&mut self, color: Color) {
+    fn set_background_color(&mut self, color: TriColor) {
         self.color = color;
     }
 
-    fn background_color(&self) -> &Color {
+    fn background_color(&self) -> &TriColor {
         &self.color
     }
 
@@ -171,16 +225,21 @@ where
         HEIGHT
     }
 
+    fn full_refresh_ms(&self) -> u32 {
+        15_000
+    }
+
     fn update_frame(
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
         _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer, WIDTH, HEIGHT)?;
         self.update_achromatic_frame(spi, buffer)?;
 
-        // Clear the chromatic layer
-        let color = self.color.get_byte_value();
+        // Fill the chromatic layer with the background
+        let (_, color) = background_bytes(self.color);
 
         self.command(spi, Command::DataStartTransmission2)?;
         self.interface.data_x_times(spi, color, NUM_DISPLAY_BITS)?;
@@ -188,22 +247,23 @@ where
         Ok(())
     }
 
-    #[allow(unused)]
+    /// Partial windows aren't supported, returns [Error::PartialUpdateUnsupported]
     fn update_partial_frame(
         &mut self,
-        spi: &mut SPI,
-        buffer: &[u8],
-        x: u32,
-        y: u32,
-        width: u32,
-        height: u32,
-    ) -> Result<(), SPI::Error> {
-        unimplemented!()
+        _spi: &mut SPI,
+        _buffer: &[u8],
+        _x: u32,
+        _y: u32,
+        _width: u32,
+        _height: u32,
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        Err(Error::PartialUpdateUnsupported)
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.command(spi, Command::DisplayRefresh)?;
-        self.wait_until_idle();
+        self.wait_until_idle_with_timeout(delay)?;
 
         Ok(())
     }
@@ -213,24 +273,25 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
 
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
-        let color = DEFAULT_BACKGROUND_COLOR.get_byte_value();
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle_with_timeout(delay)?;
+        let (bw, chromatic) = background_bytes(self.color);
 
         // Clear the black
         self.command(spi, Command::DataStartTransmission1)?;
-        self.interface.data_x_times(spi, color, NUM_DISPLAY_BITS)?;
+        self.interface.data_x_times(spi, bw, NUM_DISPLAY_BITS)?;
 
         // Clear the chromatic
         self.command(spi, Command::DataStartTransmission2)?;
-        self.interface.data_x_times(spi, color, NUM_DISPLAY_BITS)?;
+        self.interface
+            .data_x_times(spi, chromatic, NUM_DISPLAY_BITS)?;
 
         Ok(())
     }
@@ -239,29 +300,51 @@ where
         &mut self,
         _spi: &mut SPI,
         _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         Ok(())
     }
 
     fn is_busy(&self) -> bool {
         self.interface.is_busy(IS_BUSY_LOW)
     }
+
+    fn send_command_raw(&mut self, spi: &mut SPI, command: u8) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn send_data_raw(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        self.interface.data(spi, data)
+    }
+
+    fn set_spi_chunk_size(&mut self, chunk_size: usize) {
+        self.interface.set_chunk_size(chunk_size);
+    }
+
+    fn set_reset_timing(&mut self, timing: Option<ResetTiming>) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    #[cfg(feature = "test-patterns")]
+    fn set_test_pattern(&mut self, enable: bool) {
+        let pattern = crate::test_pattern::FillPattern::new(self.width(), 1);
+        self.interface.set_fill_pattern(enable.then_some(pattern));
+    }
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> Epd1in54c<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd(spi, command)
     }
 
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
         self.interface.data(spi, data)
     }
 
@@ -270,7 +353,7 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
@@ -278,7 +361,12 @@ where
         self.interface.wait_until_idle(IS_BUSY_LOW);
     }
 
-    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    fn wait_until_idle_with_timeout(&mut self, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .wait_until_idle_with_timeout(delay, IS_BUSY_LOW, BUSY_TIMEOUT_MS)
+    }
+
+    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
         let w = self.width();
         let h = self.height();
 
@@ -298,3 +386,141 @@ where
         self.send_data(spi, &[h as u8])
     }
 }
+
+/// Bytes of the b/w and the chromatic plane for a whole background in `color`
+fn background_bytes(color: TriColor) -> (u8, u8) {
+    match color {
+        TriColor::Black => (0x00, 0xFF),
+        TriColor::White => (0xFF, 0xFF),
+        TriColor::Chromatic | TriColor::Yellow => (0xFF, 0x00),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{cmd, test_epd, DcPin, HighPin, NoDelay, RecordingSpi};
+    extern crate std;
+    use std::vec;
+
+    type TestEpd = Epd1in54c<RecordingSpi, HighPin, HighPin, DcPin, HighPin, NoDelay>;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 152);
+        assert_eq!(HEIGHT, 152);
+        // 152 is a multiple of 8, the rows need no padding
+        assert_eq!(NUM_DISPLAY_BITS, 19 * 152);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, TriColor::White);
+    }
+
+    #[test]
+    fn init_sequence() {
+        let (spi, _) = test_epd::<TestEpd, _>();
+        let expected = [
+            cmd(0x06, &[0x17, 0x17, 0x17]),
+            cmd(0x04, &[]),
+            cmd(0x00, &[0x0F, 0x0D]),
+            cmd(0x61, &[0x98, 0x00, 0x98]),
+            cmd(0x50, &[0x77]),
+        ]
+        .concat();
+        assert_eq!(spi.sent, expected);
+    }
+
+    #[test]
+    fn white_clear_sets_all_bits_of_both_planes() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+
+        epd.clear_frame(&mut spi, &mut NoDelay).unwrap();
+
+        // a cleared bit in the chromatic plane would be yellow
+        let expected = [
+            cmd(0x10, &[0xFF; NUM_DISPLAY_BITS as usize]),
+            cmd(0x13, &[0xFF; NUM_DISPLAY_BITS as usize]),
+        ]
+        .concat();
+        assert_eq!(spi.sent, expected);
+    }
+
+    #[test]
+    fn clear_follows_the_background_color() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        let n = NUM_DISPLAY_BITS as usize;
+
+        for (color, bw, chromatic) in [
+            (TriColor::Black, 0x00, 0xFF),
+            (TriColor::Yellow, 0xFF, 0x00),
+        ] {
+            epd.set_background_color(color);
+            spi.sent.clear();
+            epd.clear_frame(&mut spi, &mut NoDelay).unwrap();
+            let expected = [cmd(0x10, &vec![bw; n]), cmd(0x13, &vec![chromatic; n])].concat();
+            assert_eq!(spi.sent, expected);
+        }
+    }
+
+    #[test]
+    fn update_frame_rejects_other_sizes() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+        let padded = [0xFF; 20 * HEIGHT as usize];
+
+        assert_eq!(
+            epd.update_frame(&mut spi, &padded, &mut NoDelay),
+            Err(Error::BufferSize {
+                expected: NUM_DISPLAY_BITS as usize,
+                got: padded.len(),
+            })
+        );
+        assert!(spi.sent.is_empty());
+    }
+
+    #[test]
+    fn yellow_background_fills_the_chromatic_plane() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        epd.set_background_color(TriColor::Yellow);
+        spi.sent.clear();
+        let black = [0xFF; NUM_DISPLAY_BITS as usize];
+
+        epd.update_frame(&mut spi, &black, &mut NoDelay).unwrap();
+
+        let expected = [
+            cmd(0x10, &black),
+            cmd(0x13, &[0x00; NUM_DISPLAY_BITS as usize]),
+        ]
+        .concat();
+        assert_eq!(spi.sent, expected);
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn yellow_pixels_clear_the_chromatic_bit() {
+        use crate::graphics::TriDisplay;
+        use embedded_graphics_core::prelude::*;
+
+        let mut display = Display1in54c::default();
+        Pixel(Point::new(0, 0), TriColor::Yellow)
+            .draw(&mut display)
+            .unwrap();
+        Pixel(Point::new(1, 0), TriColor::Chromatic)
+            .draw(&mut display)
+            .unwrap();
+
+        assert_eq!(display.bw_buffer()[0], 0xFF);
+        assert_eq!(display.chromatic_buffer()[0], 0x3F);
+    }
+
+    #[test]
+    fn partial_frames_are_rejected() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+
+        assert_eq!(
+            epd.update_partial_frame(&mut spi, &[0; 8], 0, 0, 8, 8, &mut NoDelay),
+            Err(Error::PartialUpdateUnsupported)
+        );
+        assert!(spi.sent.is_empty());
+    }
+}
@@ -1,8 +1,11 @@
 use crate::buffer_len;
+use crate::color::Color;
 use crate::epd2in13_v2::{DEFAULT_BACKGROUND_COLOR, HEIGHT, WIDTH};
 use crate::graphics::{Display, DisplayRotation};
+use crate::traits::FrameBuffer;
 use embedded_graphics_core::pixelcolor::BinaryColor;
 use embedded_graphics_core::prelude::*;
+use embedded_graphics_core::primitives::Rectangle;
 
 /// Full size buffer for use with the 2in13 v2 EPD
 ///
@@ -36,6 +39,10 @@ impl DrawTarget for Display2in13 {
         }
         Ok(())
     }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.fill_solid_helper(WIDTH, HEIGHT, area, color)
+    }
 }
 
 impl OriginDimensions for Display2in13 {
@@ -65,6 +72,22 @@ impl Display for Display2in13 {
     }
 }
 
+impl FrameBuffer for Display2in13 {
+    type Color = Color;
+
+    fn as_bytes(&self) -> &[u8] {
+        self.buffer()
+    }
+
+    fn as_bytes_mut(&mut self) -> &mut [u8] {
+        self.get_mut_buffer()
+    }
+
+    fn clear(&mut self, color: Color) {
+        self.clear_buffer(color);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -9,14 +9,23 @@
 //!
 
 use embedded_hal::{
-    blocking::{delay::*, spi::Write},
+    blocking::{
+        delay::*,
+        spi::{Transfer, Write},
+    },
     digital::v2::{InputPin, OutputPin},
 };
 
-use crate::buffer_len;
 use crate::color::Color;
+use crate::error::Error;
 use crate::interface::DisplayInterface;
-use crate::traits::{InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::monitor::{FrameCheck, FrameMonitor};
+use crate::power::{PowerState, Transition};
+use crate::timing::{Clock, Operation, ResetTiming, Timings};
+use crate::traits::{InternalWiAdditions, QuickRefresh, RefreshLut, WaveshareDisplay};
+use crate::window::WindowLimits;
+use crate::{buffer_len, check_buffer_len};
+use crate::{AutoCs, CsStrategy, ResetPin};
 
 pub(crate) mod command;
 use self::command::{
@@ -38,10 +47,22 @@ pub const WIDTH: u32 = 122;
 
 /// Height of the display
 pub const HEIGHT: u32 = 250;
+/// Limits of the partial window registers: 6 bit x byte address and 9 bit y address, the whole panel fits
+pub const WINDOW_LIMITS: WindowLimits = WindowLimits::new(WIDTH, HEIGHT, 511, 511);
 
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
 const IS_BUSY_LOW: bool = false;
+/// Longest time in milliseconds to wait for the busy pin before giving up with
+/// [Error::Timeout]
+pub const BUSY_TIMEOUT_MS: u32 = 10_000;
+/// Fastest SPI clock of the controller in Hz, see
+/// [check_spi_frequency()](crate::check_spi_frequency())
+pub const MAX_SPI_FREQ_HZ: u32 = 20_000_000;
+
+/// Padding bits of the last byte of every row, they hold the signature for
+/// [verify_last_frame()](Epd2in13::verify_last_frame())
+const PADDING_MASK: u8 = 0xFF >> (WIDTH % 8);
 
 /// Epd2in13 (V2) driver
 ///
@@ -54,25 +75,30 @@ pub struct Epd2in13<SPI, CS, BUSY, DC, RST, DELAY> {
     /// Background Color
     background_color: Color,
     refresh: RefreshLut,
+    /// Mode of the controller
+    state: PowerState,
+    /// Generation of the frames
+    monitor: FrameMonitor,
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
     for Epd2in13<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state = self.state.check(Transition::Init)?;
         // HW reset
-        self.interface.reset(delay, 10, 10);
+        self.interface.reset(delay, 10, 10)?;
 
         if self.refresh == RefreshLut::Quick {
             self.set_vcom_register(spi, (-9).vcom())?;
-            self.wait_until_idle();
+            self.wait_until_idle_with_timeout(delay)?;
 
             self.set_lut(spi, Some(self.refresh))?;
 
@@ -86,7 +112,7 @@ where
                 DisplayUpdateControl2::new().enable_analog().enable_clock(),
             )?;
             self.command(spi, Command::MasterActivation)?;
-            self.wait_until_idle();
+            self.wait_until_idle_with_timeout(delay)?;
 
             self.set_border_waveform(
                 spi,
@@ -97,9 +123,9 @@ where
                 },
             )?;
         } else {
-            self.wait_until_idle();
+            self.wait_until_idle_with_timeout(delay)?;
             self.command(spi, Command::SwReset)?;
-            self.wait_until_idle();
+            self.wait_until_idle_with_timeout(delay)?;
 
             self.set_driver_output(
                 spi,
@@ -145,19 +171,39 @@ where
             self.set_lut(spi, Some(self.refresh))?;
         }
 
-        self.wait_until_idle();
+        self.wait_until_idle_with_timeout(delay)?;
         Ok(())
     }
 }
 
+impl<SPI, BUSY, DC, RST, DELAY> Epd2in13<SPI, AutoCs, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Creates the driver for a board without a CS GPIO, see [AutoCs]
+    pub fn new_without_cs(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new(spi, AutoCs, busy, dc, rst, delay)
+    }
+}
+
 impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
     for Epd2in13<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
     type DisplayColor = Color;
@@ -168,24 +214,40 @@ where
         dc: DC,
         rst: RST,
         delay: &mut DELAY,
-    ) -> Result<Self, SPI::Error> {
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_background(spi, cs, busy, dc, rst, delay, DEFAULT_BACKGROUND_COLOR)
+    }
+
+    fn new_with_background(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        color: Self::DisplayColor,
+    ) -> Result<Self, Error<SPI::Error>> {
         let mut epd = Epd2in13 {
             interface: DisplayInterface::new(cs, busy, dc, rst),
             sleep_mode: DeepSleepMode::Mode1,
-            background_color: DEFAULT_BACKGROUND_COLOR,
+            background_color: color,
             refresh: RefreshLut::Full,
+            state: PowerState::Active,
+            monitor: FrameMonitor::default(),
         };
 
         epd.init(spi, delay)?;
         Ok(epd)
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.check_reset_pin()?;
         self.init(spi, delay)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        let next = self.state.check(Transition::Sleep)?;
+        self.wait_until_idle_with_timeout(delay)?;
 
         // All sample code enables and disables analog/clocks...
         self.set_display_update_control_2(
@@ -199,6 +261,7 @@ where
         self.command(spi, Command::MasterActivation)?;
 
         self.set_sleep_mode(spi, self.sleep_mode)?;
+        self.state = next;
         Ok(())
     }
 
@@ -206,22 +269,11 @@ where
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
-        _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
-        assert!(buffer.len() == buffer_len(WIDTH as usize, HEIGHT as usize));
-        self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
-        self.set_ram_address_counters(spi, 0, 0)?;
-
-        self.cmd_with_data(spi, Command::WriteRam, buffer)?;
-
-        if self.refresh == RefreshLut::Full {
-            // Always keep the base buffer equal to current if not doing partial refresh.
-            self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
-            self.set_ram_address_counters(spi, 0, 0)?;
-
-            self.cmd_with_data(spi, Command::WriteRamRed, buffer)?;
-        }
-        Ok(())
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        let result = self.write_frame(spi, buffer, self.refresh == RefreshLut::Full, delay);
+        self.monitor.written(&result);
+        result
     }
 
     /// Updating only a part of the frame is not supported when using the
@@ -235,8 +287,12 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
-        assert!((width * height / 8) as usize == buffer.len());
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        WINDOW_LIMITS.validate_aligned(x, y, width, height)?;
+        check_buffer_len(buffer, width, height)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
 
         // This should not be used when doing partial refresh. The RAM_RED must
         // be updated with the last buffer having been displayed. Doing partial
@@ -264,7 +320,8 @@ where
 
     /// Never use directly this function when using partial refresh, or also
     /// keep the base buffer in syncd using `set_partial_base_buffer` function.
-    fn display_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Refresh)?;
         if self.refresh == RefreshLut::Full {
             self.set_display_update_control_2(
                 spi,
@@ -278,10 +335,14 @@ where
         } else {
             self.set_display_update_control_2(spi, DisplayUpdateControl2::new().display())?;
         }
+        self.interface.start_measurement(self.refresh.into());
         self.command(spi, Command::MasterActivation)?;
-        self.wait_until_idle();
-
-        Ok(())
+        let result = self
+            .interface
+            .measure_busy_period(delay, IS_BUSY_LOW, BUSY_TIMEOUT_MS)
+            .and_then(|()| self.wait_until_idle_with_timeout(delay));
+        self.monitor.displayed(&result);
+        result
     }
 
     fn update_and_display_frame(
@@ -289,7 +350,7 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
 
@@ -299,32 +360,10 @@ where
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        let color = self.background_color.get_byte_value();
-
-        self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
-        self.set_ram_address_counters(spi, 0, 0)?;
-
-        self.command(spi, Command::WriteRam)?;
-        self.interface.data_x_times(
-            spi,
-            color,
-            buffer_len(WIDTH as usize, HEIGHT as usize) as u32,
-        )?;
-
-        // Always keep the base buffer equals to current if not doing partial refresh.
-        if self.refresh == RefreshLut::Full {
-            self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
-            self.set_ram_address_counters(spi, 0, 0)?;
-
-            self.command(spi, Command::WriteRamRed)?;
-            self.interface.data_x_times(
-                spi,
-                color,
-                buffer_len(WIDTH as usize, HEIGHT as usize) as u32,
-            )?;
-        }
-        Ok(())
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        let result = self.write_clear_frame(spi, delay);
+        self.monitor.written(&result);
+        result
     }
 
     fn set_background_color(&mut self, background_color: Color) {
@@ -343,14 +382,24 @@ where
         HEIGHT
     }
 
+    fn full_refresh_ms(&self) -> u32 {
+        2000
+    }
+
+    fn quick_refresh_ms(&self) -> u32 {
+        300
+    }
+
     fn set_lut(
         &mut self,
         spi: &mut SPI,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Update)?;
         let buffer = match refresh_rate {
             Some(RefreshLut::Full) | None => &LUT_FULL_UPDATE,
             Some(RefreshLut::Quick) => &LUT_PARTIAL_UPDATE,
+            Some(RefreshLut::Custom(_)) => return Err(Error::UnsupportedLut),
         };
 
         self.cmd_with_data(spi, Command::WriteLutRegister, buffer)
@@ -359,15 +408,162 @@ where
     fn is_busy(&self) -> bool {
         self.interface.is_busy(IS_BUSY_LOW)
     }
+
+    fn send_command_raw(&mut self, spi: &mut SPI, command: u8) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn send_data_raw(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        self.interface.data(spi, data)
+    }
+
+    fn set_spi_chunk_size(&mut self, chunk_size: usize) {
+        self.interface.set_chunk_size(chunk_size);
+    }
+
+    fn set_reset_timing(&mut self, timing: Option<ResetTiming>) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    #[cfg(feature = "test-patterns")]
+    fn set_test_pattern(&mut self, enable: bool) {
+        let pattern = crate::test_pattern::FillPattern::new(self.width(), 1);
+        self.interface.set_fill_pattern(enable.then_some(pattern));
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> QuickRefresh<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd2in13<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Writes the frame the panel currently shows into the base RAM (`WriteRamRed`), the
+    /// same as [set_partial_base_buffer()](Epd2in13::set_partial_base_buffer())
+    fn update_old_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer, WIDTH, HEIGHT)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.set_partial_base_buffer(spi, buffer)
+    }
+
+    /// Writes the next frame into the new RAM, the base RAM is left alone in both refresh
+    /// modes
+    fn update_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        let result = self.write_frame(spi, buffer, false, delay);
+        self.monitor.written(&result);
+        result
+    }
+
+    /// Refreshes the panel with the quick LUT, which only drives the pixels that differ
+    /// between the base and the new RAM
+    ///
+    /// Switching to the quick refresh initializes the controller again, see
+    /// [set_refresh()](Epd2in13::set_refresh()). It stays selected afterwards.
+    fn display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.set_refresh(spi, delay, RefreshLut::Quick)?;
+        self.display_frame(spi, delay)
+    }
+
+    fn update_and_display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_new_frame(spi, buffer, delay)?;
+        self.display_new_frame(spi, delay)
+    }
+
+    fn update_partial_old_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        WINDOW_LIMITS.validate_aligned(x, y, width, height)?;
+        check_buffer_len(buffer, width, height)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.set_window(spi, x, y, width, height)?;
+        self.cmd_with_data(spi, Command::WriteRamRed, buffer)
+    }
+
+    /// Writes the window into the new RAM only, unlike
+    /// [update_partial_frame()](WaveshareDisplay::update_partial_frame()) this works in both
+    /// refresh modes
+    fn update_partial_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        WINDOW_LIMITS.validate_aligned(x, y, width, height)?;
+        check_buffer_len(buffer, width, height)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.set_window(spi, x, y, width, height)?;
+        self.cmd_with_data(spi, Command::WriteRam, buffer)
+    }
+
+    /// Fills the window of the new RAM with the background color
+    fn clear_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        WINDOW_LIMITS.validate_aligned(x, y, width, height)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.set_window(spi, x, y, width, height)?;
+
+        let color = self.background_color.get_byte_value();
+        self.command(spi, Command::WriteRam)?;
+        self.interface.data_x_times(
+            spi,
+            color,
+            buffer_len(width as usize, height as usize) as u32,
+        )
+    }
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> Epd2in13<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
     /// When using partial refresh, the controller uses the provided buffer for
@@ -376,8 +572,9 @@ where
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
-    ) -> Result<(), SPI::Error> {
-        assert!(buffer_len(WIDTH as usize, HEIGHT as usize) == buffer.len());
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer, WIDTH, HEIGHT)?;
+        self.wait_until_idle();
         self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
         self.set_ram_address_counters(spi, 0, 0)?;
 
@@ -397,7 +594,7 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         refresh: RefreshLut,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         if self.refresh != refresh {
             self.refresh = refresh;
             self.init(spi, delay)?;
@@ -409,7 +606,7 @@ where
         &mut self,
         spi: &mut SPI,
         start: u16,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         assert!(start <= 295);
         self.cmd_with_data(
             spi,
@@ -422,7 +619,7 @@ where
         &mut self,
         spi: &mut SPI,
         borderwaveform: BorderWaveForm,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.cmd_with_data(
             spi,
             Command::BorderWaveformControl,
@@ -430,7 +627,7 @@ where
         )
     }
 
-    fn set_vcom_register(&mut self, spi: &mut SPI, vcom: Vcom) -> Result<(), SPI::Error> {
+    fn set_vcom_register(&mut self, spi: &mut SPI, vcom: Vcom) -> Result<(), Error<SPI::Error>> {
         self.cmd_with_data(spi, Command::WriteVcomRegister, &[vcom.0])
     }
 
@@ -438,7 +635,7 @@ where
         &mut self,
         spi: &mut SPI,
         voltage: GateDrivingVoltage,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.cmd_with_data(spi, Command::GateDrivingVoltageCtrl, &[voltage.0])
     }
 
@@ -446,12 +643,12 @@ where
         &mut self,
         spi: &mut SPI,
         number_of_lines: u8,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         assert!(number_of_lines <= 127);
         self.cmd_with_data(spi, Command::SetDummyLinePeriod, &[number_of_lines])
     }
 
-    fn set_gate_line_width(&mut self, spi: &mut SPI, width: u8) -> Result<(), SPI::Error> {
+    fn set_gate_line_width(&mut self, spi: &mut SPI, width: u8) -> Result<(), Error<SPI::Error>> {
         self.cmd_with_data(spi, Command::SetGateLineWidth, &[width & 0x0F])
     }
 
@@ -462,7 +659,7 @@ where
         vsh1: SourceDrivingVoltage,
         vsh2: SourceDrivingVoltage,
         vsl: SourceDrivingVoltage,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.cmd_with_data(
             spi,
             Command::SourceDrivingVoltageCtrl,
@@ -476,19 +673,112 @@ where
         &mut self,
         spi: &mut SPI,
         value: DisplayUpdateControl2,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[value.0])
     }
 
     /// Triggers the deep sleep mode
-    fn set_sleep_mode(&mut self, spi: &mut SPI, mode: DeepSleepMode) -> Result<(), SPI::Error> {
+    fn set_sleep_mode(
+        &mut self,
+        spi: &mut SPI,
+        mode: DeepSleepMode,
+    ) -> Result<(), Error<SPI::Error>> {
         self.cmd_with_data(spi, Command::DeepSleepMode, &[mode as u8])
     }
 
-    fn set_driver_output(&mut self, spi: &mut SPI, output: DriverOutput) -> Result<(), SPI::Error> {
+    fn set_driver_output(
+        &mut self,
+        spi: &mut SPI,
+        output: DriverOutput,
+    ) -> Result<(), Error<SPI::Error>> {
         self.cmd_with_data(spi, Command::DriverOutputControl, &output.to_bytes())
     }
 
+    /// Writes a whole frame and the signature of its generation, with `keep_base` also into
+    /// the base RAM
+    fn write_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        keep_base: bool,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer, WIDTH, HEIGHT)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
+        self.set_ram_address_counters(spi, 0, 0)?;
+
+        self.cmd_with_data(spi, Command::WriteRam, buffer)?;
+
+        if keep_base {
+            // Always keep the base buffer equal to current if not doing partial refresh.
+            self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
+            self.set_ram_address_counters(spi, 0, 0)?;
+
+            self.cmd_with_data(spi, Command::WriteRamRed, buffer)?;
+        }
+        self.write_signature(spi, buffer[buffer.len() - 1])
+    }
+
+    /// Fills the RAM with the background color and writes the signature of its generation
+    fn write_clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        let color = self.background_color.get_byte_value();
+
+        self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
+        self.set_ram_address_counters(spi, 0, 0)?;
+        self.interface.start_measurement(Operation::Clear);
+
+        self.command(spi, Command::WriteRam)?;
+        self.interface.data_x_times(
+            spi,
+            color,
+            buffer_len(WIDTH as usize, HEIGHT as usize) as u32,
+        )?;
+
+        // Always keep the base buffer equals to current if not doing partial refresh.
+        if self.refresh == RefreshLut::Full {
+            self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
+            self.set_ram_address_counters(spi, 0, 0)?;
+
+            self.command(spi, Command::WriteRamRed)?;
+            self.interface.data_x_times(
+                spi,
+                color,
+                buffer_len(WIDTH as usize, HEIGHT as usize) as u32,
+            )?;
+        }
+        self.write_signature(spi, color)?;
+        self.interface
+            .measure_busy_period(delay, IS_BUSY_LOW, BUSY_TIMEOUT_MS)
+    }
+
+    /// Writes the last byte of the last row again, with the generation of the frame in the
+    /// padding bits
+    fn write_signature(&mut self, spi: &mut SPI, last_byte: u8) -> Result<(), Error<SPI::Error>> {
+        let signature = signature(self.monitor.next_generation());
+        self.set_ram_address_counters(spi, WIDTH - 1, HEIGHT - 1)?;
+        self.cmd_with_data(
+            spi,
+            Command::WriteRam,
+            &[(last_byte & !PADDING_MASK) | signature],
+        )
+    }
+
+    /// Checks that the panel shows the last frame, without reading the RAM
+    ///
+    /// This is the weaker check for boards which don't connect the data line of the
+    /// controller to MISO, see [FrameCheck::Consistent].
+    pub fn last_frame_state(&self) -> FrameCheck {
+        self.monitor.check()
+    }
+
     /// Sets the data entry mode (ie. how X and Y positions changes when writing
     /// data to RAM)
     fn set_data_entry_mode(
@@ -496,7 +786,7 @@ where
         spi: &mut SPI,
         counter_incr_mode: DataEntryModeIncr,
         counter_direction: DataEntryModeDir,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         let mode = counter_incr_mode as u8 | counter_direction as u8;
         self.cmd_with_data(spi, Command::DataEntryModeSetting, &[mode])
     }
@@ -509,7 +799,7 @@ where
         start_y: u32,
         end_x: u32,
         end_y: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.cmd_with_data(
             spi,
             Command::SetRamXAddressStartEndPosition,
@@ -528,14 +818,26 @@ where
         )
     }
 
+    /// Sets the RAM area and the counters to a window, `x` and `width` aligned to bytes
+    fn set_window(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)?;
+        self.set_ram_address_counters(spi, x, y)
+    }
+
     /// Sets both X and Y pixels counters when writing data to RAM
     fn set_ram_address_counters(
         &mut self,
         spi: &mut SPI,
         x: u32,
         y: u32,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    ) -> Result<(), Error<SPI::Error>> {
         self.cmd_with_data(spi, Command::SetRamXAddressCounter, &[(x >> 3) as u8])?;
 
         self.cmd_with_data(
@@ -546,7 +848,7 @@ where
         Ok(())
     }
 
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd(spi, command)
     }
 
@@ -555,18 +857,94 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
     fn wait_until_idle(&mut self) {
         self.interface.wait_until_idle(IS_BUSY_LOW);
     }
+
+    fn wait_until_idle_with_timeout(&mut self, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .wait_until_idle_with_timeout(delay, IS_BUSY_LOW, BUSY_TIMEOUT_MS)
+    }
+
+    /// Current mode of the controller
+    pub fn power_state(&self) -> PowerState {
+        self.state
+    }
+
+    /// Sets the clock used to measure the durations of refreshes and clears
+    ///
+    /// The measurements can be read with [last_timings()](Self::last_timings()),
+    /// `None` disables them.
+    pub fn set_clock(&mut self, clock: Option<&'static dyn Clock>) {
+        self.interface.set_clock(clock);
+    }
+
+    /// Last measured durations, measured until the display wasn't busy anymore
+    pub fn last_timings(&self) -> Timings {
+        self.interface.timings()
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> Epd2in13<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8> + Transfer<u8, Error = <SPI as Write<u8>>::Error>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Checks that the panel shows the last frame and that the RAM still holds it
+    ///
+    /// [update_frame()](WaveshareDisplay::update_frame()) and
+    /// [clear_frame()](WaveshareDisplay::clear_frame()) write the lower 6 bits of the frame
+    /// generation into the padding bits of the last byte of the last row, which are outside of
+    /// the 122 visible columns. This reads them back from the black/white RAM. Partial updates
+    /// don't start a new generation, a window which reaches the bottom right corner overwrites
+    /// the signature and the check reports [FrameCheck::Stale] until the next whole frame.
+    ///
+    /// Needs a board which connects the data line of the controller to MISO, otherwise use
+    /// [last_frame_state()](Self::last_frame_state()).
+    pub fn verify_last_frame(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<FrameCheck, Error<<SPI as Write<u8>>::Error>> {
+        let generation = match self.monitor.check() {
+            FrameCheck::Consistent { generation } => generation,
+            check => return Ok(check),
+        };
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle();
+        self.cmd_with_data(spi, Command::ReadRamOption, &[0x00])?;
+        self.set_ram_address_counters(spi, WIDTH - 1, HEIGHT - 1)?;
+
+        // the first byte after the command is a dummy byte
+        let mut value = [0u8; 2];
+        self.interface
+            .cmd_with_read(spi, Command::ReadRam, &mut value)?;
+        Ok(self
+            .monitor
+            .verify(value[1] & PADDING_MASK == signature(generation)))
+    }
+}
+
+/// Signature of a frame generation in the padding bits
+fn signature(generation: u32) -> u8 {
+    generation as u8 & PADDING_MASK
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::{cmd, test_epd, DcPin, LowPin, NoDelay, RecordingSpi};
+    extern crate std;
+    use std::vec;
+
+    type TestEpd = Epd2in13<RecordingSpi, LowPin, LowPin, DcPin, LowPin, NoDelay>;
 
     #[test]
     fn epd_size() {
@@ -574,4 +952,160 @@ mod tests {
         assert_eq!(HEIGHT, 250);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn signature_is_written_into_the_padding_bits() {
+        assert_eq!(PADDING_MASK, 0x3F);
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+
+        let buffer = [0x00; 16 * 250];
+        epd.update_frame(&mut spi, &buffer, &mut NoDelay).unwrap();
+        // the two visible pixels stay black, the padding holds generation 1
+        let mut signature = cmd(0x4E, &[15]);
+        signature.extend(cmd(0x4F, &[249, 0]));
+        signature.extend(cmd(0x24, &[0x01]));
+        assert!(spi.sent.ends_with(&signature));
+        assert_eq!(
+            epd.last_frame_state(),
+            FrameCheck::Pending { generation: 0 }
+        );
+    }
+
+    #[test]
+    fn verify_last_frame_reads_back_the_signature() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        assert_eq!(epd.verify_last_frame(&mut spi), Ok(FrameCheck::NoFrame));
+
+        epd.clear_frame(&mut spi, &mut NoDelay).unwrap();
+        epd.display_frame(&mut spi, &mut NoDelay).unwrap();
+        spi.sent.clear();
+
+        // dummy byte, then white pixels with generation 1
+        spi.answers = vec![0x00, 0xC1];
+        assert_eq!(
+            epd.verify_last_frame(&mut spi),
+            Ok(FrameCheck::Verified { generation: 1 })
+        );
+        let mut expected = cmd(0x41, &[0x00]);
+        expected.extend(cmd(0x4E, &[15]));
+        expected.extend(cmd(0x4F, &[249, 0]));
+        expected.extend(cmd(0x27, &[]));
+        assert_eq!(spi.sent, expected);
+
+        // e.g. the controller was reset and lost the RAM
+        spi.answers = vec![0x00, 0xFF];
+        assert_eq!(
+            epd.verify_last_frame(&mut spi),
+            Ok(FrameCheck::Stale { generation: 1 })
+        );
+    }
+
+    #[test]
+    fn failed_update_is_reported_without_reading() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        epd.clear_frame(&mut spi, &mut NoDelay).unwrap();
+        epd.display_frame(&mut spi, &mut NoDelay).unwrap();
+
+        // the error is ignored by the application, the old frame stays on the panel
+        let _ = epd.update_frame(&mut spi, &[0x00; 10], &mut NoDelay);
+        let _ = epd.display_frame(&mut spi, &mut NoDelay);
+        spi.sent.clear();
+        assert_eq!(
+            epd.verify_last_frame(&mut spi),
+            Ok(FrameCheck::Stale { generation: 1 })
+        );
+        assert_eq!(epd.last_frame_state(), FrameCheck::Stale { generation: 1 });
+        assert!(spi.sent.is_empty());
+    }
+
+    #[test]
+    fn timings_cover_the_busy_periods() {
+        use crate::test_utils::{busy_after, busy_for, BusyPin, TEST_CLOCK};
+        type TimedEpd = Epd2in13<RecordingSpi, BusyPin, BusyPin, DcPin, BusyPin, NoDelay>;
+        busy_for(0);
+        let (mut spi, mut epd) = test_epd::<TimedEpd, _>();
+        epd.set_clock(Some(&TEST_CLOCK));
+
+        // 30 busy reads and the idle one
+        busy_after(Command::MasterActivation as u8, 30);
+        epd.display_frame(&mut spi, &mut NoDelay).unwrap();
+        assert_eq!(epd.last_timings().full_refresh_ms, Some(31));
+
+        epd.set_refresh(&mut spi, &mut NoDelay, RefreshLut::Quick)
+            .unwrap();
+        busy_after(Command::MasterActivation as u8, 12);
+        epd.display_frame(&mut spi, &mut NoDelay).unwrap();
+        assert_eq!(epd.last_timings().quick_refresh_ms, Some(13));
+        assert_eq!(epd.last_timings().full_refresh_ms, Some(31));
+
+        busy_after(Command::WriteRam as u8, 7);
+        epd.clear_frame(&mut spi, &mut NoDelay).unwrap();
+        assert_eq!(epd.last_timings().clear_ms, Some(8));
+    }
+
+    #[test]
+    fn old_and_new_frames_go_to_their_rams() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        let frame = [0x0F; buffer_len(WIDTH as usize, HEIGHT as usize)];
+
+        spi.sent.clear();
+        epd.update_old_frame(&mut spi, &frame, &mut NoDelay)
+            .unwrap();
+        assert!(spi.sent.ends_with(&cmd(0x26, &frame)));
+
+        // the base RAM isn't overwritten in the full refresh mode either
+        spi.sent.clear();
+        epd.update_new_frame(&mut spi, &frame, &mut NoDelay)
+            .unwrap();
+        let new_ram = cmd(0x24, &frame);
+        assert!(spi.sent.windows(new_ram.len()).any(|w| w == new_ram));
+        assert!(!spi.sent.contains(&cmd(0x26, &[])[0]));
+
+        spi.sent.clear();
+        epd.display_new_frame(&mut spi, &mut NoDelay).unwrap();
+        let lut = cmd(0x32, &LUT_PARTIAL_UPDATE);
+        assert!(spi.sent.windows(lut.len()).any(|w| w == lut));
+        assert!(spi
+            .sent
+            .ends_with(&[cmd(0x22, &[0x04]), cmd(0x20, &[])].concat()));
+
+        // the controller is only initialized again when switching to the quick refresh
+        spi.sent.clear();
+        epd.display_new_frame(&mut spi, &mut NoDelay).unwrap();
+        assert_eq!(spi.sent, [cmd(0x22, &[0x04]), cmd(0x20, &[])].concat());
+    }
+
+    #[test]
+    fn partial_old_and_new_frames() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+
+        spi.sent.clear();
+        epd.update_partial_old_frame(&mut spi, &[0xAA; 2], 8, 4, 16, 1, &mut NoDelay)
+            .unwrap();
+        let expected = [
+            cmd(0x44, &[1, 2]),
+            cmd(0x45, &[4, 0, 4, 0]),
+            cmd(0x4E, &[1]),
+            cmd(0x4F, &[4, 0]),
+            cmd(0x26, &[0xAA; 2]),
+        ]
+        .concat();
+        assert_eq!(spi.sent, expected);
+
+        spi.sent.clear();
+        epd.update_partial_new_frame(&mut spi, &[0x55; 2], 8, 4, 16, 1, &mut NoDelay)
+            .unwrap();
+        assert!(spi.sent.ends_with(&cmd(0x24, &[0x55; 2])));
+
+        spi.sent.clear();
+        epd.clear_partial_frame(&mut spi, 8, 4, 16, 2, &mut NoDelay)
+            .unwrap();
+        assert!(spi.sent.ends_with(&cmd(0x24, &[0xFF; 4])));
+
+        assert_eq!(
+            epd.update_partial_new_frame(&mut spi, &[0x55; 2], 4, 0, 16, 1, &mut NoDelay),
+            Err(Error::InvalidRegion)
+        );
+    }
 }
@@ -1,6 +1,7 @@
 use crate::color::TriColor;
 use crate::epd2in13bc::{DEFAULT_BACKGROUND_COLOR, HEIGHT, NUM_DISPLAY_BITS, WIDTH};
 use crate::graphics::{DisplayColorRendering, DisplayRotation, TriDisplay};
+use crate::traits::FrameBuffer;
 use embedded_graphics_core::prelude::*;
 
 /// Full size buffer for use with the 2.13" b/c EPD
@@ -75,3 +76,19 @@ impl TriDisplay for Display2in13bc {
         &self.buffer[self.chromatic_offset()..]
     }
 }
+
+impl FrameBuffer for Display2in13bc {
+    type Color = TriColor;
+
+    fn as_bytes(&self) -> &[u8] {
+        self.buffer()
+    }
+
+    fn as_bytes_mut(&mut self) -> &mut [u8] {
+        self.get_mut_buffer()
+    }
+
+    fn clear(&mut self, color: TriColor) {
+        self.clear_buffer(color);
+    }
+}
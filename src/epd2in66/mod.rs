@@ -0,0 +1,645 @@
+//! A simple Driver for the Waveshare 2.66" E-Ink Display via SPI
+//!
+//! The SSD1680 has a RAM of 176x296 pixels, the 152 columns of the panel are connected to the
+//! RAM columns 8 to 159. The driver moves all RAM windows and counters by these 8 pixels, so
+//! buffers have exactly `WIDTH / 8 * HEIGHT` bytes and partial windows use panel coordinates.
+//!
+//! # References
+//!
+//! - [Waveshare wiki](https://www.waveshare.com/wiki/Pico-ePaper-2.66)
+//! - [Waveshare C driver](https://github.com/waveshare/Pico_ePaper_Code/blob/main/c/lib/e-Paper/EPD_2in66.c)
+
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::{InputPin, OutputPin},
+};
+
+use crate::check_buffer_len;
+use crate::color::Color;
+use crate::error::Error;
+use crate::interface::DisplayInterface;
+use crate::power::{PowerState, Transition};
+use crate::timing::ResetTiming;
+use crate::traits::{BorderColor, InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::type_a::{border_waveform, command::Command};
+use crate::window::WindowLimits;
+use crate::{AutoCs, CsStrategy, ResetPin};
+
+mod constants;
+use self::constants::LUT_QUICK_UPDATE;
+
+#[cfg(feature = "graphics")]
+mod graphics;
+#[cfg(feature = "graphics")]
+pub use self::graphics::Display2in66;
+
+/// Width of the display
+pub const WIDTH: u32 = 152;
+/// Height of the display
+pub const HEIGHT: u32 = 296;
+/// Limits of the partial window registers: 5 bit x byte address (of which the first byte is
+/// skipped) and 9 bit y address, the whole panel fits
+pub const WINDOW_LIMITS: WindowLimits = WindowLimits::new(WIDTH, HEIGHT, 247, 511);
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+const IS_BUSY_LOW: bool = false;
+/// Longest time in milliseconds to wait for the busy pin before giving up with
+/// [Error::Timeout]
+pub const BUSY_TIMEOUT_MS: u32 = 10_000;
+/// Fastest SPI clock of the controller in Hz, see
+/// [check_spi_frequency()](crate::check_spi_frequency())
+pub const MAX_SPI_FREQ_HZ: u32 = 20_000_000;
+
+/// First RAM column of the panel, the RAM is 176 pixels wide
+pub(crate) const RAM_X_OFFSET: u32 = 8;
+const NUM_DISPLAY_BITS: u32 = WIDTH / 8 * HEIGHT;
+
+/// Epd2in66 driver
+///
+pub struct Epd2in66<SPI, CS, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+    /// Background Color
+    background_color: Color,
+    /// Refresh LUT
+    refresh: RefreshLut,
+    /// Mode of the controller
+    state: PowerState,
+    /// Border color set with `set_border_color()`, `None` for the waveforms of the vendor
+    border: Option<BorderColor>,
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd2in66<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state = self.state.check(Transition::Init)?;
+        self.interface.reset(delay, 10, 2)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.interface.cmd(spi, Command::SwReset)?;
+        self.wait_until_idle_with_timeout(delay)?;
+
+        // x and y increment, the address counter is updated in x direction
+        self.interface
+            .cmd_with_data(spi, Command::DataEntryModeSetting, &[0x03])?;
+
+        self.use_full_frame(spi)?;
+
+        if let Some(color) = self.border {
+            self.interface.cmd_with_data(
+                spi,
+                Command::BorderWaveformControl,
+                &[border_waveform(color)],
+            )?;
+        }
+
+        // the soft reset loads the waveform from the OTP, a quick LUT is gone
+        self.refresh = RefreshLut::Full;
+
+        self.wait_until_idle_with_timeout(delay)?;
+        Ok(())
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd2in66<SPI, AutoCs, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Creates the driver for a board without a CS GPIO, see [AutoCs]
+    pub fn new_without_cs(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new(spi, AutoCs, busy, dc, rst, delay)
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd2in66<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    type DisplayColor = Color;
+    fn new(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_background(spi, cs, busy, dc, rst, delay, DEFAULT_BACKGROUND_COLOR)
+    }
+
+    fn new_with_background(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        color: Self::DisplayColor,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(cs, busy, dc, rst);
+
+        let mut epd = Epd2in66 {
+            interface,
+            background_color: color,
+            refresh: RefreshLut::Full,
+            state: PowerState::Active,
+            border: None,
+        };
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.check_reset_pin()?;
+        self.init(spi, delay)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        let next = self.state.check(Transition::Sleep)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        // deep sleep mode 1, the RAM is retained
+        self.interface
+            .cmd_with_data(spi, Command::DeepSleepMode, &[0x01])?;
+        self.state = next;
+        Ok(())
+    }
+
+    /// Writes the frame into the RAM
+    ///
+    /// With the full LUT the frame is also written into the second RAM bank, which the quick
+    /// refresh compares against.
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer, WIDTH, HEIGHT)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.use_full_frame(spi)?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteRam, buffer)?;
+        if self.refresh == RefreshLut::Full {
+            self.set_ram_counter(spi, 0, 0)?;
+            self.interface
+                .cmd_with_data(spi, Command::WriteRam2, buffer)?;
+        }
+        Ok(())
+    }
+
+    fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        WINDOW_LIMITS.validate_aligned(x, y, width, height)?;
+        check_buffer_len(buffer, width, height)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)?;
+        self.set_ram_counter(spi, x, y)?;
+
+        self.interface.cmd_with_data(spi, Command::WriteRam, buffer)
+    }
+
+    /// Refreshes the panel with the LUT selected by [set_lut()](WaveshareDisplay::set_lut())
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Refresh)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        let sequence = match self.refresh {
+            // load the temperature and the LUT from the OTP, display with mode 1
+            RefreshLut::Full => 0xF7,
+            // keep the LUT of set_lut, display with mode 2
+            RefreshLut::Quick => 0xCF,
+            RefreshLut::Custom(_) => return Err(Error::UnsupportedLut),
+        };
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[sequence])?;
+        self.interface.start_measurement(self.refresh.into());
+        self.interface.cmd(spi, Command::MasterActivation)?;
+        self.wait_until_idle_with_timeout(delay)
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.use_full_frame(spi)?;
+
+        let color = self.background_color.get_byte_value();
+        self.interface.cmd(spi, Command::WriteRam)?;
+        self.interface.data_x_times(spi, color, NUM_DISPLAY_BITS)?;
+        self.set_ram_counter(spi, 0, 0)?;
+        self.interface.cmd(spi, Command::WriteRam2)?;
+        self.interface.data_x_times(spi, color, NUM_DISPLAY_BITS)
+    }
+
+    fn set_background_color(&mut self, background_color: Color) {
+        self.background_color = background_color;
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.background_color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn full_refresh_ms(&self) -> u32 {
+        3000
+    }
+
+    fn quick_refresh_ms(&self) -> u32 {
+        300
+    }
+
+    /// Selects the LUT of the next refreshes
+    ///
+    /// The full LUT is loaded from the OTP by every full refresh, the quick LUT is written
+    /// into the LUT register here.
+    fn set_lut(
+        &mut self,
+        spi: &mut SPI,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Update)?;
+        if let Some(RefreshLut::Custom(_)) = refresh_rate {
+            return Err(Error::UnsupportedLut);
+        }
+        if let Some(refresh_lut) = refresh_rate {
+            if refresh_lut == RefreshLut::Quick {
+                self.set_lut_helper(spi, &LUT_QUICK_UPDATE)?;
+                self.interface.cmd_with_data(
+                    spi,
+                    Command::WriteOtpSelection,
+                    &[0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00],
+                )?;
+                let border = self.border.map_or(0x80, border_waveform);
+                self.interface
+                    .cmd_with_data(spi, Command::BorderWaveformControl, &[border])?;
+            }
+            self.refresh = refresh_lut;
+        }
+        Ok(())
+    }
+
+    fn is_busy(&self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
+    fn send_command_raw(&mut self, spi: &mut SPI, command: u8) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn send_data_raw(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        self.interface.data(spi, data)
+    }
+
+    fn set_spi_chunk_size(&mut self, chunk_size: usize) {
+        self.interface.set_chunk_size(chunk_size);
+    }
+
+    fn set_reset_timing(&mut self, timing: Option<ResetTiming>) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    #[cfg(feature = "test-patterns")]
+    fn set_test_pattern(&mut self, enable: bool) {
+        let pattern = crate::test_pattern::FillPattern::new(self.width(), 1);
+        self.interface.set_fill_pattern(enable.then_some(pattern));
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> Epd2in66<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    fn wait_until_idle(&mut self) {
+        self.interface.wait_until_idle(IS_BUSY_LOW);
+    }
+
+    fn wait_until_idle_with_timeout(&mut self, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .wait_until_idle_with_timeout(delay, IS_BUSY_LOW, BUSY_TIMEOUT_MS)
+    }
+
+    /// Sets the color of the border and keeps it for the following refreshes
+    ///
+    /// By default the border follows the waveforms of the vendor: the OTP waveform for the full
+    /// refresh and the VCOM level for the quick refresh.
+    pub fn set_border_color(
+        &mut self,
+        spi: &mut SPI,
+        color: BorderColor,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle();
+        self.interface.cmd_with_data(
+            spi,
+            Command::BorderWaveformControl,
+            &[border_waveform(color)],
+        )?;
+        self.border = Some(color);
+        Ok(())
+    }
+
+    /// Current mode of the controller
+    pub fn power_state(&self) -> PowerState {
+        self.state
+    }
+
+    fn use_full_frame(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        // choose full frame/ram
+        self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
+
+        // start from the beginning
+        self.set_ram_counter(spi, 0, 0)
+    }
+
+    /// Sets the RAM window in panel coordinates, `end_x` and `end_y` are inclusive
+    fn set_ram_area(
+        &mut self,
+        spi: &mut SPI,
+        start_x: u32,
+        start_y: u32,
+        end_x: u32,
+        end_y: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        if start_x > end_x || start_y > end_y {
+            return Err(Error::InvalidRegion);
+        }
+
+        // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
+        // aren't relevant
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamXAddressStartEndPosition,
+            &[
+                ((start_x + RAM_X_OFFSET) >> 3) as u8,
+                ((end_x + RAM_X_OFFSET) >> 3) as u8,
+            ],
+        )?;
+
+        // 2 Databytes: A[7:0] & 0..A[8] for each - start and end
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamYAddressStartEndPosition,
+            &[
+                start_y as u8,
+                (start_y >> 8) as u8,
+                end_y as u8,
+                (end_y >> 8) as u8,
+            ],
+        )
+    }
+
+    /// Sets the RAM address counter in panel coordinates
+    fn set_ram_counter(&mut self, spi: &mut SPI, x: u32, y: u32) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamXAddressCounter,
+            &[((x + RAM_X_OFFSET) >> 3) as u8],
+        )?;
+
+        // 2 Databytes: A[7:0] & 0..A[8]
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamYAddressCounter,
+            &[y as u8, (y >> 8) as u8],
+        )
+    }
+
+    /// Writes the waveform and the voltages which follow it in the LUT
+    fn set_lut_helper(&mut self, spi: &mut SPI, lut: &[u8; 159]) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle();
+        self.interface
+            .cmd_with_data(spi, Command::WriteLutRegister, &lut[..153])?;
+        self.wait_until_idle();
+        self.interface
+            .cmd_with_data(spi, Command::WriteLutRegisterEnd, &lut[153..154])?;
+        self.interface
+            .cmd_with_data(spi, Command::GateDrivingVoltage, &lut[154..155])?;
+        self.interface
+            .cmd_with_data(spi, Command::SourceDrivingVoltage, &lut[155..158])?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteVcomRegister, &lut[158..159])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{cmd, test_epd, DcPin, LowPin, NoDelay, RecordingSpi, Sent};
+    extern crate std;
+    use std::vec::Vec;
+
+    type TestEpd = Epd2in66<RecordingSpi, LowPin, LowPin, DcPin, LowPin, NoDelay>;
+
+    /// RAM window and address counter over the whole panel, shifted by a byte
+    fn full_frame() -> Vec<Sent> {
+        [
+            cmd(0x44, &[0x01, 0x13]),
+            cmd(0x45, &[0x00, 0x00, 0x27, 0x01]),
+            cmd(0x4E, &[0x01]),
+            cmd(0x4F, &[0x00, 0x00]),
+        ]
+        .concat()
+    }
+
+    fn frame() -> Vec<u8> {
+        (0..NUM_DISPLAY_BITS).map(|i| i as u8).collect()
+    }
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 152);
+        assert_eq!(HEIGHT, 296);
+        assert_eq!(NUM_DISPLAY_BITS, 5624);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+    }
+
+    #[test]
+    fn init_sequence() {
+        let (spi, _) = test_epd::<TestEpd, _>();
+        let expected = [cmd(0x12, &[]), cmd(0x11, &[0x03]), full_frame()].concat();
+        assert_eq!(spi.sent, expected);
+    }
+
+    #[test]
+    fn full_update_writes_both_rams_without_padding() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+        let buffer = frame();
+
+        epd.update_and_display_frame(&mut spi, &buffer, &mut NoDelay)
+            .unwrap();
+
+        let expected = [
+            full_frame(),
+            cmd(0x24, &buffer),
+            cmd(0x4E, &[0x01]),
+            cmd(0x4F, &[0x00, 0x00]),
+            cmd(0x26, &buffer),
+            cmd(0x22, &[0xF7]),
+            cmd(0x20, &[]),
+        ]
+        .concat();
+        assert_eq!(spi.sent, expected);
+    }
+
+    #[test]
+    fn buffers_with_the_ram_width_are_rejected() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+        let ram_sized = [0xFF; 176 / 8 * HEIGHT as usize];
+
+        assert_eq!(
+            epd.update_frame(&mut spi, &ram_sized, &mut NoDelay),
+            Err(Error::BufferSize {
+                expected: NUM_DISPLAY_BITS as usize,
+                got: ram_sized.len(),
+            })
+        );
+        assert!(spi.sent.is_empty());
+    }
+
+    #[test]
+    fn quick_lut_only_writes_the_new_frame() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+        epd.set_lut(&mut spi, Some(RefreshLut::Quick)).unwrap();
+
+        let expected = [
+            cmd(0x32, &LUT_QUICK_UPDATE[..153]),
+            cmd(0x3F, &[0x22]),
+            cmd(0x03, &[0x17]),
+            cmd(0x04, &[0x41, 0xB0, 0x32]),
+            cmd(0x2C, &[0x36]),
+            cmd(
+                0x37,
+                &[0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00],
+            ),
+            cmd(0x3C, &[0x80]),
+        ]
+        .concat();
+        assert_eq!(spi.sent, expected);
+
+        spi.sent.clear();
+        let buffer = frame();
+        epd.update_and_display_frame(&mut spi, &buffer, &mut NoDelay)
+            .unwrap();
+        let expected = [
+            full_frame(),
+            cmd(0x24, &buffer),
+            cmd(0x22, &[0xCF]),
+            cmd(0x20, &[]),
+        ]
+        .concat();
+        assert_eq!(spi.sent, expected);
+    }
+
+    #[test]
+    fn border_color_is_kept() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+        epd.set_border_color(&mut spi, BorderColor::Black).unwrap();
+        assert_eq!(spi.sent, cmd(0x3C, &[0x00]));
+
+        spi.sent.clear();
+        epd.set_lut(&mut spi, Some(RefreshLut::Quick)).unwrap();
+        assert!(spi.sent.ends_with(&cmd(0x3C, &[0x00])));
+
+        spi.sent.clear();
+        epd.wake_up(&mut spi, &mut NoDelay).unwrap();
+        let border = cmd(0x3C, &[0x00]);
+        assert!(spi.sent.windows(border.len()).any(|sent| sent == border));
+    }
+
+    #[test]
+    fn custom_lut_is_rejected() {
+        static CUSTOM: [u8; 159] = [0x22; 159];
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+
+        assert_eq!(
+            epd.set_lut(&mut spi, Some(RefreshLut::Custom(&CUSTOM))),
+            Err(Error::UnsupportedLut)
+        );
+        assert!(spi.sent.is_empty());
+        assert_eq!(epd.refresh, RefreshLut::Full);
+    }
+
+    #[test]
+    fn partial_frame_uses_panel_coordinates() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+
+        epd.update_partial_frame(&mut spi, &[0xAA, 0x55], 136, 290, 16, 1, &mut NoDelay)
+            .unwrap();
+
+        let expected = [
+            cmd(0x44, &[0x12, 0x13]),
+            cmd(0x45, &[0x22, 0x01, 0x22, 0x01]),
+            cmd(0x4E, &[0x12]),
+            cmd(0x4F, &[0x22, 0x01]),
+            cmd(0x24, &[0xAA, 0x55]),
+        ]
+        .concat();
+        assert_eq!(spi.sent, expected);
+
+        assert_eq!(
+            epd.update_partial_frame(&mut spi, &[0xAA, 0x55], 144, 0, 16, 1, &mut NoDelay),
+            Err(Error::WindowOutOfRange)
+        );
+    }
+}
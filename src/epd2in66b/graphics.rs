@@ -0,0 +1,159 @@
+use crate::color::TriColor;
+use crate::epd2in66b::{DEFAULT_BACKGROUND_COLOR, HEIGHT, NUM_DISPLAY_BITS, WIDTH};
+use crate::graphics::{DisplayColorRendering, DisplayRotation, TriDisplay};
+use crate::traits::FrameBuffer;
+use embedded_graphics_core::prelude::*;
+
+/// Full size buffer for use with the 2.66" (B) EPD
+///
+/// The chromatic part uses 1 for red, so both parts can be sent to the controller unchanged.
+pub struct Display2in66b {
+    buffer: [u8; 2 * NUM_DISPLAY_BITS as usize],
+    rotation: DisplayRotation,
+}
+
+impl Default for Display2in66b {
+    fn default() -> Self {
+        let mut display = Display2in66b {
+            buffer: [DEFAULT_BACKGROUND_COLOR.get_byte_value(); 2 * NUM_DISPLAY_BITS as usize],
+            rotation: DisplayRotation::default(),
+        };
+        // an empty chromatic part is all zeros
+        let offset = display.chromatic_offset();
+        display.buffer[offset..].fill(0x00);
+        display
+    }
+}
+
+impl DrawTarget for Display2in66b {
+    type Color = TriColor;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for pixel in pixels {
+            self.draw_helper_tri(WIDTH, HEIGHT, pixel, DisplayColorRendering::Negative)?;
+        }
+        Ok(())
+    }
+}
+
+impl OriginDimensions for Display2in66b {
+    fn size(&self) -> Size {
+        match self.rotation {
+            DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => Size::new(WIDTH, HEIGHT),
+            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => Size::new(HEIGHT, WIDTH),
+        }
+    }
+}
+
+impl TriDisplay for Display2in66b {
+    fn buffer(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    fn get_mut_buffer(&mut self) -> &mut [u8] {
+        &mut self.buffer
+    }
+
+    fn set_rotation(&mut self, rotation: DisplayRotation) {
+        self.rotation = rotation;
+    }
+
+    fn rotation(&self) -> DisplayRotation {
+        self.rotation
+    }
+
+    fn chromatic_offset(&self) -> usize {
+        NUM_DISPLAY_BITS as usize
+    }
+
+    fn bw_buffer(&self) -> &[u8] {
+        &self.buffer[0..self.chromatic_offset()]
+    }
+
+    fn chromatic_buffer(&self) -> &[u8] {
+        &self.buffer[self.chromatic_offset()..]
+    }
+
+    fn color_rendering(&self) -> DisplayColorRendering {
+        DisplayColorRendering::Negative
+    }
+}
+
+impl FrameBuffer for Display2in66b {
+    type Color = TriColor;
+
+    fn as_bytes(&self) -> &[u8] {
+        self.buffer()
+    }
+
+    fn as_bytes_mut(&mut self) -> &mut [u8] {
+        self.get_mut_buffer()
+    }
+
+    fn clear(&mut self, color: TriColor) {
+        self.clear_buffer(color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::TriColor::{Black, Chromatic};
+    use crate::epd2in66b;
+    use embedded_graphics::{
+        prelude::*,
+        primitives::{Line, PrimitiveStyle},
+    };
+
+    // test buffer length
+    #[test]
+    fn graphics_size() {
+        let display = Display2in66b::default();
+        assert_eq!(display.buffer().len(), 11248); // (5624 = 152 * 296/8) * 2
+    }
+
+    // test default background color on all bytes
+    #[test]
+    fn graphics_default() {
+        let display = Display2in66b::default();
+        for &byte in display.bw_buffer() {
+            assert_eq!(byte, epd2in66b::DEFAULT_BACKGROUND_COLOR.get_byte_value());
+        }
+        for &byte in display.chromatic_buffer() {
+            assert_eq!(byte, 0x00);
+        }
+    }
+
+    #[test]
+    fn graphics_rotation_0() {
+        let mut display = Display2in66b::default();
+        let _ = Line::new(Point::new(0, 0), Point::new(7, 0))
+            .into_styled(PrimitiveStyle::with_stroke(Black, 1))
+            .draw(&mut display);
+
+        let buffer = display.bw_buffer();
+
+        assert_eq!(buffer[0], Black.get_byte_value());
+
+        for &byte in buffer.iter().skip(1) {
+            assert_eq!(byte, epd2in66b::DEFAULT_BACKGROUND_COLOR.get_byte_value());
+        }
+    }
+
+    #[test]
+    fn graphics_chromatic_is_set_bit() {
+        let mut display = Display2in66b::default();
+        let _ = Line::new(Point::new(0, 0), Point::new(7, 0))
+            .into_styled(PrimitiveStyle::with_stroke(Chromatic, 1))
+            .draw(&mut display);
+
+        assert_eq!(display.chromatic_buffer()[0], 0xFF);
+        for &byte in display.chromatic_buffer().iter().skip(1) {
+            assert_eq!(byte, 0x00);
+        }
+    }
+}
@@ -1,7 +1,10 @@
+use crate::color::Color;
 use crate::epd2in7b::{DEFAULT_BACKGROUND_COLOR, HEIGHT, WIDTH};
 use crate::graphics::{Display, DisplayRotation};
+use crate::traits::FrameBuffer;
 use embedded_graphics_core::pixelcolor::BinaryColor;
 use embedded_graphics_core::prelude::*;
+use embedded_graphics_core::primitives::Rectangle;
 
 /// Full size buffer for use with the 2in7B EPD
 ///
@@ -35,11 +38,18 @@ impl DrawTarget for Display2in7b {
         }
         Ok(())
     }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.fill_solid_helper(WIDTH, HEIGHT, area, color)
+    }
 }
 
 impl OriginDimensions for Display2in7b {
     fn size(&self) -> Size {
-        Size::new(WIDTH, HEIGHT)
+        match self.rotation {
+            DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => Size::new(WIDTH, HEIGHT),
+            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => Size::new(HEIGHT, WIDTH),
+        }
     }
 }
 
@@ -61,6 +71,22 @@ impl Display for Display2in7b {
     }
 }
 
+impl FrameBuffer for Display2in7b {
+    type Color = Color;
+
+    fn as_bytes(&self) -> &[u8] {
+        self.buffer()
+    }
+
+    fn as_bytes_mut(&mut self) -> &mut [u8] {
+        self.get_mut_buffer()
+    }
+
+    fn clear(&mut self, color: Color) {
+        self.clear_buffer(color);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
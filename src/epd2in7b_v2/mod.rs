@@ -0,0 +1,639 @@
+//! A simple Driver for the Waveshare 2.7" (B) V2 tri-color E-Ink Display via SPI
+//!
+//! Unlike the [first version](crate::epd2in7b) the V2 uses a SSD1675 class controller with
+//! the command set of the [type A displays](crate::epd1in54_v2): the black/white plane goes
+//! into `WriteRam` and the red plane into `WriteRam2`.
+//!
+//! # References
+//!
+//! - [Waveshare wiki](https://www.waveshare.com/wiki/2.7inch_e-Paper_HAT_(B))
+//! - [Waveshare Python driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/python/lib/waveshare_epd/epd2in7b_V2.py)
+
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::{InputPin, OutputPin},
+};
+
+use crate::check_buffer_len;
+use crate::color::TriColor;
+use crate::error::Error;
+use crate::interface::DisplayInterface;
+use crate::power::{PowerState, Transition};
+use crate::timing::ResetTiming;
+use crate::traits::{
+    InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+};
+use crate::type_a::command::Command;
+use crate::window::WindowLimits;
+use crate::{AutoCs, CsStrategy, ResetPin};
+
+#[cfg(feature = "graphics")]
+mod graphics;
+#[cfg(feature = "graphics")]
+pub use self::graphics::Display2in7b;
+
+/// Width of the display
+pub const WIDTH: u32 = 176;
+/// Height of the display
+pub const HEIGHT: u32 = 264;
+/// Limits of the partial window registers: 6 bit x byte address and 9 bit y address, the whole panel fits
+pub const WINDOW_LIMITS: WindowLimits = WindowLimits::new(WIDTH, HEIGHT, 511, 511);
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: TriColor = TriColor::White;
+
+/// Number of bytes for the b/w buffer and the same for the chromatic buffer
+const NUM_DISPLAY_BITS: u32 = WIDTH * HEIGHT / 8;
+const IS_BUSY_LOW: bool = false;
+/// Longest time in milliseconds to wait for the busy pin before giving up with
+/// [Error::Timeout]
+pub const BUSY_TIMEOUT_MS: u32 = 30_000;
+/// Fastest SPI clock of the controller in Hz, see
+/// [check_spi_frequency()](crate::check_spi_frequency())
+pub const MAX_SPI_FREQ_HZ: u32 = 20_000_000;
+
+/// Epd2in7b (V2) driver
+///
+/// Like the [5.83" (B) v2](crate::epd5in83b_v2) the controller uses a set bit for red in the
+/// chromatic plane, so an empty chromatic plane is filled with `0x00`.
+pub struct Epd2in7b<SPI, CS, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+    /// Background Color
+    color: TriColor,
+    /// Mode of the controller
+    state: PowerState,
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd2in7b<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state = self.state.check(Transition::Init)?;
+        self.interface.reset(delay, 10, 10)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.interface.cmd(spi, Command::SwReset)?;
+        self.wait_until_idle_with_timeout(delay)?;
+
+        // 264 gate lines: A[8:0] = 0x107, GD, SM and TB = 0
+        self.interface.cmd_with_data(
+            spi,
+            Command::DriverOutputControl,
+            &[(HEIGHT - 1) as u8, ((HEIGHT - 1) >> 8) as u8, 0x00],
+        )?;
+
+        // x and y increment, the address counter is updated in x direction
+        self.interface
+            .cmd_with_data(spi, Command::DataEntryModeSetting, &[0x03])?;
+
+        self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
+
+        // white border
+        self.interface
+            .cmd_with_data(spi, Command::BorderWaveformControl, &[0x05])?;
+
+        self.interface.cmd_with_data(
+            spi,
+            Command::TemperatureSensorSelection,
+            &[0x80], // 0x80: internal temperature sensor
+        )?;
+
+        self.set_ram_counter(spi, 0, 0)?;
+
+        self.wait_until_idle_with_timeout(delay)?;
+        Ok(())
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareThreeColorDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd2in7b<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    fn update_color_frame(
+        &mut self,
+        spi: &mut SPI,
+        black: &[u8],
+        chromatic: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_achromatic_frame(spi, black)?;
+        self.update_chromatic_frame(spi, chromatic)
+    }
+
+    /// Update only the black/white data of the display.
+    ///
+    /// Finish by calling `update_chromatic_frame`.
+    fn update_achromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        black: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(black, WIDTH, HEIGHT)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle();
+        self.use_full_frame(spi)?;
+        self.interface.cmd_with_data(spi, Command::WriteRam, black)
+    }
+
+    /// Update only chromatic data of the display.
+    ///
+    /// A set bit means red.
+    fn update_chromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        chromatic: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(chromatic, WIDTH, HEIGHT)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle();
+        self.use_full_frame(spi)?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteRam2, chromatic)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd2in7b<SPI, AutoCs, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Creates the driver for a board without a CS GPIO, see [AutoCs]
+    pub fn new_without_cs(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new(spi, AutoCs, busy, dc, rst, delay)
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd2in7b<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    type DisplayColor = TriColor;
+    fn new(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_background(spi, cs, busy, dc, rst, delay, DEFAULT_BACKGROUND_COLOR)
+    }
+
+    fn new_with_background(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        color: Self::DisplayColor,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(cs, busy, dc, rst);
+
+        let mut epd = Epd2in7b {
+            interface,
+            color,
+            state: PowerState::Active,
+        };
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.check_reset_pin()?;
+        self.init(spi, delay)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        let next = self.state.check(Transition::Sleep)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        // deep sleep mode 1, the RAM is retained
+        self.interface
+            .cmd_with_data(spi, Command::DeepSleepMode, &[0x01])?;
+        self.state = next;
+        Ok(())
+    }
+
+    /// Updates the black/white data and clears the chromatic layer
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer, WIDTH, HEIGHT)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.update_achromatic_frame(spi, buffer)?;
+
+        // Clear the chromatic layer, a set bit means red for the controller
+        self.interface.cmd(spi, Command::WriteRam2)?;
+        self.interface.data_x_times(spi, 0x00, NUM_DISPLAY_BITS)?;
+        Ok(())
+    }
+
+    /// Updates the black/white data of a window, the chromatic layer isn't changed
+    fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        WINDOW_LIMITS.validate_aligned(x, y, width, height)?;
+        check_buffer_len(buffer, width, height)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)?;
+        self.set_ram_counter(spi, x, y)?;
+
+        self.interface
+            .cmd_with_data(spi, Command::WriteRam, buffer)?;
+        Ok(())
+    }
+
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Refresh)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        // load the temperature and the LUT from the OTP, display and power off the analog part
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xF7])?;
+        self.interface.start_measurement(RefreshLut::Full.into());
+        self.interface.cmd(spi, Command::MasterActivation)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        Ok(())
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)?;
+        Ok(())
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.use_full_frame(spi)?;
+
+        let (bw, chromatic) = background_bytes(self.color);
+
+        self.interface.cmd(spi, Command::WriteRam)?;
+        self.interface.data_x_times(spi, bw, NUM_DISPLAY_BITS)?;
+
+        self.interface.cmd(spi, Command::WriteRam2)?;
+        self.interface
+            .data_x_times(spi, chromatic, NUM_DISPLAY_BITS)?;
+
+        Ok(())
+    }
+
+    fn set_background_color(&mut self, color: TriColor) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &TriColor {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn full_refresh_ms(&self) -> u32 {
+        16_000
+    }
+
+    /// The waveform of the OTP is the only one, anything but [RefreshLut::Full] returns
+    /// [Error::UnsupportedLut]
+    fn set_lut(
+        &mut self,
+        _spi: &mut SPI,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        match refresh_rate {
+            None | Some(RefreshLut::Full) => Ok(()),
+            Some(_) => Err(Error::UnsupportedLut),
+        }
+    }
+
+    fn is_busy(&self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
+    fn send_command_raw(&mut self, spi: &mut SPI, command: u8) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn send_data_raw(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        self.interface.data(spi, data)
+    }
+
+    fn set_spi_chunk_size(&mut self, chunk_size: usize) {
+        self.interface.set_chunk_size(chunk_size);
+    }
+
+    fn set_reset_timing(&mut self, timing: Option<ResetTiming>) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    #[cfg(feature = "test-patterns")]
+    fn set_test_pattern(&mut self, enable: bool) {
+        let pattern = crate::test_pattern::FillPattern::new(self.width(), 1);
+        self.interface.set_fill_pattern(enable.then_some(pattern));
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> Epd2in7b<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    fn wait_until_idle(&mut self) {
+        self.interface.wait_until_idle(IS_BUSY_LOW);
+    }
+
+    fn wait_until_idle_with_timeout(&mut self, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .wait_until_idle_with_timeout(delay, IS_BUSY_LOW, BUSY_TIMEOUT_MS)
+    }
+
+    /// Current mode of the controller
+    pub fn power_state(&self) -> PowerState {
+        self.state
+    }
+
+    fn use_full_frame(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        // choose full frame/ram
+        self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
+
+        // start from the beginning
+        self.set_ram_counter(spi, 0, 0)
+    }
+
+    /// Sets the RAM window, `end_x` and `end_y` are inclusive
+    fn set_ram_area(
+        &mut self,
+        spi: &mut SPI,
+        start_x: u32,
+        start_y: u32,
+        end_x: u32,
+        end_y: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        if start_x > end_x || start_y > end_y {
+            return Err(Error::InvalidRegion);
+        }
+
+        // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
+        // aren't relevant
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamXAddressStartEndPosition,
+            &[(start_x >> 3) as u8, (end_x >> 3) as u8],
+        )?;
+
+        // 2 Databytes: A[7:0] & 0..A[8] for each - start and end
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamYAddressStartEndPosition,
+            &[
+                start_y as u8,
+                (start_y >> 8) as u8,
+                end_y as u8,
+                (end_y >> 8) as u8,
+            ],
+        )
+    }
+
+    fn set_ram_counter(&mut self, spi: &mut SPI, x: u32, y: u32) -> Result<(), Error<SPI::Error>> {
+        // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
+        // aren't relevant
+        self.interface
+            .cmd_with_data(spi, Command::SetRamXAddressCounter, &[(x >> 3) as u8])?;
+
+        // 2 Databytes: A[7:0] & 0..A[8]
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamYAddressCounter,
+            &[y as u8, (y >> 8) as u8],
+        )
+    }
+}
+
+/// Bytes of the b/w and the chromatic plane for a whole background in `color`
+fn background_bytes(color: TriColor) -> (u8, u8) {
+    match color {
+        TriColor::Black => (0x00, 0x00),
+        TriColor::White => (0xFF, 0x00),
+        TriColor::Chromatic | TriColor::Yellow => (0xFF, 0xFF),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{cmd, test_epd, DcPin, LowPin, NoDelay, RecordingSpi, Sent};
+    extern crate std;
+    use std::vec::Vec;
+
+    type TestEpd = Epd2in7b<RecordingSpi, LowPin, LowPin, DcPin, LowPin, NoDelay>;
+
+    /// RAM window and address counter over the whole panel
+    fn full_frame() -> Vec<Sent> {
+        [
+            cmd(0x44, &[0x00, 0x15]),
+            cmd(0x45, &[0x00, 0x00, 0x07, 0x01]),
+            cmd(0x4E, &[0x00]),
+            cmd(0x4F, &[0x00, 0x00]),
+        ]
+        .concat()
+    }
+
+    fn plane(start: u8) -> Vec<u8> {
+        (0..NUM_DISPLAY_BITS).map(|i| start ^ i as u8).collect()
+    }
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 176);
+        assert_eq!(HEIGHT, 264);
+        assert_eq!(NUM_DISPLAY_BITS, 5808);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, TriColor::White);
+    }
+
+    #[test]
+    fn init_sequence() {
+        let (spi, _) = test_epd::<TestEpd, _>();
+        let expected = [
+            cmd(0x12, &[]),
+            cmd(0x01, &[0x07, 0x01, 0x00]),
+            cmd(0x11, &[0x03]),
+            cmd(0x44, &[0x00, 0x15]),
+            cmd(0x45, &[0x00, 0x00, 0x07, 0x01]),
+            cmd(0x3C, &[0x05]),
+            cmd(0x18, &[0x80]),
+            cmd(0x4E, &[0x00]),
+            cmd(0x4F, &[0x00, 0x00]),
+        ]
+        .concat();
+        assert_eq!(spi.sent, expected);
+    }
+
+    #[test]
+    fn color_frame_writes_black_then_red_ram() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+        let black = plane(0x00);
+        let red = plane(0xFF);
+
+        epd.update_color_frame(&mut spi, &black, &red).unwrap();
+        epd.display_frame(&mut spi, &mut NoDelay).unwrap();
+
+        let expected = [
+            full_frame(),
+            cmd(0x24, &black),
+            full_frame(),
+            cmd(0x26, &red),
+            cmd(0x22, &[0xF7]),
+            cmd(0x20, &[]),
+        ]
+        .concat();
+        assert_eq!(spi.sent, expected);
+    }
+
+    #[test]
+    fn update_frame_clears_red_ram() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+        let black = plane(0x5A);
+
+        epd.update_frame(&mut spi, &black, &mut NoDelay).unwrap();
+
+        let expected = [
+            full_frame(),
+            cmd(0x24, &black),
+            cmd(0x26, &[0x00; NUM_DISPLAY_BITS as usize]),
+        ]
+        .concat();
+        assert_eq!(spi.sent, expected);
+    }
+
+    #[test]
+    fn partial_frame_sets_inclusive_window() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+
+        epd.update_partial_frame(&mut spi, &[0xAA, 0x55], 8, 260, 16, 1, &mut NoDelay)
+            .unwrap();
+
+        let expected = [
+            cmd(0x44, &[0x01, 0x02]),
+            cmd(0x45, &[0x04, 0x01, 0x04, 0x01]),
+            cmd(0x4E, &[0x01]),
+            cmd(0x4F, &[0x04, 0x01]),
+            cmd(0x24, &[0xAA, 0x55]),
+        ]
+        .concat();
+        assert_eq!(spi.sent, expected);
+    }
+
+    #[test]
+    fn clear_frame_uses_background_color() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+        epd.set_background_color(TriColor::Chromatic);
+
+        epd.clear_frame(&mut spi, &mut NoDelay).unwrap();
+
+        let expected = [
+            full_frame(),
+            cmd(0x24, &[0xFF; NUM_DISPLAY_BITS as usize]),
+            cmd(0x26, &[0xFF; NUM_DISPLAY_BITS as usize]),
+        ]
+        .concat();
+        assert_eq!(spi.sent, expected);
+    }
+
+    #[test]
+    fn sleep_rejects_further_updates() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+
+        epd.sleep(&mut spi, &mut NoDelay).unwrap();
+        assert_eq!(spi.sent, cmd(0x10, &[0x01]));
+        assert_eq!(epd.power_state(), PowerState::DeepSleep);
+
+        assert_eq!(
+            epd.update_color_frame(&mut spi, &plane(0), &plane(0)),
+            Err(Error::IllegalTransition {
+                state: PowerState::DeepSleep,
+                transition: Transition::Update,
+            })
+        );
+        assert_eq!(spi.sent.len(), 2);
+    }
+
+    #[test]
+    fn background_uses_set_bits_for_red() {
+        assert_eq!(background_bytes(TriColor::White), (0xFF, 0x00));
+        assert_eq!(background_bytes(TriColor::Black), (0x00, 0x00));
+        assert_eq!(background_bytes(TriColor::Chromatic), (0xFF, 0xFF));
+    }
+
+    #[test]
+    fn only_the_otp_lut_is_accepted() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+
+        assert_eq!(epd.set_lut(&mut spi, None), Ok(()));
+        assert_eq!(epd.set_lut(&mut spi, Some(RefreshLut::Full)), Ok(()));
+        assert_eq!(
+            epd.set_lut(&mut spi, Some(RefreshLut::Quick)),
+            Err(Error::UnsupportedLut)
+        );
+        assert!(spi.sent.is_empty());
+    }
+}
@@ -6,7 +6,7 @@
 //!
 //!```rust, no_run
 //!# use embedded_hal_mock::*;
-//!# fn main() -> Result<(), MockError> {
+//!# fn main() -> Result<(), epd_waveshare::error::Error<MockError>> {
 //!use embedded_graphics::{
 //!    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
 //!};
@@ -58,9 +58,17 @@
 pub const WIDTH: u32 = 128;
 /// Height of epd2in9 in pixels
 pub const HEIGHT: u32 = 296;
+/// Limits of the partial window registers: 6 bit x byte address and 9 bit y address, the whole panel fits
+pub const WINDOW_LIMITS: WindowLimits = WindowLimits::new(WIDTH, HEIGHT, 511, 511);
 /// Default Background Color (white)
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
 const IS_BUSY_LOW: bool = false;
+/// Longest time in milliseconds to wait for the busy pin before giving up with
+/// [Error::Timeout]
+pub const BUSY_TIMEOUT_MS: u32 = 10_000;
+/// Fastest SPI clock of the controller in Hz, see
+/// [check_spi_frequency()](crate::check_spi_frequency())
+pub const MAX_SPI_FREQ_HZ: u32 = 20_000_000;
 
 const LUT_PARTIAL_2IN9: [u8; 159] = [
     0x0, 0x40, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x80, 0x80, 0x0, 0x0, 0x0, 0x0,
@@ -79,13 +87,20 @@ use embedded_hal::{
     digital::v2::*,
 };
 
-use crate::type_a::command::Command;
+use crate::check_buffer_len;
+use crate::error::Error;
+use crate::type_a::{border_waveform, command::Command};
+use crate::window::WindowLimits;
+use crate::{AutoCs, CsStrategy, ResetPin};
 
 use crate::color::Color;
 
+use crate::timing::{Clock, Operation, ResetTiming, Timings};
+
 use crate::traits::*;
 
 use crate::interface::DisplayInterface;
+use crate::power::{PowerState, Transition};
 use crate::traits::QuickRefresh;
 
 #[cfg(feature = "graphics")]
@@ -102,23 +117,28 @@ pub struct Epd2in9<SPI, CS, BUSY, DC, RST, DELAY> {
     background_color: Color,
     /// Refresh LUT
     refresh: RefreshLut,
+    /// Mode of the controller
+    state: PowerState,
+    /// Border color set with `set_border_color()`, `None` for the waveforms of the vendor
+    border: Option<BorderColor>,
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> Epd2in9<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.reset(delay, 10, 2);
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state = self.state.check(Transition::Init)?;
+        self.interface.reset(delay, 10, 2)?;
 
-        self.wait_until_idle();
+        self.wait_until_idle_with_timeout(delay)?;
         self.interface.cmd(spi, Command::SwReset)?;
-        self.wait_until_idle();
+        self.wait_until_idle_with_timeout(delay)?;
 
         // 3 Databytes:
         // A[7:0]
@@ -138,21 +158,49 @@ where
         self.interface
             .cmd_with_data(spi, Command::DisplayUpdateControl1, &[0x00, 0x80])?;
 
+        if let Some(color) = self.border {
+            self.interface.cmd_with_data(
+                spi,
+                Command::BorderWaveformControl,
+                &[border_waveform(color)],
+            )?;
+        }
+
         self.set_ram_counter(spi, 0, 0)?;
 
-        self.wait_until_idle();
+        self.wait_until_idle_with_timeout(delay)?;
         Ok(())
     }
 }
 
+impl<SPI, BUSY, DC, RST, DELAY> Epd2in9<SPI, AutoCs, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Creates the driver for a board without a CS GPIO, see [AutoCs]
+    pub fn new_without_cs(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new(spi, AutoCs, busy, dc, rst, delay)
+    }
+}
+
 impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
     for Epd2in9<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
     type DisplayColor = Color;
@@ -164,6 +212,14 @@ where
         HEIGHT
     }
 
+    fn full_refresh_ms(&self) -> u32 {
+        3000
+    }
+
+    fn quick_refresh_ms(&self) -> u32 {
+        300
+    }
+
     fn new(
         spi: &mut SPI,
         cs: CS,
@@ -171,13 +227,27 @@ where
         dc: DC,
         rst: RST,
         delay: &mut DELAY,
-    ) -> Result<Self, SPI::Error> {
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_background(spi, cs, busy, dc, rst, delay, DEFAULT_BACKGROUND_COLOR)
+    }
+
+    fn new_with_background(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        color: Self::DisplayColor,
+    ) -> Result<Self, Error<SPI::Error>> {
         let interface = DisplayInterface::new(cs, busy, dc, rst);
 
         let mut epd = Epd2in9 {
             interface,
-            background_color: DEFAULT_BACKGROUND_COLOR,
+            background_color: color,
             refresh: RefreshLut::Full,
+            state: PowerState::Active,
+            border: None,
         };
 
         epd.init(spi, delay)?;
@@ -185,27 +255,44 @@ where
         Ok(epd)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        let next = self.state.check(Transition::Sleep)?;
+        self.wait_until_idle_with_timeout(delay)?;
         // 0x00 for Normal mode (Power on Reset), 0x01 for Deep Sleep Mode
         self.interface
             .cmd_with_data(spi, Command::DeepSleepMode, &[0x01])?;
+        self.state = next;
         Ok(())
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.check_reset_pin()?;
         self.init(spi, delay)?;
         Ok(())
     }
 
+    /// Writes the frame into the RAM
+    ///
+    /// With the full LUT the frame is also written into the second RAM bank. The quick refresh
+    /// compares against that bank, so otherwise the first quick refresh after a full one would
+    /// show the inverse image.
     fn update_frame(
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
-        _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
-        self.interface.cmd_with_data(spi, Command::WriteRam, buffer)
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer, WIDTH, HEIGHT)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteRam, buffer)?;
+        if self.refresh == RefreshLut::Full {
+            self.set_ram_counter(spi, 0, 0)?;
+            self.interface
+                .cmd_with_data(spi, Command::WriteRam2, buffer)?;
+        }
+        Ok(())
     }
 
     fn update_partial_frame(
@@ -216,9 +303,13 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        WINDOW_LIMITS.validate_aligned(x, y, width, height)?;
+        check_buffer_len(buffer, width, height)?;
+        self.state.check(Transition::Update)?;
         //TODO This is copied from epd2in9 but it seems not working. Partial refresh supported by version 2?
-        self.wait_until_idle();
+        self.wait_until_idle_with_timeout(delay)?;
         self.set_ram_area(spi, x, y, x + width, y + height)?;
         self.set_ram_counter(spi, x, y)?;
 
@@ -228,13 +319,17 @@ where
     }
 
     /// actually is the "Turn on Display" sequence
-    fn display_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Refresh)?;
+        self.wait_until_idle_with_timeout(delay)?;
         // Enable clock signal, Enable Analog, Load temperature value, DISPLAY with DISPLAY Mode 1, Disable Analog, Disable OSC
         self.interface
             .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xF7])?;
+        self.interface.start_measurement(Operation::FullRefresh);
         self.interface.cmd(spi, Command::MasterActivation)?;
-        self.wait_until_idle();
+        self.interface
+            .measure_busy_period(delay, IS_BUSY_LOW, BUSY_TIMEOUT_MS)?;
+        self.wait_until_idle_with_timeout(delay)?;
         Ok(())
     }
 
@@ -243,14 +338,16 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.interface.start_measurement(Operation::Clear);
 
         // clear the ram with the background color
         let color = self.background_color.get_byte_value();
@@ -259,7 +356,10 @@ where
         self.interface
             .data_x_times(spi, color, WIDTH / 8 * HEIGHT)?;
         self.interface.cmd(spi, Command::WriteRam2)?;
-        self.interface.data_x_times(spi, color, WIDTH / 8 * HEIGHT)
+        self.interface
+            .data_x_times(spi, color, WIDTH / 8 * HEIGHT)?;
+        self.interface
+            .measure_busy_period(delay, IS_BUSY_LOW, BUSY_TIMEOUT_MS)
     }
 
     fn set_background_color(&mut self, background_color: Color) {
@@ -274,7 +374,11 @@ where
         &mut self,
         _spi: &mut SPI,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Update)?;
+        if let Some(RefreshLut::Custom(_)) = refresh_rate {
+            return Err(Error::UnsupportedLut);
+        }
         if let Some(refresh_lut) = refresh_rate {
             self.refresh = refresh_lut;
         }
@@ -284,22 +388,107 @@ where
     fn is_busy(&self) -> bool {
         self.interface.is_busy(IS_BUSY_LOW)
     }
+
+    fn send_command_raw(&mut self, spi: &mut SPI, command: u8) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn send_data_raw(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        self.interface.data(spi, data)
+    }
+
+    fn set_spi_chunk_size(&mut self, chunk_size: usize) {
+        self.interface.set_chunk_size(chunk_size);
+    }
+
+    fn set_reset_timing(&mut self, timing: Option<ResetTiming>) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    #[cfg(feature = "test-patterns")]
+    fn set_test_pattern(&mut self, enable: bool) {
+        let pattern = crate::test_pattern::FillPattern::new(self.width(), 1);
+        self.interface.set_fill_pattern(enable.then_some(pattern));
+    }
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> Epd2in9<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
     fn wait_until_idle(&mut self) {
         self.interface.wait_until_idle(IS_BUSY_LOW);
     }
 
-    fn use_full_frame(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    fn wait_until_idle_with_timeout(&mut self, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .wait_until_idle_with_timeout(delay, IS_BUSY_LOW, BUSY_TIMEOUT_MS)
+    }
+
+    /// Sets the color of the border and keeps it for the following refreshes
+    ///
+    /// By default the border follows the waveforms of the vendor: the OTP waveform for the full
+    /// refresh and the VCOM level for the quick refresh.
+    pub fn set_border_color(
+        &mut self,
+        spi: &mut SPI,
+        color: BorderColor,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle();
+        self.interface.cmd_with_data(
+            spi,
+            Command::BorderWaveformControl,
+            &[border_waveform(color)],
+        )?;
+        self.border = Some(color);
+        Ok(())
+    }
+
+    /// Current mode of the controller
+    pub fn power_state(&self) -> PowerState {
+        self.state
+    }
+
+    /// Sets the clock used to measure the durations of refreshes and clears
+    ///
+    /// The measurements can be read with [last_timings()](Self::last_timings()),
+    /// `None` disables them.
+    pub fn set_clock(&mut self, clock: Option<&'static dyn Clock>) {
+        self.interface.set_clock(clock);
+    }
+
+    /// Last measured durations, measured until the display wasn't busy anymore
+    pub fn last_timings(&self) -> Timings {
+        self.interface.timings()
+    }
+
+    /// Writes only the rows of `new_buffer` which differ from `old_buffer`, the frame shown last
+    ///
+    /// Saves most of the SPI traffic if only a small band of the frame changed. Only the RAM
+    /// of the new frame is written, refresh it with a quick refresh. Returns the number of
+    /// frame bytes sent, `0` if the frames are equal.
+    pub fn update_changed_rows(
+        &mut self,
+        spi: &mut SPI,
+        old_buffer: &[u8],
+        new_buffer: &[u8],
+    ) -> Result<usize, Error<SPI::Error>> {
+        check_buffer_len(old_buffer, WIDTH, HEIGHT)?;
+        check_buffer_len(new_buffer, WIDTH, HEIGHT)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle();
+        self.use_full_frame(spi)?;
+        self.interface
+            .diff_and_update_rows(spi, old_buffer, new_buffer, WIDTH as usize / 8)
+    }
+
+    fn use_full_frame(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
         // choose full frame/ram
         self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
 
@@ -314,9 +503,10 @@ where
         start_y: u32,
         end_x: u32,
         end_y: u32,
-    ) -> Result<(), SPI::Error> {
-        assert!(start_x < end_x);
-        assert!(start_y < end_y);
+    ) -> Result<(), Error<SPI::Error>> {
+        if start_x >= end_x || start_y >= end_y {
+            return Err(Error::InvalidRegion);
+        }
 
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
@@ -339,8 +529,7 @@ where
         )
     }
 
-    fn set_ram_counter(&mut self, spi: &mut SPI, x: u32, y: u32) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    fn set_ram_counter(&mut self, spi: &mut SPI, x: u32, y: u32) -> Result<(), Error<SPI::Error>> {
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
         self.interface
@@ -356,7 +545,7 @@ where
     }
 
     /// Set your own LUT, this function is also used internally for set_lut
-    fn set_lut_helper(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), SPI::Error> {
+    fn set_lut_helper(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle();
         self.interface
             .cmd_with_data(spi, Command::WriteLutRegister, buffer)?;
@@ -369,10 +558,10 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> QuickRefresh<SPI, CS, BUSY, DC, RST, DELAY>
     for Epd2in9<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
     /// To be followed immediately by `update_new_frame`.
@@ -380,9 +569,11 @@ where
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
-        _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer, WIDTH, HEIGHT)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
         self.interface
             .cmd_with_data(spi, Command::WriteRam2, buffer)
     }
@@ -393,9 +584,11 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
-        self.interface.reset(delay, 10, 2);
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer, WIDTH, HEIGHT)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.interface.reset(delay, 10, 2)?;
 
         self.set_lut_helper(spi, &LUT_PARTIAL_2IN9)?;
         self.interface.cmd_with_data(
@@ -403,13 +596,14 @@ where
             Command::WriteOtpSelection,
             &[0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00],
         )?;
+        let border = self.border.map_or(0x80, border_waveform);
         self.interface
-            .cmd_with_data(spi, Command::BorderWaveformControl, &[0x80])?;
+            .cmd_with_data(spi, Command::BorderWaveformControl, &[border])?;
         self.interface
             .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xC0])?;
         self.interface.cmd(spi, Command::MasterActivation)?;
 
-        self.wait_until_idle();
+        self.wait_until_idle_with_timeout(delay)?;
 
         self.use_full_frame(spi)?;
 
@@ -419,12 +613,20 @@ where
     }
 
     /// For a quick refresh of the new updated frame. To be used immediately after `update_new_frame`
-    fn display_new_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    fn display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Refresh)?;
+        self.wait_until_idle_with_timeout(delay)?;
         self.interface
             .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0x0F])?;
+        self.interface.start_measurement(Operation::QuickRefresh);
         self.interface.cmd(spi, Command::MasterActivation)?;
-        self.wait_until_idle();
+        self.interface
+            .measure_busy_period(delay, IS_BUSY_LOW, BUSY_TIMEOUT_MS)?;
+        self.wait_until_idle_with_timeout(delay)?;
         Ok(())
     }
 
@@ -434,60 +636,212 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_new_frame(spi, buffer, delay)?;
         self.display_new_frame(spi, delay)?;
         Ok(())
     }
 
-    /// Partial quick refresh not supported yet
-    #[allow(unused)]
+    /// Partial quick refresh isn't supported, returns [Error::PartialUpdateUnsupported]
     fn update_partial_old_frame(
         &mut self,
-        spi: &mut SPI,
-        buffer: &[u8],
-        x: u32,
-        y: u32,
-        width: u32,
-        height: u32,
-    ) -> Result<(), SPI::Error> {
-        //TODO supported by display?
-        unimplemented!()
+        _spi: &mut SPI,
+        _buffer: &[u8],
+        _x: u32,
+        _y: u32,
+        _width: u32,
+        _height: u32,
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        Err(Error::PartialUpdateUnsupported)
     }
 
-    /// Partial quick refresh not supported yet
-    #[allow(unused)]
+    /// Partial quick refresh isn't supported, returns [Error::PartialUpdateUnsupported]
     fn update_partial_new_frame(
         &mut self,
-        spi: &mut SPI,
-        buffer: &[u8],
-        x: u32,
-        y: u32,
-        width: u32,
-        height: u32,
-    ) -> Result<(), SPI::Error> {
-        //TODO supported by display?
-        unimplemented!()
+        _spi: &mut SPI,
+        _buffer: &[u8],
+        _x: u32,
+        _y: u32,
+        _width: u32,
+        _height: u32,
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        Err(Error::PartialUpdateUnsupported)
     }
 
-    /// Partial quick refresh not supported yet
-    #[allow(unused)]
+    /// Partial quick refresh isn't supported, returns [Error::PartialUpdateUnsupported]
     fn clear_partial_frame(
         &mut self,
-        spi: &mut SPI,
-        x: u32,
-        y: u32,
-        width: u32,
-        height: u32,
-    ) -> Result<(), SPI::Error> {
-        //TODO supported by display?
-        unimplemented!()
+        _spi: &mut SPI,
+        _x: u32,
+        _y: u32,
+        _width: u32,
+        _height: u32,
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        Err(Error::PartialUpdateUnsupported)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::{DcPin, LowPin, NoDelay, RecordingSpi};
+    extern crate std;
+    use crate::buffer_len;
+    use std::vec;
+    use std::vec::Vec;
+
+    type TestEpd = Epd2in9<RecordingSpi, LowPin, LowPin, DcPin, LowPin, NoDelay>;
+
+    fn test_epd(refresh: RefreshLut) -> (RecordingSpi, TestEpd) {
+        let (spi, dc) = RecordingSpi::new();
+        let epd = Epd2in9 {
+            interface: DisplayInterface::new(LowPin, LowPin, dc, LowPin),
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            refresh,
+            state: PowerState::Active,
+            border: None,
+        };
+        (spi, epd)
+    }
+
+    fn test_frame() -> Vec<u8> {
+        (0..buffer_len(WIDTH as usize, HEIGHT as usize))
+            .map(|i| i as u8)
+            .collect()
+    }
+
+    #[test]
+    fn full_update_writes_both_ram_banks() {
+        let frame = test_frame();
+
+        let (mut spi, mut epd) = test_epd(RefreshLut::Full);
+        epd.update_frame(&mut spi, &frame, &mut NoDelay).unwrap();
+        let mut expected = vec![0x24];
+        expected.extend_from_slice(&frame);
+        expected.extend_from_slice(&[0x4E, 0x00, 0x4F, 0x00, 0x00, 0x26]);
+        expected.extend_from_slice(&frame);
+        assert_eq!(spi.bytes(), expected);
+
+        let (mut spi, mut epd) = test_epd(RefreshLut::Quick);
+        epd.update_frame(&mut spi, &frame, &mut NoDelay).unwrap();
+        assert_eq!(spi.bytes()[0], 0x24);
+        assert_eq!(spi.bytes()[1..], frame[..]);
+    }
+
+    #[test]
+    fn wrongly_sized_buffers_are_rejected() {
+        let (mut spi, mut epd) = test_epd(RefreshLut::Full);
+        let frame = test_frame();
+
+        assert_eq!(
+            epd.update_frame(&mut spi, &frame[1..], &mut NoDelay),
+            Err(Error::BufferSize {
+                expected: frame.len(),
+                got: frame.len() - 1,
+            })
+        );
+        assert_eq!(
+            epd.update_partial_frame(&mut spi, &frame[..4], 0, 0, 16, 1, &mut NoDelay),
+            Err(Error::BufferSize {
+                expected: 2,
+                got: 4,
+            })
+        );
+        assert_eq!(
+            epd.update_partial_frame(&mut spi, &frame[..2], 0, 0, 24, 1, &mut NoDelay),
+            Err(Error::BufferSize {
+                expected: 3,
+                got: 2,
+            })
+        );
+        assert!(spi.sent.is_empty());
+    }
+
+    #[test]
+    fn updates_are_rejected_while_sleeping() {
+        let (mut spi, mut epd) = test_epd(RefreshLut::Full);
+        epd.sleep(&mut spi, &mut NoDelay).unwrap();
+        assert_eq!(epd.power_state(), PowerState::DeepSleep);
+
+        let sent = spi.sent.len();
+        assert_eq!(
+            epd.update_frame(&mut spi, &test_frame(), &mut NoDelay),
+            Err(Error::IllegalTransition {
+                state: PowerState::DeepSleep,
+                transition: Transition::Update,
+            })
+        );
+        assert_eq!(
+            epd.display_frame(&mut spi, &mut NoDelay),
+            Err(Error::IllegalTransition {
+                state: PowerState::DeepSleep,
+                transition: Transition::Refresh,
+            })
+        );
+        assert_eq!(spi.sent.len(), sent);
+
+        epd.wake_up(&mut spi, &mut NoDelay).unwrap();
+        assert_eq!(epd.power_state(), PowerState::Active);
+        epd.update_frame(&mut spi, &test_frame(), &mut NoDelay)
+            .unwrap();
+    }
+
+    #[test]
+    fn background_color_is_set_before_the_first_clear() {
+        let (mut spi, dc) = RecordingSpi::new();
+        let mut epd = TestEpd::new_with_background(
+            &mut spi,
+            LowPin,
+            LowPin,
+            dc,
+            LowPin,
+            &mut NoDelay,
+            Color::Black,
+        )
+        .unwrap();
+        assert_eq!(epd.background_color(), &Color::Black);
+
+        spi.sent.clear();
+        epd.clear_frame(&mut spi, &mut NoDelay).unwrap();
+        let bank = (WIDTH / 8 * HEIGHT) as usize;
+        assert_eq!(spi.sent.len(), 2 * (bank + 1));
+        assert_eq!(spi.bytes()[0], 0x24);
+        assert_eq!(spi.bytes()[bank + 1], 0x26);
+        assert!(spi.bytes()[1..=bank].iter().all(|&b| b == 0x00));
+        assert!(spi.bytes()[bank + 2..].iter().all(|&b| b == 0x00));
+    }
+
+    #[test]
+    fn changed_rows_only_are_written() {
+        let (mut spi, mut epd) = test_epd(RefreshLut::Quick);
+        let old = test_frame();
+        let mut new = old.clone();
+
+        assert_eq!(epd.update_changed_rows(&mut spi, &old, &new), Ok(0));
+
+        // rows 100 and 102 of 16 bytes each
+        new[16 * 100] ^= 0xFF;
+        new[16 * 102 + 15] ^= 0xFF;
+        spi.sent.clear();
+        assert_eq!(epd.update_changed_rows(&mut spi, &old, &new), Ok(3 * 16));
+        let data = &spi.bytes()[spi.sent.len() - 3 * 16..];
+        assert_eq!(data, &new[16 * 100..16 * 103]);
+        assert_eq!(
+            spi.bytes()[spi.sent.len() - 3 * 16 - 1],
+            Command::WriteRam as u8
+        );
+
+        assert_eq!(
+            epd.update_changed_rows(&mut spi, &old, &new[1..]),
+            Err(Error::BufferSize {
+                expected: old.len(),
+                got: old.len() - 1
+            })
+        );
+    }
 
     #[test]
     fn epd_size() {
@@ -495,4 +849,45 @@ mod tests {
         assert_eq!(HEIGHT, 296);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn timings_cover_the_busy_periods() {
+        use crate::test_utils::{busy_after, busy_for, BusyPin, TEST_CLOCK};
+        type TimedEpd = Epd2in9<RecordingSpi, BusyPin, BusyPin, DcPin, BusyPin, NoDelay>;
+        busy_for(0);
+        let (mut spi, mut epd) = crate::test_utils::test_epd::<TimedEpd, _>();
+        epd.set_clock(Some(&TEST_CLOCK));
+
+        // 30 busy reads and the idle one
+        busy_after(Command::MasterActivation as u8, 30);
+        epd.display_frame(&mut spi, &mut NoDelay).unwrap();
+        assert_eq!(epd.last_timings().full_refresh_ms, Some(31));
+
+        busy_after(Command::MasterActivation as u8, 12);
+        epd.display_new_frame(&mut spi, &mut NoDelay).unwrap();
+        assert_eq!(epd.last_timings().quick_refresh_ms, Some(13));
+
+        busy_after(Command::WriteRam as u8, 7);
+        epd.clear_frame(&mut spi, &mut NoDelay).unwrap();
+        assert_eq!(epd.last_timings().clear_ms, Some(8));
+    }
+
+    #[test]
+    fn partial_quick_refresh_is_rejected() {
+        let (mut spi, mut epd) = test_epd(RefreshLut::Quick);
+
+        assert_eq!(
+            epd.update_partial_old_frame(&mut spi, &[0; 8], 0, 0, 8, 8, &mut NoDelay),
+            Err(Error::PartialUpdateUnsupported)
+        );
+        assert_eq!(
+            epd.update_partial_new_frame(&mut spi, &[0; 8], 0, 0, 8, 8, &mut NoDelay),
+            Err(Error::PartialUpdateUnsupported)
+        );
+        assert_eq!(
+            epd.clear_partial_frame(&mut spi, 0, 0, 8, 8, &mut NoDelay),
+            Err(Error::PartialUpdateUnsupported)
+        );
+        assert!(spi.sent.is_empty());
+    }
 }
@@ -0,0 +1,52 @@
+//! SPI Commands for the Waveshare 2.9" (D) E-Ink Display (UC8151D)
+use crate::traits;
+
+#[allow(dead_code)]
+#[derive(Copy, Clone)]
+pub(crate) enum Command {
+    PanelSetting = 0x00,
+    PowerSetting = 0x01,
+    PowerOff = 0x02,
+    PowerOn = 0x04,
+    BoosterSoftStart = 0x06,
+    DeepSleep = 0x07,
+    DataStartTransmission1 = 0x10,
+    DisplayRefresh = 0x12,
+    DataStartTransmission2 = 0x13,
+
+    LutForVcom = 0x20,
+    LutWhiteToWhite = 0x21,
+    LutBlackToWhite = 0x22,
+    LutWhiteToBlack = 0x23,
+    LutBlackToBlack = 0x24,
+
+    PllControl = 0x30,
+    VcomAndDataIntervalSetting = 0x50,
+    ResolutionSetting = 0x61,
+    GetStatus = 0x71,
+    VcmDcSetting = 0x82,
+    PartialWindow = 0x90,
+    PartialIn = 0x91,
+    PartialOut = 0x92,
+}
+
+impl traits::Command for Command {
+    /// Returns the address of the command
+    fn address(self) -> u8 {
+        self as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::Command as CommandTrait;
+
+    #[test]
+    fn command_addr() {
+        assert_eq!(Command::PanelSetting.address(), 0x00);
+        assert_eq!(Command::BoosterSoftStart.address(), 0x06);
+        assert_eq!(Command::LutBlackToBlack.address(), 0x24);
+        assert_eq!(Command::PartialOut.address(), 0x92);
+    }
+}
@@ -0,0 +1,435 @@
+//! A simple Driver for the Waveshare 2.9" (D) E-Ink Display via SPI
+//!
+//! The panel is the GDEW029T5 from Good Display with an UC8151D controller. Its commands and
+//! waveforms are different from the SSD1680 of the [2.9" V2](crate::epd2in9_v2).
+//!
+//! The full refresh uses the waveform stored in the OTP of the panel, the quick refresh loads
+//! a short waveform into the LUT registers.
+//!
+//! # References
+//!
+//! - [Datasheet](https://www.waveshare.com/wiki/2.9inch_e-Paper_HAT_(D))
+//! - [Waveshare C driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_2in9d.c)
+//! - [Waveshare Python driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/python/lib/waveshare_epd/epd2in9d.py)
+
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::*,
+};
+
+use crate::check_buffer_len;
+use crate::color::Color;
+use crate::error::Error;
+use crate::interface::DisplayInterface;
+use crate::power::{PowerState, Transition};
+use crate::timing::ResetTiming;
+use crate::traits::{InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::window::WindowLimits;
+use crate::{AutoCs, CsStrategy, ResetPin};
+
+pub(crate) mod command;
+use self::command::Command;
+
+mod constants;
+use self::constants::*;
+
+#[cfg(feature = "graphics")]
+mod graphics;
+#[cfg(feature = "graphics")]
+pub use self::graphics::Display2in9d;
+
+/// Width of the display
+pub const WIDTH: u32 = 128;
+/// Height of the display
+pub const HEIGHT: u32 = 296;
+/// Limits of the partial window registers: 8 bit x and 9 bit y registers, the whole panel fits
+pub const WINDOW_LIMITS: WindowLimits = WindowLimits::new(WIDTH, HEIGHT, 255, 511);
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+const IS_BUSY_LOW: bool = true;
+/// Longest time in milliseconds to wait for the busy pin before giving up with
+/// [Error::Timeout]
+pub const BUSY_TIMEOUT_MS: u32 = 10_000;
+/// Fastest SPI clock of the controller in Hz, see
+/// [check_spi_frequency()](crate::check_spi_frequency())
+pub const MAX_SPI_FREQ_HZ: u32 = 10_000_000;
+const NUM_DISPLAY_BITS: u32 = WIDTH * HEIGHT / 8;
+
+/// Epd2in9d driver
+pub struct Epd2in9d<SPI, CS, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+    /// Background Color
+    color: Color,
+    /// Refresh LUT
+    refresh: RefreshLut,
+    /// Mode of the controller
+    state: PowerState,
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd2in9d<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state = self.state.check(Transition::Init)?;
+        self.interface.reset(delay, 10, 10)?;
+
+        // VDS_EN, VDG_EN, VGH=20V, VGL=-20V, VDH=15V, VDL=-15V
+        self.cmd_with_data(spi, Command::PowerSetting, &[0x03, 0x00, 0x2B, 0x2B, 0x03])?;
+        self.cmd_with_data(spi, Command::BoosterSoftStart, &[0x17, 0x17, 0x17])?;
+
+        self.command(spi, Command::PowerOn)?;
+        self.wait_until_idle_with_timeout(delay)?;
+
+        // Panel setting and waveforms depend on the refresh mode
+        self.set_lut(spi, None)?;
+
+        self.cmd_with_data(
+            spi,
+            Command::ResolutionSetting,
+            &[WIDTH as u8, (HEIGHT >> 8) as u8, HEIGHT as u8],
+        )?;
+
+        // white border, default data interval
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x97])?;
+
+        self.wait_until_idle_with_timeout(delay)?;
+        Ok(())
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd2in9d<SPI, AutoCs, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Creates the driver for a board without a CS GPIO, see [AutoCs]
+    pub fn new_without_cs(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new(spi, AutoCs, busy, dc, rst, delay)
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd2in9d<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    type DisplayColor = Color;
+    fn new(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_background(spi, cs, busy, dc, rst, delay, DEFAULT_BACKGROUND_COLOR)
+    }
+
+    fn new_with_background(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        color: Self::DisplayColor,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(cs, busy, dc, rst);
+
+        let mut epd = Epd2in9d {
+            interface,
+            color,
+            refresh: RefreshLut::Full,
+            state: PowerState::Active,
+        };
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        let next = self.state.check(Transition::Sleep)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        // floating border while sleeping
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0xF7])?;
+        self.command(spi, Command::PowerOff)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
+        self.state = next;
+        Ok(())
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.check_reset_pin()?;
+        self.init(spi, delay)
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn full_refresh_ms(&self) -> u32 {
+        2000
+    }
+
+    fn quick_refresh_ms(&self) -> u32 {
+        500
+    }
+
+    /// Writes the frame into the new data RAM
+    ///
+    /// The full refresh also resets the old data RAM, the quick refresh only looks at the
+    /// new data.
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer, WIDTH, HEIGHT)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        if self.refresh == RefreshLut::Full {
+            self.command(spi, Command::DataStartTransmission1)?;
+            self.interface.data_x_times(spi, 0x00, NUM_DISPLAY_BITS)?;
+        }
+        self.cmd_with_data(spi, Command::DataStartTransmission2, buffer)
+    }
+
+    fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        WINDOW_LIMITS.validate_aligned(x, y, width, height)?;
+        check_buffer_len(buffer, width, height)?;
+        self.state.check(Transition::PartialIn)?;
+        self.wait_until_idle_with_timeout(delay)?;
+
+        self.command(spi, Command::PartialIn)?;
+        self.state = PowerState::Partial;
+
+        let result = self.update_partial_window(spi, buffer, x, y, width, height);
+        self.partial_out(spi, result)
+    }
+
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Refresh)?;
+        self.command(spi, Command::DisplayRefresh)?;
+        delay.delay_ms(10);
+        self.wait_until_idle_with_timeout(delay)
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        let color = self.color.get_byte_value();
+
+        self.command(spi, Command::DataStartTransmission1)?;
+        self.interface.data_x_times(spi, 0x00, NUM_DISPLAY_BITS)?;
+
+        self.command(spi, Command::DataStartTransmission2)?;
+        self.interface.data_x_times(spi, color, NUM_DISPLAY_BITS)
+    }
+
+    /// Selects the OTP waveform for `RefreshLut::Full` or loads the register waveform for
+    /// `RefreshLut::Quick`
+    ///
+    /// The quick waveform only drives changed pixels for a short time, so ghosting builds up
+    /// and a full refresh should be done from time to time.
+    fn set_lut(
+        &mut self,
+        spi: &mut SPI,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Update)?;
+        if let Some(RefreshLut::Custom(_)) = refresh_rate {
+            return Err(Error::UnsupportedLut);
+        }
+        if let Some(refresh_lut) = refresh_rate {
+            self.refresh = refresh_lut;
+        }
+        match self.refresh {
+            RefreshLut::Full => {
+                // LUT from OTP, KW mode, scan up, shift right, booster on
+                self.cmd_with_data(spi, Command::PanelSetting, &[0x1F])
+            }
+            RefreshLut::Quick => {
+                // LUT from register, KW mode, scan up, shift right, booster on
+                self.cmd_with_data(spi, Command::PanelSetting, &[0xBF])?;
+                self.cmd_with_data(spi, Command::PllControl, &[0x3C])?;
+                self.cmd_with_data(spi, Command::VcmDcSetting, &[0x12])?;
+                self.cmd_with_data(spi, Command::LutForVcom, &LUT_VCOM_QUICK)?;
+                self.cmd_with_data(spi, Command::LutWhiteToWhite, &LUT_WW_QUICK)?;
+                self.cmd_with_data(spi, Command::LutBlackToWhite, &LUT_BW_QUICK)?;
+                self.cmd_with_data(spi, Command::LutWhiteToBlack, &LUT_WB_QUICK)?;
+                self.cmd_with_data(spi, Command::LutBlackToBlack, &LUT_BB_QUICK)
+            }
+            RefreshLut::Custom(_) => Err(Error::UnsupportedLut),
+        }
+    }
+
+    fn is_busy(&self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
+    fn send_command_raw(&mut self, spi: &mut SPI, command: u8) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn send_data_raw(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        self.interface.data(spi, data)
+    }
+
+    fn set_spi_chunk_size(&mut self, chunk_size: usize) {
+        self.interface.set_chunk_size(chunk_size);
+    }
+
+    fn set_reset_timing(&mut self, timing: Option<ResetTiming>) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    #[cfg(feature = "test-patterns")]
+    fn set_test_pattern(&mut self, enable: bool) {
+        let pattern = crate::test_pattern::FillPattern::new(self.width(), 1);
+        self.interface.set_fill_pattern(enable.then_some(pattern));
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> Epd2in9d<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Current mode of the controller
+    pub fn power_state(&self) -> PowerState {
+        self.state
+    }
+
+    /// Leaves the partial mode, also if `result` is an error
+    ///
+    /// The controller needs a hard reset if it is put to sleep inside of the partial mode.
+    fn partial_out(
+        &mut self,
+        spi: &mut SPI,
+        result: Result<(), Error<SPI::Error>>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.command(spi, Command::PartialOut)?;
+        self.state = self.state.check(Transition::PartialOut)?;
+        result
+    }
+
+    /// Sends the window and its data, the controller has to be in the partial mode
+    fn update_partial_window(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        let end_x = (x + width).saturating_sub(1);
+        let end_y = (y + height).saturating_sub(1);
+
+        self.cmd_with_data(
+            spi,
+            Command::PartialWindow,
+            &[
+                (x & 0xF8) as u8,
+                (end_x | 0x07) as u8,
+                (y >> 8) as u8,
+                y as u8,
+                (end_y >> 8) as u8,
+                end_y as u8,
+                // Gates only scan inside of the partial window
+                0x28,
+            ],
+        )?;
+        self.cmd_with_data(spi, Command::DataStartTransmission2, buffer)
+    }
+
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    fn wait_until_idle_with_timeout(&mut self, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .wait_until_idle_with_timeout(delay, IS_BUSY_LOW, BUSY_TIMEOUT_MS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 128);
+        assert_eq!(HEIGHT, 296);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+    }
+}
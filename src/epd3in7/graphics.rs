@@ -0,0 +1,133 @@
+use crate::epd3in7::{HEIGHT, WIDTH};
+use crate::graphics::{DisplayRotation, GrayDisplay};
+use crate::traits::FrameBuffer;
+use embedded_graphics_core::pixelcolor::{Gray2, GrayColor};
+use embedded_graphics_core::prelude::*;
+
+/// Full size buffer with 4 gray levels for use with the 3in7 EPD
+///
+/// Send it in [GrayScaleMode::Gray4](crate::epd3in7::GrayScaleMode::Gray4) with
+/// `update_frame` or with
+/// [update_gray_frame()](crate::traits::WaveshareGrayscaleDisplay::update_gray_frame()).
+///
+/// Can also be manually constructed:
+/// `buffer: [0xFF; WIDTH / 4 * HEIGHT]`
+pub struct Display3in7 {
+    buffer: [u8; WIDTH as usize * HEIGHT as usize / 4],
+    rotation: DisplayRotation,
+}
+
+impl Default for Display3in7 {
+    fn default() -> Self {
+        Display3in7 {
+            buffer: [Gray2::WHITE.luma() * 0x55; WIDTH as usize * HEIGHT as usize / 4],
+            rotation: DisplayRotation::default(),
+        }
+    }
+}
+
+impl Display3in7 {
+    /// Sets a single pixel to one of the 4 gray levels, `0` is black and `3` white
+    ///
+    /// The point is in the coordinates of the rotation, points outside of the display are
+    /// ignored.
+    pub fn draw_grayscale_pixel(&mut self, point: Point, level: Gray2) {
+        let _ = self.draw_helper(WIDTH, HEIGHT, Pixel(point, level));
+    }
+}
+
+impl DrawTarget for Display3in7 {
+    type Color = Gray2;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for pixel in pixels {
+            self.draw_helper(WIDTH, HEIGHT, pixel)?;
+        }
+        Ok(())
+    }
+}
+
+impl OriginDimensions for Display3in7 {
+    fn size(&self) -> Size {
+        match self.rotation {
+            DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => Size::new(WIDTH, HEIGHT),
+            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => Size::new(HEIGHT, WIDTH),
+        }
+    }
+}
+
+impl GrayDisplay for Display3in7 {
+    fn buffer(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    fn get_mut_buffer(&mut self) -> &mut [u8] {
+        &mut self.buffer
+    }
+
+    fn set_rotation(&mut self, rotation: DisplayRotation) {
+        self.rotation = rotation;
+    }
+
+    fn rotation(&self) -> DisplayRotation {
+        self.rotation
+    }
+}
+
+impl FrameBuffer for Display3in7 {
+    type Color = Gray2;
+
+    fn as_bytes(&self) -> &[u8] {
+        self.buffer()
+    }
+
+    fn as_bytes_mut(&mut self) -> &mut [u8] {
+        self.get_mut_buffer()
+    }
+
+    fn clear(&mut self, color: Gray2) {
+        self.clear_buffer(color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::GrayPlane;
+
+    #[test]
+    fn graphics_size() {
+        let display = Display3in7::default();
+        assert_eq!(display.buffer().len(), WIDTH as usize * HEIGHT as usize / 4);
+        assert!(display.buffer().iter().all(|&b| b == 0xFF));
+    }
+
+    #[test]
+    fn grayscale_pixels() {
+        let mut display = Display3in7::default();
+        display.draw_grayscale_pixel(Point::new(0, 0), Gray2::new(0));
+        display.draw_grayscale_pixel(Point::new(1, 0), Gray2::new(1));
+        display.draw_grayscale_pixel(Point::new(2, 0), Gray2::new(2));
+        display.draw_grayscale_pixel(Point::new(WIDTH as i32, 0), Gray2::BLACK);
+
+        let buffer = display.buffer();
+        assert_eq!(buffer[0], 0b00_01_10_11);
+        assert!(buffer.iter().skip(1).all(|&b| b == 0xFF));
+        assert_eq!(GrayPlane::High.byte(&buffer[..2]), 0b0011_1111);
+        assert_eq!(GrayPlane::Low.byte(&buffer[..2]), 0b0101_1111);
+    }
+
+    #[test]
+    fn graphics_rotation_90() {
+        let mut display = Display3in7::with_rotation(DisplayRotation::Rotate90);
+        display.draw_grayscale_pixel(Point::new(0, WIDTH as i32 - 1), Gray2::BLACK);
+
+        let buffer = display.buffer();
+        assert_eq!(buffer[0], 0b00_11_11_11);
+        assert!(buffer.iter().skip(1).all(|&b| b == 0xFF));
+    }
+}
@@ -0,0 +1,681 @@
+//! A simple Driver for the 3.7" 280x480 E-Ink Display (GDEH037Z97) via SPI
+//!
+//! The panel shows 4 gray levels. The controller has two RAM planes and takes the high bit of
+//! the gray levels in the first and the low bit in the second, see [GrayPlane]. The driver runs
+//! in one of two [GrayScaleMode]s:
+//!
+//! - [GrayScaleMode::Bw]: frames have 1 bit per pixel like on the other black/white displays and
+//!   only go to the first plane, the refresh is faster
+//! - [GrayScaleMode::Gray4]: frames have 2 bits per pixel like the buffer of [Display3in7] and
+//!   are split into both planes, the refresh uses the gray waveform of the OTP
+//!
+//! # Examples
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::*;
+//!# fn main() -> Result<(), epd_waveshare::error::Error<MockError>> {
+//!use embedded_graphics::{pixelcolor::Gray2, prelude::*, primitives::{Line, PrimitiveStyle}};
+//!use epd_waveshare::{epd3in7::*, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let cs_pin = pin::Mock::new(&expectations);
+//!# let busy_in = pin::Mock::new(&expectations);
+//!# let dc = pin::Mock::new(&expectations);
+//!# let rst = pin::Mock::new(&expectations);
+//!# let mut delay = delay::MockNoop::new();
+//!
+//!// Setup EPD for frames with 4 gray levels
+//!let mut epd = Epd3in7::new(&mut spi, cs_pin, busy_in, dc, rst, &mut delay)?;
+//!epd.set_gray_scale_mode(GrayScaleMode::Gray4);
+//!
+//!let mut display = Display3in7::default();
+//!let _ = Line::new(Point::new(0, 120), Point::new(0, 295))
+//!    .into_styled(PrimitiveStyle::with_stroke(Gray2::new(1), 1))
+//!    .draw(&mut display);
+//!
+//!epd.update_and_display_frame(&mut spi, display.buffer(), &mut delay)?;
+//!epd.sleep(&mut spi, &mut delay)?;
+//!# Ok(())
+//!# }
+//!```
+//!
+//! # References
+//!
+//! - [Waveshare product page](https://www.waveshare.com/wiki/3.7inch_e-Paper_HAT)
+//! - [Waveshare C driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_3in7.c)
+
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::{InputPin, OutputPin},
+};
+
+use crate::check_buffer_len;
+use crate::color::{Color, GrayPlane};
+use crate::error::Error;
+use crate::interface::DisplayInterface;
+use crate::power::{PowerState, Transition};
+use crate::timing::ResetTiming;
+use crate::traits::{InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareGrayscaleDisplay};
+use crate::type_a::command::Command;
+use crate::window::WindowLimits;
+use crate::{AutoCs, CsStrategy, ResetPin};
+
+#[cfg(feature = "graphics")]
+mod graphics;
+#[cfg(feature = "graphics")]
+pub use self::graphics::Display3in7;
+
+/// Width of the display
+pub const WIDTH: u32 = 280;
+/// Height of the display
+pub const HEIGHT: u32 = 480;
+/// Limits of the partial window registers: 10 bit x and y addresses, the whole panel fits
+pub const WINDOW_LIMITS: WindowLimits = WindowLimits::new(WIDTH, HEIGHT, 1023, 1023);
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+const IS_BUSY_LOW: bool = false;
+/// Longest time in milliseconds to wait for the busy pin before giving up with
+/// [Error::Timeout]
+pub const BUSY_TIMEOUT_MS: u32 = 10_000;
+/// Fastest SPI clock of the controller in Hz, see
+/// [check_spi_frequency()](crate::check_spi_frequency())
+pub const MAX_SPI_FREQ_HZ: u32 = 20_000_000;
+
+/// Bytes of a plane, both RAM planes have this size
+const NUM_DISPLAY_BITS: u32 = WIDTH / 8 * HEIGHT;
+
+/// Frame format and waveform used by [Epd3in7]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GrayScaleMode {
+    /// Black and white frames with 1 bit per pixel, only the first RAM plane is written
+    #[default]
+    Bw,
+    /// Frames with 4 gray levels and 2 bits per pixel, written into both RAM planes
+    Gray4,
+}
+
+/// Epd3in7 driver
+///
+pub struct Epd3in7<SPI, CS, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+    /// Background Color
+    color: Color,
+    /// Mode of the controller
+    state: PowerState,
+    /// Format of the frames and waveform of the refresh
+    mode: GrayScaleMode,
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd3in7<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state = self.state.check(Transition::Init)?;
+        self.interface.reset(delay, 10, 10)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.interface.cmd(spi, Command::SwReset)?;
+        self.wait_until_idle_with_timeout(delay)?;
+
+        // 480 gate lines: A[9:0] = 0x1DF, GD, SM and TB = 0
+        self.interface.cmd_with_data(
+            spi,
+            Command::DriverOutputControl,
+            &[(HEIGHT - 1) as u8, ((HEIGHT - 1) >> 8) as u8, 0x00],
+        )?;
+
+        // gate and source voltages, values from the vendor code
+        self.interface
+            .cmd_with_data(spi, Command::GateDrivingVoltage, &[0x00])?;
+        self.interface
+            .cmd_with_data(spi, Command::SourceDrivingVoltage, &[0x41, 0xA8, 0x32])?;
+
+        // x and y increment, the address counter is updated in x direction
+        self.interface
+            .cmd_with_data(spi, Command::DataEntryModeSetting, &[0x03])?;
+
+        self.interface
+            .cmd_with_data(spi, Command::BorderWaveformControl, &[0x03])?;
+
+        self.interface.cmd_with_data(
+            spi,
+            Command::BoosterSoftStartControl,
+            &[0xAE, 0xC7, 0xC3, 0xC0, 0xC0],
+        )?;
+
+        self.interface.cmd_with_data(
+            spi,
+            Command::TemperatureSensorSelection,
+            &[0x80], // 0x80: internal temperature sensor
+        )?;
+
+        self.interface
+            .cmd_with_data(spi, Command::WriteVcomRegister, &[0x44])?;
+
+        // display options of the vendor code, selects the waveforms of the OTP
+        self.interface.cmd_with_data(
+            spi,
+            Command::WriteOtpSelection,
+            &[0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0x4F, 0xFF, 0xFF, 0xFF, 0xFF],
+        )?;
+
+        self.use_full_frame(spi)?;
+
+        self.wait_until_idle_with_timeout(delay)?;
+        Ok(())
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd3in7<SPI, AutoCs, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Creates the driver for a board without a CS GPIO, see [AutoCs]
+    pub fn new_without_cs(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new(spi, AutoCs, busy, dc, rst, delay)
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd3in7<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    type DisplayColor = Color;
+    fn new(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_background(spi, cs, busy, dc, rst, delay, DEFAULT_BACKGROUND_COLOR)
+    }
+
+    fn new_with_background(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        color: Self::DisplayColor,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(cs, busy, dc, rst);
+
+        let mut epd = Epd3in7 {
+            interface,
+            color,
+            state: PowerState::Active,
+            mode: GrayScaleMode::default(),
+        };
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.check_reset_pin()?;
+        self.init(spi, delay)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        let next = self.state.check(Transition::Sleep)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        // deep sleep mode 2, the RAM isn't retained
+        self.interface
+            .cmd_with_data(spi, Command::DeepSleepMode, &[0x03])?;
+        self.state = next;
+        Ok(())
+    }
+
+    /// Writes a frame in the format of the [GrayScaleMode]
+    ///
+    /// [GrayScaleMode::Bw] takes `WIDTH / 8 * HEIGHT` bytes, [GrayScaleMode::Gray4] twice as many.
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer, self.bits_per_pixel() * WIDTH, HEIGHT)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.use_full_frame(spi)?;
+        self.write_planes(spi, buffer, 0, 0)
+    }
+
+    /// Writes a window in the format of the [GrayScaleMode]
+    fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        WINDOW_LIMITS.validate_aligned(x, y, width, height)?;
+        check_buffer_len(buffer, self.bits_per_pixel() * width, height)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)?;
+        self.set_ram_counter(spi, x, y)?;
+        self.write_planes(spi, buffer, x, y)
+    }
+
+    /// Refreshes the panel with the waveform of the OTP for the [GrayScaleMode]
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Refresh)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        // load the temperature and the LUT from the OTP, display in mode 1 or 2 and power off
+        // the analog part
+        let sequence = match self.mode {
+            GrayScaleMode::Bw => 0xF7,
+            GrayScaleMode::Gray4 => 0xFF,
+        };
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[sequence])?;
+        self.interface.start_measurement(RefreshLut::Full.into());
+        self.interface.cmd(spi, Command::MasterActivation)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        Ok(())
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)?;
+        Ok(())
+    }
+
+    /// Fills both RAM planes with the background color
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.use_full_frame(spi)?;
+
+        let color = self.color.get_byte_value();
+        self.interface.cmd(spi, Command::WriteRam)?;
+        self.interface.data_x_times(spi, color, NUM_DISPLAY_BITS)?;
+        self.set_ram_counter(spi, 0, 0)?;
+        self.interface.cmd(spi, Command::WriteRam2)?;
+        self.interface.data_x_times(spi, color, NUM_DISPLAY_BITS)?;
+        Ok(())
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn full_refresh_ms(&self) -> u32 {
+        3000
+    }
+
+    /// Twice as many bytes in [GrayScaleMode::Gray4]
+    fn buffer_len(&self) -> usize {
+        crate::buffer_len((self.bits_per_pixel() * WIDTH) as usize, HEIGHT as usize)
+    }
+
+    /// Only the full LUT of the OTP is supported, other LUTs return [Error::UnsupportedLut]
+    ///
+    /// The waveform is picked by the [GrayScaleMode].
+    fn set_lut(
+        &mut self,
+        _spi: &mut SPI,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Update)?;
+        match refresh_rate {
+            None | Some(RefreshLut::Full) => Ok(()),
+            Some(_) => Err(Error::UnsupportedLut),
+        }
+    }
+
+    fn is_busy(&self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
+    fn send_command_raw(&mut self, spi: &mut SPI, command: u8) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn send_data_raw(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        self.interface.data(spi, data)
+    }
+
+    fn set_spi_chunk_size(&mut self, chunk_size: usize) {
+        self.interface.set_chunk_size(chunk_size);
+    }
+
+    fn set_reset_timing(&mut self, timing: Option<ResetTiming>) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    #[cfg(feature = "test-patterns")]
+    fn set_test_pattern(&mut self, enable: bool) {
+        let pattern = crate::test_pattern::FillPattern::new(self.width(), 1);
+        self.interface.set_fill_pattern(enable.then_some(pattern));
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareGrayscaleDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd3in7<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Switches to [GrayScaleMode::Gray4] and writes the frame into both planes
+    fn update_gray_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Error<SPI::Error>> {
+        // two bits per pixel
+        check_buffer_len(buffer, 2 * WIDTH, HEIGHT)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle();
+        self.mode = GrayScaleMode::Gray4;
+        self.use_full_frame(spi)?;
+        self.write_planes(spi, buffer, 0, 0)
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> Epd3in7<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Selects the format of the frames and the waveform for the next updates and refreshes
+    pub fn set_gray_scale_mode(&mut self, mode: GrayScaleMode) {
+        self.mode = mode;
+    }
+
+    /// Format of the frames and waveform currently used
+    pub fn gray_scale_mode(&self) -> GrayScaleMode {
+        self.mode
+    }
+
+    /// Current mode of the controller
+    pub fn power_state(&self) -> PowerState {
+        self.state
+    }
+
+    fn bits_per_pixel(&self) -> u32 {
+        match self.mode {
+            GrayScaleMode::Bw => 1,
+            GrayScaleMode::Gray4 => 2,
+        }
+    }
+
+    fn wait_until_idle(&mut self) {
+        self.interface.wait_until_idle(IS_BUSY_LOW);
+    }
+
+    fn wait_until_idle_with_timeout(&mut self, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .wait_until_idle_with_timeout(delay, IS_BUSY_LOW, BUSY_TIMEOUT_MS)
+    }
+
+    /// Writes the buffer into the plane(s) of the [GrayScaleMode], the RAM window has to be
+    /// set and the counter at `x`, `y` already
+    fn write_planes(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        match self.mode {
+            GrayScaleMode::Bw => self.interface.cmd_with_data(spi, Command::WriteRam, buffer),
+            GrayScaleMode::Gray4 => {
+                self.send_gray_plane(spi, Command::WriteRam, buffer, GrayPlane::High)?;
+                self.set_ram_counter(spi, x, y)?;
+                self.send_gray_plane(spi, Command::WriteRam2, buffer, GrayPlane::Low)
+            }
+        }
+    }
+
+    /// Sends one plane of a buffer with 2 bits per pixel
+    fn send_gray_plane(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        buffer: &[u8],
+        gray_plane: GrayPlane,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)?;
+        let mut plane = [0u8; 64];
+        for chunk in buffer.chunks(2 * plane.len()) {
+            for (dst, pair) in plane.iter_mut().zip(chunk.chunks(2)) {
+                *dst = gray_plane.byte(pair);
+            }
+            self.interface.data(spi, &plane[..chunk.len() / 2])?;
+        }
+        Ok(())
+    }
+
+    fn use_full_frame(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        // choose full frame/ram
+        self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
+
+        // start from the beginning
+        self.set_ram_counter(spi, 0, 0)
+    }
+
+    /// Sets the RAM window, `end_x` and `end_y` are inclusive
+    fn set_ram_area(
+        &mut self,
+        spi: &mut SPI,
+        start_x: u32,
+        start_y: u32,
+        end_x: u32,
+        end_y: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        if start_x > end_x || start_y > end_y {
+            return Err(Error::InvalidRegion);
+        }
+
+        // 2 Databytes: A[7:0] & 0..A[9:8] for each - start and end, x is in pixels
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamXAddressStartEndPosition,
+            &[
+                start_x as u8,
+                (start_x >> 8) as u8,
+                end_x as u8,
+                (end_x >> 8) as u8,
+            ],
+        )?;
+
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamYAddressStartEndPosition,
+            &[
+                start_y as u8,
+                (start_y >> 8) as u8,
+                end_y as u8,
+                (end_y >> 8) as u8,
+            ],
+        )
+    }
+
+    fn set_ram_counter(&mut self, spi: &mut SPI, x: u32, y: u32) -> Result<(), Error<SPI::Error>> {
+        // 2 Databytes: A[7:0] & 0..A[9:8], x is in pixels
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamXAddressCounter,
+            &[x as u8, (x >> 8) as u8],
+        )?;
+
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamYAddressCounter,
+            &[y as u8, (y >> 8) as u8],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{cmd, test_epd, DcPin, LowPin, NoDelay, RecordingSpi, Sent};
+    extern crate std;
+    use std::vec::Vec;
+
+    type TestEpd = Epd3in7<RecordingSpi, LowPin, LowPin, DcPin, LowPin, NoDelay>;
+
+    /// RAM window and address counter over the whole panel
+    fn full_frame() -> Vec<Sent> {
+        [
+            cmd(0x44, &[0x00, 0x00, 0x17, 0x01]),
+            cmd(0x45, &[0x00, 0x00, 0xDF, 0x01]),
+            cmd(0x4E, &[0x00, 0x00]),
+            cmd(0x4F, &[0x00, 0x00]),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 280);
+        assert_eq!(HEIGHT, 480);
+        assert_eq!(NUM_DISPLAY_BITS, 16800);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+    }
+
+    #[test]
+    fn bw_frames_only_go_to_the_first_plane() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+        assert_eq!(epd.buffer_len(), NUM_DISPLAY_BITS as usize);
+
+        let frame = [0x0F; NUM_DISPLAY_BITS as usize];
+        epd.update_and_display_frame(&mut spi, &frame, &mut NoDelay)
+            .unwrap();
+        let expected = [
+            full_frame(),
+            cmd(0x24, &frame),
+            cmd(0x22, &[0xF7]),
+            cmd(0x20, &[]),
+        ]
+        .concat();
+        assert_eq!(spi.sent, expected);
+    }
+
+    #[test]
+    fn gray_frames_are_split_into_both_planes() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        epd.set_gray_scale_mode(GrayScaleMode::Gray4);
+        spi.sent.clear();
+        assert_eq!(epd.buffer_len(), 2 * NUM_DISPLAY_BITS as usize);
+        assert_eq!(epd.gray_buffer_len(), epd.buffer_len());
+
+        // a 1 bit frame is too short for 4 gray levels
+        assert_eq!(
+            epd.update_frame(&mut spi, &[0xFF; NUM_DISPLAY_BITS as usize], &mut NoDelay),
+            Err(Error::BufferSize {
+                expected: 2 * NUM_DISPLAY_BITS as usize,
+                got: NUM_DISPLAY_BITS as usize
+            })
+        );
+        assert!(spi.sent.is_empty());
+
+        // light gray: high bit set, low bit cleared
+        let frame = [0xAA; 2 * NUM_DISPLAY_BITS as usize];
+        epd.update_and_display_frame(&mut spi, &frame, &mut NoDelay)
+            .unwrap();
+        let expected = [
+            full_frame(),
+            cmd(0x24, &[0xFF; NUM_DISPLAY_BITS as usize]),
+            cmd(0x4E, &[0x00, 0x00]),
+            cmd(0x4F, &[0x00, 0x00]),
+            cmd(0x26, &[0x00; NUM_DISPLAY_BITS as usize]),
+            cmd(0x22, &[0xFF]),
+            cmd(0x20, &[]),
+        ]
+        .concat();
+        assert_eq!(spi.sent, expected);
+    }
+
+    #[test]
+    fn gray_windows_restart_the_counter_for_the_second_plane() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+
+        epd.update_gray_frame(&mut spi, &[0x55; 2 * NUM_DISPLAY_BITS as usize])
+            .unwrap();
+        assert_eq!(epd.gray_scale_mode(), GrayScaleMode::Gray4);
+
+        spi.sent.clear();
+        epd.update_partial_frame(
+            &mut spi,
+            &[0x00, 0xFF, 0x00, 0xFF],
+            264,
+            2,
+            16,
+            1,
+            &mut NoDelay,
+        )
+        .unwrap();
+        let expected = [
+            cmd(0x44, &[0x08, 0x01, 0x17, 0x01]),
+            cmd(0x45, &[0x02, 0x00, 0x02, 0x00]),
+            cmd(0x4E, &[0x08, 0x01]),
+            cmd(0x4F, &[0x02, 0x00]),
+            cmd(0x24, &[0x0F, 0x0F]),
+            cmd(0x4E, &[0x08, 0x01]),
+            cmd(0x4F, &[0x02, 0x00]),
+            cmd(0x26, &[0x0F, 0x0F]),
+        ]
+        .concat();
+        assert_eq!(spi.sent, expected);
+    }
+}
@@ -0,0 +1,729 @@
+//! A simple Driver for the 4.26" 800x480 E-Ink Display via SPI
+//!
+//! Like the [10.2"](crate::epd10in2) the panel uses a SSD1677 with 10 bit RAM addresses, the
+//! x addresses count pixels instead of bytes.
+//!
+//! [RefreshLut::Quick] selects the fast mode of the controller: the waveform of the OTP is
+//! loaded for a fixed temperature, which shortens the refresh at the cost of more ghosting.
+//!
+//! The controller also has a waveform for 4 gray levels in the OTP, frames with gray levels
+//! are sent with [update_gray_frame()](WaveshareGrayscaleDisplay::update_gray_frame()) or
+//! [update_gray_planes()](Epd4in26::update_gray_planes()) and shown by the next
+//! [display_frame()](WaveshareDisplay::display_frame()). The full size `Display4in26Gray` is
+//! only available with the `large-buffers` feature.
+//!
+//! # References
+//!
+//! - SSD1677 datasheet of Solomon Systech, the fast mode is described with the temperature
+//!   sensor control (0x1A) and the display update sequence 0x91
+
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::{InputPin, OutputPin},
+};
+
+use crate::check_buffer_len;
+use crate::color::{Color, GrayPlane};
+use crate::error::Error;
+use crate::interface::DisplayInterface;
+use crate::power::{PowerState, Transition};
+use crate::timing::ResetTiming;
+use crate::traits::{InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareGrayscaleDisplay};
+use crate::type_a::command::Command;
+use crate::window::WindowLimits;
+use crate::{AutoCs, CsStrategy, ResetPin};
+
+#[cfg(feature = "graphics")]
+mod graphics;
+#[cfg(feature = "graphics")]
+pub use self::graphics::Display4in26;
+#[cfg(feature = "large-buffers")]
+mod gray_graphics;
+#[cfg(feature = "large-buffers")]
+pub use self::gray_graphics::Display4in26Gray;
+
+/// Width of the display
+pub const WIDTH: u32 = 800;
+/// Height of the display
+pub const HEIGHT: u32 = 480;
+/// Limits of the partial window registers: 10 bit x and y addresses, the whole panel fits
+pub const WINDOW_LIMITS: WindowLimits = WindowLimits::new(WIDTH, HEIGHT, 1023, 1023);
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+const IS_BUSY_LOW: bool = false;
+/// Longest time in milliseconds to wait for the busy pin before giving up with
+/// [Error::Timeout]
+pub const BUSY_TIMEOUT_MS: u32 = 30_000;
+/// Fastest SPI clock of the controller in Hz, see
+/// [check_spi_frequency()](crate::check_spi_frequency())
+pub const MAX_SPI_FREQ_HZ: u32 = 20_000_000;
+
+/// Bytes of a frame, both RAM banks have this size
+const NUM_DISPLAY_BITS: u32 = WIDTH / 8 * HEIGHT;
+
+/// Temperature in °C written for the fast and the gray mode, the controller loads the waveform
+/// of the OTP for this temperature instead of the measured one
+const FAST_MODE_TEMPERATURE: u8 = 0x5A;
+
+/// Epd4in26 driver
+///
+pub struct Epd4in26<SPI, CS, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+    /// Background Color
+    color: Color,
+    /// Refresh LUT
+    refresh: RefreshLut,
+    /// Mode of the controller
+    state: PowerState,
+    /// The RAM holds a frame with gray levels, refreshed with the gray waveform
+    gray: bool,
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd4in26<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state = self.state.check(Transition::Init)?;
+        self.interface.reset(delay, 10, 10)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.interface.cmd(spi, Command::SwReset)?;
+        self.wait_until_idle_with_timeout(delay)?;
+
+        // soft start phases and duration of the booster, values from the vendor code
+        self.interface.cmd_with_data(
+            spi,
+            Command::BoosterSoftStartControl,
+            &[0xAE, 0xC7, 0xC3, 0xC0, 0x80],
+        )?;
+
+        // 480 gate lines: A[9:0] = 0x1DF, GD, SM and TB = 0
+        self.interface.cmd_with_data(
+            spi,
+            Command::DriverOutputControl,
+            &[(HEIGHT - 1) as u8, ((HEIGHT - 1) >> 8) as u8, 0x00],
+        )?;
+
+        // x and y increment, the address counter is updated in x direction
+        self.interface
+            .cmd_with_data(spi, Command::DataEntryModeSetting, &[0x03])?;
+
+        self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
+
+        // white border
+        self.interface
+            .cmd_with_data(spi, Command::BorderWaveformControl, &[0x01])?;
+
+        self.interface.cmd_with_data(
+            spi,
+            Command::TemperatureSensorSelection,
+            &[0x80], // 0x80: internal temperature sensor
+        )?;
+
+        self.set_ram_counter(spi, 0, 0)?;
+
+        self.wait_until_idle_with_timeout(delay)?;
+        Ok(())
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd4in26<SPI, AutoCs, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Creates the driver for a board without a CS GPIO, see [AutoCs]
+    pub fn new_without_cs(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new(spi, AutoCs, busy, dc, rst, delay)
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd4in26<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    type DisplayColor = Color;
+    fn new(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_background(spi, cs, busy, dc, rst, delay, DEFAULT_BACKGROUND_COLOR)
+    }
+
+    fn new_with_background(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        color: Self::DisplayColor,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(cs, busy, dc, rst);
+
+        let mut epd = Epd4in26 {
+            interface,
+            color,
+            refresh: RefreshLut::Full,
+            state: PowerState::Active,
+            gray: false,
+        };
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.check_reset_pin()?;
+        self.init(spi, delay)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        let next = self.state.check(Transition::Sleep)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        // deep sleep mode 2, the RAM isn't retained
+        self.interface
+            .cmd_with_data(spi, Command::DeepSleepMode, &[0x03])?;
+        self.state = next;
+        Ok(())
+    }
+
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer, WIDTH, HEIGHT)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.use_full_frame(spi)?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteRam, buffer)?;
+        self.gray = false;
+        Ok(())
+    }
+
+    /// Updates a window of the frame
+    ///
+    /// Windows with the full width and a part of the rows can be used to send a whole frame
+    /// in several bands.
+    fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        WINDOW_LIMITS.validate_aligned(x, y, width, height)?;
+        check_buffer_len(buffer, width, height)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)?;
+        self.set_ram_counter(spi, x, y)?;
+
+        self.interface
+            .cmd_with_data(spi, Command::WriteRam, buffer)?;
+        self.gray = false;
+        Ok(())
+    }
+
+    /// Refreshes the panel with the LUT selected by [set_lut()](WaveshareDisplay::set_lut())
+    ///
+    /// A frame with gray levels is refreshed with the gray waveform instead.
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Refresh)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        let sequence = match self.refresh {
+            // display mode 2 with the loaded LUT and power off the analog part
+            _ if self.gray => {
+                self.load_lut_for_fixed_temperature(spi, delay)?;
+                0xCF
+            }
+            // load the temperature and the LUT from the OTP, display and power off the analog part
+            RefreshLut::Full => 0xF7,
+            RefreshLut::Quick => {
+                self.load_lut_for_fixed_temperature(spi, delay)?;
+                // display with the loaded LUT and power off the analog part
+                0xC7
+            }
+            RefreshLut::Custom(_) => return Err(Error::UnsupportedLut),
+        };
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[sequence])?;
+        self.interface.start_measurement(self.refresh.into());
+        self.interface.cmd(spi, Command::MasterActivation)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        Ok(())
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)?;
+        Ok(())
+    }
+
+    /// Fills both RAM banks with the background color
+    ///
+    /// The fill is sent in transfers of up to 256 bytes, not one transfer per byte.
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.use_full_frame(spi)?;
+
+        let color = self.color.get_byte_value();
+        self.interface.cmd(spi, Command::WriteRam)?;
+        self.interface.data_x_times(spi, color, NUM_DISPLAY_BITS)?;
+        self.set_ram_counter(spi, 0, 0)?;
+        self.interface.cmd(spi, Command::WriteRam2)?;
+        self.interface.data_x_times(spi, color, NUM_DISPLAY_BITS)?;
+        self.gray = false;
+        Ok(())
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn full_refresh_ms(&self) -> u32 {
+        3500
+    }
+
+    fn quick_refresh_ms(&self) -> u32 {
+        1500
+    }
+
+    /// Selects the full or the fast mode for the next refreshes, both use the LUT of the OTP
+    fn set_lut(
+        &mut self,
+        _spi: &mut SPI,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Update)?;
+        if let Some(RefreshLut::Custom(_)) = refresh_rate {
+            return Err(Error::UnsupportedLut);
+        }
+        if let Some(refresh_lut) = refresh_rate {
+            self.refresh = refresh_lut;
+        }
+        Ok(())
+    }
+
+    fn is_busy(&self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
+    fn send_command_raw(&mut self, spi: &mut SPI, command: u8) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn send_data_raw(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        self.interface.data(spi, data)
+    }
+
+    fn set_spi_chunk_size(&mut self, chunk_size: usize) {
+        self.interface.set_chunk_size(chunk_size);
+    }
+
+    fn set_reset_timing(&mut self, timing: Option<ResetTiming>) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    #[cfg(feature = "test-patterns")]
+    fn set_test_pattern(&mut self, enable: bool) {
+        let pattern = crate::test_pattern::FillPattern::new(self.width(), 1);
+        self.interface.set_fill_pattern(enable.then_some(pattern));
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> Epd4in26<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    fn wait_until_idle(&mut self) {
+        self.interface.wait_until_idle(IS_BUSY_LOW);
+    }
+
+    fn wait_until_idle_with_timeout(&mut self, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .wait_until_idle_with_timeout(delay, IS_BUSY_LOW, BUSY_TIMEOUT_MS)
+    }
+
+    /// Current mode of the controller
+    pub fn power_state(&self) -> PowerState {
+        self.state
+    }
+
+    /// Writes a frame with 4 gray levels given as two planes with 1 bit per pixel
+    ///
+    /// `high` holds the high bits of the levels and goes to `WriteRam`, `low` the low bits and
+    /// goes to `WriteRam2`, see [GrayPlane]. Like a black/white frame each plane has
+    /// `WIDTH / 8 * HEIGHT` bytes.
+    pub fn update_gray_planes(
+        &mut self,
+        spi: &mut SPI,
+        high: &[u8],
+        low: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(high, WIDTH, HEIGHT)?;
+        check_buffer_len(low, WIDTH, HEIGHT)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle();
+        self.use_full_frame(spi)?;
+        self.interface.cmd_with_data(spi, Command::WriteRam, high)?;
+        self.set_ram_counter(spi, 0, 0)?;
+        self.interface.cmd_with_data(spi, Command::WriteRam2, low)?;
+        self.gray = true;
+        Ok(())
+    }
+
+    /// Loads the LUT of the OTP for [FAST_MODE_TEMPERATURE] instead of the measured one
+    fn load_lut_for_fixed_temperature(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd_with_data(
+            spi,
+            Command::TemperatureSensorControl,
+            &[FAST_MODE_TEMPERATURE, 0x00],
+        )?;
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0x91])?;
+        self.interface.cmd(spi, Command::MasterActivation)?;
+        self.wait_until_idle_with_timeout(delay)
+    }
+
+    /// Sends one plane of a buffer with 2 bits per pixel
+    fn send_gray_plane(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        buffer: &[u8],
+        gray_plane: GrayPlane,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)?;
+        let mut plane = [0u8; 64];
+        for chunk in buffer.chunks(2 * plane.len()) {
+            for (dst, pair) in plane.iter_mut().zip(chunk.chunks(2)) {
+                *dst = gray_plane.byte(pair);
+            }
+            self.interface.data(spi, &plane[..chunk.len() / 2])?;
+        }
+        Ok(())
+    }
+
+    fn use_full_frame(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        // choose full frame/ram
+        self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
+
+        // start from the beginning
+        self.set_ram_counter(spi, 0, 0)
+    }
+
+    /// Sets the RAM window, `end_x` and `end_y` are inclusive
+    fn set_ram_area(
+        &mut self,
+        spi: &mut SPI,
+        start_x: u32,
+        start_y: u32,
+        end_x: u32,
+        end_y: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        if start_x > end_x || start_y > end_y {
+            return Err(Error::InvalidRegion);
+        }
+
+        // 2 Databytes: A[7:0] & 0..A[9:8] for each - start and end, x is in pixels
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamXAddressStartEndPosition,
+            &[
+                start_x as u8,
+                (start_x >> 8) as u8,
+                end_x as u8,
+                (end_x >> 8) as u8,
+            ],
+        )?;
+
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamYAddressStartEndPosition,
+            &[
+                start_y as u8,
+                (start_y >> 8) as u8,
+                end_y as u8,
+                (end_y >> 8) as u8,
+            ],
+        )
+    }
+
+    fn set_ram_counter(&mut self, spi: &mut SPI, x: u32, y: u32) -> Result<(), Error<SPI::Error>> {
+        // 2 Databytes: A[7:0] & 0..A[9:8], x is in pixels
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamXAddressCounter,
+            &[x as u8, (x >> 8) as u8],
+        )?;
+
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamYAddressCounter,
+            &[y as u8, (y >> 8) as u8],
+        )
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareGrayscaleDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd4in26<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Writes the high bit of the gray levels to `WriteRam` and the low bit to `WriteRam2`
+    ///
+    /// The gray waveform is used until the next black/white update or clear.
+    fn update_gray_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Error<SPI::Error>> {
+        // two bits per pixel
+        check_buffer_len(buffer, 2 * WIDTH, HEIGHT)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle();
+        self.use_full_frame(spi)?;
+        self.send_gray_plane(spi, Command::WriteRam, buffer, GrayPlane::High)?;
+        self.set_ram_counter(spi, 0, 0)?;
+        self.send_gray_plane(spi, Command::WriteRam2, buffer, GrayPlane::Low)?;
+        self.gray = true;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{cmd, test_epd, DcPin, LowPin, NoDelay, RecordingSpi, Sent};
+    extern crate std;
+    use std::vec::Vec;
+
+    type TestEpd = Epd4in26<RecordingSpi, LowPin, LowPin, DcPin, LowPin, NoDelay>;
+
+    /// RAM window and address counter over the whole panel
+    fn full_frame() -> Vec<Sent> {
+        [
+            cmd(0x44, &[0x00, 0x00, 0x1F, 0x03]),
+            cmd(0x45, &[0x00, 0x00, 0xDF, 0x01]),
+            cmd(0x4E, &[0x00, 0x00]),
+            cmd(0x4F, &[0x00, 0x00]),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 800);
+        assert_eq!(HEIGHT, 480);
+        assert_eq!(crate::buffer_len(WIDTH as usize, HEIGHT as usize), 48000);
+        assert_eq!(NUM_DISPLAY_BITS, 48000);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+    }
+
+    #[test]
+    fn init_sequence() {
+        let (spi, _) = test_epd::<TestEpd, _>();
+        let expected = [
+            cmd(0x12, &[]),
+            cmd(0x0C, &[0xAE, 0xC7, 0xC3, 0xC0, 0x80]),
+            cmd(0x01, &[0xDF, 0x01, 0x00]),
+            cmd(0x11, &[0x03]),
+            cmd(0x44, &[0x00, 0x00, 0x1F, 0x03]),
+            cmd(0x45, &[0x00, 0x00, 0xDF, 0x01]),
+            cmd(0x3C, &[0x01]),
+            cmd(0x18, &[0x80]),
+            cmd(0x4E, &[0x00, 0x00]),
+            cmd(0x4F, &[0x00, 0x00]),
+        ]
+        .concat();
+        assert_eq!(spi.sent, expected);
+    }
+
+    #[test]
+    fn clear_frame_fills_both_rams() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+        epd.set_background_color(Color::Black);
+
+        epd.clear_frame(&mut spi, &mut NoDelay).unwrap();
+
+        let expected = [
+            full_frame(),
+            cmd(0x24, &[0x00; NUM_DISPLAY_BITS as usize]),
+            cmd(0x4E, &[0x00, 0x00]),
+            cmd(0x4F, &[0x00, 0x00]),
+            cmd(0x26, &[0x00; NUM_DISPLAY_BITS as usize]),
+        ]
+        .concat();
+        assert_eq!(spi.sent, expected);
+    }
+
+    #[test]
+    fn quick_refresh_loads_the_lut_for_a_fixed_temperature() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+
+        epd.display_frame(&mut spi, &mut NoDelay).unwrap();
+        assert_eq!(spi.sent, [cmd(0x22, &[0xF7]), cmd(0x20, &[])].concat());
+
+        spi.sent.clear();
+        epd.set_lut(&mut spi, Some(RefreshLut::Quick)).unwrap();
+        epd.display_frame(&mut spi, &mut NoDelay).unwrap();
+        let expected = [
+            cmd(0x1A, &[0x5A, 0x00]),
+            cmd(0x22, &[0x91]),
+            cmd(0x20, &[]),
+            cmd(0x22, &[0xC7]),
+            cmd(0x20, &[]),
+        ]
+        .concat();
+        assert_eq!(spi.sent, expected);
+    }
+
+    #[test]
+    fn gray_frame_is_split_into_planes_and_refreshed_in_display_mode_2() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+
+        // dark gray: high bit cleared, low bit set
+        let buffer = [0x55; 2 * NUM_DISPLAY_BITS as usize];
+        epd.update_gray_frame(&mut spi, &buffer).unwrap();
+        let expected = [
+            full_frame(),
+            cmd(0x24, &[0x00; NUM_DISPLAY_BITS as usize]),
+            cmd(0x4E, &[0x00, 0x00]),
+            cmd(0x4F, &[0x00, 0x00]),
+            cmd(0x26, &[0xFF; NUM_DISPLAY_BITS as usize]),
+        ]
+        .concat();
+        assert_eq!(spi.sent, expected);
+
+        spi.sent.clear();
+        epd.display_frame(&mut spi, &mut NoDelay).unwrap();
+        let expected = [
+            cmd(0x1A, &[0x5A, 0x00]),
+            cmd(0x22, &[0x91]),
+            cmd(0x20, &[]),
+            cmd(0x22, &[0xCF]),
+            cmd(0x20, &[]),
+        ]
+        .concat();
+        assert_eq!(spi.sent, expected);
+
+        // a black/white frame uses the full waveform again
+        let frame = [0xFF; NUM_DISPLAY_BITS as usize];
+        epd.update_frame(&mut spi, &frame, &mut NoDelay).unwrap();
+        spi.sent.clear();
+        epd.display_frame(&mut spi, &mut NoDelay).unwrap();
+        assert_eq!(spi.sent, [cmd(0x22, &[0xF7]), cmd(0x20, &[])].concat());
+    }
+
+    #[test]
+    fn gray_planes_are_sent_as_given() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+        let high = [0x0F; NUM_DISPLAY_BITS as usize];
+        let low = [0x33; NUM_DISPLAY_BITS as usize];
+
+        assert_eq!(
+            epd.update_gray_planes(&mut spi, &high, &low[1..]),
+            Err(Error::BufferSize {
+                expected: NUM_DISPLAY_BITS as usize,
+                got: NUM_DISPLAY_BITS as usize - 1
+            })
+        );
+        assert!(spi.sent.is_empty());
+
+        epd.update_gray_planes(&mut spi, &high, &low).unwrap();
+        let expected = [
+            full_frame(),
+            cmd(0x24, &high),
+            cmd(0x4E, &[0x00, 0x00]),
+            cmd(0x4F, &[0x00, 0x00]),
+            cmd(0x26, &low),
+        ]
+        .concat();
+        assert_eq!(spi.sent, expected);
+    }
+
+    #[test]
+    fn partial_frame_uses_ten_bit_pixel_addresses() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+
+        epd.update_partial_frame(&mut spi, &[0xAA, 0x55], 784, 479, 16, 1, &mut NoDelay)
+            .unwrap();
+
+        let expected = [
+            cmd(0x44, &[0x10, 0x03, 0x1F, 0x03]),
+            cmd(0x45, &[0xDF, 0x01, 0xDF, 0x01]),
+            cmd(0x4E, &[0x10, 0x03]),
+            cmd(0x4F, &[0xDF, 0x01]),
+            cmd(0x24, &[0xAA, 0x55]),
+        ]
+        .concat();
+        assert_eq!(spi.sent, expected);
+    }
+}
@@ -0,0 +1,551 @@
+//! A simple Driver for the Waveshare 4.2" V2 E-Ink Display via SPI
+//!
+//! The V2 (GDEQ042T81) uses an UC8176 instead of the IL0398 of the [first version](crate::epd4in2).
+//! Both share the command set, but the UC8176 refreshes with the waveform from its OTP and only
+//! needs LUTs in the registers for the quick refresh, which also runs with lower source
+//! voltages.
+//!
+//! # References
+//!
+//! - [Waveshare wiki](https://www.waveshare.com/wiki/4.2inch_e-Paper_Module_Manual)
+//! - [Waveshare C driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_4in2_V2.c)
+
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::*,
+};
+
+use crate::check_buffer_len;
+use crate::color::Color;
+use crate::epd4in2::command::Command;
+use crate::error::Error;
+use crate::interface::DisplayInterface;
+use crate::power::{PowerState, Transition};
+use crate::timing::ResetTiming;
+use crate::traits::{InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::window::WindowLimits;
+use crate::{AutoCs, CsStrategy, ResetPin};
+
+//The Lookup Tables for the Display
+mod constants;
+use crate::epd4in2_v2::constants::*;
+
+/// Width of the display
+pub const WIDTH: u32 = 400;
+/// Height of the display
+pub const HEIGHT: u32 = 300;
+/// Limits of the partial window registers: 9 bit x and y registers, the whole panel fits
+pub const WINDOW_LIMITS: WindowLimits = WindowLimits::new(WIDTH, HEIGHT, 511, 511);
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+const IS_BUSY_LOW: bool = true;
+/// Longest time in milliseconds to wait for the busy pin before giving up with
+/// [Error::Timeout]
+pub const BUSY_TIMEOUT_MS: u32 = 10_000;
+/// Fastest SPI clock of the controller in Hz, see
+/// [check_spi_frequency()](crate::check_spi_frequency())
+pub const MAX_SPI_FREQ_HZ: u32 = 10_000_000;
+
+/// Full size buffer for use with the 4.2" V2 EPD, the same as for the first version
+#[cfg(feature = "graphics")]
+pub use crate::epd4in2::Display4in2 as Display4in2V2;
+
+/// Epd4in2V2 driver
+///
+pub struct Epd4in2V2<SPI, CS, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+    /// Background Color
+    color: Color,
+    /// Refresh LUT
+    refresh: RefreshLut,
+    /// Mode of the controller
+    state: PowerState,
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd4in2V2<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        // reset the device
+        self.state = self.state.check(Transition::Init)?;
+        self.interface.reset(delay, 10, 10)?;
+
+        // the driving voltages have to be set before the power is switched on
+        self.set_driving_voltages(spi)?;
+
+        // start the booster, phase C with a higher driving strength than the IL0398
+        self.cmd_with_data(spi, Command::BoosterSoftStart, &[0x17, 0x17, 0x27])?;
+
+        // power on
+        self.command(spi, Command::PowerOn)?;
+        delay.delay_ms(5);
+        self.wait_until_idle_with_timeout(delay)?;
+
+        // 3A 100HZ
+        self.cmd_with_data(spi, Command::PllControl, &[0x3A])?;
+
+        self.send_resolution(spi)?;
+
+        self.cmd_with_data(spi, Command::VcmDcSetting, &[0x12])?;
+
+        // white border, default data interval
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x77])?;
+
+        self.load_lut(spi)?;
+
+        self.wait_until_idle_with_timeout(delay)?;
+        Ok(())
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd4in2V2<SPI, AutoCs, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Creates the driver for a board without a CS GPIO, see [AutoCs]
+    pub fn new_without_cs(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new(spi, AutoCs, busy, dc, rst, delay)
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd4in2V2<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    type DisplayColor = Color;
+    fn new(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_background(spi, cs, busy, dc, rst, delay, DEFAULT_BACKGROUND_COLOR)
+    }
+
+    fn new_with_background(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        color: Self::DisplayColor,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(cs, busy, dc, rst);
+
+        let mut epd = Epd4in2V2 {
+            interface,
+            color,
+            refresh: RefreshLut::Full,
+            state: PowerState::Active,
+        };
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.check_reset_pin()?;
+        self.init(spi, delay)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        let next = self.state.check(Transition::Sleep)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        // border floating
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0xF7])?;
+        self.command(spi, Command::PowerOff)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
+        self.state = next;
+        Ok(())
+    }
+
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer, WIDTH, HEIGHT)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        let color_value = self.color.get_byte_value();
+
+        self.command(spi, Command::DataStartTransmission1)?;
+        self.interface
+            .data_x_times(spi, color_value, WIDTH / 8 * HEIGHT)?;
+
+        self.cmd_with_data(spi, Command::DataStartTransmission2, buffer)
+    }
+
+    fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        WINDOW_LIMITS.validate_aligned(x, y, width, height)?;
+        check_buffer_len(buffer, width, height)?;
+        self.state.check(Transition::PartialIn)?;
+        self.wait_until_idle_with_timeout(delay)?;
+
+        self.command(spi, Command::PartialIn)?;
+        self.state = PowerState::Partial;
+
+        let result = self.update_partial_window(spi, buffer, x, y, width, height);
+        self.partial_out(spi, result)
+    }
+
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Refresh)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.interface.start_measurement(self.refresh.into());
+        self.command(spi, Command::DisplayRefresh)?;
+        Ok(())
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.send_resolution(spi)?;
+
+        let color_value = self.color.get_byte_value();
+
+        self.command(spi, Command::DataStartTransmission1)?;
+        self.interface
+            .data_x_times(spi, color_value, WIDTH / 8 * HEIGHT)?;
+
+        self.command(spi, Command::DataStartTransmission2)?;
+        self.interface
+            .data_x_times(spi, color_value, WIDTH / 8 * HEIGHT)
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn full_refresh_ms(&self) -> u32 {
+        3500
+    }
+
+    fn quick_refresh_ms(&self) -> u32 {
+        1500
+    }
+
+    /// Switches between the OTP waveform (`Full`) and the LUTs of the quick refresh
+    ///
+    /// The driving voltages are always written before the panel setting and the LUTs, so the
+    /// short quick waveform never runs with the voltages of the full one and vice versa.
+    fn set_lut(
+        &mut self,
+        spi: &mut SPI,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Update)?;
+        if let Some(RefreshLut::Custom(_)) = refresh_rate {
+            return Err(Error::UnsupportedLut);
+        }
+        if let Some(refresh_lut) = refresh_rate {
+            self.refresh = refresh_lut;
+        }
+        self.wait_until_idle();
+        self.set_driving_voltages(spi)?;
+        self.load_lut(spi)
+    }
+
+    fn is_busy(&self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
+    fn send_command_raw(&mut self, spi: &mut SPI, command: u8) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn send_data_raw(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        self.interface.data(spi, data)
+    }
+
+    fn set_spi_chunk_size(&mut self, chunk_size: usize) {
+        self.interface.set_chunk_size(chunk_size);
+    }
+
+    fn set_reset_timing(&mut self, timing: Option<ResetTiming>) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    #[cfg(feature = "test-patterns")]
+    fn set_test_pattern(&mut self, enable: bool) {
+        let pattern = crate::test_pattern::FillPattern::new(self.width(), 1);
+        self.interface.set_fill_pattern(enable.then_some(pattern));
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> Epd4in2V2<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Current mode of the controller
+    pub fn power_state(&self) -> PowerState {
+        self.state
+    }
+
+    /// Leaves the partial mode, also if `result` is an error
+    ///
+    /// The controller needs a hard reset if it is put to sleep inside of the partial mode.
+    fn partial_out(
+        &mut self,
+        spi: &mut SPI,
+        result: Result<(), Error<SPI::Error>>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.command(spi, Command::PartialOut)?;
+        self.state = self.state.check(Transition::PartialOut)?;
+        result
+    }
+
+    /// Sends the window and its data, the controller has to be in the partial mode
+    fn update_partial_window(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.command(spi, Command::PartialWindow)?;
+
+        // x should be the multiple of 8, the last 3 bit will always be ignored
+        let start_x = x & !0x07;
+        let end_x = (start_x + width - 1) | 0x07;
+        let end_y = y + height - 1;
+        self.send_data(
+            spi,
+            &[
+                (start_x >> 8) as u8,
+                start_x as u8,
+                (end_x >> 8) as u8,
+                end_x as u8,
+                (y >> 8) as u8,
+                y as u8,
+                (end_y >> 8) as u8,
+                end_y as u8,
+                // Gates scan both inside and outside of the partial window. (default)
+                0x01,
+            ],
+        )?;
+
+        self.cmd_with_data(spi, Command::DataStartTransmission2, buffer)
+    }
+
+    /// Power setting with the gate (VGH/VGL) and source (VDH/VDL) driving voltages of the
+    /// current LUT
+    fn set_driving_voltages(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        let voltages = match self.refresh {
+            RefreshLut::Full => &POWER_SETTING_FULL,
+            RefreshLut::Quick => &POWER_SETTING_QUICK,
+            RefreshLut::Custom(_) => return Err(Error::UnsupportedLut),
+        };
+        self.cmd_with_data(spi, Command::PowerSetting, voltages)
+    }
+
+    /// Panel setting and, for the quick refresh, the LUT registers
+    fn load_lut(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        match self.refresh {
+            // KW mode, LUT from OTP
+            RefreshLut::Full => self.cmd_with_data(spi, Command::PanelSetting, &[0x1F]),
+            RefreshLut::Quick => {
+                // KW mode, LUT from register
+                self.cmd_with_data(spi, Command::PanelSetting, &[0x3F])?;
+                self.cmd_with_data(spi, Command::LutForVcom, &LUT_VCOM_QUICK)?;
+                self.cmd_with_data(spi, Command::LutWhiteToWhite, &LUT_WW_QUICK)?;
+                self.cmd_with_data(spi, Command::LutBlackToWhite, &LUT_BW_QUICK)?;
+                self.cmd_with_data(spi, Command::LutWhiteToBlack, &LUT_WB_QUICK)?;
+                self.cmd_with_data(spi, Command::LutBlackToBlack, &LUT_BB_QUICK)
+            }
+            RefreshLut::Custom(_) => Err(Error::UnsupportedLut),
+        }
+    }
+
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        self.interface.data(spi, data)
+    }
+
+    fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    fn wait_until_idle(&mut self) {
+        self.interface.wait_until_idle(IS_BUSY_LOW);
+    }
+
+    fn wait_until_idle_with_timeout(&mut self, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .wait_until_idle_with_timeout(delay, IS_BUSY_LOW, BUSY_TIMEOUT_MS)
+    }
+
+    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        self.cmd_with_data(
+            spi,
+            Command::ResolutionSetting,
+            &[
+                (WIDTH >> 8) as u8,
+                WIDTH as u8,
+                (HEIGHT >> 8) as u8,
+                HEIGHT as u8,
+            ],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{cmd, test_epd, DcPin, HighPin, NoDelay, RecordingSpi};
+
+    type TestEpd = Epd4in2V2<RecordingSpi, HighPin, HighPin, DcPin, HighPin, NoDelay>;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 400);
+        assert_eq!(HEIGHT, 300);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+    }
+
+    #[test]
+    fn init_sequence() {
+        let (spi, epd) = test_epd::<TestEpd, _>();
+        let expected = [
+            cmd(0x01, &POWER_SETTING_FULL),
+            cmd(0x06, &[0x17, 0x17, 0x27]),
+            cmd(0x04, &[]),
+            cmd(0x30, &[0x3A]),
+            cmd(0x61, &[0x01, 0x90, 0x01, 0x2C]),
+            cmd(0x82, &[0x12]),
+            cmd(0x50, &[0x77]),
+            cmd(0x00, &[0x1F]),
+        ]
+        .concat();
+        assert_eq!(spi.sent, expected);
+        assert_eq!(epd.power_state(), PowerState::Active);
+    }
+
+    #[test]
+    fn voltages_are_set_before_the_luts() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+
+        spi.sent.clear();
+        epd.set_lut(&mut spi, Some(RefreshLut::Quick)).unwrap();
+        let expected = [
+            cmd(0x01, &POWER_SETTING_QUICK),
+            cmd(0x00, &[0x3F]),
+            cmd(0x20, &LUT_VCOM_QUICK),
+            cmd(0x21, &LUT_WW_QUICK),
+            cmd(0x22, &LUT_BW_QUICK),
+            cmd(0x23, &LUT_WB_QUICK),
+            cmd(0x24, &LUT_BB_QUICK),
+        ]
+        .concat();
+        assert_eq!(spi.sent, expected);
+
+        spi.sent.clear();
+        epd.set_lut(&mut spi, Some(RefreshLut::Full)).unwrap();
+        let expected = [cmd(0x01, &POWER_SETTING_FULL), cmd(0x00, &[0x1F])].concat();
+        assert_eq!(spi.sent, expected);
+    }
+
+    #[test]
+    fn partial_frame_is_wrapped_in_partial_mode() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+
+        // the controller would start the window at x = 8
+        assert_eq!(
+            epd.update_partial_frame(&mut spi, &[0xAA, 0x55], 12, 299, 16, 1, &mut NoDelay),
+            Err(Error::InvalidRegion)
+        );
+        assert!(spi.sent.is_empty());
+
+        epd.update_partial_frame(&mut spi, &[0xAA, 0x55], 8, 299, 16, 1, &mut NoDelay)
+            .unwrap();
+        let expected = [
+            cmd(0x91, &[]),
+            cmd(
+                0x90,
+                &[0x00, 0x08, 0x00, 0x17, 0x01, 0x2B, 0x01, 0x2B, 0x01],
+            ),
+            cmd(0x13, &[0xAA, 0x55]),
+            cmd(0x92, &[]),
+        ]
+        .concat();
+        assert_eq!(spi.sent, expected);
+        assert_eq!(epd.power_state(), PowerState::Active);
+    }
+}
@@ -1,6 +1,7 @@
 use crate::color::OctColor;
 use crate::epd5in65f::{DEFAULT_BACKGROUND_COLOR, HEIGHT, WIDTH};
 use crate::graphics::{DisplayRotation, OctDisplay};
+use crate::traits::FrameBuffer;
 use embedded_graphics_core::prelude::*;
 
 /// Full size buffer for use with the 5in65f EPD
@@ -39,7 +40,10 @@ impl DrawTarget for Display5in65f {
 
 impl OriginDimensions for Display5in65f {
     fn size(&self) -> Size {
-        Size::new(WIDTH, HEIGHT)
+        match self.rotation {
+            DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => Size::new(WIDTH, HEIGHT),
+            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => Size::new(HEIGHT, WIDTH),
+        }
     }
 }
 
@@ -61,6 +65,22 @@ impl OctDisplay for Display5in65f {
     }
 }
 
+impl FrameBuffer for Display5in65f {
+    type Color = OctColor;
+
+    fn as_bytes(&self) -> &[u8] {
+        self.buffer()
+    }
+
+    fn as_bytes_mut(&mut self) -> &mut [u8] {
+        self.get_mut_buffer()
+    }
+
+    fn clear(&mut self, color: OctColor) {
+        self.clear_buffer(color);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -12,8 +12,12 @@ use embedded_hal::{
 };
 
 use crate::color::OctColor;
+use crate::error::Error;
 use crate::interface::DisplayInterface;
+use crate::timing::ResetTiming;
 use crate::traits::{InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::window::WindowLimits;
+use crate::{AutoCs, CsStrategy, ResetPin};
 
 pub(crate) mod command;
 use self::command::Command;
@@ -27,9 +31,17 @@ pub use self::graphics::Display5in65f;
 pub const WIDTH: u32 = 600;
 /// Height of the display
 pub const HEIGHT: u32 = 448;
+/// Limits of the partial window registers: 10 bit x and y registers, the whole panel fits
+pub const WINDOW_LIMITS: WindowLimits = WindowLimits::new(WIDTH, HEIGHT, 1023, 1023);
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: OctColor = OctColor::White;
 const IS_BUSY_LOW: bool = true;
+/// Longest time in milliseconds to wait for the busy pin before giving up with
+/// [Error::Timeout]
+pub const BUSY_TIMEOUT_MS: u32 = 60_000;
+/// Fastest SPI clock of the controller in Hz, see
+/// [check_spi_frequency()](crate::check_spi_frequency())
+pub const MAX_SPI_FREQ_HZ: u32 = 10_000_000;
 
 /// Epd5in65f driver
 ///
@@ -44,32 +56,34 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST,
     for Epd5in65f<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        // Reset the device
-        self.interface.reset(delay, 10, 2);
-
-        self.cmd_with_data(spi, Command::PanelSetting, &[0xEF, 0x08])?;
-        self.cmd_with_data(spi, Command::PowerSetting, &[0x37, 0x00, 0x23, 0x23])?;
-        self.cmd_with_data(spi, Command::PowerOffSequenceSetting, &[0x00])?;
-        self.cmd_with_data(spi, Command::BoosterSoftStart, &[0xC7, 0xC7, 0x1D])?;
-        self.cmd_with_data(spi, Command::PllControl, &[0x3C])?;
-        self.cmd_with_data(spi, Command::TemperatureSensor, &[0x00])?;
-        self.update_vcom(spi)?;
-        self.cmd_with_data(spi, Command::TconSetting, &[0x22])?;
-        self.send_resolution(spi)?;
-
-        self.cmd_with_data(spi, Command::FlashMode, &[0xAA])?;
-
-        delay.delay_ms(100);
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        init(&mut self.interface, spi, delay, &PANEL, self.color)
+    }
+}
 
-        self.update_vcom(spi)?;
-        Ok(())
+impl<SPI, BUSY, DC, RST, DELAY> Epd5in65f<SPI, AutoCs, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Creates the driver for a board without a CS GPIO, see [AutoCs]
+    pub fn new_without_cs(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new(spi, AutoCs, busy, dc, rst, delay)
     }
 }
 
@@ -77,10 +91,10 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DEL
     for Epd5in65f<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
     type DisplayColor = OctColor;
@@ -91,10 +105,20 @@ where
         dc: DC,
         rst: RST,
         delay: &mut DELAY,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(cs, busy, dc, rst);
-        let color = DEFAULT_BACKGROUND_COLOR;
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_background(spi, cs, busy, dc, rst, delay, DEFAULT_BACKGROUND_COLOR)
+    }
 
+    fn new_with_background(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        color: Self::DisplayColor,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(cs, busy, dc, rst);
         let mut epd = Epd5in65f { interface, color };
 
         epd.init(spi, delay)?;
@@ -102,28 +126,26 @@ where
         Ok(epd)
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.check_reset_pin()?;
         self.init(spi, delay)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
-        Ok(())
+    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .cmd_with_data(spi, Command::DeepSleep, &[0xA5])
     }
 
     fn update_frame(
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
-        _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
-        self.wait_busy_high();
-        self.update_vcom(spi)?;
-        self.send_resolution(spi)?;
-        self.cmd_with_data(spi, Command::DataStartTransmission1, buffer)?;
-        Ok(())
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        update_frame(&mut self.interface, spi, buffer, delay, self.color)
     }
 
+    /// Partial windows aren't supported, returns [Error::PartialUpdateUnsupported]
     fn update_partial_frame(
         &mut self,
         _spi: &mut SPI,
@@ -132,19 +154,13 @@ where
         _y: u32,
         _width: u32,
         _height: u32,
-    ) -> Result<(), SPI::Error> {
-        unimplemented!();
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        Err(Error::PartialUpdateUnsupported)
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_busy_high();
-        self.command(spi, Command::PowerOn)?;
-        self.wait_busy_high();
-        self.command(spi, Command::DisplayRefresh)?;
-        self.wait_busy_high();
-        self.command(spi, Command::PowerOff)?;
-        self.wait_busy_low();
-        Ok(())
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        display_frame(&mut self.interface, spi, delay)
     }
 
     fn update_and_display_frame(
@@ -152,21 +168,14 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        let bg = OctColor::colors_byte(self.color, self.color);
-        self.wait_busy_high();
-        self.update_vcom(spi)?;
-        self.send_resolution(spi)?;
-        self.command(spi, Command::DataStartTransmission1)?;
-        self.interface.data_x_times(spi, bg, WIDTH * HEIGHT / 2)?;
-        self.display_frame(spi, delay)?;
-        Ok(())
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        clear_frame(&mut self.interface, spi, delay, self.color)
     }
 
     fn set_background_color(&mut self, color: OctColor) {
@@ -185,72 +194,260 @@ where
         HEIGHT
     }
 
+    fn full_refresh_ms(&self) -> u32 {
+        12_000
+    }
+
+    /// 4 bits per pixel
+    fn buffer_len(&self) -> usize {
+        (WIDTH * HEIGHT / 2) as usize
+    }
+
+    /// The waveform of the OTP is the only one, anything but [RefreshLut::Full] returns
+    /// [Error::UnsupportedLut]
     fn set_lut(
         &mut self,
         _spi: &mut SPI,
-        _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
-        unimplemented!();
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        match refresh_rate {
+            None | Some(RefreshLut::Full) => Ok(()),
+            Some(_) => Err(Error::UnsupportedLut),
+        }
     }
 
     fn is_busy(&self) -> bool {
         self.interface.is_busy(IS_BUSY_LOW)
     }
-}
 
-impl<SPI, CS, BUSY, DC, RST, DELAY> Epd5in65f<SPI, CS, BUSY, DC, RST, DELAY>
-where
-    SPI: Write<u8>,
-    CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
-    DELAY: DelayMs<u8>,
-{
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    fn send_command_raw(&mut self, spi: &mut SPI, command: u8) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd(spi, command)
     }
 
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    fn send_data_raw(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
         self.interface.data(spi, data)
     }
 
-    fn cmd_with_data(
-        &mut self,
-        spi: &mut SPI,
-        command: Command,
-        data: &[u8],
-    ) -> Result<(), SPI::Error> {
-        self.interface.cmd_with_data(spi, command, data)
+    fn set_spi_chunk_size(&mut self, chunk_size: usize) {
+        self.interface.set_chunk_size(chunk_size);
     }
 
-    fn wait_busy_high(&mut self) {
-        self.interface.wait_until_idle(true);
+    fn set_reset_timing(&mut self, timing: Option<ResetTiming>) {
+        self.interface.set_reset_timing(timing);
     }
-    fn wait_busy_low(&mut self) {
-        self.interface.wait_until_idle(false);
-    }
-    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
-        let w = self.width();
-        let h = self.height();
-
-        self.command(spi, Command::TconResolution)?;
-        self.send_data(spi, &[(w >> 8) as u8])?;
-        self.send_data(spi, &[w as u8])?;
-        self.send_data(spi, &[(h >> 8) as u8])?;
-        self.send_data(spi, &[h as u8])
+
+    #[cfg(feature = "test-patterns")]
+    fn set_test_pattern(&mut self, enable: bool) {
+        let pattern = crate::test_pattern::FillPattern::new(self.width(), 4);
+        self.interface.set_fill_pattern(enable.then_some(pattern));
     }
+}
 
-    fn update_vcom(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
-        let bg_color = (self.color.get_nibble() & 0b111) << 5;
-        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x17 | bg_color])?;
-        Ok(())
+/// What differs between the 7 color panels with the command set of the 5.65 inch (F) one
+pub(crate) struct Panel {
+    /// Low phase of the reset pulse in ms
+    pub(crate) reset_ms: u8,
+    /// Value of the PLL control register, which sets the frame rate
+    pub(crate) pll: u8,
+}
+
+const PANEL: Panel = Panel {
+    reset_ms: 10,
+    pll: 0x3C,
+};
+
+/// Resets the controller and sends the power, booster and panel settings of `panel`
+pub(crate) fn init<SPI, CS, BUSY, DC, RST, DELAY>(
+    interface: &mut DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+    spi: &mut SPI,
+    delay: &mut DELAY,
+    panel: &Panel,
+    color: OctColor,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    interface.reset(delay, panel.reset_ms, 2)?;
+    wait_busy_high_with_timeout(interface, delay)?;
+
+    interface.cmd_with_data(spi, Command::PanelSetting, &[0xEF, 0x08])?;
+    interface.cmd_with_data(spi, Command::PowerSetting, &[0x37, 0x00, 0x23, 0x23])?;
+    interface.cmd_with_data(spi, Command::PowerOffSequenceSetting, &[0x00])?;
+    interface.cmd_with_data(spi, Command::BoosterSoftStart, &[0xC7, 0xC7, 0x1D])?;
+    interface.cmd_with_data(spi, Command::PllControl, &[panel.pll])?;
+    interface.cmd_with_data(spi, Command::TemperatureSensor, &[0x00])?;
+    update_vcom(interface, spi, color)?;
+    interface.cmd_with_data(spi, Command::TconSetting, &[0x22])?;
+    send_resolution(interface, spi)?;
+
+    interface.cmd_with_data(spi, Command::FlashMode, &[0xAA])?;
+
+    delay.delay_ms(100);
+
+    update_vcom(interface, spi, color)
+}
+
+/// Sends a frame with 4 bits per pixel, returns [Error::BufferSize] for other lengths
+pub(crate) fn update_frame<SPI, CS, BUSY, DC, RST, DELAY>(
+    interface: &mut DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+    spi: &mut SPI,
+    buffer: &[u8],
+    delay: &mut DELAY,
+    color: OctColor,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    let expected = (WIDTH * HEIGHT / 2) as usize;
+    if buffer.len() != expected {
+        return Err(Error::BufferSize {
+            expected,
+            got: buffer.len(),
+        });
     }
+    wait_busy_high_with_timeout(interface, delay)?;
+    update_vcom(interface, spi, color)?;
+    send_resolution(interface, spi)?;
+    interface.cmd_with_data(spi, Command::DataStartTransmission1, buffer)
+}
+
+/// Switches the power on, refreshes the panel and switches the power off again
+///
+/// The busy pin is low while the controller works on power on and the refresh, but high
+/// while it switches the power off.
+pub(crate) fn display_frame<SPI, CS, BUSY, DC, RST, DELAY>(
+    interface: &mut DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+    spi: &mut SPI,
+    delay: &mut DELAY,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    wait_busy_high_with_timeout(interface, delay)?;
+    interface.cmd(spi, Command::PowerOn)?;
+    wait_busy_high_with_timeout(interface, delay)?;
+    interface.cmd(spi, Command::DisplayRefresh)?;
+    wait_busy_high_with_timeout(interface, delay)?;
+    interface.cmd(spi, Command::PowerOff)?;
+    wait_busy_low_with_timeout(interface, delay)
+}
+
+/// Fills the whole frame with `color`, two pixels per byte, and refreshes the panel
+pub(crate) fn clear_frame<SPI, CS, BUSY, DC, RST, DELAY>(
+    interface: &mut DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+    spi: &mut SPI,
+    delay: &mut DELAY,
+    color: OctColor,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    let bg = OctColor::colors_byte(color, color);
+    wait_busy_high_with_timeout(interface, delay)?;
+    update_vcom(interface, spi, color)?;
+    send_resolution(interface, spi)?;
+    interface.cmd(spi, Command::DataStartTransmission1)?;
+    interface.data_x_times(spi, bg, WIDTH * HEIGHT / 2)?;
+    display_frame(interface, spi, delay)
+}
+
+fn wait_busy_high_with_timeout<SPI, CS, BUSY, DC, RST, DELAY>(
+    interface: &mut DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+    delay: &mut DELAY,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    interface.wait_until_idle_with_timeout(delay, IS_BUSY_LOW, BUSY_TIMEOUT_MS)
+}
+
+fn wait_busy_low_with_timeout<SPI, CS, BUSY, DC, RST, DELAY>(
+    interface: &mut DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+    delay: &mut DELAY,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    interface.wait_until_idle_with_timeout(delay, !IS_BUSY_LOW, BUSY_TIMEOUT_MS)
+}
+
+fn send_resolution<SPI, CS, BUSY, DC, RST, DELAY>(
+    interface: &mut DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+    spi: &mut SPI,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    interface.cmd_with_data(
+        spi,
+        Command::TconResolution,
+        &[
+            (WIDTH >> 8) as u8,
+            WIDTH as u8,
+            (HEIGHT >> 8) as u8,
+            HEIGHT as u8,
+        ],
+    )
+}
+
+fn update_vcom<SPI, CS, BUSY, DC, RST, DELAY>(
+    interface: &mut DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+    spi: &mut SPI,
+    color: OctColor,
+) -> Result<(), Error<SPI::Error>>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    let bg_color = (color.get_nibble() & 0b111) << 5;
+    interface.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x17 | bg_color])
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::{test_epd, DcPin, HighPin, NoDelay, RecordingSpi};
+
+    type TestEpd = Epd5in65f<RecordingSpi, HighPin, HighPin, DcPin, HighPin, NoDelay>;
 
     #[test]
     fn epd_size() {
@@ -258,4 +455,26 @@ mod tests {
         assert_eq!(HEIGHT, 448);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, OctColor::White);
     }
+
+    #[test]
+    fn partial_frames_and_luts_are_rejected() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+
+        assert_eq!(
+            epd.update_partial_frame(&mut spi, &[0; 8], 0, 0, 8, 8, &mut NoDelay),
+            Err(Error::PartialUpdateUnsupported)
+        );
+        assert_eq!(epd.set_lut(&mut spi, None), Ok(()));
+        assert_eq!(epd.set_lut(&mut spi, Some(RefreshLut::Full)), Ok(()));
+        assert_eq!(
+            epd.set_lut(&mut spi, Some(RefreshLut::Quick)),
+            Err(Error::UnsupportedLut)
+        );
+        assert_eq!(
+            epd.set_lut(&mut spi, Some(RefreshLut::Custom(&[0; 8]))),
+            Err(Error::UnsupportedLut)
+        );
+        assert!(spi.sent.is_empty());
+    }
 }
@@ -0,0 +1,534 @@
+//! A simple Driver for the 5.79" 792x272 E-Ink Display via SPI
+//!
+//! The panel is driven by two cascaded SSD1683: the primary controller drives the left and
+//! the secondary one the right half of the panel, each with a RAM of 400x272 pixels. The
+//! secondary controller takes the same commands as the primary one with the highest bit of
+//! the command set, e.g. `0xA4` instead of `0x24` for writing its RAM. Its sources are
+//! mirrored, so its RAM is filled with decrementing x addresses.
+//!
+//! The driver hides the split: a frame is one buffer with 99 bytes per row, the first 50 bytes
+//! of every row go to the primary and the last 50 bytes to the secondary controller. Both
+//! halves share the byte in the middle of the row, the panel only shows 396 of the 400 source
+//! lines of each controller. The refresh is started by the primary controller for both
+//! halves.
+//!
+//! # References
+//!
+//! - SSD1683 datasheet of Solomon Systech
+//! - Initialisation sequence of the Waveshare example code for the 5.79" module
+
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::{InputPin, OutputPin},
+};
+
+use crate::check_buffer_len;
+use crate::color::Color;
+use crate::error::Error;
+use crate::interface::DisplayInterface;
+use crate::power::{PowerState, Transition};
+use crate::timing::ResetTiming;
+use crate::traits::{InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::type_a::command::Command;
+use crate::{AutoCs, CsStrategy, ResetPin};
+
+#[cfg(feature = "graphics")]
+mod graphics;
+#[cfg(feature = "graphics")]
+pub use self::graphics::Display5in79;
+
+/// Width of the display
+pub const WIDTH: u32 = 792;
+/// Height of the display
+pub const HEIGHT: u32 = 272;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+const IS_BUSY_LOW: bool = false;
+/// Longest time in milliseconds to wait for the busy pin before giving up with
+/// [Error::Timeout]
+pub const BUSY_TIMEOUT_MS: u32 = 30_000;
+/// Fastest SPI clock of the controller in Hz, see
+/// [check_spi_frequency()](crate::check_spi_frequency())
+pub const MAX_SPI_FREQ_HZ: u32 = 20_000_000;
+
+/// Bytes of a row of the frame
+const ROW_BYTES: usize = WIDTH as usize / 8;
+/// Bytes of a row in the RAM of each controller
+const HALF_ROW_BYTES: usize = 50;
+/// First byte of a row which is sent to the secondary controller
+const SECONDARY_FIRST_BYTE: usize = ROW_BYTES - HALF_ROW_BYTES;
+/// Last x address (in bytes) of the RAM of each controller
+const LAST_X: u8 = HALF_ROW_BYTES as u8 - 1;
+
+/// A command for the secondary controller, which drives the right half of the panel
+#[derive(Copy, Clone)]
+struct Secondary(Command);
+
+impl crate::traits::Command for Secondary {
+    /// The address of the command for the primary controller with the highest bit set
+    fn address(self) -> u8 {
+        self.0.address() | 0x80
+    }
+}
+
+/// Epd5in79 driver
+///
+pub struct Epd5in79<SPI, CS, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+    /// Background Color
+    color: Color,
+    /// Mode of the controller
+    state: PowerState,
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd5in79<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state = self.state.check(Transition::Init)?;
+        self.interface.reset(delay, 10, 2)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        // resets both controllers
+        self.interface.cmd(spi, Command::SwReset)?;
+        self.wait_until_idle_with_timeout(delay)?;
+
+        // 0x80: internal temperature sensor
+        self.interface
+            .cmd_with_data(spi, Command::TemperatureSensorSelection, &[0x80])?;
+
+        // white border
+        self.interface
+            .cmd_with_data(spi, Command::BorderWaveformControl, &[0x01])?;
+
+        // primary: x increment, y decrement, the address counter is updated in x direction
+        self.interface
+            .cmd_with_data(spi, Command::DataEntryModeSetting, &[0x01])?;
+        // secondary: the sources are mirrored, x and y decrement
+        self.interface
+            .cmd_with_data(spi, Secondary(Command::DataEntryModeSetting), &[0x00])?;
+
+        self.use_full_frame(spi)?;
+
+        self.wait_until_idle_with_timeout(delay)?;
+        Ok(())
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd5in79<SPI, AutoCs, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Creates the driver for a board without a CS GPIO, see [AutoCs]
+    pub fn new_without_cs(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new(spi, AutoCs, busy, dc, rst, delay)
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd5in79<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    type DisplayColor = Color;
+    fn new(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_background(spi, cs, busy, dc, rst, delay, DEFAULT_BACKGROUND_COLOR)
+    }
+
+    fn new_with_background(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        color: Self::DisplayColor,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(cs, busy, dc, rst);
+
+        let mut epd = Epd5in79 {
+            interface,
+            color,
+            state: PowerState::Active,
+        };
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.check_reset_pin()?;
+        self.init(spi, delay)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        let next = self.state.check(Transition::Sleep)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        // deep sleep mode 1, puts both controllers to sleep
+        self.interface
+            .cmd_with_data(spi, Command::DeepSleepMode, &[0x01])?;
+        self.state = next;
+        Ok(())
+    }
+
+    /// Splits the rows of the frame and writes the halves to the two controllers
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer, WIDTH, HEIGHT)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.use_full_frame(spi)?;
+
+        self.interface.cmd(spi, Command::WriteRam)?;
+        for row in buffer.chunks(ROW_BYTES) {
+            self.interface.data(spi, &row[..HALF_ROW_BYTES])?;
+        }
+        self.interface.cmd(spi, Secondary(Command::WriteRam))?;
+        for row in buffer.chunks(ROW_BYTES) {
+            self.interface.data(spi, &row[SECONDARY_FIRST_BYTE..])?;
+        }
+        Ok(())
+    }
+
+    /// Partial windows aren't supported, returns [Error::PartialUpdateUnsupported]
+    fn update_partial_frame(
+        &mut self,
+        _spi: &mut SPI,
+        _buffer: &[u8],
+        _x: u32,
+        _y: u32,
+        _width: u32,
+        _height: u32,
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        Err(Error::PartialUpdateUnsupported)
+    }
+
+    /// Refreshes both halves of the panel with the LUT of the OTP
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Refresh)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        // load the temperature and the LUT from the OTP, display and power off the analog part
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xF7])?;
+        self.interface.start_measurement(RefreshLut::Full.into());
+        self.interface.cmd(spi, Command::MasterActivation)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        Ok(())
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)?;
+        Ok(())
+    }
+
+    /// Fills both RAM banks of both controllers with the background color
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.use_full_frame(spi)?;
+
+        let color = self.color.get_byte_value();
+        let half_frame = HALF_ROW_BYTES as u32 * HEIGHT;
+        self.interface.cmd(spi, Command::WriteRam)?;
+        self.interface.data_x_times(spi, color, half_frame)?;
+        self.interface.cmd(spi, Secondary(Command::WriteRam))?;
+        self.interface.data_x_times(spi, color, half_frame)?;
+        self.set_ram_counters(spi)?;
+        self.interface.cmd(spi, Command::WriteRam2)?;
+        self.interface.data_x_times(spi, color, half_frame)?;
+        self.interface.cmd(spi, Secondary(Command::WriteRam2))?;
+        self.interface.data_x_times(spi, color, half_frame)?;
+        Ok(())
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn full_refresh_ms(&self) -> u32 {
+        3000
+    }
+
+    /// Only the full LUT of the OTP is supported, other LUTs return [Error::UnsupportedLut]
+    fn set_lut(
+        &mut self,
+        _spi: &mut SPI,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Update)?;
+        match refresh_rate {
+            None | Some(RefreshLut::Full) => Ok(()),
+            Some(_) => Err(Error::UnsupportedLut),
+        }
+    }
+
+    fn is_busy(&self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
+    fn send_command_raw(&mut self, spi: &mut SPI, command: u8) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn send_data_raw(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        self.interface.data(spi, data)
+    }
+
+    fn set_spi_chunk_size(&mut self, chunk_size: usize) {
+        self.interface.set_chunk_size(chunk_size);
+    }
+
+    fn set_reset_timing(&mut self, timing: Option<ResetTiming>) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    #[cfg(feature = "test-patterns")]
+    fn set_test_pattern(&mut self, enable: bool) {
+        let pattern = crate::test_pattern::FillPattern::new(self.width(), 1);
+        self.interface.set_fill_pattern(enable.then_some(pattern));
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> Epd5in79<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    fn wait_until_idle_with_timeout(&mut self, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .wait_until_idle_with_timeout(delay, IS_BUSY_LOW, BUSY_TIMEOUT_MS)
+    }
+
+    /// Current mode of the controller
+    pub fn power_state(&self) -> PowerState {
+        self.state
+    }
+
+    /// Selects the whole RAM of both controllers and moves the address counters to its start
+    fn use_full_frame(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        let last_y = [(HEIGHT - 1) as u8, ((HEIGHT - 1) >> 8) as u8];
+
+        // primary: x increments from 0, y decrements from the last row
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamXAddressStartEndPosition,
+            &[0x00, LAST_X],
+        )?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamYAddressStartEndPosition,
+            &[last_y[0], last_y[1], 0x00, 0x00],
+        )?;
+
+        // secondary: x decrements from the last byte, y decrements from the last row
+        self.interface.cmd_with_data(
+            spi,
+            Secondary(Command::SetRamXAddressStartEndPosition),
+            &[LAST_X, 0x00],
+        )?;
+        self.interface.cmd_with_data(
+            spi,
+            Secondary(Command::SetRamYAddressStartEndPosition),
+            &[last_y[0], last_y[1], 0x00, 0x00],
+        )?;
+
+        self.set_ram_counters(spi)
+    }
+
+    /// Moves the address counters of both controllers to the start of their window
+    fn set_ram_counters(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        let last_y = [(HEIGHT - 1) as u8, ((HEIGHT - 1) >> 8) as u8];
+        self.interface
+            .cmd_with_data(spi, Command::SetRamXAddressCounter, &[0x00])?;
+        self.interface
+            .cmd_with_data(spi, Command::SetRamYAddressCounter, &last_y)?;
+        self.interface
+            .cmd_with_data(spi, Secondary(Command::SetRamXAddressCounter), &[LAST_X])?;
+        self.interface
+            .cmd_with_data(spi, Secondary(Command::SetRamYAddressCounter), &last_y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{cmd, test_epd, DcPin, LowPin, NoDelay, RecordingSpi, Sent};
+    extern crate std;
+    use std::vec::Vec;
+
+    type TestEpd = Epd5in79<RecordingSpi, LowPin, LowPin, DcPin, LowPin, NoDelay>;
+
+    fn counters() -> Vec<Sent> {
+        [
+            cmd(0x4E, &[0x00]),
+            cmd(0x4F, &[0x0F, 0x01]),
+            cmd(0xCE, &[0x31]),
+            cmd(0xCF, &[0x0F, 0x01]),
+        ]
+        .concat()
+    }
+
+    /// RAM windows and address counters over the whole RAM of both controllers
+    fn full_frame() -> Vec<Sent> {
+        [
+            cmd(0x44, &[0x00, 0x31]),
+            cmd(0x45, &[0x0F, 0x01, 0x00, 0x00]),
+            cmd(0xC4, &[0x31, 0x00]),
+            cmd(0xC5, &[0x0F, 0x01, 0x00, 0x00]),
+            counters(),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 792);
+        assert_eq!(HEIGHT, 272);
+        assert_eq!(ROW_BYTES, 99);
+        assert_eq!(SECONDARY_FIRST_BYTE, 49);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+    }
+
+    #[test]
+    fn init_sequence() {
+        let (spi, _) = test_epd::<TestEpd, _>();
+        let expected = [
+            cmd(0x12, &[]),
+            cmd(0x18, &[0x80]),
+            cmd(0x3C, &[0x01]),
+            cmd(0x11, &[0x01]),
+            cmd(0x91, &[0x00]),
+            full_frame(),
+        ]
+        .concat();
+        assert_eq!(spi.sent, expected);
+    }
+
+    #[test]
+    fn rows_are_split_between_the_controllers() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+        let frame: Vec<u8> = (0..ROW_BYTES * HEIGHT as usize)
+            .map(|i| (i % ROW_BYTES) as u8)
+            .collect();
+
+        epd.update_frame(&mut spi, &frame, &mut NoDelay).unwrap();
+
+        let left: Vec<u8> = (0..HEIGHT).flat_map(|_| 0..50).collect();
+        let right: Vec<u8> = (0..HEIGHT).flat_map(|_| 49..99).collect();
+        let expected = [full_frame(), cmd(0x24, &left), cmd(0xA4, &right)].concat();
+        assert_eq!(spi.sent, expected);
+
+        assert_eq!(
+            epd.update_frame(&mut spi, &frame[1..], &mut NoDelay),
+            Err(Error::BufferSize {
+                expected: frame.len(),
+                got: frame.len() - 1
+            })
+        );
+    }
+
+    #[test]
+    fn clear_frame_fills_both_rams_of_both_controllers() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+        epd.set_background_color(Color::Black);
+
+        epd.clear_frame(&mut spi, &mut NoDelay).unwrap();
+
+        let half = [0x00; HALF_ROW_BYTES * HEIGHT as usize];
+        let expected = [
+            full_frame(),
+            cmd(0x24, &half),
+            cmd(0xA4, &half),
+            counters(),
+            cmd(0x26, &half),
+            cmd(0xA6, &half),
+        ]
+        .concat();
+        assert_eq!(spi.sent, expected);
+    }
+
+    #[test]
+    fn the_primary_controller_refreshes_both_halves() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+
+        epd.display_frame(&mut spi, &mut NoDelay).unwrap();
+        assert_eq!(spi.sent, [cmd(0x22, &[0xF7]), cmd(0x20, &[])].concat());
+
+        assert_eq!(
+            epd.set_lut(&mut spi, Some(RefreshLut::Quick)),
+            Err(Error::UnsupportedLut)
+        );
+    }
+
+    #[test]
+    fn partial_frames_are_rejected() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+
+        assert_eq!(
+            epd.update_partial_frame(&mut spi, &[0; 8], 0, 0, 8, 8, &mut NoDelay),
+            Err(Error::PartialUpdateUnsupported)
+        );
+        assert!(spi.sent.is_empty());
+    }
+}
@@ -2,6 +2,7 @@ use crate::color::TriColor;
 use crate::epd5in83b_v2::{DEFAULT_BACKGROUND_COLOR, HEIGHT, NUM_DISPLAY_BITS, WIDTH};
 use crate::graphics::{DisplayColorRendering, DisplayRotation};
 use crate::prelude::TriDisplay;
+use crate::traits::FrameBuffer;
 use embedded_graphics_core::prelude::*;
 
 /// Full size buffer for use with the 5in83 EPD
@@ -43,7 +44,10 @@ impl DrawTarget for Display5in83 {
 
 impl OriginDimensions for Display5in83 {
     fn size(&self) -> Size {
-        Size::new(WIDTH, HEIGHT)
+        match self.rotation {
+            DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => Size::new(WIDTH, HEIGHT),
+            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => Size::new(HEIGHT, WIDTH),
+        }
     }
 }
 
@@ -75,6 +79,26 @@ impl TriDisplay for Display5in83 {
     fn chromatic_buffer(&self) -> &[u8] {
         &self.buffer[self.chromatic_offset()..]
     }
+
+    fn color_rendering(&self) -> DisplayColorRendering {
+        DisplayColorRendering::Negative
+    }
+}
+
+impl FrameBuffer for Display5in83 {
+    type Color = TriColor;
+
+    fn as_bytes(&self) -> &[u8] {
+        self.buffer()
+    }
+
+    fn as_bytes_mut(&mut self) -> &mut [u8] {
+        self.get_mut_buffer()
+    }
+
+    fn clear(&mut self, color: TriColor) {
+        self.clear_buffer(color);
+    }
 }
 
 #[cfg(test)]
@@ -7,14 +7,24 @@
 //! - [Waveshare Python driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/python/lib/waveshare_epd/epd5in83b_V2.py)
 
 use embedded_hal::{
-    blocking::{delay::*, spi::Write},
+    blocking::{
+        delay::*,
+        spi::{Transfer, Write},
+    },
     digital::v2::{InputPin, OutputPin},
 };
 
-use crate::color::Color;
+use crate::check_buffer_len;
+use crate::error::Error;
+use crate::external_sensor;
 use crate::interface::DisplayInterface;
+use crate::panel_setting::PanelSettingConfig;
+use crate::power::{PowerState, Transition};
 use crate::prelude::{TriColor, WaveshareDisplay, WaveshareThreeColorDisplay};
+use crate::timing::ResetTiming;
 use crate::traits::{InternalWiAdditions, RefreshLut};
+use crate::window::WindowLimits;
+use crate::{AutoCs, CsStrategy, ResetPin};
 
 pub(crate) mod command;
 use self::command::Command;
@@ -28,33 +38,56 @@ pub use self::graphics::Display5in83;
 pub const WIDTH: u32 = 648;
 /// Height of the display
 pub const HEIGHT: u32 = 480;
+/// Limits of the partial window registers: 10 bit x and y registers, the whole panel fits
+pub const WINDOW_LIMITS: WindowLimits = WindowLimits::new(WIDTH, HEIGHT, 1023, 1023);
 /// Default Background Color
-pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+pub const DEFAULT_BACKGROUND_COLOR: TriColor = TriColor::White;
+/// Default panel setting: black/white/red mode with the LUTs from the OTP
+pub const PANEL_SETTING: PanelSettingConfig = PanelSettingConfig::from_bits(0x0F);
 const IS_BUSY_LOW: bool = true;
+/// Longest time in milliseconds to wait for the busy pin before giving up with
+/// [Error::Timeout]
+pub const BUSY_TIMEOUT_MS: u32 = 60_000;
+/// Fastest SPI clock of the controller in Hz, see
+/// [check_spi_frequency()](crate::check_spi_frequency())
+pub const MAX_SPI_FREQ_HZ: u32 = 10_000_000;
 const NUM_DISPLAY_BITS: u32 = WIDTH * HEIGHT / 8;
 
+// Border bits of the Vcom and data interval setting
+const BLACK_BORDER: u8 = 0x00;
+const WHITE_BORDER: u8 = 0x10;
+const CHROMATIC_BORDER: u8 = 0x20;
+const FLOATING_BORDER: u8 = 0x80;
+
 /// Epd7in5 driver
 ///
 pub struct Epd5in83<SPI, CS, BUSY, DC, RST, DELAY> {
     /// Connection Interface
     interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
     /// Background Color
-    color: Color,
+    color: TriColor,
+    /// Panel setting
+    panel_setting: PanelSettingConfig,
+    /// Border color
+    border: TriColor,
+    /// Mode of the controller
+    state: PowerState,
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
     for Epd5in83<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         // Reset the device
-        self.interface.reset(delay, 10, 10);
+        self.state = self.state.check(Transition::Init)?;
+        self.interface.reset(delay, 10, 10)?;
 
         // Start the booster
         self.cmd_with_data(spi, Command::BoosterSoftStart, &[0x17, 0x17, 0x1e, 0x17])?;
@@ -65,10 +98,10 @@ where
         // Power on
         self.command(spi, Command::PowerOn)?;
         delay.delay_ms(5);
-        self.wait_until_idle();
+        self.wait_until_idle_with_timeout(delay)?;
 
-        // Set the panel settings: BWROTP
-        self.cmd_with_data(spi, Command::PanelSetting, &[0x0F])?;
+        // Set the panel settings: BWROTP unless changed with set_panel_setting
+        self.cmd_with_data(spi, Command::PanelSetting, &[self.panel_setting.bits()])?;
 
         // Set the real resolution
         self.send_resolution(spi)?;
@@ -77,12 +110,16 @@ where
         self.cmd_with_data(spi, Command::DualSPI, &[0x00])?;
 
         // Set Vcom and data interval
-        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x11, 0x07])?;
+        self.cmd_with_data(
+            spi,
+            Command::VcomAndDataIntervalSetting,
+            &self.data_interval(),
+        )?;
 
         // Set S2G and G2S non-overlap periods to 12 (default)
         self.cmd_with_data(spi, Command::TconSetting, &[0x22])?;
 
-        self.wait_until_idle();
+        self.wait_until_idle_with_timeout(delay)?;
         Ok(())
     }
 }
@@ -91,10 +128,10 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareThreeColorDisplay<SPI, CS, BUSY, DC
     for Epd5in83<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
     fn update_color_frame(
@@ -102,40 +139,73 @@ where
         spi: &mut SPI,
         black: &[u8],
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_achromatic_frame(spi, black)?;
         self.update_chromatic_frame(spi, chromatic)?;
         Ok(())
     }
 
-    fn update_achromatic_frame(&mut self, spi: &mut SPI, black: &[u8]) -> Result<(), SPI::Error> {
+    fn update_achromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        black: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(black, WIDTH, HEIGHT)?;
+        self.state.check(Transition::Update)?;
         self.wait_until_idle();
-        self.cmd_with_data(spi, Command::DataStartTransmission1, black)?;
+        let command = self.bw_data_command();
+        self.cmd_with_data(spi, command, black)?;
         Ok(())
     }
 
+    /// Nothing is sent in the [black/white mode](Epd5in83::set_black_white_mode())
     fn update_chromatic_frame(
         &mut self,
         spi: &mut SPI,
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(chromatic, WIDTH, HEIGHT)?;
+        self.state.check(Transition::Update)?;
+        if self.panel_setting.black_white_mode() {
+            return Ok(());
+        }
         self.wait_until_idle();
         self.cmd_with_data(spi, Command::DataStartTransmission2, chromatic)?;
         Ok(())
     }
 }
 
+impl<SPI, BUSY, DC, RST, DELAY> Epd5in83<SPI, AutoCs, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Creates the driver for a board without a CS GPIO, see [AutoCs]
+    pub fn new_without_cs(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new(spi, AutoCs, busy, dc, rst, delay)
+    }
+}
+
 impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
     for Epd5in83<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
-    type DisplayColor = Color;
+    type DisplayColor = TriColor;
     fn new(
         spi: &mut SPI,
         cs: CS,
@@ -143,34 +213,60 @@ where
         dc: DC,
         rst: RST,
         delay: &mut DELAY,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(cs, busy, dc, rst);
-        let color = DEFAULT_BACKGROUND_COLOR;
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_background(spi, cs, busy, dc, rst, delay, DEFAULT_BACKGROUND_COLOR)
+    }
 
-        let mut epd = Epd5in83 { interface, color };
+    fn new_with_background(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        color: Self::DisplayColor,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(cs, busy, dc, rst);
+        let mut epd = Epd5in83 {
+            interface,
+            color,
+            panel_setting: PANEL_SETTING,
+            border: TriColor::White,
+            state: PowerState::Active,
+        };
 
         epd.init(spi, delay)?;
 
         Ok(epd)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    /// Floats the border before the deep sleep, `wake_up` drives it again
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        let next = self.state.check(Transition::Sleep)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        let [border, interval] = self.data_interval();
+        self.cmd_with_data(
+            spi,
+            Command::VcomAndDataIntervalSetting,
+            &[border | FLOATING_BORDER, interval],
+        )?;
         self.command(spi, Command::PowerOff)?;
-        self.wait_until_idle();
+        self.wait_until_idle_with_timeout(delay)?;
         self.cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
+        self.state = next;
         Ok(())
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.check_reset_pin()?;
         self.init(spi, delay)
     }
 
-    fn set_background_color(&mut self, color: Color) {
+    fn set_background_color(&mut self, color: TriColor) {
         self.color = color;
     }
 
-    fn background_color(&self) -> &Color {
+    fn background_color(&self) -> &TriColor {
         &self.color
     }
 
@@ -182,17 +278,27 @@ where
         HEIGHT
     }
 
+    fn full_refresh_ms(&self) -> u32 {
+        16_000
+    }
+
     fn update_frame(
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
-        _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer, WIDTH, HEIGHT)?;
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
         self.update_achromatic_frame(spi, buffer)?;
-        let color = self.color.get_byte_value();
+        if self.panel_setting.black_white_mode() {
+            return Ok(());
+        }
+        let (_, chromatic) = background_bytes(self.color);
         self.command(spi, Command::DataStartTransmission2)?;
-        self.interface.data_x_times(spi, color, NUM_DISPLAY_BITS)?;
+        self.interface
+            .data_x_times(spi, chromatic, NUM_DISPLAY_BITS)?;
         Ok(())
     }
 
@@ -204,49 +310,24 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
-        if buffer.len() as u32 != width / 8 * height {
-            //TODO panic or error
-        }
-
-        let hrst_upper = (x / 8) as u8 >> 6;
-        let hrst_lower = ((x / 8) << 3) as u8;
-        let hred_upper = ((x + width) / 8) as u8 >> 6;
-        let hred_lower = (((x + width) / 8) << 3) as u8 & 0b111;
-        let vrst_upper = (y >> 8) as u8;
-        let vrst_lower = y as u8;
-        let vred_upper = ((y + height) >> 8) as u8;
-        let vred_lower = (y + height) as u8;
-        let pt_scan = 0x01; // Gates scan both inside and outside of the partial window. (default)
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        WINDOW_LIMITS.validate_aligned(x, y, width, height)?;
+        check_buffer_len(buffer, width, height)?;
+        self.state.check(Transition::PartialIn)?;
+        self.wait_until_idle_with_timeout(delay)?;
 
         self.command(spi, Command::PartialIn)?;
-        self.command(spi, Command::PartialWindow)?;
-        self.send_data(
-            spi,
-            &[
-                hrst_upper, hrst_lower, hred_upper, hred_lower, vrst_upper, vrst_lower, vred_upper,
-                vred_lower, pt_scan,
-            ],
-        )?;
-        self.command(spi, Command::DataStartTransmission1)?;
-        self.send_data(spi, buffer)?;
+        self.state = PowerState::Partial;
 
-        let color = TriColor::Black.get_byte_value(); //We need it black, so red channel will be rendered transparent
-        self.command(spi, Command::DataStartTransmission2)?;
-        self.interface
-            .data_x_times(spi, color, width * height / 8)?;
-
-        self.command(spi, Command::DisplayRefresh)?;
-        self.wait_until_idle();
-
-        self.command(spi, Command::PartialOut)?;
-        Ok(())
+        let result = self.update_partial_window(spi, buffer, x, y, width, height, delay);
+        self.partial_out(spi, result)
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Refresh)?;
         self.command(spi, Command::DisplayRefresh)?;
-        self.wait_until_idle();
+        self.wait_until_idle_with_timeout(delay)?;
         Ok(())
     }
 
@@ -255,52 +336,251 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
 
-        // The Waveshare controllers all implement clear using 0x33
-        self.command(spi, Command::DataStartTransmission1)?;
-        self.interface.data_x_times(spi, 0xFF, NUM_DISPLAY_BITS)?;
+        let (bw, chromatic) = background_bytes(self.color);
+        let command = self.bw_data_command();
+        self.command(spi, command)?;
+        self.interface.data_x_times(spi, bw, NUM_DISPLAY_BITS)?;
+        if self.panel_setting.black_white_mode() {
+            return Ok(());
+        }
 
         self.command(spi, Command::DataStartTransmission2)?;
-        self.interface.data_x_times(spi, 0x00, NUM_DISPLAY_BITS)?;
+        self.interface
+            .data_x_times(spi, chromatic, NUM_DISPLAY_BITS)?;
 
         Ok(())
     }
 
+    /// The waveform of the OTP is the only one, anything but [RefreshLut::Full] returns
+    /// [Error::UnsupportedLut]
     fn set_lut(
         &mut self,
         _spi: &mut SPI,
-        _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
-        unimplemented!();
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        match refresh_rate {
+            None | Some(RefreshLut::Full) => Ok(()),
+            Some(_) => Err(Error::UnsupportedLut),
+        }
     }
 
     fn is_busy(&self) -> bool {
         self.interface.is_busy(IS_BUSY_LOW)
     }
+
+    fn send_command_raw(&mut self, spi: &mut SPI, command: u8) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn send_data_raw(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        self.interface.data(spi, data)
+    }
+
+    fn set_spi_chunk_size(&mut self, chunk_size: usize) {
+        self.interface.set_chunk_size(chunk_size);
+    }
+
+    fn set_reset_timing(&mut self, timing: Option<ResetTiming>) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    #[cfg(feature = "test-patterns")]
+    fn set_test_pattern(&mut self, enable: bool) {
+        let pattern = crate::test_pattern::FillPattern::new(self.width(), 1);
+        self.interface.set_fill_pattern(enable.then_some(pattern));
+    }
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> Epd5in83<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    /// Replaces the [PANEL_SETTING] and writes it to the controller
+    ///
+    /// Returns [Error::InvalidPanelSetting] for the settings rejected by
+    /// [PanelSettingConfig::validate()] and for LUTs from the registers, the driver only uses
+    /// the LUTs of the OTP. The setting is kept for [wake_up()](WaveshareDisplay::wake_up()).
+    pub fn set_panel_setting(
+        &mut self,
+        spi: &mut SPI,
+        config: PanelSettingConfig,
+    ) -> Result<(), Error<SPI::Error>> {
+        config.validate_otp()?;
+        self.state.check(Transition::Update)?;
+        self.interface.wait_until_idle(IS_BUSY_LOW);
+        self.panel_setting = config;
+        self.cmd_with_data(spi, Command::PanelSetting, &[config.bits()])?;
+        // the polarity of the data depends on the color mode
+        let interval = self.data_interval();
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &interval)
+    }
+
+    /// Current panel setting
+    pub fn panel_setting(&self) -> PanelSettingConfig {
+        self.panel_setting
+    }
+
+    /// Drives the panel in the black/white mode of the controller
+    ///
+    /// The black/white refresh of the OTP is a lot faster than the tri-color one, but can't
+    /// show red. In this mode the black/white data is sent as the new data of the controller
+    /// and the chromatic data is ignored.
+    pub fn set_black_white_mode(
+        &mut self,
+        spi: &mut SPI,
+        enabled: bool,
+    ) -> Result<(), Error<SPI::Error>> {
+        let config = self.panel_setting.with_black_white_mode(enabled);
+        self.set_panel_setting(spi, config)
+    }
+
+    /// Sets the color of the border and keeps it for the following refreshes
+    ///
+    /// The border is white by default. It floats during the deep sleep, as recommended for
+    /// panels which are powered off for a long time.
+    pub fn set_border_color(
+        &mut self,
+        spi: &mut SPI,
+        color: TriColor,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Update)?;
+        self.border = color;
+        let interval = self.data_interval();
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &interval)
+    }
+
+    /// Vcom and data interval setting for the current color mode and border
+    fn data_interval(&self) -> [u8; 2] {
+        let border = match self.border {
+            TriColor::Black => BLACK_BORDER,
+            TriColor::White => WHITE_BORDER,
+            TriColor::Chromatic | TriColor::Yellow => CHROMATIC_BORDER,
+        };
+        if self.panel_setting.black_white_mode() {
+            [border, 0x07]
+        } else {
+            [border | 0x01, 0x07]
+        }
+    }
+
+    /// Data transmission of the black/white data, the "new" data in the black/white mode
+    fn bw_data_command(&self) -> Command {
+        if self.panel_setting.black_white_mode() {
+            Command::DataStartTransmission2
+        } else {
+            Command::DataStartTransmission1
+        }
+    }
+
+    /// Current mode of the controller
+    pub fn power_state(&self) -> PowerState {
+        self.state
+    }
+
+    /// Powers the booster and regulators off without entering deep sleep
+    ///
+    /// Unlike [sleep()](WaveshareDisplay::sleep()) the controller keeps its registers, new
+    /// frames can still be written and [power_on()](Self::power_on()) is a lot cheaper than
+    /// the reset and initialisation of [wake_up()](WaveshareDisplay::wake_up()). The panel
+    /// can't be refreshed while it is powered off.
+    pub fn power_off(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        let next = self.state.check(Transition::PowerOff)?;
+        self.wait_until_idle();
+        self.command(spi, Command::PowerOff)?;
+        self.wait_until_idle();
+        self.state = next;
+        Ok(())
+    }
+
+    /// Powers the booster and regulators on again after [power_off()](Self::power_off())
+    pub fn power_on(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        let next = self.state.check(Transition::PowerOn)?;
+        self.command(spi, Command::PowerOn)?;
+        delay.delay_ms(5);
+        self.wait_until_idle_with_timeout(delay)?;
+        self.state = next;
+        Ok(())
+    }
+
+    /// Leaves the partial mode, also if `result` is an error
+    ///
+    /// The controller needs a hard reset if it is put to sleep inside of the partial mode.
+    fn partial_out(
+        &mut self,
+        spi: &mut SPI,
+        result: Result<(), Error<SPI::Error>>,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.command(spi, Command::PartialOut)?;
+        self.state = self.state.check(Transition::PartialOut)?;
+        result
+    }
+
+    /// Sends the window and its data and refreshes it, the controller has to be in the partial
+    /// mode
+    #[allow(clippy::too_many_arguments)]
+    fn update_partial_window(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        let hrst_upper = (x / 8) as u8 >> 6;
+        let hrst_lower = ((x / 8) << 3) as u8;
+        let hred_upper = ((x + width) / 8) as u8 >> 6;
+        let hred_lower = (((x + width) / 8) << 3) as u8 & 0b111;
+        let vrst_upper = (y >> 8) as u8;
+        let vrst_lower = y as u8;
+        let vred_upper = ((y + height) >> 8) as u8;
+        let vred_lower = (y + height) as u8;
+        let pt_scan = 0x01; // Gates scan both inside and outside of the partial window. (default)
+
+        self.command(spi, Command::PartialWindow)?;
+        self.send_data(
+            spi,
+            &[
+                hrst_upper, hrst_lower, hred_upper, hred_lower, vrst_upper, vrst_lower, vred_upper,
+                vred_lower, pt_scan,
+            ],
+        )?;
+        let command = self.bw_data_command();
+        self.command(spi, command)?;
+        self.send_data(spi, buffer)?;
+
+        if !self.panel_setting.black_white_mode() {
+            let color = TriColor::Black.get_byte_value(); //We need it black, so red channel will be rendered transparent
+            self.command(spi, Command::DataStartTransmission2)?;
+            self.interface
+                .data_x_times(spi, color, width * height / 8)?;
+        }
+
+        self.command(spi, Command::DisplayRefresh)?;
+        self.wait_until_idle_with_timeout(delay)
+    }
+
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd(spi, command)
     }
 
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
         self.interface.data(spi, data)
     }
 
@@ -309,7 +589,7 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
@@ -317,7 +597,12 @@ where
         self.interface.wait_until_idle(IS_BUSY_LOW);
     }
 
-    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    fn wait_until_idle_with_timeout(&mut self, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .wait_until_idle_with_timeout(delay, IS_BUSY_LOW, BUSY_TIMEOUT_MS)
+    }
+
+    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
         let w = self.width();
         let h = self.height();
 
@@ -329,14 +614,384 @@ where
     }
 }
 
+impl<SPI, CS, BUSY, DC, RST, DELAY> Epd5in83<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8> + Transfer<u8, Error = <SPI as Write<u8>>::Error>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Writes `value` to the register `reg` of the external temperature sensor
+    ///
+    /// The controller is the I2C master of the sensor, on some boards it is an LM75 compatible
+    /// sensor at address 0x48. Needs a board which connects the data line of the controller
+    /// to MISO.
+    pub fn external_sensor_write(
+        &mut self,
+        spi: &mut SPI,
+        reg: u8,
+        value: u8,
+    ) -> Result<(), Error<<SPI as Write<u8>>::Error>> {
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle();
+        self.cmd_with_data(
+            spi,
+            Command::TemperatureSensorWrite,
+            &[external_sensor::write_attributes(1, reg), value, 0x00],
+        )?;
+        // the I2C transaction runs after the command
+        self.wait_until_idle();
+        Ok(())
+    }
+
+    /// Reads the first byte of the register `reg` of the external temperature sensor
+    ///
+    /// For the temperature register of an LM75 this is the temperature in whole °C as `i8`.
+    /// See [external_sensor_write()](Self::external_sensor_write()) for the requirements.
+    pub fn external_sensor_read(
+        &mut self,
+        spi: &mut SPI,
+        reg: u8,
+    ) -> Result<u8, Error<<SPI as Write<u8>>::Error>> {
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle();
+        // set the pointer of the sensor without data
+        self.cmd_with_data(
+            spi,
+            Command::TemperatureSensorWrite,
+            &[external_sensor::write_attributes(0, reg), 0x00, 0x00],
+        )?;
+        self.wait_until_idle();
+
+        let mut value = [0u8; 2];
+        self.interface
+            .cmd_with_read(spi, Command::TemperatureSensorRead, &mut value)?;
+        self.wait_until_idle();
+        Ok(value[0])
+    }
+}
+
+/// Bytes of the b/w and the chromatic plane for a whole background in `color`
+///
+/// A set bit in the chromatic plane is red.
+fn background_bytes(color: TriColor) -> (u8, u8) {
+    match color {
+        TriColor::Black => (0x00, 0x00),
+        TriColor::White => (0xFF, 0x00),
+        TriColor::Chromatic | TriColor::Yellow => (0xFF, 0xFF),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::{HighPin, NoDelay};
+    extern crate std;
+    use crate::buffer_len;
+    use std::vec;
+    use std::vec::Vec;
+
+    /// Records all bytes and fails on writes which contain `0xEE`
+    #[derive(Default)]
+    struct FailingSpi(Vec<u8>);
+    impl Write<u8> for FailingSpi {
+        type Error = ();
+        fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+            if words.contains(&0xEE) {
+                return Err(());
+            }
+            self.0.extend_from_slice(words);
+            Ok(())
+        }
+    }
+
+    fn test_epd(
+        state: PowerState,
+    ) -> Epd5in83<FailingSpi, HighPin, HighPin, HighPin, HighPin, NoDelay> {
+        Epd5in83 {
+            interface: DisplayInterface::new(HighPin, HighPin, HighPin, HighPin),
+            color: DEFAULT_BACKGROUND_COLOR,
+            panel_setting: PANEL_SETTING,
+            border: TriColor::White,
+            state,
+        }
+    }
+
+    #[test]
+    fn partial_out_is_sent_on_errors() {
+        let mut spi = FailingSpi::default();
+        let mut epd = test_epd(PowerState::Active);
+
+        assert_eq!(
+            epd.update_partial_frame(&mut spi, &[0xEE], 0, 0, 8, 1, &mut NoDelay),
+            Err(Error::Spi(()))
+        );
+        assert_eq!(spi.0.first(), Some(&(Command::PartialIn as u8)));
+        assert_eq!(spi.0.last(), Some(&(Command::PartialOut as u8)));
+        assert_eq!(epd.power_state(), PowerState::Active);
+
+        epd.sleep(&mut spi, &mut NoDelay).unwrap();
+        assert_eq!(epd.power_state(), PowerState::DeepSleep);
+    }
+
+    #[test]
+    fn illegal_transitions_send_nothing() {
+        let mut spi = FailingSpi::default();
+        let mut epd = test_epd(PowerState::DeepSleep);
+        let frame = vec![0x00; buffer_len(WIDTH as usize, HEIGHT as usize)];
+        assert_eq!(
+            epd.update_frame(&mut spi, &frame, &mut NoDelay),
+            Err(Error::IllegalTransition {
+                state: PowerState::DeepSleep,
+                transition: Transition::Update,
+            })
+        );
+        assert_eq!(
+            epd.update_partial_frame(&mut spi, &[0x00], 0, 0, 8, 1, &mut NoDelay),
+            Err(Error::IllegalTransition {
+                state: PowerState::DeepSleep,
+                transition: Transition::PartialIn,
+            })
+        );
+
+        let mut epd = test_epd(PowerState::Partial);
+        assert_eq!(
+            epd.sleep(&mut spi, &mut NoDelay),
+            Err(Error::IllegalTransition {
+                state: PowerState::Partial,
+                transition: Transition::Sleep,
+            })
+        );
+        assert!(spi.0.is_empty());
+    }
+
+    #[test]
+    fn power_off_keeps_the_registers() {
+        let mut spi = FailingSpi::default();
+        let mut epd = test_epd(PowerState::Active);
+
+        epd.power_off(&mut spi).unwrap();
+        assert_eq!(spi.0, [Command::PowerOff as u8]);
+        assert_eq!(epd.power_state(), PowerState::PoweredOff);
+
+        assert_eq!(
+            epd.display_frame(&mut spi, &mut NoDelay),
+            Err(Error::IllegalTransition {
+                state: PowerState::PoweredOff,
+                transition: Transition::Refresh,
+            })
+        );
+        assert_eq!(spi.0.len(), 1);
+
+        epd.power_on(&mut spi, &mut NoDelay).unwrap();
+        assert_eq!(spi.0, [Command::PowerOff as u8, Command::PowerOn as u8]);
+        assert_eq!(epd.power_state(), PowerState::Active);
+        assert_eq!(
+            epd.power_on(&mut spi, &mut NoDelay),
+            Err(Error::IllegalTransition {
+                state: PowerState::Active,
+                transition: Transition::PowerOn,
+            })
+        );
+    }
 
     #[test]
     fn epd_size() {
         assert_eq!(WIDTH, 648);
         assert_eq!(HEIGHT, 480);
-        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, TriColor::White);
+    }
+
+    /// Records the writes and answers transfers with the scripted bytes
+    #[derive(Default)]
+    struct SensorSpi {
+        written: Vec<u8>,
+        answers: Vec<u8>,
+    }
+    impl Write<u8> for SensorSpi {
+        type Error = ();
+        fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+            self.written.extend_from_slice(words);
+            Ok(())
+        }
+    }
+    impl Transfer<u8> for SensorSpi {
+        type Error = ();
+        fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Self::Error> {
+            for word in words.iter_mut() {
+                *word = self.answers.remove(0);
+            }
+            Ok(words)
+        }
+    }
+
+    #[test]
+    fn external_sensor_exchange() {
+        let mut spi = SensorSpi {
+            answers: vec![0x17, 0x80],
+            ..Default::default()
+        };
+        let mut epd: Epd5in83<_, _, _, _, _, NoDelay> = Epd5in83 {
+            interface: DisplayInterface::new(HighPin, HighPin, HighPin, HighPin),
+            color: DEFAULT_BACKGROUND_COLOR,
+            panel_setting: PANEL_SETTING,
+            border: TriColor::White,
+            state: PowerState::Active,
+        };
+
+        // LM75 configuration register: shutdown off
+        epd.external_sensor_write(&mut spi, 0x01, 0x00).unwrap();
+        assert_eq!(spi.written, [0x42, 0b01_001_000, 0x00, 0x00]);
+
+        // LM75 temperature register: 23.5 °C
+        spi.written.clear();
+        assert_eq!(epd.external_sensor_read(&mut spi, 0x00), Ok(0x17));
+        assert_eq!(spi.written, [0x42, 0x00, 0x00, 0x00, 0x43]);
+        assert!(spi.answers.is_empty());
+
+        epd.state = PowerState::DeepSleep;
+        assert_eq!(
+            epd.external_sensor_read(&mut spi, 0x00),
+            Err(Error::IllegalTransition {
+                state: PowerState::DeepSleep,
+                transition: Transition::Update,
+            })
+        );
+    }
+
+    #[test]
+    fn black_white_mode_sends_the_new_data_only() {
+        let mut spi = FailingSpi::default();
+        let mut epd = test_epd(PowerState::Active);
+
+        epd.set_black_white_mode(&mut spi, true).unwrap();
+        assert_eq!(spi.0, [0x00, 0x1F, 0x50, 0x10, 0x07]);
+
+        spi.0.clear();
+        let plane = vec![0x55; buffer_len(WIDTH as usize, HEIGHT as usize)];
+        epd.update_color_frame(&mut spi, &plane, &plane).unwrap();
+        assert_eq!(spi.0[0], 0x13);
+        assert_eq!(spi.0.len(), 1 + plane.len());
+
+        assert_eq!(
+            epd.set_panel_setting(&mut spi, PANEL_SETTING.with_lut_from_register(true)),
+            Err(Error::InvalidPanelSetting)
+        );
+        assert_eq!(epd.panel_setting().bits(), 0x1F);
+    }
+
+    #[test]
+    fn chromatic_plane_alone() {
+        let mut spi = FailingSpi::default();
+        let mut epd = test_epd(PowerState::Active);
+
+        // the black/white plane in the RAM isn't touched
+        let plane = vec![0x0F; buffer_len(WIDTH as usize, HEIGHT as usize)];
+        epd.update_chromatic_frame(&mut spi, &plane).unwrap();
+        epd.display_frame(&mut spi, &mut NoDelay).unwrap();
+        let mut expected = vec![0x13];
+        expected.extend_from_slice(&plane);
+        expected.push(0x12);
+        assert_eq!(spi.0, expected);
+    }
+
+    #[test]
+    fn color_planes_of_the_wrong_size_send_nothing() {
+        let mut spi = FailingSpi::default();
+        let mut epd = test_epd(PowerState::Active);
+
+        let plane = vec![0x55; buffer_len(WIDTH as usize, HEIGHT as usize)];
+        let short = &plane[1..];
+        let expected = Err(Error::BufferSize {
+            expected: plane.len(),
+            got: short.len(),
+        });
+        assert_eq!(epd.update_color_frame(&mut spi, short, &plane), expected);
+        assert_eq!(epd.update_chromatic_frame(&mut spi, short), expected);
+        assert!(spi.0.is_empty());
+    }
+
+    #[test]
+    fn border_colors_and_floating_sleep() {
+        let mut spi = FailingSpi::default();
+        let mut epd = test_epd(PowerState::Active);
+
+        for (color, bits) in [
+            (TriColor::Black, 0x01),
+            (TriColor::White, 0x11),
+            (TriColor::Chromatic, 0x21),
+            (TriColor::Yellow, 0x21),
+        ] {
+            spi.0.clear();
+            epd.set_border_color(&mut spi, color).unwrap();
+            assert_eq!(spi.0, [0x50, bits, 0x07]);
+        }
+
+        epd.set_border_color(&mut spi, TriColor::Black).unwrap();
+        spi.0.clear();
+        epd.set_black_white_mode(&mut spi, true).unwrap();
+        assert_eq!(spi.0, [0x00, 0x1F, 0x50, 0x00, 0x07]);
+
+        spi.0.clear();
+        epd.sleep(&mut spi, &mut NoDelay).unwrap();
+        assert_eq!(spi.0, [0x50, 0x80, 0x07, 0x02, 0x07, 0xA5]);
+        assert_eq!(
+            epd.set_border_color(&mut spi, TriColor::White),
+            Err(Error::IllegalTransition {
+                state: PowerState::DeepSleep,
+                transition: Transition::Update,
+            })
+        );
+    }
+
+    #[test]
+    fn clear_and_update_fill_the_background() {
+        let mut spi = FailingSpi::default();
+        let mut epd = test_epd(PowerState::Active);
+        let n = NUM_DISPLAY_BITS as usize;
+        let frame = vec![0x55; n];
+
+        for (color, bw, chromatic) in [
+            (TriColor::Black, 0x00, 0x00),
+            (TriColor::White, 0xFF, 0x00),
+            (TriColor::Chromatic, 0xFF, 0xFF),
+        ] {
+            epd.set_background_color(color);
+
+            spi.0.clear();
+            epd.clear_frame(&mut spi, &mut NoDelay).unwrap();
+            assert_eq!(spi.0.len(), 2 + 2 * n);
+            assert_eq!(spi.0[0], 0x10);
+            assert!(spi.0[1..=n].iter().all(|&b| b == bw));
+            assert_eq!(spi.0[n + 1], 0x13);
+            assert!(spi.0[n + 2..].iter().all(|&b| b == chromatic));
+
+            // only the chromatic plane is filled with the background
+            spi.0.clear();
+            epd.update_frame(&mut spi, &frame, &mut NoDelay).unwrap();
+            assert_eq!(&spi.0[..=n], &[&[0x10][..], &frame[..]].concat()[..]);
+            assert_eq!(spi.0[n + 1], 0x13);
+            assert!(spi.0[n + 2..].iter().all(|&b| b == chromatic));
+        }
+    }
+
+    #[test]
+    fn luts_other_than_full_are_rejected() {
+        let mut spi = FailingSpi::default();
+        let mut epd = test_epd(PowerState::Active);
+
+        assert_eq!(epd.set_lut(&mut spi, None), Ok(()));
+        assert_eq!(epd.set_lut(&mut spi, Some(RefreshLut::Full)), Ok(()));
+        assert_eq!(
+            epd.set_lut(&mut spi, Some(RefreshLut::Quick)),
+            Err(Error::UnsupportedLut)
+        );
+        assert_eq!(
+            epd.set_lut(&mut spi, Some(RefreshLut::Custom(&[0; 8]))),
+            Err(Error::UnsupportedLut)
+        );
+        assert!(spi.0.is_empty());
     }
 }
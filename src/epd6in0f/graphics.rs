@@ -0,0 +1,138 @@
+use crate::color::OctColor;
+use crate::epd6in0f::{DEFAULT_BACKGROUND_COLOR, HEIGHT, WIDTH};
+use crate::graphics::{DisplayRotation, OctDisplay};
+use crate::traits::FrameBuffer;
+use embedded_graphics_core::prelude::*;
+
+/// Full size buffer for use with the 6in0f EPD
+///
+/// Can also be manually constructed:
+/// `buffer: [DEFAULT_BACKGROUND_COLOR.get_byte_value(); WIDTH / 2 * HEIGHT]`
+pub struct Display6in0f {
+    buffer: [u8; WIDTH as usize * HEIGHT as usize / 2],
+    rotation: DisplayRotation,
+}
+
+impl Default for Display6in0f {
+    fn default() -> Self {
+        Display6in0f {
+            buffer: [OctColor::colors_byte(DEFAULT_BACKGROUND_COLOR, DEFAULT_BACKGROUND_COLOR);
+                WIDTH as usize * HEIGHT as usize / 2],
+            rotation: DisplayRotation::default(),
+        }
+    }
+}
+
+impl DrawTarget for Display6in0f {
+    type Color = OctColor;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for pixel in pixels {
+            self.draw_helper(WIDTH, HEIGHT, pixel)?;
+        }
+        Ok(())
+    }
+}
+
+impl OriginDimensions for Display6in0f {
+    fn size(&self) -> Size {
+        match self.rotation {
+            DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => Size::new(WIDTH, HEIGHT),
+            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => Size::new(HEIGHT, WIDTH),
+        }
+    }
+}
+
+impl OctDisplay for Display6in0f {
+    fn buffer(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    fn get_mut_buffer(&mut self) -> &mut [u8] {
+        &mut self.buffer
+    }
+
+    fn set_rotation(&mut self, rotation: DisplayRotation) {
+        self.rotation = rotation;
+    }
+
+    fn rotation(&self) -> DisplayRotation {
+        self.rotation
+    }
+}
+
+impl FrameBuffer for Display6in0f {
+    type Color = OctColor;
+
+    fn as_bytes(&self) -> &[u8] {
+        self.buffer()
+    }
+
+    fn as_bytes_mut(&mut self) -> &mut [u8] {
+        self.get_mut_buffer()
+    }
+
+    fn clear(&mut self, color: OctColor) {
+        self.clear_buffer(color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epd6in0f;
+    use crate::graphics::{DisplayRotation, OctDisplay};
+    use embedded_graphics::{
+        prelude::*,
+        primitives::{Line, PrimitiveStyle},
+    };
+
+    // test buffer length
+    #[test]
+    fn graphics_size() {
+        let display = Display6in0f::default();
+        assert_eq!(display.buffer().len(), 448 * 600 / 2);
+    }
+
+    // test default background color on all bytes
+    #[test]
+    fn graphics_default() {
+        let display = Display6in0f::default();
+        for &byte in display.buffer() {
+            assert_eq!(
+                byte,
+                OctColor::colors_byte(
+                    epd6in0f::DEFAULT_BACKGROUND_COLOR,
+                    epd6in0f::DEFAULT_BACKGROUND_COLOR,
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn two_pixels_per_byte() {
+        let mut display = Display6in0f::default();
+        let _ = Line::new(Point::new(0, 0), Point::new(0, 1))
+            .into_styled(PrimitiveStyle::with_stroke(OctColor::Red, 1))
+            .draw(&mut display);
+        let _ = Line::new(Point::new(3, 1), Point::new(3, 1))
+            .into_styled(PrimitiveStyle::with_stroke(OctColor::Blue, 1))
+            .draw(&mut display);
+
+        // the left pixel is in the high nibble, a row has WIDTH / 2 bytes
+        let buffer = display.buffer();
+        assert_eq!(buffer[0], 0x41);
+        assert_eq!(buffer[WIDTH as usize / 2], 0x41);
+        assert_eq!(buffer[WIDTH as usize / 2 + 1], 0x13);
+
+        // the top left corner of the rotated display is the end of the first row
+        display.set_rotation(DisplayRotation::Rotate90);
+        assert_eq!(display.size(), Size::new(HEIGHT, WIDTH));
+        let _ = Pixel(Point::new(0, 0), OctColor::Green).draw(&mut display);
+        assert_eq!(display.buffer()[WIDTH as usize / 2 - 1], 0x12);
+    }
+}
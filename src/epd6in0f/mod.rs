@@ -0,0 +1,358 @@
+//! A simple Driver for the Waveshare 6 inch (F) E-Ink Display via SPI
+//!
+//! The 7 color ACeP panel has the same resolution as the [5.65 inch (F)](crate::epd5in65f) one,
+//! but an EK79686 controller with a slower PLL and a longer reset. Apart from those the init and
+//! refresh sequences are the ones of the 5.65 inch driver. Pixels are sent with 4 bits each, two
+//! [OctColor]s per byte with the left pixel in the high nibble, see [OctColor::colors_byte()].
+//!
+//! Partial updates and LUT selection aren't supported by the panel.
+
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::{InputPin, OutputPin},
+};
+
+use crate::color::OctColor;
+use crate::epd5in65f::{self, command::Command, Panel};
+use crate::error::Error;
+use crate::interface::DisplayInterface;
+use crate::timing::ResetTiming;
+use crate::traits::{InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::{AutoCs, CsStrategy, ResetPin};
+
+#[cfg(feature = "graphics")]
+mod graphics;
+#[cfg(feature = "graphics")]
+pub use self::graphics::Display6in0f;
+
+/// Width of the display
+pub const WIDTH: u32 = 600;
+/// Height of the display
+pub const HEIGHT: u32 = 448;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: OctColor = OctColor::White;
+const IS_BUSY_LOW: bool = true;
+/// Longest time in milliseconds to wait for the busy pin before giving up with
+/// [Error::Timeout]
+pub const BUSY_TIMEOUT_MS: u32 = 60_000;
+/// Fastest SPI clock of the controller in Hz, see
+/// [check_spi_frequency()](crate::check_spi_frequency())
+pub const MAX_SPI_FREQ_HZ: u32 = 10_000_000;
+
+/// The EK79686 needs a longer low phase of the reset than the 5.65 inch panel, 0x3A sets a
+/// frame rate of 50 Hz
+const PANEL: Panel = Panel {
+    reset_ms: 20,
+    pll: 0x3A,
+};
+
+/// Epd6in0f driver
+///
+pub struct Epd6in0f<SPI, CS, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+    /// Background Color
+    color: OctColor,
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd6in0f<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        epd5in65f::init(&mut self.interface, spi, delay, &PANEL, self.color)
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd6in0f<SPI, AutoCs, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Creates the driver for a board without a CS GPIO, see [AutoCs]
+    pub fn new_without_cs(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new(spi, AutoCs, busy, dc, rst, delay)
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd6in0f<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    type DisplayColor = OctColor;
+    fn new(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_background(spi, cs, busy, dc, rst, delay, DEFAULT_BACKGROUND_COLOR)
+    }
+
+    fn new_with_background(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        color: Self::DisplayColor,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(cs, busy, dc, rst);
+        let mut epd = Epd6in0f { interface, color };
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.check_reset_pin()?;
+        self.init(spi, delay)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .cmd_with_data(spi, Command::DeepSleep, &[0xA5])
+    }
+
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        epd5in65f::update_frame(&mut self.interface, spi, buffer, delay, self.color)
+    }
+
+    /// The controller can't update windows, returns [Error::PartialUpdateUnsupported]
+    fn update_partial_frame(
+        &mut self,
+        _spi: &mut SPI,
+        _buffer: &[u8],
+        _x: u32,
+        _y: u32,
+        _width: u32,
+        _height: u32,
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        Err(Error::PartialUpdateUnsupported)
+    }
+
+    /// Switches the power on, refreshes the panel and switches the power off again
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        epd5in65f::display_frame(&mut self.interface, spi, delay)
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)
+    }
+
+    /// Fills the whole frame with the background color, two pixels per byte
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        epd5in65f::clear_frame(&mut self.interface, spi, delay, self.color)
+    }
+
+    fn set_background_color(&mut self, color: OctColor) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &OctColor {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn full_refresh_ms(&self) -> u32 {
+        25_000
+    }
+
+    /// 4 bits per pixel
+    fn buffer_len(&self) -> usize {
+        (WIDTH * HEIGHT / 2) as usize
+    }
+
+    /// The waveform of the OTP is the only one, anything but [RefreshLut::Full] returns
+    /// [Error::UnsupportedLut]
+    fn set_lut(
+        &mut self,
+        _spi: &mut SPI,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        match refresh_rate {
+            None | Some(RefreshLut::Full) => Ok(()),
+            Some(_) => Err(Error::UnsupportedLut),
+        }
+    }
+
+    fn is_busy(&self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
+    fn send_command_raw(&mut self, spi: &mut SPI, command: u8) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn send_data_raw(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        self.interface.data(spi, data)
+    }
+
+    fn set_spi_chunk_size(&mut self, chunk_size: usize) {
+        self.interface.set_chunk_size(chunk_size);
+    }
+
+    fn set_reset_timing(&mut self, timing: Option<ResetTiming>) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    #[cfg(feature = "test-patterns")]
+    fn set_test_pattern(&mut self, enable: bool) {
+        let pattern = crate::test_pattern::FillPattern::new(self.width(), 4);
+        self.interface.set_fill_pattern(enable.then_some(pattern));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{
+        busy_after, busy_for, cmd, test_epd, BusyPin, DcPin, HighPin, NoDelay, RecordingSpi,
+    };
+
+    type TestEpd = Epd6in0f<RecordingSpi, HighPin, HighPin, DcPin, HighPin, NoDelay>;
+    type BusyEpd = Epd6in0f<RecordingSpi, BusyPin, BusyPin, DcPin, BusyPin, NoDelay>;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 600);
+        assert_eq!(HEIGHT, 448);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, OctColor::White);
+
+        let (_spi, epd) = test_epd::<TestEpd, _>();
+        assert_eq!(epd.buffer_len(), 600 * 448 / 2);
+    }
+
+    #[test]
+    fn nibbles_of_the_colors() {
+        let nibbles = [
+            (OctColor::Black, 0x0),
+            (OctColor::White, 0x1),
+            (OctColor::Green, 0x2),
+            (OctColor::Blue, 0x3),
+            (OctColor::Red, 0x4),
+            (OctColor::Yellow, 0x5),
+            (OctColor::Orange, 0x6),
+        ];
+        for &(color, nibble) in &nibbles {
+            assert_eq!(color.get_nibble(), nibble);
+            assert_eq!(OctColor::from_nibble(nibble), Ok(color));
+        }
+        assert_eq!(
+            OctColor::colors_byte(OctColor::White, OctColor::White),
+            0x11
+        );
+    }
+
+    #[test]
+    fn init_sends_the_resolution() {
+        let (spi, _epd) = test_epd::<TestEpd, _>();
+        assert_eq!(spi.sent[..3], cmd(0x00, &[0xEF, 0x08])[..]);
+        let resolution = cmd(0x61, &[0x02, 0x58, 0x01, 0xC0]);
+        assert!(spi.sent.windows(5).any(|w| w == &resolution[..]));
+    }
+
+    #[test]
+    fn clear_frame_in_white() {
+        // High is idle for power on and the refresh, power off is done once the pin reads low
+        busy_for(u32::MAX);
+        let (mut spi, mut epd) = test_epd::<BusyEpd, _>();
+        spi.sent.clear();
+        busy_after(0x02, 3);
+        epd.clear_frame(&mut spi, &mut NoDelay).unwrap();
+
+        let mut expected = cmd(0x50, &[0x37]);
+        expected.extend(cmd(0x61, &[0x02, 0x58, 0x01, 0xC0]));
+        expected.extend(cmd(0x10, &[0x11; WIDTH as usize * HEIGHT as usize / 2]));
+        expected.extend(cmd(0x04, &[]));
+        expected.extend(cmd(0x12, &[]));
+        expected.extend(cmd(0x02, &[]));
+        assert_eq!(spi.sent, expected);
+    }
+
+    #[test]
+    fn power_off_waits_for_busy_low() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+
+        assert_eq!(
+            epd.display_frame(&mut spi, &mut NoDelay),
+            Err(Error::Timeout)
+        );
+        assert_eq!(spi.sent.last(), Some(&(true, 0x02)));
+    }
+
+    #[test]
+    fn update_frame_checks_the_buffer_length() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        assert_eq!(
+            epd.update_frame(&mut spi, &[0x11; 100], &mut NoDelay),
+            Err(Error::BufferSize {
+                expected: WIDTH as usize * HEIGHT as usize / 2,
+                got: 100,
+            })
+        );
+    }
+
+    #[test]
+    fn partial_frames_and_luts_are_rejected() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+
+        assert_eq!(
+            epd.update_partial_frame(&mut spi, &[0; 8], 0, 0, 8, 8, &mut NoDelay),
+            Err(Error::PartialUpdateUnsupported)
+        );
+        assert_eq!(epd.set_lut(&mut spi, None), Ok(()));
+        assert_eq!(epd.set_lut(&mut spi, Some(RefreshLut::Full)), Ok(()));
+        assert_eq!(
+            epd.set_lut(&mut spi, Some(RefreshLut::Quick)),
+            Err(Error::UnsupportedLut)
+        );
+        assert!(spi.sent.is_empty());
+    }
+}
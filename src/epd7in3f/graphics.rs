@@ -0,0 +1,120 @@
+use crate::color::OctColor;
+use crate::epd7in3f::{DEFAULT_BACKGROUND_COLOR, HEIGHT, WIDTH};
+use crate::graphics::{DisplayRotation, OctDisplay};
+use crate::traits::FrameBuffer;
+use embedded_graphics_core::prelude::*;
+
+/// Full size buffer for use with the 7in3f EPD
+///
+/// Can also be manually constructed:
+/// `buffer: [OctColor::colors_byte(DEFAULT_BACKGROUND_COLOR, DEFAULT_BACKGROUND_COLOR); WIDTH / 2 * HEIGHT]`
+pub struct Display7in3f {
+    buffer: [u8; WIDTH as usize * HEIGHT as usize / 2],
+    rotation: DisplayRotation,
+}
+
+impl Default for Display7in3f {
+    fn default() -> Self {
+        Display7in3f {
+            buffer: [OctColor::colors_byte(DEFAULT_BACKGROUND_COLOR, DEFAULT_BACKGROUND_COLOR);
+                WIDTH as usize * HEIGHT as usize / 2],
+            rotation: DisplayRotation::default(),
+        }
+    }
+}
+
+impl DrawTarget for Display7in3f {
+    type Color = OctColor;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for pixel in pixels {
+            self.draw_helper(WIDTH, HEIGHT, pixel)?;
+        }
+        Ok(())
+    }
+}
+
+impl OriginDimensions for Display7in3f {
+    fn size(&self) -> Size {
+        match self.rotation {
+            DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => Size::new(WIDTH, HEIGHT),
+            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => Size::new(HEIGHT, WIDTH),
+        }
+    }
+}
+
+impl OctDisplay for Display7in3f {
+    fn buffer(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    fn get_mut_buffer(&mut self) -> &mut [u8] {
+        &mut self.buffer
+    }
+
+    fn set_rotation(&mut self, rotation: DisplayRotation) {
+        self.rotation = rotation;
+    }
+
+    fn rotation(&self) -> DisplayRotation {
+        self.rotation
+    }
+}
+
+impl FrameBuffer for Display7in3f {
+    type Color = OctColor;
+
+    fn as_bytes(&self) -> &[u8] {
+        self.buffer()
+    }
+
+    fn as_bytes_mut(&mut self) -> &mut [u8] {
+        self.get_mut_buffer()
+    }
+
+    fn clear(&mut self, color: OctColor) {
+        self.clear_buffer(color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+    use embedded_graphics::{
+        pixelcolor::Rgb888,
+        prelude::*,
+        primitives::{Line, PrimitiveStyle},
+    };
+    use std::boxed::Box;
+
+    #[test]
+    fn graphics_size() {
+        let display = Box::new(Display7in3f::default());
+        assert_eq!(display.buffer().len(), 800 * 480 / 2);
+        assert!(display.buffer().iter().all(|&b| b == 0x11));
+    }
+
+    #[test]
+    fn rgb_is_mapped_to_the_nearest_palette_color() {
+        let mut display = Box::new(Display7in3f::default());
+
+        let _ = Line::new(Point::new(0, 0), Point::new(1, 0))
+            .into_styled(PrimitiveStyle::with_stroke(
+                Rgb888::new(0xF0, 0x70, 0x10),
+                1,
+            ))
+            .draw(&mut display.color_converted());
+
+        let buffer = display.buffer();
+        assert_eq!(
+            OctColor::split_byte(buffer[0]),
+            Ok((OctColor::Orange, OctColor::Orange))
+        );
+        assert!(buffer.iter().skip(1).all(|&b| b == 0x11));
+    }
+}
@@ -0,0 +1,474 @@
+//! A simple Driver for the Waveshare 7.3" (F) E-Ink Display via SPI
+//!
+//! The ACeP panel shows the seven colors of [OctColor] with 4 bits per pixel, the first pixel
+//! of a byte is in the upper nibble. The palette is the one of the [5.65" (F)](crate::epd5in65f),
+//! the controller and its init sequence are different though.
+//!
+//! A full frame has 192000 bytes. If that doesn't fit into the memory of the microcontroller,
+//! the frame can be produced row by row with
+//! [update_frame_by_rows()](Epd7in3f::update_frame_by_rows()). The full size `Display7in3f`
+//! is only available with the `large-buffers` feature. It draws [OctColor], images in
+//! `Rgb888` can be drawn through `DrawTargetExt::color_converted()` of embedded-graphics,
+//! which maps every pixel to the nearest color of the palette.
+//!
+//! # References
+//!
+//! - [Waveshare wiki](https://www.waveshare.com/wiki/7.3inch_e-Paper_HAT_(F)_Manual)
+//! - [Waveshare C driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_7in3f.c)
+
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::{InputPin, OutputPin},
+};
+
+use crate::color::OctColor;
+use crate::error::Error;
+use crate::interface::DisplayInterface;
+use crate::power::{PowerState, Transition};
+use crate::timing::ResetTiming;
+use crate::traits::{InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::{AutoCs, CsStrategy, ResetPin};
+
+pub(crate) mod command;
+use self::command::Command;
+
+#[cfg(feature = "large-buffers")]
+mod graphics;
+#[cfg(feature = "large-buffers")]
+pub use self::graphics::Display7in3f;
+
+/// Width of the display
+pub const WIDTH: u32 = 800;
+/// Height of the display
+pub const HEIGHT: u32 = 480;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: OctColor = OctColor::White;
+const IS_BUSY_LOW: bool = true;
+/// Longest time in milliseconds to wait for the busy pin before giving up with
+/// [Error::Timeout]
+pub const BUSY_TIMEOUT_MS: u32 = 60_000;
+/// Fastest SPI clock of the controller in Hz, see
+/// [check_spi_frequency()](crate::check_spi_frequency())
+pub const MAX_SPI_FREQ_HZ: u32 = 10_000_000;
+
+/// Bytes of a row, 2 pixels per byte
+const ROW_BYTES: usize = WIDTH as usize / 2;
+
+/// Epd7in3f driver
+///
+pub struct Epd7in3f<SPI, CS, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+    /// Background Color
+    color: OctColor,
+    /// Mode of the controller
+    state: PowerState,
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd7in3f<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        // reset the device
+        self.state = self.state.check(Transition::Init)?;
+        self.interface.reset(delay, 20, 2)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        delay.delay_ms(30);
+
+        self.cmd_with_data(
+            spi,
+            Command::UnlockKey,
+            &[0x49, 0x55, 0x20, 0x08, 0x09, 0x18],
+        )?;
+        self.cmd_with_data(
+            spi,
+            Command::PowerSetting,
+            &[0x3F, 0x00, 0x32, 0x2A, 0x0E, 0x2A],
+        )?;
+        self.cmd_with_data(spi, Command::PanelSetting, &[0x5F, 0x69])?;
+        self.cmd_with_data(
+            spi,
+            Command::PowerOffSequenceSetting,
+            &[0x00, 0x54, 0x00, 0x44],
+        )?;
+
+        self.cmd_with_data(spi, Command::BoosterSoftStart1, &[0x40, 0x1F, 0x1F, 0x2C])?;
+        self.cmd_with_data(spi, Command::BoosterSoftStart2, &[0x6F, 0x1F, 0x1F, 0x22])?;
+        self.cmd_with_data(spi, Command::BoosterSoftStart3, &[0x6F, 0x1F, 0x1F, 0x22])?;
+
+        self.cmd_with_data(spi, Command::ImageProcess, &[0x00, 0x04])?;
+        self.cmd_with_data(spi, Command::PllControl, &[0x3C])?;
+        // internal temperature sensor
+        self.cmd_with_data(spi, Command::TemperatureSensorEnable, &[0x00])?;
+
+        // border and data interval of the vendor driver
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x3F])?;
+        self.cmd_with_data(spi, Command::TconSetting, &[0x02, 0x00])?;
+        self.cmd_with_data(
+            spi,
+            Command::TconResolution,
+            &[
+                (WIDTH >> 8) as u8,
+                WIDTH as u8,
+                (HEIGHT >> 8) as u8,
+                HEIGHT as u8,
+            ],
+        )?;
+
+        self.cmd_with_data(spi, Command::VcmDcSetting, &[0x1E])?;
+        self.cmd_with_data(spi, Command::PowerOnSetting, &[0x00])?;
+        self.cmd_with_data(spi, Command::AutoGateIdle, &[0x00])?;
+        self.cmd_with_data(spi, Command::PowerSaving, &[0x2F])?;
+        self.cmd_with_data(spi, Command::CascadeSetting, &[0x00])?;
+        self.cmd_with_data(spi, Command::TemperatureSetting, &[0x00])?;
+        Ok(())
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd7in3f<SPI, AutoCs, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Creates the driver for a board without a CS GPIO, see [AutoCs]
+    pub fn new_without_cs(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new(spi, AutoCs, busy, dc, rst, delay)
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd7in3f<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    type DisplayColor = OctColor;
+    fn new(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_background(spi, cs, busy, dc, rst, delay, DEFAULT_BACKGROUND_COLOR)
+    }
+
+    fn new_with_background(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        color: Self::DisplayColor,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(cs, busy, dc, rst);
+
+        let mut epd = Epd7in3f {
+            interface,
+            color,
+            state: PowerState::Active,
+        };
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.check_reset_pin()?;
+        self.init(spi, delay)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        let next = self.state.check(Transition::Sleep)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
+        self.state = next;
+        Ok(())
+    }
+
+    /// Writes a frame with 4 bits per pixel, see [OctColor::colors_byte()]
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        let expected = self.buffer_len();
+        if buffer.len() != expected {
+            return Err(Error::BufferSize {
+                expected,
+                got: buffer.len(),
+            });
+        }
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.cmd_with_data(spi, Command::DataStartTransmission, buffer)
+    }
+
+    /// The controller can't update windows, returns [Error::PartialUpdateUnsupported]
+    fn update_partial_frame(
+        &mut self,
+        _spi: &mut SPI,
+        _buffer: &[u8],
+        _x: u32,
+        _y: u32,
+        _width: u32,
+        _height: u32,
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        Err(Error::PartialUpdateUnsupported)
+    }
+
+    /// Switches the power on, refreshes the panel and switches the power off again
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Refresh)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.command(spi, Command::PowerOn)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.interface.start_measurement(RefreshLut::Full.into());
+        self.cmd_with_data(spi, Command::DisplayRefresh, &[0x00])?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.cmd_with_data(spi, Command::PowerOff, &[0x00])?;
+        self.wait_until_idle_with_timeout(delay)
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        let bg = OctColor::colors_byte(self.color, self.color);
+        self.command(spi, Command::DataStartTransmission)?;
+        self.interface
+            .data_x_times(spi, bg, ROW_BYTES as u32 * HEIGHT)?;
+        self.display_frame(spi, delay)
+    }
+
+    fn set_background_color(&mut self, color: OctColor) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &OctColor {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn full_refresh_ms(&self) -> u32 {
+        35_000
+    }
+
+    /// 4 bits per pixel
+    fn buffer_len(&self) -> usize {
+        ROW_BYTES * HEIGHT as usize
+    }
+
+    /// The waveform of the OTP is the only one, anything but [RefreshLut::Full] returns
+    /// [Error::UnsupportedLut]
+    fn set_lut(
+        &mut self,
+        _spi: &mut SPI,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        match refresh_rate {
+            None | Some(RefreshLut::Full) => Ok(()),
+            Some(_) => Err(Error::UnsupportedLut),
+        }
+    }
+
+    fn is_busy(&self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
+    fn send_command_raw(&mut self, spi: &mut SPI, command: u8) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn send_data_raw(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        self.interface.data(spi, data)
+    }
+
+    fn set_spi_chunk_size(&mut self, chunk_size: usize) {
+        self.interface.set_chunk_size(chunk_size);
+    }
+
+    fn set_reset_timing(&mut self, timing: Option<ResetTiming>) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    #[cfg(feature = "test-patterns")]
+    fn set_test_pattern(&mut self, enable: bool) {
+        let pattern = crate::test_pattern::FillPattern::new(self.width(), 4);
+        self.interface.set_fill_pattern(enable.then_some(pattern));
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> Epd7in3f<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Current mode of the controller
+    pub fn power_state(&self) -> PowerState {
+        self.state
+    }
+
+    /// Writes a frame row by row, without a buffer for the whole frame
+    ///
+    /// `fill_row` is called for every row from the top with the index of the row and a buffer
+    /// of `WIDTH / 2` bytes, which it fills like a row of the buffer of
+    /// [update_frame()](WaveshareDisplay::update_frame()).
+    pub fn update_frame_by_rows<F>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        mut fill_row: F,
+    ) -> Result<(), Error<SPI::Error>>
+    where
+        F: FnMut(u32, &mut [u8]),
+    {
+        self.state.check(Transition::Update)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.command(spi, Command::DataStartTransmission)?;
+        let mut row = [0u8; ROW_BYTES];
+        for y in 0..HEIGHT {
+            fill_row(y, &mut row);
+            self.interface.data(spi, &row)?;
+        }
+        Ok(())
+    }
+
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    fn wait_until_idle_with_timeout(&mut self, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .wait_until_idle_with_timeout(delay, IS_BUSY_LOW, BUSY_TIMEOUT_MS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{cmd, test_epd, DcPin, HighPin, NoDelay, RecordingSpi};
+    extern crate std;
+    use std::vec::Vec;
+
+    type TestEpd = Epd7in3f<RecordingSpi, HighPin, HighPin, DcPin, HighPin, NoDelay>;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 800);
+        assert_eq!(HEIGHT, 480);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, OctColor::White);
+    }
+
+    #[test]
+    fn init_starts_with_the_unlock_key() {
+        let (spi, _epd) = test_epd::<TestEpd, _>();
+        assert_eq!(
+            spi.sent[..7],
+            cmd(0xAA, &[0x49, 0x55, 0x20, 0x08, 0x09, 0x18])[..]
+        );
+        let resolution = cmd(0x61, &[0x03, 0x20, 0x01, 0xE0]);
+        assert!(spi.sent.windows(5).any(|w| w == &resolution[..]));
+    }
+
+    #[test]
+    fn clear_frame_in_white() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+        epd.clear_frame(&mut spi, &mut NoDelay).unwrap();
+
+        let mut expected = cmd(0x10, &[0x11; ROW_BYTES * HEIGHT as usize]);
+        expected.extend(cmd(0x04, &[]));
+        expected.extend(cmd(0x12, &[0x00]));
+        expected.extend(cmd(0x02, &[0x00]));
+        assert_eq!(spi.sent, expected);
+    }
+
+    #[test]
+    fn rows_are_streamed_like_a_whole_frame() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        let frame: Vec<u8> = (0..ROW_BYTES * HEIGHT as usize)
+            .map(|i| (i / ROW_BYTES) as u8)
+            .collect();
+
+        spi.sent.clear();
+        epd.update_frame(&mut spi, &frame, &mut NoDelay).unwrap();
+        let whole = core::mem::take(&mut spi.sent);
+
+        epd.update_frame_by_rows(&mut spi, &mut NoDelay, |y, row| row.fill(y as u8))
+            .unwrap();
+        assert_eq!(spi.sent, whole);
+    }
+
+    #[test]
+    fn partial_frames_and_luts_are_rejected() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+
+        assert_eq!(
+            epd.update_partial_frame(&mut spi, &[0; 8], 0, 0, 8, 8, &mut NoDelay),
+            Err(Error::PartialUpdateUnsupported)
+        );
+        assert_eq!(epd.set_lut(&mut spi, None), Ok(()));
+        assert_eq!(epd.set_lut(&mut spi, Some(RefreshLut::Full)), Ok(()));
+        assert_eq!(
+            epd.set_lut(&mut spi, Some(RefreshLut::Quick)),
+            Err(Error::UnsupportedLut)
+        );
+        assert!(spi.sent.is_empty());
+    }
+}
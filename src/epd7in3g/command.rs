@@ -0,0 +1,76 @@
+//! SPI Commands for the Waveshare 7.3" (G) E-Ink Display
+
+use crate::traits;
+
+/// Epd7in3g commands
+///
+/// Should rarely (never?) be needed directly.
+///
+/// Most of the registers are undocumented, the names follow the UC81xx controllers with the
+/// same addresses and the values come from the vendor driver.
+#[allow(dead_code)]
+#[derive(Copy, Clone)]
+pub(crate) enum Command {
+    /// Set Resolution, LUT selection, gate scan direction, source shift direction, booster
+    /// switch, soft reset
+    PanelSetting = 0x00,
+    /// Selecting internal and external power
+    PowerSetting = 0x01,
+    /// Turns off the charge pump, T-con, source and gate driver and VCOM, the BUSY signal
+    /// is "0" until the power is off
+    PowerOff = 0x02,
+    /// Setting Power OFF sequence
+    PowerOffSequenceSetting = 0x03,
+    /// Turning On the Power, the BUSY signal is "0" until the power is on
+    PowerOn = 0x04,
+    /// Booster soft start of the gate driving voltages
+    BoosterSoftStart1 = 0x05,
+    /// Booster soft start of the source driving voltages
+    BoosterSoftStart2 = 0x06,
+    /// Deep sleep, returns to standby only with a hardware reset
+    ///
+    /// The only parameter is a check code, the command is executed if it is 0xA5.
+    DeepSleep = 0x07,
+    /// Booster soft start of the VCOM driving voltage
+    BoosterSoftStart3 = 0x08,
+    /// Writes the image, 2 bits per pixel, the first pixel in the upper bits
+    DataStartTransmission = 0x10,
+    /// Refreshes the panel according to the SRAM data
+    ///
+    /// The only parameter is 0x01, the BUSY signal is "0" until the refresh is finished.
+    DisplayRefresh = 0x12,
+    /// Frame rate of the waveform
+    PllControl = 0x30,
+    /// Border output and the interval between the VCOM and the data output
+    VcomAndDataIntervalSetting = 0x50,
+    /// Non overlapping period of the gate and source outputs
+    TconSetting = 0x60,
+    /// Horizontal and vertical resolution, two bytes each
+    TconResolution = 0x61,
+    /// Undocumented, sent by the vendor driver before the power on
+    PowerOnSetting = 0x84,
+    /// Power saving during the refresh
+    PowerSaving = 0xE3,
+    /// Undocumented key sent at the start of the vendor init sequence
+    UnlockKey = 0xAA,
+}
+
+impl traits::Command for Command {
+    /// Returns the address of the command
+    fn address(self) -> u8 {
+        self as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::Command as CommandTrait;
+
+    #[test]
+    fn command_addr() {
+        assert_eq!(Command::UnlockKey.address(), 0xAA);
+        assert_eq!(Command::DataStartTransmission.address(), 0x10);
+        assert_eq!(Command::DisplayRefresh.address(), 0x12);
+    }
+}
@@ -0,0 +1,162 @@
+use crate::color::QuadColor;
+use crate::epd7in3g::{DEFAULT_BACKGROUND_COLOR, HEIGHT, WIDTH};
+use crate::graphics::{DisplayRotation, QuadDisplay};
+use crate::traits::FrameBuffer;
+use embedded_graphics_core::prelude::*;
+
+/// Full size buffer for use with the 7in3g EPD
+///
+/// Can also be manually constructed:
+/// `buffer: [DEFAULT_BACKGROUND_COLOR.get_byte_value(); WIDTH / 4 * HEIGHT]`
+pub struct Display7in3g {
+    buffer: [u8; WIDTH as usize * HEIGHT as usize / 4],
+    rotation: DisplayRotation,
+}
+
+impl Default for Display7in3g {
+    fn default() -> Self {
+        Display7in3g {
+            buffer: [DEFAULT_BACKGROUND_COLOR.get_byte_value();
+                WIDTH as usize * HEIGHT as usize / 4],
+            rotation: DisplayRotation::default(),
+        }
+    }
+}
+
+impl DrawTarget for Display7in3g {
+    type Color = QuadColor;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for pixel in pixels {
+            self.draw_helper(WIDTH, HEIGHT, pixel)?;
+        }
+        Ok(())
+    }
+}
+
+impl OriginDimensions for Display7in3g {
+    fn size(&self) -> Size {
+        match self.rotation {
+            DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => Size::new(WIDTH, HEIGHT),
+            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => Size::new(HEIGHT, WIDTH),
+        }
+    }
+}
+
+impl QuadDisplay for Display7in3g {
+    fn buffer(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    fn get_mut_buffer(&mut self) -> &mut [u8] {
+        &mut self.buffer
+    }
+
+    fn set_rotation(&mut self, rotation: DisplayRotation) {
+        self.rotation = rotation;
+    }
+
+    fn rotation(&self) -> DisplayRotation {
+        self.rotation
+    }
+}
+
+impl FrameBuffer for Display7in3g {
+    type Color = QuadColor;
+
+    fn as_bytes(&self) -> &[u8] {
+        self.buffer()
+    }
+
+    fn as_bytes_mut(&mut self) -> &mut [u8] {
+        self.get_mut_buffer()
+    }
+
+    fn clear(&mut self, color: QuadColor) {
+        self.clear_buffer(color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epd7in3g;
+    use crate::graphics::{DisplayRotation, QuadDisplay};
+    use embedded_graphics::{
+        prelude::*,
+        primitives::{Line, PrimitiveStyle},
+    };
+
+    // test buffer length
+    #[test]
+    fn graphics_size() {
+        let display = Display7in3g::default();
+        assert_eq!(display.buffer().len(), 800 * 480 / 4);
+    }
+
+    // test default background color on all bytes
+    #[test]
+    fn graphics_default() {
+        let display = Display7in3g::default();
+        for &byte in display.buffer() {
+            assert_eq!(byte, epd7in3g::DEFAULT_BACKGROUND_COLOR.get_byte_value());
+        }
+    }
+
+    #[test]
+    fn graphics_rotation_0() {
+        let mut display = Display7in3g::default();
+
+        let _ = Line::new(Point::new(0, 0), Point::new(2, 0))
+            .into_styled(PrimitiveStyle::with_stroke(QuadColor::Red, 1))
+            .draw(&mut display);
+
+        let buffer = display.buffer();
+        assert_eq!(
+            QuadColor::split_byte(buffer[0]),
+            [
+                QuadColor::Red,
+                QuadColor::Red,
+                QuadColor::Red,
+                epd7in3g::DEFAULT_BACKGROUND_COLOR
+            ]
+        );
+        for &byte in buffer.iter().skip(1) {
+            assert_eq!(byte, epd7in3g::DEFAULT_BACKGROUND_COLOR.get_byte_value());
+        }
+    }
+
+    #[test]
+    fn graphics_rotation_270() {
+        let mut display = Display7in3g::default();
+        display.set_rotation(DisplayRotation::Rotate270);
+
+        let _ = Line::new(
+            Point::new(HEIGHT as i32 - 1, 0),
+            Point::new(HEIGHT as i32 - 1, 3),
+        )
+        .into_styled(PrimitiveStyle::with_stroke(QuadColor::Yellow, 1))
+        .draw(&mut display);
+
+        let buffer = display.buffer();
+        assert_eq!(buffer[0], QuadColor::Yellow.get_byte_value());
+        for &byte in buffer.iter().skip(1) {
+            assert_eq!(byte, epd7in3g::DEFAULT_BACKGROUND_COLOR.get_byte_value());
+        }
+    }
+
+    #[test]
+    fn graphics_colors() {
+        let mut display = Display7in3g::default();
+        for c in QuadColor::ALL {
+            display.clear_buffer(c);
+            for &b in display.buffer() {
+                assert_eq!(QuadColor::split_byte(b), [c; 4]);
+            }
+        }
+    }
+}
@@ -0,0 +1,502 @@
+//! A simple Driver for the Waveshare 7.3" (G) E-Ink Display via SPI
+//!
+//! The G panels show black, white, yellow and red with 2 bits per pixel in a single plane,
+//! see [QuadColor]. The first pixel of a byte is in the upper two bits. The controller
+//! doesn't support partial refreshes or LUTs from the registers.
+//!
+//! # References
+//!
+//! - [Waveshare wiki](https://www.waveshare.com/wiki/7.3inch_e-Paper_HAT_(G)_Manual)
+//! - [Waveshare C driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_7in3g.c)
+
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::{InputPin, OutputPin},
+};
+
+use crate::check_buffer_len;
+use crate::color::QuadColor;
+use crate::error::Error;
+use crate::interface::DisplayInterface;
+use crate::monitor::{FrameCheck, FrameMonitor};
+use crate::power::{PowerState, Transition};
+use crate::timing::ResetTiming;
+use crate::traits::{InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::{AutoCs, CsStrategy, ResetPin};
+
+pub(crate) mod command;
+use self::command::Command;
+
+#[cfg(feature = "graphics")]
+mod graphics;
+#[cfg(feature = "graphics")]
+pub use self::graphics::Display7in3g;
+
+/// Width of the display
+pub const WIDTH: u32 = 800;
+/// Height of the display
+pub const HEIGHT: u32 = 480;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: QuadColor = QuadColor::White;
+const IS_BUSY_LOW: bool = true;
+/// Longest time in milliseconds to wait for the busy pin before giving up with
+/// [Error::Timeout]
+pub const BUSY_TIMEOUT_MS: u32 = 60_000;
+/// Fastest SPI clock of the controller in Hz, see
+/// [check_spi_frequency()](crate::check_spi_frequency())
+pub const MAX_SPI_FREQ_HZ: u32 = 10_000_000;
+
+/// Bytes of a frame, 4 pixels per byte
+const FRAME_BYTES: u32 = WIDTH / 4 * HEIGHT;
+
+/// Epd7in3g driver
+///
+pub struct Epd7in3g<SPI, CS, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+    /// Background Color
+    color: QuadColor,
+    /// Mode of the controller
+    state: PowerState,
+    /// Generation of the frames
+    monitor: FrameMonitor,
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd7in3g<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        // reset the device
+        self.state = self.state.check(Transition::Init)?;
+        self.interface.reset(delay, 20, 2)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        delay.delay_ms(30);
+
+        self.cmd_with_data(
+            spi,
+            Command::UnlockKey,
+            &[0x49, 0x55, 0x20, 0x08, 0x09, 0x18],
+        )?;
+        self.cmd_with_data(spi, Command::PowerSetting, &[0x3F])?;
+        self.cmd_with_data(spi, Command::PanelSetting, &[0x4F, 0x69])?;
+
+        self.cmd_with_data(spi, Command::BoosterSoftStart1, &[0x40, 0x1F, 0x1F, 0x2C])?;
+        self.cmd_with_data(spi, Command::BoosterSoftStart3, &[0x6F, 0x1F, 0x1F, 0x22])?;
+        self.cmd_with_data(spi, Command::BoosterSoftStart2, &[0x6F, 0x1F, 0x14, 0x14])?;
+
+        self.cmd_with_data(
+            spi,
+            Command::PowerOffSequenceSetting,
+            &[0x00, 0x54, 0x00, 0x44],
+        )?;
+        self.cmd_with_data(spi, Command::TconSetting, &[0x02, 0x00])?;
+        // the PLL has to be set for the second version of the controller
+        self.cmd_with_data(spi, Command::PllControl, &[0x08])?;
+
+        // border and data interval of the vendor driver
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x3F])?;
+
+        self.cmd_with_data(
+            spi,
+            Command::TconResolution,
+            &[
+                (WIDTH >> 8) as u8,
+                WIDTH as u8,
+                (HEIGHT >> 8) as u8,
+                HEIGHT as u8,
+            ],
+        )?;
+
+        self.cmd_with_data(spi, Command::PowerSaving, &[0x2F])?;
+        self.cmd_with_data(spi, Command::PowerOnSetting, &[0x01])?;
+
+        self.command(spi, Command::PowerOn)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        Ok(())
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd7in3g<SPI, AutoCs, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Creates the driver for a board without a CS GPIO, see [AutoCs]
+    pub fn new_without_cs(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new(spi, AutoCs, busy, dc, rst, delay)
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd7in3g<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    type DisplayColor = QuadColor;
+    fn new(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_background(spi, cs, busy, dc, rst, delay, DEFAULT_BACKGROUND_COLOR)
+    }
+
+    fn new_with_background(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        color: Self::DisplayColor,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(cs, busy, dc, rst);
+
+        let mut epd = Epd7in3g {
+            interface,
+            color,
+            state: PowerState::Active,
+            monitor: FrameMonitor::default(),
+        };
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.check_reset_pin()?;
+        self.init(spi, delay)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        let next = self.state.check(Transition::Sleep)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.cmd_with_data(spi, Command::PowerOff, &[0x00])?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
+        self.state = next;
+        Ok(())
+    }
+
+    /// Writes a frame with 2 bits per pixel, see [QuadColor::colors_byte()]
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        let result = self.write_frame(spi, buffer, delay);
+        self.monitor.written(&result);
+        result
+    }
+
+    /// The controller can't update windows, returns [Error::PartialUpdateUnsupported]
+    fn update_partial_frame(
+        &mut self,
+        _spi: &mut SPI,
+        _buffer: &[u8],
+        _x: u32,
+        _y: u32,
+        _width: u32,
+        _height: u32,
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        Err(Error::PartialUpdateUnsupported)
+    }
+
+    /// Refreshes the panel and switches the power off, the next update switches it on again
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        let result = self.refresh(spi, delay);
+        self.monitor.displayed(&result);
+        result
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        let result = self.write_clear_frame(spi, delay);
+        self.monitor.written(&result);
+        result?;
+        self.display_frame(spi, delay)
+    }
+
+    fn set_background_color(&mut self, color: QuadColor) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &QuadColor {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn full_refresh_ms(&self) -> u32 {
+        24_000
+    }
+
+    /// 2 bits per pixel
+    fn buffer_len(&self) -> usize {
+        crate::buffer_len(2 * WIDTH as usize, HEIGHT as usize)
+    }
+
+    /// The waveform of the OTP is the only one, anything but [RefreshLut::Full] returns
+    /// [Error::UnsupportedLut]
+    fn set_lut(
+        &mut self,
+        _spi: &mut SPI,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        match refresh_rate {
+            None | Some(RefreshLut::Full) => Ok(()),
+            Some(_) => Err(Error::UnsupportedLut),
+        }
+    }
+
+    fn is_busy(&self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
+    fn send_command_raw(&mut self, spi: &mut SPI, command: u8) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn send_data_raw(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        self.interface.data(spi, data)
+    }
+
+    fn set_spi_chunk_size(&mut self, chunk_size: usize) {
+        self.interface.set_chunk_size(chunk_size);
+    }
+
+    fn set_reset_timing(&mut self, timing: Option<ResetTiming>) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    #[cfg(feature = "test-patterns")]
+    fn set_test_pattern(&mut self, enable: bool) {
+        let pattern = crate::test_pattern::FillPattern::new(self.width(), 2);
+        self.interface.set_fill_pattern(enable.then_some(pattern));
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> Epd7in3g<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Current mode of the controller
+    pub fn power_state(&self) -> PowerState {
+        self.state
+    }
+
+    /// Checks that the panel shows the last frame
+    ///
+    /// The controller can't read back its RAM, so this only checks the bookkeeping of the
+    /// driver: it finds updates and refreshes which failed while their errors were ignored,
+    /// but not a controller which lost its RAM. See [FrameCheck::Consistent].
+    pub fn verify_last_frame(&mut self, _spi: &mut SPI) -> Result<FrameCheck, Error<SPI::Error>> {
+        Ok(self.monitor.check())
+    }
+
+    fn write_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        // two bits per pixel
+        check_buffer_len(buffer, 2 * WIDTH, HEIGHT)?;
+        self.state.check(Transition::Update)?;
+        self.power_on(spi, delay)?;
+        self.cmd_with_data(spi, Command::DataStartTransmission, buffer)
+    }
+
+    fn write_clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Update)?;
+        self.power_on(spi, delay)?;
+        self.command(spi, Command::DataStartTransmission)?;
+        self.interface
+            .data_x_times(spi, self.color.get_byte_value(), FRAME_BYTES)
+    }
+
+    fn refresh(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.state.check(Transition::Refresh)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.interface.start_measurement(RefreshLut::Full.into());
+        self.cmd_with_data(spi, Command::DisplayRefresh, &[0x01])?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.cmd_with_data(spi, Command::PowerOff, &[0x00])?;
+        self.wait_until_idle_with_timeout(delay)
+    }
+
+    /// Switches the power on again, [display_frame()](WaveshareDisplay::display_frame())
+    /// switches it off after every refresh
+    fn power_on(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle_with_timeout(delay)?;
+        self.command(spi, Command::PowerOn)?;
+        self.wait_until_idle_with_timeout(delay)
+    }
+
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    fn wait_until_idle_with_timeout(&mut self, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .wait_until_idle_with_timeout(delay, IS_BUSY_LOW, BUSY_TIMEOUT_MS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{cmd, test_epd, DcPin, HighPin, NoDelay, RecordingSpi};
+
+    type TestEpd = Epd7in3g<RecordingSpi, HighPin, HighPin, DcPin, HighPin, NoDelay>;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 800);
+        assert_eq!(HEIGHT, 480);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, QuadColor::White);
+    }
+
+    #[test]
+    fn init_ends_with_power_on() {
+        let (spi, _epd) = test_epd::<TestEpd, _>();
+        assert_eq!(
+            spi.sent[..7],
+            cmd(0xAA, &[0x49, 0x55, 0x20, 0x08, 0x09, 0x18])[..]
+        );
+        let resolution = cmd(0x61, &[0x03, 0x20, 0x01, 0xE0]);
+        assert!(spi.sent.windows(5).any(|w| w == &resolution[..]));
+        assert_eq!(spi.sent.last(), Some(&(true, 0x04)));
+    }
+
+    #[test]
+    fn clear_frame_in_white() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+        epd.clear_frame(&mut spi, &mut NoDelay).unwrap();
+
+        let mut expected = cmd(0x04, &[]);
+        expected.extend(cmd(0x10, &[0x55; FRAME_BYTES as usize]));
+        expected.extend(cmd(0x12, &[0x01]));
+        expected.extend(cmd(0x02, &[0x00]));
+        assert_eq!(spi.sent, expected);
+    }
+
+    #[test]
+    fn update_frame_checks_the_length() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        assert_eq!(epd.buffer_len(), 800 / 4 * 480);
+        assert_eq!(
+            epd.update_frame(&mut spi, &[0x55; 800 / 8 * 480], &mut NoDelay),
+            Err(Error::BufferSize {
+                expected: 800 / 4 * 480,
+                got: 800 / 8 * 480,
+            })
+        );
+    }
+
+    #[test]
+    fn verify_last_frame_checks_the_bookkeeping() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        assert_eq!(epd.verify_last_frame(&mut spi), Ok(FrameCheck::NoFrame));
+
+        epd.clear_frame(&mut spi, &mut NoDelay).unwrap();
+        assert_eq!(
+            epd.verify_last_frame(&mut spi),
+            Ok(FrameCheck::Consistent { generation: 1 })
+        );
+
+        let _ = epd.update_frame(&mut spi, &[0x00; 10], &mut NoDelay);
+        let _ = epd.display_frame(&mut spi, &mut NoDelay);
+        assert_eq!(
+            epd.verify_last_frame(&mut spi),
+            Ok(FrameCheck::Stale { generation: 1 })
+        );
+
+        let buffer = [0x00; FRAME_BYTES as usize];
+        epd.update_and_display_frame(&mut spi, &buffer, &mut NoDelay)
+            .unwrap();
+        assert_eq!(
+            epd.verify_last_frame(&mut spi),
+            Ok(FrameCheck::Consistent { generation: 2 })
+        );
+    }
+
+    #[test]
+    fn partial_frames_and_luts_are_rejected() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+
+        assert_eq!(
+            epd.update_partial_frame(&mut spi, &[0; 8], 0, 0, 8, 8, &mut NoDelay),
+            Err(Error::PartialUpdateUnsupported)
+        );
+        assert_eq!(epd.set_lut(&mut spi, None), Ok(()));
+        assert_eq!(epd.set_lut(&mut spi, Some(RefreshLut::Full)), Ok(()));
+        assert_eq!(
+            epd.set_lut(&mut spi, Some(RefreshLut::Quick)),
+            Err(Error::UnsupportedLut)
+        );
+        assert!(spi.sent.is_empty());
+    }
+}
@@ -14,9 +14,14 @@ use embedded_hal::{
     digital::v2::{InputPin, OutputPin},
 };
 
+use crate::check_buffer_len;
 use crate::color::Color;
+use crate::error::Error;
 use crate::interface::DisplayInterface;
+use crate::timing::ResetTiming;
 use crate::traits::{InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::window::WindowLimits;
+use crate::{AutoCs, CsStrategy, ResetPin};
 
 pub(crate) mod command;
 use self::command::Command;
@@ -30,9 +35,19 @@ pub use self::graphics::Display7in5;
 pub const WIDTH: u32 = 880;
 /// Height of the display
 pub const HEIGHT: u32 = 528;
+/// Limits of the partial window registers: 10 bit x and y registers, the whole panel fits
+pub const WINDOW_LIMITS: WindowLimits = WindowLimits::new(WIDTH, HEIGHT, 1023, 1023);
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White; // Inverted for HD as compared to 7in5 v2 (HD: 0xFF = White)
 const IS_BUSY_LOW: bool = false;
+/// Longest time in milliseconds to wait for the busy pin before giving up with
+/// [Error::Timeout]
+pub const BUSY_TIMEOUT_MS: u32 = 10_000;
+/// Fastest SPI clock of the controller in Hz, see
+/// [check_spi_frequency()](crate::check_spi_frequency())
+pub const MAX_SPI_FREQ_HZ: u32 = 20_000_000;
+/// Number of gates the RAM is set up for in `init`, one more than the start of the y window
+const RAM_LINES: u32 = 0x2B0;
 
 /// EPD7in5 (HD) driver
 ///
@@ -47,29 +62,29 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST,
     for Epd7in5<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         // Reset the device
-        self.interface.reset(delay, 10, 2);
+        self.interface.reset(delay, 10, 2)?;
 
         // HD procedure as described here:
         // https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/python/lib/waveshare_epd/epd7in5_HD.py
         // and as per specs:
         // https://www.waveshare.com/w/upload/2/27/7inch_HD_e-Paper_Specification.pdf
 
-        self.wait_until_idle();
+        self.wait_until_idle_with_timeout(delay)?;
         self.command(spi, Command::SwReset)?;
-        self.wait_until_idle();
+        self.wait_until_idle_with_timeout(delay)?;
 
         self.cmd_with_data(spi, Command::AutoWriteRed, &[0xF7])?;
-        self.wait_until_idle();
+        self.wait_until_idle_with_timeout(delay)?;
         self.cmd_with_data(spi, Command::AutoWriteBw, &[0xF7])?;
-        self.wait_until_idle();
+        self.wait_until_idle_with_timeout(delay)?;
 
         self.cmd_with_data(spi, Command::SoftStart, &[0xAE, 0xC7, 0xC3, 0xC0, 0x40])?;
 
@@ -87,7 +102,7 @@ where
         self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xB1])?;
 
         self.command(spi, Command::MasterActivation)?;
-        self.wait_until_idle();
+        self.wait_until_idle_with_timeout(delay)?;
 
         self.cmd_with_data(spi, Command::SetRamXAc, &[0x00, 0x00])?;
         self.cmd_with_data(spi, Command::SetRamYAc, &[0x00, 0x00])?;
@@ -96,14 +111,34 @@ where
     }
 }
 
+impl<SPI, BUSY, DC, RST, DELAY> Epd7in5<SPI, AutoCs, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Creates the driver for a board without a CS GPIO, see [AutoCs]
+    pub fn new_without_cs(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new(spi, AutoCs, busy, dc, rst, delay)
+    }
+}
+
 impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
     for Epd7in5<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
     type DisplayColor = Color;
@@ -114,10 +149,20 @@ where
         dc: DC,
         rst: RST,
         delay: &mut DELAY,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(cs, busy, dc, rst);
-        let color = DEFAULT_BACKGROUND_COLOR;
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_background(spi, cs, busy, dc, rst, delay, DEFAULT_BACKGROUND_COLOR)
+    }
 
+    fn new_with_background(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        color: Self::DisplayColor,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(cs, busy, dc, rst);
         let mut epd = Epd7in5 { interface, color };
 
         epd.init(spi, delay)?;
@@ -125,12 +170,13 @@ where
         Ok(epd)
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.check_reset_pin()?;
         self.init(spi, delay)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle_with_timeout(delay)?;
         self.cmd_with_data(spi, Command::DeepSleep, &[0x01])?;
         Ok(())
     }
@@ -139,30 +185,48 @@ where
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
-        _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle();
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer, WIDTH, HEIGHT)?;
+        self.wait_until_idle_with_timeout(delay)?;
         self.cmd_with_data(spi, Command::SetRamYAc, &[0x00, 0x00])?;
         self.cmd_with_data(spi, Command::WriteRamBw, buffer)?;
         self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xF7])?;
         Ok(())
     }
 
+    /// Writes the window into the b/w RAM, the rest of the RAM keeps the previous frame
+    ///
+    /// The window is refreshed together with the whole panel by
+    /// [display_frame()](WaveshareDisplay::display_frame()).
     fn update_partial_frame(
         &mut self,
-        _spi: &mut SPI,
-        _buffer: &[u8],
-        _x: u32,
-        _y: u32,
-        _width: u32,
-        _height: u32,
-    ) -> Result<(), SPI::Error> {
-        unimplemented!();
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        WINDOW_LIMITS.validate_aligned(x, y, width, height)?;
+        check_buffer_len(buffer, width, height)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.set_ram_x(spi, x, x + width - 1)?;
+        // the y counter counts down and wraps around inside of the y window set in `init`,
+        // line y of a full frame ends up at the address (RAM_LINES - y) % RAM_LINES
+        let ram_y = (RAM_LINES - y) % RAM_LINES;
+        self.cmd_with_data(spi, Command::SetRamYAc, &[ram_y as u8, (ram_y >> 8) as u8])?;
+        self.cmd_with_data(spi, Command::WriteRamBw, buffer)?;
+        // full frames only reset the y counter
+        self.set_ram_x(spi, 0, WIDTH - 1)?;
+        self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xF7])?;
+        Ok(())
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.command(spi, Command::MasterActivation)?;
-        self.wait_until_idle();
+        self.wait_until_idle_with_timeout(delay)?;
         Ok(())
     }
 
@@ -171,17 +235,17 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         let pixel_count = WIDTH * HEIGHT / 8;
         let background_color_byte = self.color.get_byte_value();
 
-        self.wait_until_idle();
+        self.wait_until_idle_with_timeout(delay)?;
         self.cmd_with_data(spi, Command::SetRamYAc, &[0x00, 0x00])?;
 
         for cmd in &[Command::WriteRamBw, Command::WriteRamRed] {
@@ -192,7 +256,7 @@ where
 
         self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xF7])?;
         self.command(spi, Command::MasterActivation)?;
-        self.wait_until_idle();
+        self.wait_until_idle_with_timeout(delay)?;
         Ok(())
     }
 
@@ -212,29 +276,59 @@ where
         HEIGHT
     }
 
+    fn full_refresh_ms(&self) -> u32 {
+        4000
+    }
+
+    /// Only the full LUT of the OTP is supported, other LUTs return [Error::UnsupportedLut]
     fn set_lut(
         &mut self,
         _spi: &mut SPI,
-        _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
-        unimplemented!();
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        match refresh_rate {
+            None | Some(RefreshLut::Full) => Ok(()),
+            Some(_) => Err(Error::UnsupportedLut),
+        }
     }
 
     fn is_busy(&self) -> bool {
         self.interface.is_busy(IS_BUSY_LOW)
     }
+
+    fn send_command_raw(&mut self, spi: &mut SPI, command: u8) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn send_data_raw(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        self.interface.data(spi, data)
+    }
+
+    fn set_spi_chunk_size(&mut self, chunk_size: usize) {
+        self.interface.set_chunk_size(chunk_size);
+    }
+
+    fn set_reset_timing(&mut self, timing: Option<ResetTiming>) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    #[cfg(feature = "test-patterns")]
+    fn set_test_pattern(&mut self, enable: bool) {
+        let pattern = crate::test_pattern::FillPattern::new(self.width(), 1);
+        self.interface.set_fill_pattern(enable.then_some(pattern));
+    }
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> Epd7in5<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd(spi, command)
     }
 
@@ -243,18 +337,32 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
-    fn wait_until_idle(&mut self) {
-        self.interface.wait_until_idle(IS_BUSY_LOW)
+    /// Sets the x window of the RAM and moves the x counter to its start, x is in pixels
+    fn set_ram_x(&mut self, spi: &mut SPI, start: u32, end: u32) -> Result<(), Error<SPI::Error>> {
+        self.cmd_with_data(
+            spi,
+            Command::SetRamXStartEnd,
+            &[start as u8, (start >> 8) as u8, end as u8, (end >> 8) as u8],
+        )?;
+        self.cmd_with_data(spi, Command::SetRamXAc, &[start as u8, (start >> 8) as u8])
+    }
+
+    fn wait_until_idle_with_timeout(&mut self, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .wait_until_idle_with_timeout(delay, IS_BUSY_LOW, BUSY_TIMEOUT_MS)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::{cmd, test_epd, DcPin, LowPin, NoDelay, RecordingSpi};
+
+    type TestEpd = Epd7in5<RecordingSpi, LowPin, LowPin, DcPin, LowPin, NoDelay>;
 
     #[test]
     fn epd_size() {
@@ -262,4 +370,53 @@ mod tests {
         assert_eq!(HEIGHT, 528);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn partial_window() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+
+        epd.update_partial_frame(&mut spi, &[0xAA; 2 * 3], 264, 2, 16, 3, &mut NoDelay)
+            .unwrap();
+        let expected = [
+            cmd(0x44, &[0x08, 0x01, 0x17, 0x01]),
+            cmd(0x4E, &[0x08, 0x01]),
+            cmd(0x4F, &[0xAE, 0x02]),
+            cmd(0x24, &[0xAA; 6]),
+            cmd(0x44, &[0x00, 0x00, 0x6F, 0x03]),
+            cmd(0x4E, &[0x00, 0x00]),
+            cmd(0x22, &[0xF7]),
+        ]
+        .concat();
+        assert_eq!(spi.sent, expected);
+
+        // the first line stays at the start of the RAM like in full frames
+        spi.sent.clear();
+        epd.update_partial_frame(&mut spi, &[0x00], 0, 0, 8, 1, &mut NoDelay)
+            .unwrap();
+        assert_eq!(spi.sent[8..11], cmd(0x4F, &[0x00, 0x00])[..]);
+    }
+
+    #[test]
+    fn partial_window_is_validated() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+
+        assert_eq!(
+            epd.update_partial_frame(&mut spi, &[0x00; 2], 872, 0, 16, 1, &mut NoDelay),
+            Err(Error::WindowOutOfRange)
+        );
+        assert_eq!(
+            epd.update_partial_frame(&mut spi, &[0x00; 2], 0, 0, 8, 1, &mut NoDelay),
+            Err(Error::BufferSize {
+                expected: 1,
+                got: 2
+            })
+        );
+        assert_eq!(
+            epd.set_lut(&mut spi, Some(RefreshLut::Quick)),
+            Err(Error::UnsupportedLut)
+        );
+        assert!(spi.sent.is_empty());
+    }
 }
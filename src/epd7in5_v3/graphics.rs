@@ -1,6 +1,7 @@
 use crate::color::TriColor;
 use crate::epd7in5_v3::{DEFAULT_BACKGROUND_COLOR, HEIGHT, NUM_DISPLAY_BITS, WIDTH};
 use crate::graphics::{DisplayRotation, TriDisplay};
+use crate::traits::FrameBuffer;
 use embedded_graphics_core::prelude::*;
 
 /// Full size buffer for use with the 7in5 EPD
@@ -43,7 +44,10 @@ impl DrawTarget for Display7in5 {
 
 impl OriginDimensions for Display7in5 {
     fn size(&self) -> Size {
-        Size::new(WIDTH, HEIGHT)
+        match self.rotation {
+            DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => Size::new(WIDTH, HEIGHT),
+            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => Size::new(HEIGHT, WIDTH),
+        }
     }
 }
 
@@ -76,6 +80,10 @@ impl TriDisplay for Display7in5 {
         &self.buffer[self.chromatic_offset()..]
     }
 
+    fn color_rendering(&self) -> crate::graphics::DisplayColorRendering {
+        crate::graphics::DisplayColorRendering::Negative
+    }
+
     fn clear_buffer(&mut self, background_color: TriColor) {
         let offset = self.chromatic_offset();
 
@@ -92,6 +100,22 @@ impl TriDisplay for Display7in5 {
     }
 }
 
+impl FrameBuffer for Display7in5 {
+    type Color = TriColor;
+
+    fn as_bytes(&self) -> &[u8] {
+        self.buffer()
+    }
+
+    fn as_bytes_mut(&mut self) -> &mut [u8] {
+        self.get_mut_buffer()
+    }
+
+    fn clear(&mut self, color: TriColor) {
+        self.clear_buffer(color);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
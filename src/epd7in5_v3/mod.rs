@@ -8,15 +8,25 @@
 //!
 
 use embedded_hal::{
-    blocking::{delay::*, spi::Write},
+    blocking::{
+        delay::*,
+        spi::{Transfer, Write},
+    },
     digital::v2::{InputPin, OutputPin},
 };
 
+use crate::check_buffer_len;
 use crate::color::TriColor;
+use crate::error::Error;
+use crate::external_sensor;
 use crate::interface::DisplayInterface;
+use crate::panel_setting::PanelSettingConfig;
+use crate::timing::ResetTiming;
 use crate::traits::{
     InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
 };
+use crate::window::WindowLimits;
+use crate::{AutoCs, CsStrategy, ResetPin};
 
 pub(crate) mod command;
 use self::command::Command;
@@ -30,14 +40,73 @@ pub use self::graphics::Display7in5;
 pub const WIDTH: u32 = 800;
 /// Height of the display
 pub const HEIGHT: u32 = 480;
+/// Limits of the partial window registers: 10 bit x and y registers, the whole panel fits
+pub const WINDOW_LIMITS: WindowLimits = WindowLimits::new(WIDTH, HEIGHT, 1023, 1023);
 /// Default Background Color
-//pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
-
 pub const DEFAULT_BACKGROUND_COLOR: TriColor = TriColor::White;
+/// Default panel setting: black/white/red mode with the LUTs from the OTP
+pub const PANEL_SETTING: PanelSettingConfig = PanelSettingConfig::from_bits(0x0F);
 
 /// Number of bits for b/w buffer and same for chromatic buffer
 const NUM_DISPLAY_BITS: u32 = WIDTH * HEIGHT / 8;
 const IS_BUSY_LOW: bool = true;
+/// Longest time in milliseconds to wait for the busy pin before giving up with
+/// [Error::Timeout]
+pub const BUSY_TIMEOUT_MS: u32 = 60_000;
+/// Fastest SPI clock of the controller in Hz, see
+/// [check_spi_frequency()](crate::check_spi_frequency())
+pub const MAX_SPI_FREQ_HZ: u32 = 10_000_000;
+
+/// Value of the VcmDcSetting register for a VCOM of `mv` millivolts
+///
+/// The controller takes -100 mV to -4050 mV in steps of 50 mV, other values are `None`.
+fn vcom_register(mv: i16) -> Option<u8> {
+    if !(-4050..=-100).contains(&mv) || mv % 50 != 0 {
+        return None;
+    }
+    Some(((-100 - mv) / 50) as u8)
+}
+
+/// VCOM in millivolts for a value of the VcmDcSetting or ReadVcomValue register
+fn vcom_millivolts(register: u8) -> i16 {
+    -100 - 50 * i16::from(register & 0x7F)
+}
+
+/// Flags of the controller returned by [Epd7in5::read_status()]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Status {
+    /// The controller is still busy, the busy pin is low
+    pub busy: bool,
+    /// The power off sequence ran
+    pub power_off: bool,
+    /// The power on sequence ran
+    pub power_on: bool,
+    /// The data of the last transmission was received completely
+    pub data_received: bool,
+    /// The I2C master for the external temperature sensor is idle
+    pub i2c_idle: bool,
+    /// The last transfer of the I2C master failed
+    pub i2c_error: bool,
+    /// The partial window mode is active
+    pub partial: bool,
+}
+
+impl From<u8> for Status {
+    fn from(flags: u8) -> Self {
+        let bit = |n: u8| flags & (1 << n) != 0;
+        Status {
+            // the flag is active low
+            busy: !bit(0),
+            power_off: bit(1),
+            power_on: bit(2),
+            data_received: bit(3),
+            i2c_idle: bit(4),
+            i2c_error: bit(5),
+            partial: bit(6),
+        }
+    }
+}
 
 /// Epd7in5 (V3) driver
 ///
@@ -46,21 +115,25 @@ pub struct Epd7in5<SPI, CS, BUSY, DC, RST, DELAY> {
     interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
     /// Background Color
     color: TriColor,
+    /// Panel setting
+    panel_setting: PanelSettingConfig,
+    /// VCOM register set with `set_vcom()`, `None` for the value of the OTP
+    vcom: Option<u8>,
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
     for Epd7in5<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         // Reset the device
-        self.interface.reset(delay, 200, 4);
+        self.interface.reset(delay, 200, 4)?;
 
         // V2 procedure as described here:
         // https://github.com/waveshare/e-Paper/blob/master/RaspberryPi%26JetsonNano/python/lib/waveshare_epd/epd7in5bc_V2.py
@@ -71,11 +144,14 @@ where
         self.cmd_with_data(spi, Command::PowerSetting, &[0x07, 0x07, 0x3F, 0x3F])?;
         self.command(spi, Command::PowerOn)?;
         self.wait_until_idle(spi, delay)?;
-        self.cmd_with_data(spi, Command::PanelSetting, &[0x0F])?;
+        self.cmd_with_data(spi, Command::PanelSetting, &[self.panel_setting.bits()])?;
         //self.cmd_with_data(spi, Command::PllControl, &[0x06])?;
         self.cmd_with_data(spi, Command::TconResolution, &[0x03, 0x20, 0x01, 0xE0])?;
         self.cmd_with_data(spi, Command::DualSpi, &[0x00])?;
         self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x11, 0x07])?;
+        if let Some(vcom) = self.vcom {
+            self.cmd_with_data(spi, Command::VcmDcSetting, &[vcom])?;
+        }
         self.cmd_with_data(spi, Command::TconSetting, &[0x22])?;
         self.cmd_with_data(spi, Command::SpiFlashControl, &[0x00, 0x00, 0x00, 0x00])?;
         self.wait_until_idle(spi, delay)?;
@@ -87,10 +163,10 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareThreeColorDisplay<SPI, CS, BUSY, DC
     for Epd7in5<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
     fn update_color_frame(
@@ -98,7 +174,7 @@ where
         spi: &mut SPI,
         black: &[u8],
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_achromatic_frame(spi, black)?;
         self.update_chromatic_frame(spi, chromatic)
     }
@@ -106,7 +182,12 @@ where
     /// Update only the black/white data of the display.
     ///
     /// Finish by calling `update_chromatic_frame`.
-    fn update_achromatic_frame(&mut self, spi: &mut SPI, black: &[u8]) -> Result<(), SPI::Error> {
+    fn update_achromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        black: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(black, WIDTH, HEIGHT)?;
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
         self.interface.data(spi, black)?;
         Ok(())
@@ -119,7 +200,8 @@ where
         &mut self,
         spi: &mut SPI,
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(chromatic, WIDTH, HEIGHT)?;
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
         self.interface.data(spi, chromatic)?;
 
@@ -128,14 +210,34 @@ where
     }
 }
 
+impl<SPI, BUSY, DC, RST, DELAY> Epd7in5<SPI, AutoCs, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Creates the driver for a board without a CS GPIO, see [AutoCs]
+    pub fn new_without_cs(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new(spi, AutoCs, busy, dc, rst, delay)
+    }
+}
+
 impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
     for Epd7in5<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
     type DisplayColor = TriColor;
@@ -146,22 +248,38 @@ where
         dc: DC,
         rst: RST,
         delay: &mut DELAY,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(cs, busy, dc, rst);
-        let color = DEFAULT_BACKGROUND_COLOR;
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_background(spi, cs, busy, dc, rst, delay, DEFAULT_BACKGROUND_COLOR)
+    }
 
-        let mut epd = Epd7in5 { interface, color };
+    fn new_with_background(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        color: Self::DisplayColor,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(cs, busy, dc, rst);
+        let mut epd = Epd7in5 {
+            interface,
+            color,
+            panel_setting: PANEL_SETTING,
+            vcom: None,
+        };
 
         epd.init(spi, delay)?;
 
         Ok(epd)
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.check_reset_pin()?;
         self.init(spi, delay)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.command(spi, Command::PowerOff)?;
         self.wait_until_idle(spi, delay)?;
@@ -174,7 +292,8 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer, WIDTH, HEIGHT)?;
         self.wait_until_idle(spi, delay)?;
         self.cmd_with_data(spi, Command::DataStartTransmission2, buffer)?;
 
@@ -191,6 +310,7 @@ where
         Ok(())
     }
 
+    /// Partial windows aren't supported, returns [Error::PartialUpdateUnsupported]
     fn update_partial_frame(
         &mut self,
         _spi: &mut SPI,
@@ -199,11 +319,12 @@ where
         _y: u32,
         _width: u32,
         _height: u32,
-    ) -> Result<(), SPI::Error> {
-        unimplemented!();
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        Err(Error::PartialUpdateUnsupported)
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.command(spi, Command::DisplayRefresh)?;
         Ok(())
@@ -214,13 +335,13 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.command(spi, Command::DisplayRefresh)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.send_resolution(spi)?;
 
@@ -250,33 +371,117 @@ where
         HEIGHT
     }
 
+    fn full_refresh_ms(&self) -> u32 {
+        5000
+    }
+
+    /// The waveform of the OTP is the only one, anything but [RefreshLut::Full] returns
+    /// [Error::UnsupportedLut]
     fn set_lut(
         &mut self,
         _spi: &mut SPI,
-        _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
-        unimplemented!();
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        match refresh_rate {
+            None | Some(RefreshLut::Full) => Ok(()),
+            Some(_) => Err(Error::UnsupportedLut),
+        }
     }
 
     fn is_busy(&self) -> bool {
         self.interface.is_busy(IS_BUSY_LOW)
     }
+
+    fn send_command_raw(&mut self, spi: &mut SPI, command: u8) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn send_data_raw(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        self.interface.data(spi, data)
+    }
+
+    fn set_spi_chunk_size(&mut self, chunk_size: usize) {
+        self.interface.set_chunk_size(chunk_size);
+    }
+
+    fn set_reset_timing(&mut self, timing: Option<ResetTiming>) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    #[cfg(feature = "test-patterns")]
+    fn set_test_pattern(&mut self, enable: bool) {
+        let pattern = crate::test_pattern::FillPattern::new(self.width(), 1);
+        self.interface.set_fill_pattern(enable.then_some(pattern));
+    }
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> Epd7in5<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    /// Replaces the [PANEL_SETTING] and writes it to the controller
+    ///
+    /// Returns [Error::InvalidPanelSetting] for the settings rejected by
+    /// [PanelSettingConfig::validate()] and for LUTs from the registers, the driver only uses
+    /// the LUTs of the OTP. The setting is kept for [wake_up()](WaveshareDisplay::wake_up()).
+    pub fn set_panel_setting(
+        &mut self,
+        spi: &mut SPI,
+        config: PanelSettingConfig,
+    ) -> Result<(), Error<SPI::Error>> {
+        config.validate_otp()?;
+        self.interface.wait_until_idle(IS_BUSY_LOW);
+        self.panel_setting = config;
+        self.cmd_with_data(spi, Command::PanelSetting, &[config.bits()])
+    }
+
+    /// Current panel setting
+    pub fn panel_setting(&self) -> PanelSettingConfig {
+        self.panel_setting
+    }
+
+    /// Powers the booster and regulators off without entering deep sleep
+    ///
+    /// Unlike [sleep()](WaveshareDisplay::sleep()) the controller keeps its registers and
+    /// [power_on()](Self::power_on()) is a lot cheaper than the reset and initialisation of
+    /// [wake_up()](WaveshareDisplay::wake_up()). Don't refresh the panel while it is powered
+    /// off.
+    pub fn power_off(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.command(spi, Command::PowerOff)?;
+        self.wait_until_idle(spi, delay)
+    }
+
+    /// Sets VCOM to `mv` millivolts and keeps it across [wake_up()](WaveshareDisplay::wake_up())
+    ///
+    /// By default the controller uses the VCOM of its OTP, which often doesn't match the one
+    /// printed on the flex cable of the panel. See [measure_vcom()](Self::measure_vcom()) to
+    /// find it. The controller takes -100 mV to -4050 mV in steps of 50 mV, other values return
+    /// [Error::InvalidVoltage] without sending anything.
+    pub fn set_vcom(&mut self, spi: &mut SPI, mv: i16) -> Result<(), Error<SPI::Error>> {
+        let vcom = vcom_register(mv).ok_or(Error::InvalidVoltage)?;
+        self.wait_until_idle_raw()?;
+        self.cmd_with_data(spi, Command::VcmDcSetting, &[vcom])?;
+        self.vcom = Some(vcom);
+        Ok(())
+    }
+
+    /// Powers the booster and regulators on again after [power_off()](Self::power_off())
+    pub fn power_on(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.command(spi, Command::PowerOn)?;
+        self.wait_until_idle(spi, delay)
+    }
+
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd(spi, command)
     }
 
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
         self.interface.data(spi, data)
     }
 
@@ -285,24 +490,33 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
-    fn wait_until_idle(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wait_until_idle(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        let mut waited_ms = 0;
         while self.interface.is_busy(IS_BUSY_LOW) {
+            if waited_ms >= BUSY_TIMEOUT_MS {
+                return Err(Error::Timeout);
+            }
             self.interface.cmd(spi, Command::GetStatus)?;
             delay.delay_ms(20);
+            waited_ms += 20;
         }
         Ok(())
     }
 
-    fn wait_until_idle_raw(&mut self) -> Result<(), SPI::Error> {
+    fn wait_until_idle_raw(&mut self) -> Result<(), Error<SPI::Error>> {
         self.interface.wait_until_idle(IS_BUSY_LOW);
         Ok(())
     }
 
-    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
         let w = self.width();
         let h = self.height();
 
@@ -314,9 +528,139 @@ where
     }
 }
 
+impl<SPI, CS, BUSY, DC, RST, DELAY> Epd7in5<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8> + Transfer<u8, Error = <SPI as Write<u8>>::Error>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Writes `value` to the register `reg` of the external temperature sensor
+    ///
+    /// The controller is the I2C master of the sensor, on some boards it is an LM75 compatible
+    /// sensor at address 0x48. Needs a board which connects the data line of the controller
+    /// to MISO.
+    pub fn external_sensor_write(
+        &mut self,
+        spi: &mut SPI,
+        reg: u8,
+        value: u8,
+    ) -> Result<(), Error<<SPI as Write<u8>>::Error>> {
+        self.wait_until_idle_raw()?;
+        self.cmd_with_data(
+            spi,
+            Command::TemperatureSensorWrite,
+            &[external_sensor::write_attributes(1, reg), value, 0x00],
+        )?;
+        // the I2C transaction runs after the command
+        self.wait_until_idle_raw()?;
+        Ok(())
+    }
+
+    /// Reads the first byte of the register `reg` of the external temperature sensor
+    ///
+    /// For the temperature register of an LM75 this is the temperature in whole °C as `i8`.
+    /// See [external_sensor_write()](Self::external_sensor_write()) for the requirements.
+    pub fn external_sensor_read(
+        &mut self,
+        spi: &mut SPI,
+        reg: u8,
+    ) -> Result<u8, Error<<SPI as Write<u8>>::Error>> {
+        self.wait_until_idle_raw()?;
+        // set the pointer of the sensor without data
+        self.cmd_with_data(
+            spi,
+            Command::TemperatureSensorWrite,
+            &[external_sensor::write_attributes(0, reg), 0x00, 0x00],
+        )?;
+        self.wait_until_idle_raw()?;
+
+        let mut value = [0u8; 2];
+        self.interface
+            .cmd_with_read(spi, Command::TemperatureSensorRead, &mut value)?;
+        self.wait_until_idle_raw()?;
+        Ok(value[0])
+    }
+
+    /// Reads the temperature of the sensor the controller uses, in whole °C
+    ///
+    /// This is the internal sensor unless the external one is selected with command 0x41.
+    /// Needs a board which connects the data line of the controller to MISO.
+    pub fn read_temperature(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<i8, Error<<SPI as Write<u8>>::Error>> {
+        self.wait_until_idle_raw()?;
+        // 11 bit two's complement in 1/8 °C, the first byte holds the whole degrees
+        let mut value = [0u8; 2];
+        self.interface
+            .cmd_with_read(spi, Command::TemperatureSensor, &mut value)?;
+        Ok(value[0] as i8)
+    }
+
+    /// Lets the controller measure VCOM on the panel and returns it in millivolts
+    ///
+    /// The measurement takes about 5 s and needs the power on, as it is after `new` and
+    /// [wake_up()](WaveshareDisplay::wake_up()). The result isn't used until it is passed to
+    /// [set_vcom()](Self::set_vcom()). Needs a board which connects the data line of the
+    /// controller to MISO.
+    pub fn measure_vcom(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<i16, Error<<SPI as Write<u8>>::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        // 5 s measurement time, start
+        self.cmd_with_data(spi, Command::AutoMeasurementVcom, &[0x11])?;
+        self.wait_until_idle(spi, delay)?;
+        let vcom = self.read_vcom(spi)?;
+        Ok(vcom_millivolts(vcom))
+    }
+
+    /// Reads the VCOM register, the result of the last auto measurement
+    ///
+    /// The lower 7 bits hold the VCOM in steps of -50 mV from -0.1 V, like the register of
+    /// `VcmDcSetting`. Needs a board which connects the data line of the controller to MISO.
+    pub fn read_vcom(&mut self, spi: &mut SPI) -> Result<u8, Error<<SPI as Write<u8>>::Error>> {
+        self.wait_until_idle_raw()?;
+        let mut value = [0u8];
+        self.interface
+            .cmd_with_read(spi, Command::ReadVcomValue, &mut value)?;
+        Ok(value[0] & 0x7F)
+    }
+
+    /// Reads the status flags of the controller
+    ///
+    /// Doesn't wait for the busy pin, so it can be polled during a refresh. Needs a board which
+    /// connects the data line of the controller to MISO.
+    pub fn read_status(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<Status, Error<<SPI as Write<u8>>::Error>> {
+        let mut flags = [0u8];
+        self.interface
+            .cmd_with_read(spi, Command::GetStatus, &mut flags)?;
+        Ok(Status::from(flags[0]))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::{self, DcPin, HighPin, NoDelay, RecordingSpi};
+    extern crate std;
+    use std::vec;
+
+    fn test_epd() -> (
+        RecordingSpi,
+        Epd7in5<RecordingSpi, HighPin, HighPin, DcPin, HighPin, NoDelay>,
+    ) {
+        let (mut spi, epd) = test_utils::test_epd();
+        spi.sent.clear();
+        (spi, epd)
+    }
 
     #[test]
     fn epd_size() {
@@ -324,4 +668,95 @@ mod tests {
         assert_eq!(HEIGHT, 480);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, TriColor::White);
     }
+
+    #[test]
+    fn read_temperature_and_vcom() {
+        let (mut spi, mut epd) = test_epd();
+
+        // -3.5 °C
+        spi.answers = vec![0xFC, 0x80];
+        assert_eq!(epd.read_temperature(&mut spi), Ok(-4));
+        assert_eq!(spi.sent, [(true, 0x40)]);
+
+        spi.sent.clear();
+        spi.answers = vec![0xA8];
+        assert_eq!(epd.read_vcom(&mut spi), Ok(0x28));
+        assert_eq!(spi.sent, [(true, 0x81)]);
+        assert!(spi.answers.is_empty());
+    }
+
+    #[test]
+    fn vcom_measurement_and_setting() {
+        let (mut spi, mut epd) = test_epd();
+
+        spi.answers = vec![0x28];
+        assert_eq!(epd.measure_vcom(&mut spi, &mut NoDelay), Ok(-2100));
+        assert_eq!(spi.sent, [(true, 0x80), (false, 0x11), (true, 0x81)]);
+
+        spi.sent.clear();
+        for mv in [-2125, -50, 0, -4100] {
+            assert_eq!(epd.set_vcom(&mut spi, mv), Err(Error::InvalidVoltage));
+        }
+        assert!(spi.sent.is_empty());
+
+        epd.set_vcom(&mut spi, -2100).unwrap();
+        assert_eq!(spi.sent, [(true, 0x82), (false, 0x28)]);
+
+        spi.sent.clear();
+        epd.wake_up(&mut spi, &mut NoDelay).unwrap();
+        assert!(spi
+            .sent
+            .windows(2)
+            .any(|w| w == [(true, 0x82), (false, 0x28)]));
+
+        assert_eq!(vcom_register(-100), Some(0x00));
+        assert_eq!(vcom_register(-4050), Some(0x4F));
+        assert_eq!(vcom_millivolts(0x4F), -4050);
+    }
+
+    #[test]
+    fn read_status_flags() {
+        let (mut spi, mut epd) = test_epd();
+
+        spi.answers = vec![0b0001_1101];
+        assert_eq!(
+            epd.read_status(&mut spi),
+            Ok(Status {
+                busy: false,
+                power_off: false,
+                power_on: true,
+                data_received: true,
+                i2c_idle: true,
+                i2c_error: false,
+                partial: false,
+            })
+        );
+        assert_eq!(spi.sent, [(true, 0x71)]);
+
+        spi.answers = vec![0b0110_0010];
+        let status = epd.read_status(&mut spi).unwrap();
+        assert!(status.busy && status.power_off && status.i2c_error && status.partial);
+    }
+
+    #[test]
+    fn partial_frames_and_luts_are_rejected() {
+        let (mut spi, mut epd) = test_epd();
+        spi.sent.clear();
+
+        assert_eq!(
+            epd.update_partial_frame(&mut spi, &[0; 8], 0, 0, 8, 8, &mut NoDelay),
+            Err(Error::PartialUpdateUnsupported)
+        );
+        assert_eq!(epd.set_lut(&mut spi, None), Ok(()));
+        assert_eq!(epd.set_lut(&mut spi, Some(RefreshLut::Full)), Ok(()));
+        assert_eq!(
+            epd.set_lut(&mut spi, Some(RefreshLut::Quick)),
+            Err(Error::UnsupportedLut)
+        );
+        assert_eq!(
+            epd.set_lut(&mut spi, Some(RefreshLut::Custom(&[0; 8]))),
+            Err(Error::UnsupportedLut)
+        );
+        assert!(spi.sent.is_empty());
+    }
 }
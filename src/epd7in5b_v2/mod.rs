@@ -0,0 +1,609 @@
+//! A simple Driver for the Waveshare 7.5" (B) v2 E-Ink Display via SPI
+//!
+//! The panel uses the same UC8179 controller as the [V3 driver](crate::epd7in5_v3), so the
+//! command set is shared with it.
+//!
+//! # References
+//!
+//! - [Datasheet](https://www.waveshare.com/wiki/7.5inch_e-Paper_HAT_(B))
+//! - [Waveshare C driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_7in5b_V2.c)
+//! - [Waveshare Python driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/python/lib/waveshare_epd/epd7in5b_V2.py)
+
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::{InputPin, OutputPin},
+};
+
+use crate::check_buffer_len;
+use crate::color::TriColor;
+use crate::epd7in5_v3::command::Command;
+use crate::error::Error;
+use crate::interface::DisplayInterface;
+use crate::panel_setting::PanelSettingConfig;
+use crate::timing::ResetTiming;
+use crate::traits::{
+    InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+};
+use crate::window::WindowLimits;
+use crate::{AutoCs, CsStrategy, ResetPin};
+
+#[cfg(feature = "graphics")]
+mod graphics;
+#[cfg(feature = "graphics")]
+pub use self::graphics::Display7in5b;
+
+/// Width of the display
+pub const WIDTH: u32 = 800;
+/// Height of the display
+pub const HEIGHT: u32 = 480;
+/// Limits of the partial window registers: 10 bit x and y registers, the whole panel fits
+pub const WINDOW_LIMITS: WindowLimits = WindowLimits::new(WIDTH, HEIGHT, 1023, 1023);
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: TriColor = TriColor::White;
+/// Default panel setting: black/white/red mode with the LUTs from the OTP
+pub const PANEL_SETTING: PanelSettingConfig = PanelSettingConfig::from_bits(0x0F);
+
+/// Number of bytes for the b/w buffer and the same for the chromatic buffer
+const NUM_DISPLAY_BITS: u32 = 48000;
+const IS_BUSY_LOW: bool = true;
+/// Longest time in milliseconds to wait for the busy pin before giving up with
+/// [Error::Timeout]
+pub const BUSY_TIMEOUT_MS: u32 = 60_000;
+/// Fastest SPI clock of the controller in Hz, see
+/// [check_spi_frequency()](crate::check_spi_frequency())
+pub const MAX_SPI_FREQ_HZ: u32 = 10_000_000;
+
+/// Epd7in5b (V2) driver
+///
+/// The chromatic buffer uses 0 for red and 1 for no red, like the images of the Waveshare
+/// drivers. The controller expects the opposite, so the chromatic data is inverted while it
+/// is sent.
+pub struct Epd7in5b<SPI, CS, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+    /// Background Color
+    color: TriColor,
+    /// Panel setting
+    panel_setting: PanelSettingConfig,
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd7in5b<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        // Reset the device
+        self.interface.reset(delay, 200, 4)?;
+
+        // Set the power settings: VGH=20V,VGL=-20V,VDH=15V,VDL=-15V
+        self.cmd_with_data(spi, Command::PowerSetting, &[0x07, 0x07, 0x3F, 0x3F])?;
+
+        // Start the booster with the values of the Waveshare C driver
+        self.cmd_with_data(spi, Command::BoosterSoftStart, &[0x17, 0x17, 0x27, 0x17])?;
+
+        // Power on
+        self.command(spi, Command::PowerOn)?;
+        delay.delay_ms(100);
+        self.wait_until_idle_with_timeout(delay)?;
+
+        // Set the panel settings: BWROTP unless changed with set_panel_setting
+        self.cmd_with_data(spi, Command::PanelSetting, &[self.panel_setting.bits()])?;
+
+        // Set the real resolution
+        self.send_resolution(spi)?;
+
+        // Disable dual SPI
+        self.cmd_with_data(spi, Command::DualSpi, &[0x00])?;
+
+        // Set Vcom and data interval
+        self.cmd_with_data(
+            spi,
+            Command::VcomAndDataIntervalSetting,
+            &self.data_interval(),
+        )?;
+
+        // Set S2G and G2S non-overlap periods to 12 (default)
+        self.cmd_with_data(spi, Command::TconSetting, &[0x22])?;
+
+        self.cmd_with_data(spi, Command::SpiFlashControl, &[0x00, 0x00, 0x00, 0x00])?;
+
+        self.wait_until_idle_with_timeout(delay)?;
+        Ok(())
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareThreeColorDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd7in5b<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    fn update_color_frame(
+        &mut self,
+        spi: &mut SPI,
+        black: &[u8],
+        chromatic: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_achromatic_frame(spi, black)?;
+        self.update_chromatic_frame(spi, chromatic)
+    }
+
+    /// Update only the black/white data of the display.
+    ///
+    /// Finish by calling `update_chromatic_frame`.
+    fn update_achromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        black: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(black, WIDTH, HEIGHT)?;
+        self.wait_until_idle();
+        let command = self.bw_data_command();
+        self.cmd_with_data(spi, command, black)
+    }
+
+    /// Update only chromatic data of the display.
+    ///
+    /// A cleared bit means red, the data is inverted before it is sent to the controller.
+    /// Nothing is sent in the [black/white mode](Epd7in5b::set_black_white_mode()).
+    fn update_chromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        chromatic: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(chromatic, WIDTH, HEIGHT)?;
+        if self.panel_setting.black_white_mode() {
+            return Ok(());
+        }
+        self.wait_until_idle();
+        self.command(spi, Command::DataStartTransmission2)?;
+
+        let mut inverted = [0u8; 64];
+        for chunk in chromatic.chunks(inverted.len()) {
+            for (dst, src) in inverted.iter_mut().zip(chunk) {
+                *dst = !src;
+            }
+            self.send_data(spi, &inverted[..chunk.len()])?;
+        }
+        Ok(())
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd7in5b<SPI, AutoCs, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Creates the driver for a board without a CS GPIO, see [AutoCs]
+    pub fn new_without_cs(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new(spi, AutoCs, busy, dc, rst, delay)
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd7in5b<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    type DisplayColor = TriColor;
+    fn new(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SPI::Error>> {
+        Self::new_with_background(spi, cs, busy, dc, rst, delay, DEFAULT_BACKGROUND_COLOR)
+    }
+
+    fn new_with_background(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        color: Self::DisplayColor,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let interface = DisplayInterface::new(cs, busy, dc, rst);
+        let mut epd = Epd7in5b {
+            interface,
+            color,
+            panel_setting: PANEL_SETTING,
+        };
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface.check_reset_pin()?;
+        self.init(spi, delay)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle_with_timeout(delay)?;
+        self.command(spi, Command::PowerOff)?;
+        self.wait_until_idle_with_timeout(delay)?;
+        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
+        Ok(())
+    }
+
+    /// Updates the black/white data and clears the chromatic layer
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        check_buffer_len(buffer, WIDTH, HEIGHT)?;
+        self.update_achromatic_frame(spi, buffer)?;
+        if self.panel_setting.black_white_mode() {
+            return Ok(());
+        }
+
+        // Clear the chromatic layer, a set bit means red for the controller
+        self.command(spi, Command::DataStartTransmission2)?;
+        self.interface.data_x_times(spi, 0x00, NUM_DISPLAY_BITS)?;
+        Ok(())
+    }
+
+    /// Partial windows aren't supported, returns [Error::PartialUpdateUnsupported]
+    fn update_partial_frame(
+        &mut self,
+        _spi: &mut SPI,
+        _buffer: &[u8],
+        _x: u32,
+        _y: u32,
+        _width: u32,
+        _height: u32,
+        _delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        Err(Error::PartialUpdateUnsupported)
+    }
+
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.command(spi, Command::DisplayRefresh)?;
+        delay.delay_ms(100);
+        self.wait_until_idle_with_timeout(delay)?;
+        Ok(())
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)?;
+        Ok(())
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.wait_until_idle_with_timeout(delay)?;
+
+        let bw = match self.color {
+            TriColor::Black => 0x00,
+            TriColor::White | TriColor::Chromatic | TriColor::Yellow => 0xFF,
+        };
+        let chromatic = match self.color {
+            TriColor::Chromatic | TriColor::Yellow => 0xFF,
+            TriColor::Black | TriColor::White => 0x00,
+        };
+
+        let command = self.bw_data_command();
+        self.command(spi, command)?;
+        self.interface.data_x_times(spi, bw, NUM_DISPLAY_BITS)?;
+        if self.panel_setting.black_white_mode() {
+            return Ok(());
+        }
+
+        self.command(spi, Command::DataStartTransmission2)?;
+        self.interface
+            .data_x_times(spi, chromatic, NUM_DISPLAY_BITS)?;
+
+        Ok(())
+    }
+
+    fn set_background_color(&mut self, color: TriColor) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &TriColor {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn full_refresh_ms(&self) -> u32 {
+        16_000
+    }
+
+    /// The waveform of the OTP is the only one, anything but [RefreshLut::Full] returns
+    /// [Error::UnsupportedLut]
+    fn set_lut(
+        &mut self,
+        _spi: &mut SPI,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<SPI::Error>> {
+        match refresh_rate {
+            None | Some(RefreshLut::Full) => Ok(()),
+            Some(_) => Err(Error::UnsupportedLut),
+        }
+    }
+
+    fn is_busy(&self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
+    fn send_command_raw(&mut self, spi: &mut SPI, command: u8) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn send_data_raw(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        self.interface.data(spi, data)
+    }
+
+    fn set_spi_chunk_size(&mut self, chunk_size: usize) {
+        self.interface.set_chunk_size(chunk_size);
+    }
+
+    fn set_reset_timing(&mut self, timing: Option<ResetTiming>) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    #[cfg(feature = "test-patterns")]
+    fn set_test_pattern(&mut self, enable: bool) {
+        let pattern = crate::test_pattern::FillPattern::new(self.width(), 1);
+        self.interface.set_fill_pattern(enable.then_some(pattern));
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> Epd7in5b<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Replaces the [PANEL_SETTING] and writes it to the controller
+    ///
+    /// Returns [Error::InvalidPanelSetting] for the settings rejected by
+    /// [PanelSettingConfig::validate()] and for LUTs from the registers, the driver only uses
+    /// the LUTs of the OTP. The setting is kept for [wake_up()](WaveshareDisplay::wake_up()).
+    pub fn set_panel_setting(
+        &mut self,
+        spi: &mut SPI,
+        config: PanelSettingConfig,
+    ) -> Result<(), Error<SPI::Error>> {
+        config.validate_otp()?;
+        self.interface.wait_until_idle(IS_BUSY_LOW);
+        self.panel_setting = config;
+        self.cmd_with_data(spi, Command::PanelSetting, &[config.bits()])?;
+        // the polarity of the data depends on the color mode
+        let interval = self.data_interval();
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &interval)
+    }
+
+    /// Current panel setting
+    pub fn panel_setting(&self) -> PanelSettingConfig {
+        self.panel_setting
+    }
+
+    /// Drives the panel in the black/white mode of the controller
+    ///
+    /// The black/white refresh of the OTP is a lot faster than the tri-color one, but can't
+    /// show red. In this mode the black/white data is sent as the new data of the controller
+    /// and the chromatic data is ignored.
+    pub fn set_black_white_mode(
+        &mut self,
+        spi: &mut SPI,
+        enabled: bool,
+    ) -> Result<(), Error<SPI::Error>> {
+        let config = self.panel_setting.with_black_white_mode(enabled);
+        self.set_panel_setting(spi, config)
+    }
+
+    /// Vcom and data interval setting for the current color mode, white border
+    fn data_interval(&self) -> [u8; 2] {
+        if self.panel_setting.black_white_mode() {
+            [0x10, 0x07]
+        } else {
+            [0x11, 0x07]
+        }
+    }
+
+    /// Data transmission of the black/white data, the "new" data in the black/white mode
+    fn bw_data_command(&self) -> Command {
+        if self.panel_setting.black_white_mode() {
+            Command::DataStartTransmission2
+        } else {
+            Command::DataStartTransmission1
+        }
+    }
+
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        self.interface.data(spi, data)
+    }
+
+    fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    fn wait_until_idle(&mut self) {
+        self.interface.wait_until_idle(IS_BUSY_LOW);
+    }
+
+    fn wait_until_idle_with_timeout(&mut self, delay: &mut DELAY) -> Result<(), Error<SPI::Error>> {
+        self.interface
+            .wait_until_idle_with_timeout(delay, IS_BUSY_LOW, BUSY_TIMEOUT_MS)
+    }
+
+    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), Error<SPI::Error>> {
+        let w = self.width();
+        let h = self.height();
+
+        self.command(spi, Command::TconResolution)?;
+        self.send_data(spi, &[(w >> 8) as u8])?;
+        self.send_data(spi, &[w as u8])?;
+        self.send_data(spi, &[(h >> 8) as u8])?;
+        self.send_data(spi, &[h as u8])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer_len;
+    use crate::test_utils::{cmd, test_epd, DcPin, HighPin, NoDelay, RecordingSpi, Sent};
+    extern crate std;
+    use std::vec;
+    use std::vec::Vec;
+
+    type TestEpd = Epd7in5b<RecordingSpi, HighPin, HighPin, DcPin, HighPin, NoDelay>;
+
+    /// A data transmission command followed by a whole plane of `byte`
+    fn fill(command: u8, byte: u8) -> Vec<Sent> {
+        cmd(command, &vec![byte; NUM_DISPLAY_BITS as usize])
+    }
+
+    fn plane(byte: u8) -> Vec<u8> {
+        vec![byte; buffer_len(WIDTH as usize, HEIGHT as usize)]
+    }
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 800);
+        assert_eq!(HEIGHT, 480);
+        assert_eq!(NUM_DISPLAY_BITS, WIDTH * HEIGHT / 8);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, TriColor::White);
+    }
+
+    #[test]
+    fn clear_frame_fills_both_planes() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+
+        spi.sent.clear();
+        epd.clear_frame(&mut spi, &mut NoDelay).unwrap();
+        assert_eq!(spi.sent, [fill(0x10, 0xFF), fill(0x13, 0x00)].concat());
+
+        epd.set_background_color(TriColor::Chromatic);
+        spi.sent.clear();
+        epd.clear_frame(&mut spi, &mut NoDelay).unwrap();
+        assert_eq!(spi.sent, [fill(0x10, 0xFF), fill(0x13, 0xFF)].concat());
+
+        epd.set_background_color(TriColor::Black);
+        spi.sent.clear();
+        epd.clear_frame(&mut spi, &mut NoDelay).unwrap();
+        assert_eq!(spi.sent, [fill(0x10, 0x00), fill(0x13, 0x00)].concat());
+    }
+
+    #[test]
+    fn update_frame_clears_the_chromatic_plane() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        let black = plane(0x5A);
+
+        spi.sent.clear();
+        epd.update_frame(&mut spi, &black, &mut NoDelay).unwrap();
+        assert_eq!(spi.sent, [cmd(0x10, &black), fill(0x13, 0x00)].concat());
+
+        // a cleared bit of the buffer is red, the controller wants a set bit
+        spi.sent.clear();
+        epd.update_chromatic_frame(&mut spi, &plane(0x0F)).unwrap();
+        assert_eq!(spi.sent, cmd(0x13, &plane(0xF0)));
+    }
+
+    #[test]
+    fn black_white_mode_sends_the_new_data_only() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        let black = plane(0x5A);
+
+        spi.sent.clear();
+        epd.set_black_white_mode(&mut spi, true).unwrap();
+        assert_eq!(
+            spi.sent,
+            [cmd(0x00, &[0x1F]), cmd(0x50, &[0x10, 0x07])].concat()
+        );
+
+        spi.sent.clear();
+        epd.update_frame(&mut spi, &black, &mut NoDelay).unwrap();
+        epd.update_chromatic_frame(&mut spi, &black).unwrap();
+        assert_eq!(spi.sent, cmd(0x13, &black));
+
+        spi.sent.clear();
+        epd.clear_frame(&mut spi, &mut NoDelay).unwrap();
+        assert_eq!(spi.sent, fill(0x13, 0xFF));
+
+        spi.sent.clear();
+        epd.set_black_white_mode(&mut spi, false).unwrap();
+        assert_eq!(
+            spi.sent,
+            [cmd(0x00, &[0x0F]), cmd(0x50, &[0x11, 0x07])].concat()
+        );
+        spi.sent.clear();
+        epd.update_frame(&mut spi, &black, &mut NoDelay).unwrap();
+        assert_eq!(spi.sent, [cmd(0x10, &black), fill(0x13, 0x00)].concat());
+    }
+
+    #[test]
+    fn partial_frames_and_luts_are_rejected() {
+        let (mut spi, mut epd) = test_epd::<TestEpd, _>();
+        spi.sent.clear();
+
+        assert_eq!(
+            epd.update_partial_frame(&mut spi, &[0; 8], 0, 0, 8, 8, &mut NoDelay),
+            Err(Error::PartialUpdateUnsupported)
+        );
+        assert_eq!(epd.set_lut(&mut spi, None), Ok(()));
+        assert_eq!(epd.set_lut(&mut spi, Some(RefreshLut::Full)), Ok(()));
+        assert_eq!(
+            epd.set_lut(&mut spi, Some(RefreshLut::Quick)),
+            Err(Error::UnsupportedLut)
+        );
+        assert!(spi.sent.is_empty());
+    }
+}
@@ -0,0 +1,61 @@
+//! Errors returned by the drivers
+
+use crate::power::{PowerState, Transition};
+
+/// Errors of the drivers
+///
+/// Wraps the error of the SPI bus and adds the errors detected by the drivers themselves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error<SpiError> {
+    /// Error of the SPI bus
+    Spi(SpiError),
+    /// The region is empty, ends before it starts or isn't aligned the way the controller
+    /// needs it
+    InvalidRegion,
+    /// The partial window is larger than the area the window registers of the controller can
+    /// address, or an image is wider than [dither::MAX_WIDTH](crate::dither::MAX_WIDTH)
+    WindowTooLarge,
+    /// The partial window starts or ends outside of the area the window registers of the
+    /// controller can address
+    WindowOutOfRange,
+    /// The length of the buffer doesn't match the size of the frame or window
+    BufferSize {
+        /// Length in bytes the frame or window needs
+        expected: usize,
+        /// Length of the given buffer
+        got: usize,
+    },
+    /// The device was still busy when the timeout expired
+    Timeout,
+    /// The operation is not allowed in the current mode of the controller, no command was
+    /// sent
+    IllegalTransition {
+        /// Mode of the controller
+        state: PowerState,
+        /// The rejected operation
+        transition: Transition,
+    },
+    /// The panel setting can't work with the controller or the driver, see
+    /// [PanelSettingConfig::validate()](crate::panel_setting::PanelSettingConfig::validate())
+    InvalidPanelSetting,
+    /// The driver can't use the LUT, e.g. a
+    /// [RefreshLut::Custom](crate::traits::RefreshLut::Custom) of a driver whose waveforms
+    /// aren't written by the driver
+    UnsupportedLut,
+    /// The driver can only send whole frames, not windows of the panel
+    PartialUpdateUnsupported,
+    /// The controller can only leave the deep sleep with a pulse on the reset pin, but the
+    /// driver was created with [NoReset](crate::NoReset) instead
+    NoResetPin,
+    /// Setting the level of the reset pin failed
+    ResetPin,
+    /// The voltage is outside of the range of the controller or between two of its steps
+    InvalidVoltage,
+}
+
+impl<SpiError> From<SpiError> for Error<SpiError> {
+    fn from(error: SpiError) -> Self {
+        Error::Spi(error)
+    }
+}
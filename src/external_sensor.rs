@@ -0,0 +1,36 @@
+//! Framing of the external temperature sensor commands of the UC8179
+//!
+//! The controller is the I2C master of an external temperature sensor (LM75 compatible,
+//! address `0b1001_A2A1A0`). `TemperatureSensorWrite` (0x42) takes three bytes:
+//!
+//! | Byte    | Bits  | Meaning                                                        |
+//! | ------- | ----- | -------------------------------------------------------------- |
+//! | `WATTR` | [7:6] | Bytes sent after the pointer: 00 none, 01 one, 10 two          |
+//! |         | [5:3] | Pointer (register) of the sensor                               |
+//! |         | [2:0] | A2..A0 of the sensor address                                   |
+//! | `WMSB`  |       | First data byte                                                |
+//! | `WLSB`  |       | Second data byte                                               |
+//!
+//! `TemperatureSensorRead` (0x43) returns two bytes read from the last written pointer. The
+//! controller runs the I2C transaction after the command and reports busy meanwhile, so the
+//! drivers wait for the busy pin between two transactions.
+
+/// A2..A0 of the sensor on the boards, which results in the LM75 default address 0x48
+pub(crate) const SENSOR_ADDRESS: u8 = 0b000;
+
+/// `WATTR` for writing `data_bytes` bytes to `register`
+pub(crate) const fn write_attributes(data_bytes: u8, register: u8) -> u8 {
+    ((data_bytes & 0b11) << 6) | ((register & 0b111) << 3) | SENSOR_ADDRESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attributes() {
+        assert_eq!(write_attributes(0, 0), 0x00);
+        assert_eq!(write_attributes(1, 1), 0b01_001_000);
+        assert_eq!(write_attributes(2, 3), 0b10_011_000);
+    }
+}
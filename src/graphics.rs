@@ -1,14 +1,17 @@
 //! Graphics Support for EPDs
 
 use crate::buffer_len;
-use crate::color::{Color, OctColor, TriColor};
-use embedded_graphics_core::pixelcolor::BinaryColor;
+use crate::color::{Color, OctColor, QuadColor, TriColor};
+pub use crate::traits::FrameBuffer;
+use embedded_graphics_core::pixelcolor::{BinaryColor, Gray2, GrayColor};
 use embedded_graphics_core::prelude::*;
+use embedded_graphics_core::primitives::Rectangle;
 
 /// Displayrotation
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum DisplayRotation {
     /// No rotation
+    #[default]
     Rotate0,
     /// Rotate by 90 degrees clockwise
     Rotate90,
@@ -18,12 +21,6 @@ pub enum DisplayRotation {
     Rotate270,
 }
 
-impl Default for DisplayRotation {
-    fn default() -> Self {
-        DisplayRotation::Rotate0
-    }
-}
-
 /// Display specific pixel output configuration
 ///
 /// Different chromatic displays differently treat the bits in chromatic color planes.
@@ -61,12 +58,38 @@ pub trait Display: DrawTarget<Color = BinaryColor> {
     /// Returns a mutable buffer
     fn get_mut_buffer(&mut self) -> &mut [u8];
 
+    /// Creates a display buffer filled with the default background color and the given rotation
+    fn with_rotation(rotation: DisplayRotation) -> Self
+    where
+        Self: Default,
+    {
+        let mut display = Self::default();
+        display.set_rotation(rotation);
+        display
+    }
+
     /// Sets the rotation of the display
+    ///
+    /// Coordinates passed to the [DrawTarget] are mapped through the rotation before
+    /// they are written into the buffer and [OriginDimensions::size] reports the
+    /// rotated dimensions, so embedded-graphics always sees (0, 0) at the top left.
     fn set_rotation(&mut self, rotation: DisplayRotation);
 
     /// Get the current rotation of the display
     fn rotation(&self) -> DisplayRotation;
 
+    /// Iterates over the pixels of the buffer, row by row in the rotated coordinates
+    ///
+    /// The bits are unpacked the same way they were drawn, so a point drawn through the
+    /// [DrawTarget] is yielded with the same coordinates, e.g. to check a frame in a test.
+    fn pixels(&self) -> PixelIter<'_> {
+        let rotation = self.rotation();
+        PixelIter {
+            buffer: self.buffer(),
+            positions: Positions::new(self.bounding_box().size, rotation),
+        }
+    }
+
     /// Helperfunction for the Embedded Graphics draw trait
     ///
     /// Becomes uneccesary when const_generics become stablised
@@ -101,6 +124,50 @@ pub trait Display: DrawTarget<Color = BinaryColor> {
         }
         Ok(())
     }
+
+    /// Helperfunction for [DrawTarget::fill_solid]
+    ///
+    /// Sets whole bytes of each row at once instead of going pixel by pixel. The area is given
+    /// in rotated coordinates and clipped to the display.
+    fn fill_solid_helper(
+        &mut self,
+        width: u32,
+        height: u32,
+        area: &Rectangle,
+        color: BinaryColor,
+    ) -> Result<(), Self::Error> {
+        let rotation = self.rotation();
+        let area = area.intersection(&self.bounding_box());
+        let Some(bottom_right) = area.bottom_right() else {
+            return Ok(());
+        };
+
+        // the opposite corners stay opposite corners in the buffer
+        let (ax, ay) = find_rotation(
+            area.top_left.x as u32,
+            area.top_left.y as u32,
+            width,
+            height,
+            rotation,
+        );
+        let (bx, by) = find_rotation(
+            bottom_right.x as u32,
+            bottom_right.y as u32,
+            width,
+            height,
+            rotation,
+        );
+        let (x0, x1) = (ax.min(bx), ax.max(bx));
+        let (y0, y1) = (ay.min(by), ay.max(by));
+
+        let row_len = width.div_ceil(8) as usize;
+        let buffer = self.get_mut_buffer();
+        for y in y0..=y1 {
+            let row = &mut buffer[y as usize * row_len..][..row_len];
+            fill_row(row, x0, x1, color);
+        }
+        Ok(())
+    }
 }
 
 /// Necessary traits for all displays to implement for drawing
@@ -123,7 +190,21 @@ pub trait TriDisplay: DrawTarget<Color = TriColor> {
     /// Returns a mutable buffer
     fn get_mut_buffer(&mut self) -> &mut [u8];
 
+    /// Creates a display buffer filled with the default background color and the given rotation
+    fn with_rotation(rotation: DisplayRotation) -> Self
+    where
+        Self: Default,
+    {
+        let mut display = Self::default();
+        display.set_rotation(rotation);
+        display
+    }
+
     /// Sets the rotation of the display
+    ///
+    /// Coordinates passed to the [DrawTarget] are mapped through the rotation before
+    /// they are written into the buffer and [OriginDimensions::size] reports the
+    /// rotated dimensions, so embedded-graphics always sees (0, 0) at the top left.
     fn set_rotation(&mut self, rotation: DisplayRotation);
 
     /// Get the current rotation of the display
@@ -138,6 +219,25 @@ pub trait TriDisplay: DrawTarget<Color = TriColor> {
     /// return the chromatic part of the buffer
     fn chromatic_buffer(&self) -> &[u8];
 
+    /// Rendering mode of the chromatic plane, the one passed to
+    /// [draw_helper_tri()](TriDisplay::draw_helper_tri())
+    fn color_rendering(&self) -> DisplayColorRendering {
+        DisplayColorRendering::Positive
+    }
+
+    /// Iterates over the pixels of both planes, row by row in the rotated coordinates
+    ///
+    /// See [Display::pixels()], the planes are combined according to the
+    /// [color_rendering()](TriDisplay::color_rendering()).
+    fn pixels(&self) -> TriPixelIter<'_> {
+        TriPixelIter {
+            bw: self.bw_buffer(),
+            chromatic: self.chromatic_buffer(),
+            rendering: self.color_rendering(),
+            positions: Positions::new(self.bounding_box().size, self.rotation()),
+        }
+    }
+
     /// Helperfunction for the Embedded Graphics draw trait
     ///
     /// Becomes uneccesary when const_generics become stablised
@@ -148,8 +248,8 @@ pub trait TriDisplay: DrawTarget<Color = TriColor> {
     /// * `height` - Screen height in pixels
     /// * `pixel` - Pixel to draw
     /// * `rendering` - Chooses rendering mode for the color plane,
-    ///  whether it is positive or negative. Check [DisplayColorRendering] for details.
-    ///  This is a hardware defined setting, that needs to be checked from the datasheet.
+    ///   whether it is positive or negative. Check [DisplayColorRendering] for details.
+    ///   This is a hardware defined setting, that needs to be checked from the datasheet.
     fn draw_helper_tri(
         &mut self,
         width: u32,
@@ -169,55 +269,8 @@ pub trait TriDisplay: DrawTarget<Color = TriColor> {
         let index = index as usize;
         let offset = self.chromatic_offset();
 
-        let buffer = self.get_mut_buffer();
-
-        // "Draw" the Pixel on that bit
-        match color {
-            TriColor::Black => {
-                // clear bit in bw-buffer -> black
-                buffer[index] &= !bit;
-                match rendering {
-                    DisplayColorRendering::Positive => {
-                        // set bit in chromatic-buffer -> white
-                        buffer[index + offset] |= bit;
-                    }
-                    DisplayColorRendering::Negative => {
-                        // clear bit in chromatic-buffer -> white
-                        buffer[index + offset] &= !bit;
-                    }
-                }
-            }
-            TriColor::White => {
-                // set bit in bw-buffer -> white
-                buffer[index] |= bit;
-                match rendering {
-                    DisplayColorRendering::Positive => {
-                        // set bit in chromatic-buffer -> white
-                        buffer[index + offset] |= bit;
-                    }
-                    DisplayColorRendering::Negative => {
-                        // clear bit in chromatic-buffer -> white
-                        buffer[index + offset] &= !bit;
-                    }
-                }
-            }
-            TriColor::Chromatic => {
-                match rendering {
-                    DisplayColorRendering::Positive => {
-                        // set bit in b/w buffer (white)
-                        buffer[index] |= bit;
-                        // clear bit in chromatic buffer -> chromatic
-                        buffer[index + offset] &= !bit;
-                    }
-                    DisplayColorRendering::Negative => {
-                        // set bit in b/w buffer (white)
-                        buffer[index] |= bit;
-                        // set bit in chromatic-buffer -> chromatic
-                        buffer[index + offset] |= bit;
-                    }
-                }
-            }
-        }
+        let (bw, chromatic) = self.get_mut_buffer().split_at_mut(offset);
+        set_tri_color_bit(&mut bw[index], &mut chromatic[index], bit, color, rendering);
         Ok(())
     }
 }
@@ -242,7 +295,21 @@ pub trait OctDisplay: DrawTarget<Color = OctColor> {
     /// Returns a mutable buffer
     fn get_mut_buffer(&mut self) -> &mut [u8];
 
+    /// Creates a display buffer filled with the default background color and the given rotation
+    fn with_rotation(rotation: DisplayRotation) -> Self
+    where
+        Self: Default,
+    {
+        let mut display = Self::default();
+        display.set_rotation(rotation);
+        display
+    }
+
     /// Sets the rotation of the display
+    ///
+    /// Coordinates passed to the [DrawTarget] are mapped through the rotation before
+    /// they are written into the buffer and [OriginDimensions::size] reports the
+    /// rotated dimensions, so embedded-graphics always sees (0, 0) at the top left.
     fn set_rotation(&mut self, rotation: DisplayRotation);
 
     /// Get the current rotation of the display
@@ -281,6 +348,144 @@ pub trait OctDisplay: DrawTarget<Color = OctColor> {
     }
 }
 
+/// Necessary traits for all displays to implement for drawing
+///
+/// Adds support for:
+/// - Drawing (With the help of DrawTarget/Embedded Graphics)
+/// - Rotations
+/// - Clearing
+pub trait QuadDisplay: DrawTarget<Color = QuadColor> {
+    /// Clears the buffer of the display with the chosen background color
+    fn clear_buffer(&mut self, background_color: QuadColor) {
+        for elem in self.get_mut_buffer().iter_mut() {
+            *elem = background_color.get_byte_value();
+        }
+    }
+
+    /// Returns the buffer
+    fn buffer(&self) -> &[u8];
+
+    /// Returns a mutable buffer
+    fn get_mut_buffer(&mut self) -> &mut [u8];
+
+    /// Creates a display buffer filled with the default background color and the given rotation
+    fn with_rotation(rotation: DisplayRotation) -> Self
+    where
+        Self: Default,
+    {
+        let mut display = Self::default();
+        display.set_rotation(rotation);
+        display
+    }
+
+    /// Sets the rotation of the display
+    ///
+    /// Coordinates passed to the [DrawTarget] are mapped through the rotation before
+    /// they are written into the buffer and [OriginDimensions::size] reports the
+    /// rotated dimensions, so embedded-graphics always sees (0, 0) at the top left.
+    fn set_rotation(&mut self, rotation: DisplayRotation);
+
+    /// Get the current rotation of the display
+    fn rotation(&self) -> DisplayRotation;
+
+    /// Helperfunction for the Embedded Graphics draw trait
+    ///
+    /// Becomes uneccesary when const_generics become stablised
+    fn draw_helper(
+        &mut self,
+        width: u32,
+        height: u32,
+        pixel: Pixel<QuadColor>,
+    ) -> Result<(), Self::Error> {
+        let rotation = self.rotation();
+        let buffer = self.get_mut_buffer();
+
+        let Pixel(point, color) = pixel;
+        if outside_display(point, width, height, rotation) {
+            return Ok(());
+        }
+
+        // Give us index inside the buffer and the shift of the 2 bits of the pixel
+        let (index, shift) =
+            find_quad_position(point.x as u32, point.y as u32, width, height, rotation);
+        let index = index as usize;
+
+        buffer[index] = (buffer[index] & !(0x03 << shift)) | (color.get_bits() << shift);
+        Ok(())
+    }
+}
+
+/// Necessary traits for all displays with 4 gray levels to implement for drawing
+///
+/// The buffer has 2 bits per pixel with the first pixel in the highest bits, the layout
+/// [update_gray_frame()](crate::traits::WaveshareGrayscaleDisplay::update_gray_frame())
+/// expects. The driver splits it into the two RAM planes, see [GrayPlane](crate::color::GrayPlane).
+///
+/// Adds support for:
+/// - Drawing (With the help of DrawTarget/Embedded Graphics)
+/// - Rotations
+/// - Clearing
+pub trait GrayDisplay: DrawTarget<Color = Gray2> {
+    /// Clears the buffer of the display with the chosen background color
+    fn clear_buffer(&mut self, background_color: Gray2) {
+        for elem in self.get_mut_buffer().iter_mut() {
+            *elem = background_color.luma() * 0x55;
+        }
+    }
+
+    /// Returns the buffer
+    fn buffer(&self) -> &[u8];
+
+    /// Returns a mutable buffer
+    fn get_mut_buffer(&mut self) -> &mut [u8];
+
+    /// Creates a display buffer filled with the default background color and the given rotation
+    fn with_rotation(rotation: DisplayRotation) -> Self
+    where
+        Self: Default,
+    {
+        let mut display = Self::default();
+        display.set_rotation(rotation);
+        display
+    }
+
+    /// Sets the rotation of the display
+    ///
+    /// Coordinates passed to the [DrawTarget] are mapped through the rotation before
+    /// they are written into the buffer and [OriginDimensions::size] reports the
+    /// rotated dimensions, so embedded-graphics always sees (0, 0) at the top left.
+    fn set_rotation(&mut self, rotation: DisplayRotation);
+
+    /// Get the current rotation of the display
+    fn rotation(&self) -> DisplayRotation;
+
+    /// Helperfunction for the Embedded Graphics draw trait
+    ///
+    /// Becomes uneccesary when const_generics become stablised
+    fn draw_helper(
+        &mut self,
+        width: u32,
+        height: u32,
+        pixel: Pixel<Gray2>,
+    ) -> Result<(), Self::Error> {
+        let rotation = self.rotation();
+        let buffer = self.get_mut_buffer();
+
+        let Pixel(point, color) = pixel;
+        if outside_display(point, width, height, rotation) {
+            return Ok(());
+        }
+
+        // same layout as the 4 color buffers
+        let (index, shift) =
+            find_quad_position(point.x as u32, point.y as u32, width, height, rotation);
+        let index = index as usize;
+
+        buffer[index] = (buffer[index] & !(0x03 << shift)) | (color.luma() << shift);
+        Ok(())
+    }
+}
+
 /// A variable Display without a predefined buffer
 ///
 /// The buffer can be created as following:
@@ -317,16 +522,52 @@ pub struct VarDisplay<'a> {
 impl<'a> VarDisplay<'a> {
     /// Create a new variable sized display.
     ///
-    /// Buffersize must be at least (width + 7) / 8 * height bytes.
+    /// Buffersize must be at least (width + 7) / 8 * height bytes, see [VarDisplay::try_new()]
+    /// for a version which doesn't panic.
     pub fn new(width: u32, height: u32, buffer: &'a mut [u8]) -> VarDisplay<'a> {
-        let len = buffer.len() as u32;
-        assert!(buffer_len(width as usize, height as usize) >= len as usize);
-        VarDisplay {
+        match Self::try_new::<()>(width, height, buffer) {
+            Ok(display) => display,
+            Err(_) => panic!("buffer too small for the display"),
+        }
+    }
+
+    /// Create a new variable sized display on top of a caller provided buffer
+    ///
+    /// Returns [Error::BufferSize](crate::error::Error::BufferSize) if the buffer is shorter than
+    /// [buffer_len(width, height)](crate::buffer_len). A longer buffer is fine, only its start is
+    /// used, so one scratch buffer can be shared between displays of different sizes.
+    ///
+    /// ```rust
+    /// # use epd_waveshare::{epd2in9, epd1in54, error::Error, graphics::VarDisplay, prelude::*};
+    /// # fn main() -> Result<(), Error<()>> {
+    /// let mut scratch = [0xFF; 200 / 8 * 200];
+    ///
+    /// let display = VarDisplay::try_new(epd1in54::WIDTH, epd1in54::HEIGHT, &mut scratch)?;
+    /// assert_eq!(display.buffer().len(), 200 / 8 * 200);
+    ///
+    /// let display = VarDisplay::try_new(epd2in9::WIDTH, epd2in9::HEIGHT, &mut scratch)?;
+    /// assert_eq!(display.buffer().len(), 128 / 8 * 296);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn try_new<E>(
+        width: u32,
+        height: u32,
+        buffer: &'a mut [u8],
+    ) -> Result<VarDisplay<'a>, crate::error::Error<E>> {
+        let expected = buffer_len(width as usize, height as usize);
+        if buffer.len() < expected {
+            return Err(crate::error::Error::BufferSize {
+                expected,
+                got: buffer.len(),
+            });
+        }
+        Ok(VarDisplay {
             width,
             height,
             rotation: DisplayRotation::default(),
-            buffer,
-        }
+            buffer: &mut buffer[..expected],
+        })
     }
 }
 
@@ -343,11 +584,22 @@ impl<'a> DrawTarget for VarDisplay<'a> {
         }
         Ok(())
     }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.fill_solid_helper(self.width, self.height, area, color)
+    }
 }
 
 impl<'a> OriginDimensions for VarDisplay<'a> {
     fn size(&self) -> Size {
-        Size::new(self.width, self.height)
+        match self.rotation {
+            DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => {
+                Size::new(self.width, self.height)
+            }
+            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => {
+                Size::new(self.height, self.width)
+            }
+        }
     }
 }
 
@@ -369,8 +621,231 @@ impl<'a> Display for VarDisplay<'a> {
     }
 }
 
+impl<'a> FrameBuffer for VarDisplay<'a> {
+    type Color = Color;
+
+    fn as_bytes(&self) -> &[u8] {
+        self.buffer()
+    }
+
+    fn as_bytes_mut(&mut self) -> &mut [u8] {
+        self.get_mut_buffer()
+    }
+
+    fn clear(&mut self, color: Color) {
+        self.clear_buffer(color);
+    }
+}
+
+/// Positions in the buffer of the pixels of a display, in the order of the rotated coordinates
+#[derive(Debug, Clone)]
+pub(crate) struct Positions {
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+    /// Rotated width, the length of a row of the iteration
+    row: u32,
+    next: u32,
+}
+
+impl Positions {
+    /// Positions for a display of the rotated `size`
+    pub(crate) fn new(size: Size, rotation: DisplayRotation) -> Self {
+        let (width, height) = match rotation {
+            DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => (size.width, size.height),
+            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => (size.height, size.width),
+        };
+        Positions {
+            width,
+            height,
+            rotation,
+            row: size.width,
+            next: 0,
+        }
+    }
+}
+
+impl Iterator for Positions {
+    /// Rotated point, index of its byte and its bit
+    type Item = (Point, usize, u8);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.width * self.height {
+            return None;
+        }
+        let (x, y) = (self.next % self.row, self.next / self.row);
+        self.next += 1;
+        let (index, bit) = find_position(x, y, self.width, self.height, self.rotation);
+        Some((Point::new(x as i32, y as i32), index as usize, bit))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.width * self.height - self.next) as usize;
+        (len, Some(len))
+    }
+}
+
+/// Iterator over the pixels of a [Display], see [Display::pixels()]
+#[derive(Debug, Clone)]
+pub struct PixelIter<'a> {
+    buffer: &'a [u8],
+    positions: Positions,
+}
+
+impl Iterator for PixelIter<'_> {
+    type Item = (Point, Color);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (point, index, bit) = self.positions.next()?;
+        let color = if self.buffer[index] & bit == 0 {
+            Color::Black
+        } else {
+            Color::White
+        };
+        Some((point, color))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.positions.size_hint()
+    }
+}
+
+impl ExactSizeIterator for PixelIter<'_> {}
+
+/// Iterator over the pixels of a [TriDisplay], see [TriDisplay::pixels()]
+#[derive(Clone)]
+pub struct TriPixelIter<'a> {
+    bw: &'a [u8],
+    chromatic: &'a [u8],
+    rendering: DisplayColorRendering,
+    positions: Positions,
+}
+
+impl<'a> TriPixelIter<'a> {
+    pub(crate) fn new(
+        bw: &'a [u8],
+        chromatic: &'a [u8],
+        rendering: DisplayColorRendering,
+        positions: Positions,
+    ) -> Self {
+        TriPixelIter {
+            bw,
+            chromatic,
+            rendering,
+            positions,
+        }
+    }
+}
+
+impl Iterator for TriPixelIter<'_> {
+    type Item = (Point, TriColor);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (point, index, bit) = self.positions.next()?;
+        let color = tri_color_of(self.bw[index], self.chromatic[index], bit, self.rendering);
+        Some((point, color))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.positions.size_hint()
+    }
+}
+
+impl ExactSizeIterator for TriPixelIter<'_> {}
+
+/// Reads the [TriColor] of a single pixel back, the inverse of [set_tri_color_bit()]
+fn tri_color_of(bw: u8, chromatic: u8, bit: u8, rendering: DisplayColorRendering) -> TriColor {
+    let chromatic_set = chromatic & bit != 0;
+    let is_chromatic = match rendering {
+        DisplayColorRendering::Positive => !chromatic_set,
+        DisplayColorRendering::Negative => chromatic_set,
+    };
+    if is_chromatic {
+        TriColor::Chromatic
+    } else if bw & bit == 0 {
+        TriColor::Black
+    } else {
+        TriColor::White
+    }
+}
+
+/// Sets the bit of a single [TriColor] pixel in the b/w and the chromatic byte it belongs to
+pub(crate) fn set_tri_color_bit(
+    bw: &mut u8,
+    chromatic: &mut u8,
+    bit: u8,
+    color: TriColor,
+    rendering: DisplayColorRendering,
+) {
+    // "Draw" the Pixel on that bit
+    match color {
+        TriColor::Black => {
+            // clear bit in bw-buffer -> black
+            *bw &= !bit;
+            match rendering {
+                DisplayColorRendering::Positive => {
+                    // set bit in chromatic-buffer -> white
+                    *chromatic |= bit;
+                }
+                DisplayColorRendering::Negative => {
+                    // clear bit in chromatic-buffer -> white
+                    *chromatic &= !bit;
+                }
+            }
+        }
+        TriColor::White => {
+            // set bit in bw-buffer -> white
+            *bw |= bit;
+            match rendering {
+                DisplayColorRendering::Positive => {
+                    // set bit in chromatic-buffer -> white
+                    *chromatic |= bit;
+                }
+                DisplayColorRendering::Negative => {
+                    // clear bit in chromatic-buffer -> white
+                    *chromatic &= !bit;
+                }
+            }
+        }
+        TriColor::Chromatic | TriColor::Yellow => {
+            match rendering {
+                DisplayColorRendering::Positive => {
+                    // set bit in b/w buffer (white)
+                    *bw |= bit;
+                    // clear bit in chromatic buffer -> chromatic
+                    *chromatic &= !bit;
+                }
+                DisplayColorRendering::Negative => {
+                    // set bit in b/w buffer (white)
+                    *bw |= bit;
+                    // set bit in chromatic-buffer -> chromatic
+                    *chromatic |= bit;
+                }
+            }
+        }
+    }
+}
+
 // Checks if a pos is outside the defined display
-fn outside_display(p: Point, width: u32, height: u32, rotation: DisplayRotation) -> bool {
+/// Sets the bits `start..=end` of a row of a 1 bit per pixel buffer to the color
+fn fill_row(row: &mut [u8], start: u32, end: u32, color: BinaryColor) {
+    for index in start / 8..=end / 8 {
+        let first = if index == start / 8 { start % 8 } else { 0 };
+        let last = if index == end / 8 { end % 8 } else { 7 };
+        let mask = (0xFF >> first) & (0xFF << (7 - last));
+        match color {
+            BinaryColor::On => row[index as usize] &= !mask,
+            BinaryColor::Off => row[index as usize] |= mask,
+        }
+    }
+}
+
+pub(crate) fn outside_display(
+    p: Point,
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+) -> bool {
     if p.x < 0 || p.y < 0 {
         return true;
     }
@@ -420,25 +895,39 @@ fn find_oct_position(x: u32, y: u32, width: u32, height: u32, rotation: DisplayR
     let (nx, ny) = find_rotation(x, y, width, height, rotation);
     (
         /* what byte address is this? */
-        nx / 2 + (width / 2) * ny,
+        nx / 2 + width.div_ceil(2) * ny,
         /* is this the lower nibble (within byte)? */
         (nx & 0x1) == 0,
     )
 }
 
+#[rustfmt::skip]
+//returns index position in the u8-slice and the shift of the 2 bits inside that u8
+fn find_quad_position(x: u32, y: u32, width: u32, height: u32, rotation: DisplayRotation) -> (u32, u8) {
+    let (nx, ny) = find_rotation(x, y, width, height, rotation);
+    (
+        nx / 4 + width.div_ceil(4) * ny,
+        /* the first pixel is in the upper bits */
+        6 - 2 * (nx % 4) as u8,
+    )
+}
+
 #[rustfmt::skip]
 //returns index position in the u8-slice and the bit-position inside that u8
-fn find_position(x: u32, y: u32, width: u32, height: u32, rotation: DisplayRotation) -> (u32, u8) {
+pub(crate) fn find_position(x: u32, y: u32, width: u32, height: u32, rotation: DisplayRotation) -> (u32, u8) {
     let (nx, ny) = find_rotation(x, y, width, height, rotation);
     (
-        nx / 8 + ((width + 7) / 8) * ny,
+        nx / 8 + width.div_ceil(8) * ny,
         0x80 >> (nx % 8),
     )
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{buffer_len, find_position, outside_display, Display, DisplayRotation, VarDisplay};
+    use super::{
+        buffer_len, find_oct_position, find_position, find_quad_position, outside_display, Display,
+        DisplayRotation, VarDisplay,
+    };
     use crate::color::Black;
     use crate::color::Color;
     use embedded_graphics::{
@@ -465,11 +954,59 @@ mod tests {
         }
     }
 
+    #[test]
+    fn frame_buffers_of_all_kinds() {
+        use super::{FrameBuffer, TriDisplay};
+        use crate::color::{QuadColor, TriColor};
+
+        fn fill_and_sum<F: FrameBuffer>(frame: &mut F, color: F::Color) -> u32 {
+            frame.clear(color);
+            frame.as_bytes_mut()[0] = 0x01;
+            frame.as_bytes().iter().map(|&b| u32::from(b)).sum()
+        }
+
+        let mut bw = crate::epd1in54::Display1in54::default();
+        assert_eq!(fill_and_sum(&mut bw, Color::Black), 1);
+        assert_eq!(bw.buffer()[0], 0x01);
+
+        let mut tri = crate::epd2in13bc::Display2in13bc::default();
+        let mut expected = crate::epd2in13bc::Display2in13bc::default();
+        fill_and_sum(&mut tri, TriColor::Chromatic);
+        TriDisplay::clear_buffer(&mut expected, TriColor::Chromatic);
+        assert_eq!(tri.as_bytes()[1..], TriDisplay::buffer(&expected)[1..]);
+        assert_eq!(tri.as_bytes().len(), 2 * tri.bw_buffer().len());
+
+        let mut quad = crate::epd7in3g::Display7in3g::default();
+        let bytes = quad.as_bytes().len() as u32;
+        let white = u32::from(QuadColor::White.get_byte_value());
+        assert_eq!(
+            fill_and_sum(&mut quad, QuadColor::White),
+            1 + (bytes - 1) * white
+        );
+    }
+
+    #[test]
+    fn var_display_validates_the_buffer() {
+        use crate::error::Error;
+
+        let mut buffer = [0u8; 20];
+        assert!(VarDisplay::try_new::<()>(10, 10, &mut buffer).is_ok());
+        assert!(matches!(
+            VarDisplay::try_new::<()>(10, 11, &mut buffer),
+            Err(Error::BufferSize {
+                expected: 22,
+                got: 20
+            })
+        ));
+        let display = VarDisplay::try_new::<()>(8, 10, &mut buffer).unwrap();
+        assert_eq!(display.buffer().len(), 10);
+    }
+
     #[test]
     fn rotation_overflow() {
         use crate::epd4in2::{HEIGHT, WIDTH};
-        let width = WIDTH as u32;
-        let height = HEIGHT as u32;
+        let width = WIDTH;
+        let height = HEIGHT;
         test_rotation_overflow(width, height, DisplayRotation::Rotate0);
         test_rotation_overflow(width, height, DisplayRotation::Rotate90);
         test_rotation_overflow(width, height, DisplayRotation::Rotate180);
@@ -480,7 +1017,7 @@ mod tests {
         let max_value = width / 8 * height;
         for x in 0..(width + height) {
             //limit x because it runs too long
-            for y in 0..(u32::max_value()) {
+            for y in 0..(u32::MAX) {
                 if outside_display(Point::new(x as i32, y as i32), width, height, rotation2) {
                     break;
                 } else {
@@ -539,4 +1076,231 @@ mod tests {
             assert_eq!(byte, DEFAULT_BACKGROUND_COLOR.get_byte_value());
         }
     }
+
+    #[test]
+    fn rotated_size_and_constructor() {
+        use crate::epd2in9::{Display2in9, HEIGHT, WIDTH};
+
+        let display = Display2in9::default();
+        assert_eq!(display.size(), Size::new(WIDTH, HEIGHT));
+
+        let display = Display2in9::with_rotation(DisplayRotation::Rotate90);
+        assert_eq!(display.size(), Size::new(HEIGHT, WIDTH));
+
+        let display = Display2in9::with_rotation(DisplayRotation::Rotate180);
+        assert_eq!(display.size(), Size::new(WIDTH, HEIGHT));
+
+        let display = Display2in9::with_rotation(DisplayRotation::Rotate270);
+        assert_eq!(display.size(), Size::new(HEIGHT, WIDTH));
+    }
+
+    #[test]
+    fn single_pixel_in_every_rotation() {
+        use crate::epd2in9::Display2in9;
+
+        // 128 x 296 pixels, 16 bytes per row
+        let cases = [
+            (
+                DisplayRotation::Rotate0,
+                Point::new(10, 3),
+                3 * 16 + 1,
+                0x20,
+            ),
+            (
+                DisplayRotation::Rotate90,
+                Point::new(10, 3),
+                10 * 16 + 15,
+                0x08,
+            ),
+            (
+                DisplayRotation::Rotate180,
+                Point::new(10, 3),
+                292 * 16 + 14,
+                0x04,
+            ),
+            (
+                DisplayRotation::Rotate270,
+                Point::new(10, 3),
+                285 * 16,
+                0x10,
+            ),
+            // only inside of the display in landscape
+            (
+                DisplayRotation::Rotate90,
+                Point::new(200, 100),
+                200 * 16 + 3,
+                0x10,
+            ),
+        ];
+        for (rotation, point, index, bit) in cases {
+            let mut display = Display2in9::with_rotation(rotation);
+            assert!(display.bounding_box().contains(point));
+            Pixel(point, Black).draw(&mut display).unwrap();
+
+            for (i, &byte) in display.buffer().iter().enumerate() {
+                let expected = if i == index { !bit } else { 0xFF };
+                assert_eq!(byte, expected, "{:?} byte {}", rotation, i);
+            }
+        }
+    }
+
+    #[test]
+    fn pixels_round_trip_across_byte_boundaries() {
+        extern crate std;
+        use std::vec::Vec;
+
+        let points = [
+            Point::new(0, 0),
+            Point::new(1, 0),
+            Point::new(7, 0),
+            Point::new(8, 0),
+        ];
+        for rotation in [
+            DisplayRotation::Rotate0,
+            DisplayRotation::Rotate90,
+            DisplayRotation::Rotate180,
+            DisplayRotation::Rotate270,
+        ] {
+            let mut buffer = [Color::Black.get_byte_value(); buffer_len(20, 12)];
+            let mut display = VarDisplay::new(20, 12, &mut buffer);
+            display.set_rotation(rotation);
+            for point in points {
+                Pixel(point, crate::color::White)
+                    .draw(&mut display)
+                    .unwrap();
+            }
+
+            assert_eq!(display.pixels().len(), 20 * 12);
+            let white: Vec<_> = display
+                .pixels()
+                .filter(|(_, color)| *color == Color::White)
+                .map(|(point, _)| point)
+                .collect();
+            assert_eq!(white, points, "{:?}", rotation);
+        }
+    }
+
+    fn rotation() -> impl proptest::strategy::Strategy<Value = DisplayRotation> {
+        proptest::sample::select(
+            &[
+                DisplayRotation::Rotate0,
+                DisplayRotation::Rotate90,
+                DisplayRotation::Rotate180,
+                DisplayRotation::Rotate270,
+            ][..],
+        )
+    }
+
+    // size of the display as seen by the user
+    fn rotated_size(width: u32, height: u32, rotation: DisplayRotation) -> (u32, u32) {
+        match rotation {
+            DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => (width, height),
+            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => (height, width),
+        }
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn find_position_is_a_bijection(width in 1u32..40, height in 1u32..40, rotation in rotation()) {
+            extern crate std;
+            let (w, h) = rotated_size(width, height, rotation);
+            let mut seen = std::collections::HashSet::new();
+            for x in 0..w {
+                for y in 0..h {
+                    proptest::prop_assert!(!outside_display(Point::new(x as i32, y as i32), width, height, rotation));
+                    let (index, bit) = find_position(x, y, width, height, rotation);
+                    proptest::prop_assert!((index as usize) < buffer_len(width as usize, height as usize));
+                    proptest::prop_assert!(seen.insert((index, bit)));
+                }
+            }
+            proptest::prop_assert!(outside_display(Point::new(w as i32, 0), width, height, rotation));
+            proptest::prop_assert!(outside_display(Point::new(0, h as i32), width, height, rotation));
+        }
+
+        #[test]
+        fn find_oct_position_is_a_bijection(width in 1u32..40, height in 1u32..40, rotation in rotation()) {
+            extern crate std;
+            let (w, h) = rotated_size(width, height, rotation);
+            let mut seen = std::collections::HashSet::new();
+            for x in 0..w {
+                for y in 0..h {
+                    let (index, lower) = find_oct_position(x, y, width, height, rotation);
+                    proptest::prop_assert!(index < width.div_ceil(2) * height);
+                    proptest::prop_assert!(seen.insert((index, lower)));
+                }
+            }
+        }
+
+        #[test]
+        fn find_quad_position_is_a_bijection(width in 1u32..40, height in 1u32..40, rotation in rotation()) {
+            extern crate std;
+            let (w, h) = rotated_size(width, height, rotation);
+            let mut seen = std::collections::HashSet::new();
+            for x in 0..w {
+                for y in 0..h {
+                    let (index, shift) = find_quad_position(x, y, width, height, rotation);
+                    proptest::prop_assert!(index < width.div_ceil(4) * height);
+                    proptest::prop_assert!(shift <= 6 && shift % 2 == 0);
+                    proptest::prop_assert!(seen.insert((index, shift)));
+                }
+            }
+        }
+
+        #[test]
+        fn fill_solid_matches_pixel_by_pixel(
+            width in 1u32..40,
+            height in 1u32..40,
+            rotation in rotation(),
+            x in -8i32..48,
+            y in -8i32..48,
+            w in 0u32..48,
+            h in 0u32..48,
+            black in proptest::bool::ANY,
+        ) {
+            extern crate std;
+            use embedded_graphics_core::primitives::Rectangle;
+
+            let color = if black { Black } else { crate::color::White };
+            let background = if black { Color::White } else { Color::Black };
+            let len = buffer_len(width as usize, height as usize);
+            let mut filled = std::vec![background.get_byte_value(); len];
+            let mut drawn = filled.clone();
+
+            let area = Rectangle::new(Point::new(x, y), Size::new(w, h));
+            let mut display = VarDisplay::new(width, height, &mut filled);
+            display.set_rotation(rotation);
+            display.fill_solid(&area, color).unwrap();
+
+            let mut display = VarDisplay::new(width, height, &mut drawn);
+            display.set_rotation(rotation);
+            display
+                .draw_iter(area.points().map(|point| Pixel(point, color)))
+                .unwrap();
+
+            proptest::prop_assert_eq!(filled, drawn);
+        }
+
+        #[test]
+        fn pixel_round_trip(
+            width in 1u32..40,
+            height in 1u32..40,
+            rotation in rotation(),
+            px in 0u32..40,
+            py in 0u32..40,
+        ) {
+            extern crate std;
+            let (w, h) = rotated_size(width, height, rotation);
+            let (px, py) = (px % w, py % h);
+
+            let mut buffer = std::vec![Color::White.get_byte_value(); buffer_len(width as usize, height as usize)];
+            let mut display = VarDisplay::new(width, height, &mut buffer);
+            display.set_rotation(rotation);
+            let _ = Pixel(Point::new(px as i32, py as i32), Black).draw(&mut display);
+
+            let (index, bit) = find_position(px, py, width, height, rotation);
+            let black_pixels: u32 = display.buffer().iter().map(|b| b.count_zeros()).sum();
+            proptest::prop_assert_eq!(black_pixels, 1);
+            proptest::prop_assert_eq!(display.buffer()[index as usize] & bit, 0);
+        }
+    }
 }
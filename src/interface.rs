@@ -1,12 +1,24 @@
+use crate::error::Error;
+#[cfg(feature = "test-patterns")]
+use crate::test_pattern::FillPattern;
+use crate::timing::{Clock, Operation, ResetTiming, Timings};
 use crate::traits::Command;
+use crate::type_a::command::Command as TypeACommand;
+use crate::{CsStrategy, ResetPin};
 use core::marker::PhantomData;
 use embedded_hal::{
-    blocking::{delay::*, spi::Write},
+    blocking::{
+        delay::*,
+        spi::{Transfer, Write},
+    },
     digital::v2::*,
 };
 
 /// The Connection Interface of all (?) Waveshare EPD-Devices
 ///
+/// Only a thin generic layer: the pins, the SPI bus and the delay are passed as trait objects
+/// to the functions of [raw], so the loops sending data and polling the busy pin are compiled
+/// once instead of once per driver and pin combination.
 pub(crate) struct DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY> {
     /// SPI
     _spi: PhantomData<SPI>,
@@ -20,51 +32,147 @@ pub(crate) struct DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY> {
     dc: DC,
     /// Pin for Resetting
     rst: RST,
+    /// Busy period measurements
+    measurement: raw::Measurement,
+    /// Largest number of bytes in a single write to the SPI bus
+    chunk_size: usize,
+    /// Reset pulse replacing the one of the driver
+    reset_timing: Option<ResetTiming>,
+    /// Pattern replacing the fills and the number of fills since it was set
+    #[cfg(feature = "test-patterns")]
+    pattern: Option<(FillPattern, u32)>,
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
     pub fn new(cs: CS, busy: BUSY, dc: DC, rst: RST) -> Self {
         DisplayInterface {
-            _spi: PhantomData::default(),
-            _delay: PhantomData::default(),
+            _spi: PhantomData,
+            _delay: PhantomData,
             cs,
             busy,
             dc,
             rst,
+            measurement: raw::Measurement::default(),
+            chunk_size: raw::DEFAULT_CHUNK_SIZE,
+            reset_timing: None,
+            #[cfg(feature = "test-patterns")]
+            pattern: None,
         }
     }
 
+    /// Splits longer transfers into writes of up to `chunk_size` bytes, CS stays low in between
+    pub(crate) fn set_chunk_size(&mut self, chunk_size: usize) {
+        self.chunk_size = chunk_size.max(1);
+    }
+
+    /// Replaces the reset pulse the driver asks for, `None` goes back to it
+    pub(crate) fn set_reset_timing(&mut self, timing: Option<ResetTiming>) {
+        self.reset_timing = timing;
+    }
+
+    /// Sends `pattern` instead of the repeated byte in the following
+    /// [data_x_times()](Self::data_x_times()) calls, `None` restores the plain fills
+    #[cfg(feature = "test-patterns")]
+    pub(crate) fn set_fill_pattern(&mut self, pattern: Option<FillPattern>) {
+        self.pattern = pattern.map(|pattern| (pattern, 0));
+    }
+
+    /// Sets or removes the clock used for measuring the busy periods
+    pub(crate) fn set_clock(&mut self, clock: Option<&'static dyn Clock>) {
+        self.measurement.set_clock(clock);
+    }
+
+    /// Starts measuring an operation
+    ///
+    /// The measurement ends with [measure_busy_period()](DisplayInterface::measure_busy_period()),
+    /// the other waits don't touch it. Does nothing if no clock is set.
+    pub(crate) fn start_measurement(&mut self, operation: Operation) {
+        self.measurement.start(operation);
+    }
+
+    /// Waits for the busy period of the measured operation and records its duration
+    ///
+    /// Returns right away if no measurement was started, so drivers without a clock don't
+    /// block. Gives up like [wait_until_idle_with_timeout()](DisplayInterface::wait_until_idle_with_timeout())
+    /// and drops the measurement in that case.
+    pub(crate) fn measure_busy_period(
+        &mut self,
+        delay: &mut DELAY,
+        is_busy_low: bool,
+        timeout_ms: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        if !self.measurement.is_pending() {
+            return Ok(());
+        }
+        let waited = raw::wait_until_idle_with_timeout(&self.busy, delay, is_busy_low, timeout_ms);
+        match waited {
+            Ok(()) => self.measurement.finish(),
+            Err(raw::TimedOut) => self.measurement.cancel(),
+        }
+        waited.map_err(|raw::TimedOut| Error::Timeout)
+    }
+
+    /// Last measured durations
+    pub(crate) fn timings(&self) -> Timings {
+        self.measurement.timings
+    }
+
     /// Basic function for sending [Commands](Command).
     ///
     /// Enables direct interaction with the device with the help of [data()](DisplayInterface::data())
-    pub(crate) fn cmd<T: Command>(&mut self, spi: &mut SPI, command: T) -> Result<(), SPI::Error> {
+    pub(crate) fn cmd<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        command: T,
+    ) -> Result<(), Error<SPI::Error>> {
+        let address = command.address();
         // low for commands
-        let _ = self.dc.set_low();
-
-        // Transfer the command over spi
-        self.write(spi, &[command.address()])
+        self.transfer(spi, |cs, dc, bus| raw::send(cs, dc, bus, false, &[address]))
     }
 
     /// Basic function for sending an array of u8-values of data over spi
     ///
     /// Enables direct interaction with the device with the help of [command()](Epd4in2::command())
-    pub(crate) fn data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    pub(crate) fn data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        // high for data, one u8 per transfer
+        self.transfer(spi, |cs, dc, bus| raw::send_bytewise(cs, dc, bus, data))
+    }
+
+    /// Sends an array of u8-values of data in a single transfer
+    ///
+    /// Unlike [data()](Self::data()) CS stays low for the whole slice, which saves a CS toggle
+    /// per byte for controllers that accept that.
+    pub(crate) fn data_block(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
         // high for data
-        let _ = self.dc.set_high();
+        self.transfer(spi, |cs, dc, bus| raw::send(cs, dc, bus, true, data))
+    }
 
-        for val in data.iter().copied() {
-            // Transfer data one u8 at a time over spi
-            self.write(spi, &[val])?;
+    /// Sends data bytewise like [data()](Self::data()), every byte is passed through `map` first
+    pub(crate) fn data_mapped<F: Fn(u8) -> u8>(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+        map: F,
+    ) -> Result<(), Error<SPI::Error>> {
+        let mut mapped = [0u8; 64];
+        for chunk in data.chunks(mapped.len()) {
+            for (dst, &byte) in mapped.iter_mut().zip(chunk) {
+                *dst = map(byte);
+            }
+            self.data(spi, &mapped[..chunk.len()])?;
         }
-
         Ok(())
     }
 
@@ -76,49 +184,112 @@ where
         spi: &mut SPI,
         command: T,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.cmd(spi, command)?;
         self.data(spi, data)
     }
 
     /// Basic function for sending the same byte of data (one u8) multiple times over spi
     ///
+    /// The byte is sent from a small stack buffer in transfers of up to 256 bytes, instead of
+    /// one transfer per byte.
+    ///
     /// Enables direct interaction with the device with the help of [command()](ConnectionInterface::command())
     pub(crate) fn data_x_times(
         &mut self,
         spi: &mut SPI,
         val: u8,
         repetitions: u32,
-    ) -> Result<(), SPI::Error> {
-        // high for data
-        let _ = self.dc.set_high();
-        // Transfer data (u8) over spi
-        for _ in 0..repetitions {
-            self.write(spi, &[val])?;
+    ) -> Result<(), Error<SPI::Error>> {
+        #[cfg(feature = "test-patterns")]
+        if let Some((pattern, fill)) = self.pattern.as_mut() {
+            let (pattern, fill) = (*pattern, *fill);
+            self.pattern = Some((pattern, fill + 1));
+            return self.transfer(spi, |cs, dc, bus| {
+                raw::send_pattern(cs, dc, bus, repetitions, &|index| {
+                    pattern.byte(val, index, fill)
+                })
+            });
         }
-        Ok(())
+        self.transfer(spi, |cs, dc, bus| {
+            raw::send_x_times(cs, dc, bus, val, repetitions)
+        })
     }
 
-    // spi write helper/abstraction function
-    fn write(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
-        // activate spi with cs low
-        let _ = self.cs.set_low();
-
-        // transfer spi data
-        // Be careful!! Linux has a default limit of 4096 bytes per spi transfer
-        // see https://raspberrypi.stackexchange.com/questions/65595/spi-transfer-fails-with-buffer-size-greater-than-4096
-        if cfg!(target_os = "linux") {
-            for data_chunk in data.chunks(4096) {
-                spi.write(data_chunk)?;
-            }
-        } else {
-            spi.write(data)?;
+    /// Writes only the rows of `new_buf` which differ from `old_buf` to the RAM of a type A
+    /// (SSD16xx) controller
+    ///
+    /// A single Y window spanning the first to the last changed row is set and the rows in
+    /// between are sent, nothing is sent if the frames are equal. The X window has to span
+    /// the whole width and the data entry mode has to increment X first, as after
+    /// `use_full_frame` of the type A drivers.
+    ///
+    /// Returns the number of frame bytes sent. Both buffers need the same length, otherwise
+    /// [Error::BufferSize] is returned, and [Error::InvalidRegion] if it isn't a multiple of
+    /// `width_bytes`.
+    pub(crate) fn diff_and_update_rows(
+        &mut self,
+        spi: &mut SPI,
+        old_buf: &[u8],
+        new_buf: &[u8],
+        width_bytes: usize,
+    ) -> Result<usize, Error<SPI::Error>> {
+        if old_buf.len() != new_buf.len() {
+            return Err(Error::BufferSize {
+                expected: old_buf.len(),
+                got: new_buf.len(),
+            });
+        }
+        if width_bytes == 0 || new_buf.len() % width_bytes != 0 {
+            return Err(Error::InvalidRegion);
         }
+        let (first, last) = match dirty_rows(old_buf, new_buf, width_bytes) {
+            Some(rows) => rows,
+            None => return Ok(0),
+        };
 
-        // deactivate spi with cs high
-        let _ = self.cs.set_high();
+        // 2 Databytes: A[7:0] & 0..A[8] for each - start and end
+        self.cmd_with_data(
+            spi,
+            TypeACommand::SetRamYAddressStartEndPosition,
+            &[
+                first as u8,
+                (first >> 8) as u8,
+                last as u8,
+                (last >> 8) as u8,
+            ],
+        )?;
+        self.cmd_with_data(
+            spi,
+            TypeACommand::SetRamYAddressCounter,
+            &[first as u8, (first >> 8) as u8],
+        )?;
+        let rows = &new_buf[first * width_bytes..(last + 1) * width_bytes];
+        self.cmd_with_data(spi, TypeACommand::WriteRam, rows)?;
+        Ok(rows.len())
+    }
 
-        Ok(())
+    /// Runs `f` with the pins and the bus as trait objects
+    ///
+    /// The bus keeps the SPI error, [raw] only sees that a write failed.
+    fn transfer<F>(&mut self, spi: &mut SPI, f: F) -> Result<(), Error<SPI::Error>>
+    where
+        F: FnOnce(
+            &mut dyn CsStrategy,
+            &mut dyn raw::OutputLevel,
+            &mut dyn raw::Bus,
+        ) -> Result<(), raw::BusError>,
+    {
+        let mut bus = SpiBus {
+            spi,
+            chunk_size: self.chunk_size,
+            error: None,
+        };
+        let _ = f(&mut self.cs, &mut self.dc, &mut bus);
+        match bus.error {
+            Some(error) => Err(Error::Spi(error)),
+            None => Ok(()),
+        }
     }
 
     /// Waits until device isn't busy anymore (busy == HIGH)
@@ -135,13 +306,22 @@ where
     /// Most likely there was a mistake with the 2in9 busy connection
     /// //TODO: use the #cfg feature to make this compile the right way for the certain types
     pub(crate) fn wait_until_idle(&mut self, is_busy_low: bool) {
-        // //tested: worked without the delay for all tested devices
-        // //self.delay_ms(1);
-        while self.is_busy(is_busy_low) {
-            // //tested: REMOVAL of DELAY: it's only waiting for the signal anyway and should continue work asap
-            // //old: shorten the time? it was 100 in the beginning
-            // //self.delay_ms(5);
-        }
+        raw::wait_until_idle(&self.busy, is_busy_low);
+    }
+
+    /// Same as [wait_until_idle()](DisplayInterface::wait_until_idle()), but gives up after
+    /// `timeout_ms` milliseconds
+    ///
+    /// The busy pin is polled once per millisecond. Returns [Error::Timeout] if the device is
+    /// still busy afterwards.
+    pub(crate) fn wait_until_idle_with_timeout(
+        &mut self,
+        delay: &mut DELAY,
+        is_busy_low: bool,
+        timeout_ms: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        raw::wait_until_idle_with_timeout(&self.busy, delay, is_busy_low, timeout_ms)
+            .map_err(|raw::TimedOut| Error::Timeout)
     }
 
     /// Checks if device is still busy
@@ -158,8 +338,7 @@ where
     /// Most likely there was a mistake with the 2in9 busy connection
     /// //TODO: use the #cfg feature to make this compile the right way for the certain types
     pub(crate) fn is_busy(&self, is_busy_low: bool) -> bool {
-        (is_busy_low && self.busy.is_low().unwrap_or(false))
-            || (!is_busy_low && self.busy.is_high().unwrap_or(false))
+        raw::BusyInput::is_busy(&self.busy, is_busy_low)
     }
 
     /// Resets the device.
@@ -169,15 +348,1063 @@ where
     /// The timing of keeping the reset pin low seems to be important and different per device.
     /// Most displays seem to require keeping it low for 10ms, but the 7in5_v2 only seems to reset
     /// properly with 2ms
-    pub(crate) fn reset(&mut self, delay: &mut DELAY, initial_delay: u8, duration: u8) {
-        let _ = self.rst.set_high();
-        delay.delay_ms(initial_delay);
-
-        let _ = self.rst.set_low();
-        delay.delay_ms(duration);
-        let _ = self.rst.set_high();
-        //TODO: the upstream libraries always sleep for 200ms here
+    ///
+    /// A timing set with [set_reset_timing()](Self::set_reset_timing()) takes precedence.
+    /// Returns `false` without any delay if there is no reset pin, see
+    /// [check_reset_pin()](Self::check_reset_pin()), and [Error::ResetPin] if the pin fails.
+    pub(crate) fn reset(
+        &mut self,
+        delay: &mut DELAY,
+        initial_delay: u8,
+        duration: u8,
+    ) -> Result<bool, Error<SPI::Error>> {
+        self.reset_with(delay, initial_delay, duration, true)
+    }
+
+    /// Fails with [Error::NoResetPin] if the reset pin is [NoReset](crate::NoReset)
+    ///
+    /// Leaving the deep sleep needs the pin on all controllers.
+    pub(crate) fn check_reset_pin(&self) -> Result<(), Error<SPI::Error>> {
+        if RST::CONNECTED {
+            Ok(())
+        } else {
+            Err(Error::NoResetPin)
+        }
+    }
+
+    /// Resets the device with a reset pin of the given polarity
+    ///
+    /// The pin is released for `initial_ms`, asserted for `pulse_ms` and released again,
+    /// followed by 200ms for the controller to start. `active_low` is `true` for all Waveshare
+    /// boards, carrier boards with an inverting driver on the reset line need `false`.
+    pub(crate) fn reset_with(
+        &mut self,
+        delay: &mut DELAY,
+        initial_ms: u8,
+        pulse_ms: u8,
+        active_low: bool,
+    ) -> Result<bool, Error<SPI::Error>> {
+        if !RST::CONNECTED {
+            // nothing to wait for without a pin
+            return Ok(false);
+        }
+        let timing = self.reset_timing.unwrap_or(ResetTiming {
+            initial_ms,
+            assert_ms: pulse_ms,
+            deassert_ms: 200,
+        });
+        raw::reset(&mut self.rst, delay, timing, active_low)
+            .map_err(|raw::PinFailed| Error::ResetPin)?;
+        Ok(true)
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8> + Transfer<u8, Error = <SPI as Write<u8>>::Error>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Reads `buffer.len()` bytes of data after a command
+    ///
+    /// Needs a board which connects the data line of the controller to MISO.
+    pub(crate) fn read_data(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &mut [u8],
+    ) -> Result<(), Error<<SPI as Write<u8>>::Error>> {
+        // high for data
+        let _ = self.dc.set_high();
+        self.cs.select();
+        buffer.fill(0x00);
+        let result = spi.transfer(buffer).map(drop);
+        self.cs.deselect();
+        result?;
+        Ok(())
+    }
+
+    /// Sends a command and reads `buffer.len()` bytes of its answer
+    ///
+    /// See [read_data()](Self::read_data()) for the requirements.
+    pub(crate) fn cmd_with_read<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        command: T,
+        buffer: &mut [u8],
+    ) -> Result<(), Error<<SPI as Write<u8>>::Error>> {
+        self.cmd(spi, command)?;
+        self.read_data(spi, buffer)
+    }
+}
+
+/// [raw::Bus] over an SPI device, keeps the first error for the generic layer
+///
+/// Writes longer than `chunk_size` are split into several writes to the device.
+struct SpiBus<'a, SPI: Write<u8>> {
+    spi: &'a mut SPI,
+    chunk_size: usize,
+    error: Option<SPI::Error>,
+}
+
+impl<SPI: Write<u8>> raw::Bus for SpiBus<'_, SPI> {
+    fn write(&mut self, data: &[u8]) -> Result<(), raw::BusError> {
+        let spi = &mut self.spi;
+        data.chunks(self.chunk_size)
+            .try_for_each(|chunk| spi.write(chunk))
+            .map_err(|error| {
+                self.error = Some(error);
+                raw::BusError
+            })
+    }
+}
+
+/// Non-generic part of the interface
+///
+/// Everything in here works on trait objects and is compiled only once.
+mod raw {
+    use super::*;
+
+    /// Output pin without its error type
+    pub(crate) trait OutputLevel {
+        /// Sets the level, `false` if the pin failed
+        fn set_level(&mut self, high: bool) -> bool;
+    }
+
+    impl<P: OutputPin> OutputLevel for P {
+        fn set_level(&mut self, high: bool) -> bool {
+            if high {
+                self.set_high().is_ok()
+            } else {
+                self.set_low().is_ok()
+            }
+        }
+    }
+
+    /// Busy pin without its error type, a failing read counts as idle
+    pub(crate) trait BusyInput {
+        fn is_busy(&self, is_busy_low: bool) -> bool;
+    }
+
+    impl<P: InputPin> BusyInput for P {
+        fn is_busy(&self, is_busy_low: bool) -> bool {
+            (is_busy_low && self.is_low().unwrap_or(false))
+                || (!is_busy_low && self.is_high().unwrap_or(false))
+        }
+    }
+
+    /// A write on the bus failed, the error itself is kept by the bus
+    pub(crate) struct BusError;
+
+    /// SPI bus without its error type
+    pub(crate) trait Bus {
+        fn write(&mut self, data: &[u8]) -> Result<(), BusError>;
+    }
+
+    /// Size of the stack buffer [send_x_times()] fills the repeated byte into
+    pub(crate) const FILL_CHUNK: usize = 256;
+
+    /// Default for the largest write to the SPI bus
+    ///
+    /// Linux limits SPI transfers to 4096 bytes by default, see
+    /// https://raspberrypi.stackexchange.com/questions/65595/spi-transfer-fails-with-buffer-size-greater-than-4096
+    /// and many DMA engines can't do much more either.
+    pub(crate) const DEFAULT_CHUNK_SIZE: usize = 4096;
+
+    /// The busy pin didn't report idle before the timeout
+    pub(crate) struct TimedOut;
+
+    /// Setting the level of a pin failed
+    pub(crate) struct PinFailed;
+
+    /// Optional clock, the measurement in progress and the last measured durations
+    #[derive(Default)]
+    pub(crate) struct Measurement {
+        clock: Option<&'static dyn Clock>,
+        pending: Option<(Operation, u32)>,
+        pub(crate) timings: Timings,
+    }
+
+    impl Measurement {
+        pub(crate) fn set_clock(&mut self, clock: Option<&'static dyn Clock>) {
+            self.clock = clock;
+            self.pending = None;
+        }
+
+        pub(crate) fn start(&mut self, operation: Operation) {
+            if let Some(clock) = self.clock {
+                self.pending = Some((operation, clock.now_ms()));
+            }
+        }
+
+        pub(crate) fn is_pending(&self) -> bool {
+            self.pending.is_some()
+        }
+
+        pub(crate) fn finish(&mut self) {
+            if let (Some(clock), Some((operation, start))) = (self.clock, self.pending.take()) {
+                self.timings
+                    .record(operation, clock.now_ms().wrapping_sub(start));
+            }
+        }
+
+        pub(crate) fn cancel(&mut self) {
+            self.pending = None;
+        }
+    }
+
+    /// Runs `transfer` with CS low, CS is released again when it fails too
+    fn selected(
+        cs: &mut dyn CsStrategy,
+        transfer: impl FnOnce() -> Result<(), BusError>,
+    ) -> Result<(), BusError> {
+        cs.select();
+        let result = transfer();
+        cs.deselect();
+        result
+    }
+
+    // spi write helper/abstraction function, the bus splits the data into chunks
+    fn write(cs: &mut dyn CsStrategy, bus: &mut dyn Bus, data: &[u8]) -> Result<(), BusError> {
+        selected(cs, || bus.write(data))
+    }
+
+    /// Sends `data` in a single transfer, DC low for commands and high for data
+    pub(crate) fn send(
+        cs: &mut dyn CsStrategy,
+        dc: &mut dyn OutputLevel,
+        bus: &mut dyn Bus,
+        is_data: bool,
+        data: &[u8],
+    ) -> Result<(), BusError> {
+        dc.set_level(is_data);
+        write(cs, bus, data)
+    }
+
+    /// Sends `data` with one transfer per byte
+    pub(crate) fn send_bytewise(
+        cs: &mut dyn CsStrategy,
+        dc: &mut dyn OutputLevel,
+        bus: &mut dyn Bus,
+        data: &[u8],
+    ) -> Result<(), BusError> {
+        dc.set_level(true);
+        data.iter().try_for_each(|&val| write(cs, bus, &[val]))
+    }
+
+    /// Sends `val` `repetitions` times, in writes of up to [FILL_CHUNK] bytes with CS kept low
+    pub(crate) fn send_x_times(
+        cs: &mut dyn CsStrategy,
+        dc: &mut dyn OutputLevel,
+        bus: &mut dyn Bus,
+        val: u8,
+        repetitions: u32,
+    ) -> Result<(), BusError> {
+        dc.set_level(true);
+        let chunk = [val; FILL_CHUNK];
+        selected(cs, || {
+            // counted in u32, `usize` only has 16 bits on some targets
+            let mut left = repetitions;
+            while left > 0 {
+                let len = left.min(FILL_CHUNK as u32);
+                bus.write(&chunk[..len as usize])?;
+                left -= len;
+            }
+            Ok(())
+        })
+    }
+
+    /// Sends the bytes `pattern(0..len)`, in writes of up to [FILL_CHUNK] bytes with CS kept low
+    #[cfg(feature = "test-patterns")]
+    pub(crate) fn send_pattern(
+        cs: &mut dyn CsStrategy,
+        dc: &mut dyn OutputLevel,
+        bus: &mut dyn Bus,
+        len: u32,
+        pattern: &dyn Fn(u32) -> u8,
+    ) -> Result<(), BusError> {
+        dc.set_level(true);
+        let mut chunk = [0u8; FILL_CHUNK];
+        selected(cs, || {
+            let mut index = 0;
+            while index < len {
+                let chunk_len = (len - index).min(FILL_CHUNK as u32);
+                for (offset, byte) in chunk[..chunk_len as usize].iter_mut().enumerate() {
+                    *byte = pattern(index + offset as u32);
+                }
+                bus.write(&chunk[..chunk_len as usize])?;
+                index += chunk_len;
+            }
+            Ok(())
+        })
+    }
+
+    pub(crate) fn wait_until_idle(busy: &dyn BusyInput, is_busy_low: bool) {
+        // //tested: worked without the delay for all tested devices
+        // //self.delay_ms(1);
+        while busy.is_busy(is_busy_low) {
+            // //tested: REMOVAL of DELAY: it's only waiting for the signal anyway and should continue work asap
+            // //old: shorten the time? it was 100 in the beginning
+            // //self.delay_ms(5);
+        }
+    }
+
+    pub(crate) fn wait_until_idle_with_timeout(
+        busy: &dyn BusyInput,
+        delay: &mut dyn DelayMs<u8>,
+        is_busy_low: bool,
+        timeout_ms: u32,
+    ) -> Result<(), TimedOut> {
+        let mut waited_ms = 0;
+        while busy.is_busy(is_busy_low) {
+            if waited_ms >= timeout_ms {
+                return Err(TimedOut);
+            }
+            delay.delay_ms(1);
+            waited_ms += 1;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn reset(
+        rst: &mut dyn crate::sealed::ResetLevel,
+        delay: &mut dyn DelayMs<u8>,
+        timing: ResetTiming,
+        active_low: bool,
+    ) -> Result<(), PinFailed> {
+        let mut set_level = |high| {
+            if rst.set_level(high) {
+                Ok(())
+            } else {
+                Err(PinFailed)
+            }
+        };
+        set_level(active_low)?;
+        delay.delay_ms(timing.initial_ms);
+
+        set_level(!active_low)?;
+        delay.delay_ms(timing.assert_ms);
+        set_level(active_low)?;
+        // the upstream libraries always sleep for 200ms here
         // 10ms works fine with just for the 7in5_v2 but this needs to be validated for other devices
-        delay.delay_ms(200);
+        delay.delay_ms(timing.deassert_ms);
+        Ok(())
+    }
+}
+
+/// First and last row in which `old` and `new` differ, `None` if they are equal
+///
+/// Rows are `width_bytes` long, a trailing partial row is compared as a row of its own.
+pub(crate) fn dirty_rows(old: &[u8], new: &[u8], width_bytes: usize) -> Option<(usize, usize)> {
+    let changed = |(_, (old, new)): &(usize, (&[u8], &[u8]))| old != new;
+    let rows = || {
+        old.chunks(width_bytes)
+            .zip(new.chunks(width_bytes))
+            .enumerate()
+    };
+    let (first, _) = rows().find(changed)?;
+    let (last, _) = rows().rev().find(changed)?;
+    Some((first, last))
+}
+
+/// SPI bus which knows the clock it was configured with
+///
+/// embedded-hal has no way to ask a bus for its frequency, implement this for the bus of the
+/// HAL to use [check_spi_frequency()].
+pub trait SpiFrequency {
+    /// Configured SPI clock in Hz
+    fn frequency_hz(&self) -> u32;
+}
+
+/// The SPI clock is faster than the display can follow, see [check_spi_frequency()]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FrequencyTooHighError {
+    /// Highest clock the display supports in Hz
+    pub max_hz: u32,
+    /// Configured clock of the bus in Hz
+    pub got_hz: u32,
+}
+
+/// Checks the clock of `spi` against `display_max`, the `MAX_SPI_FREQ_HZ` of the driver
+///
+/// A too fast clock doesn't fail on the bus, the controller just misses bits and the frame
+/// shows garbage. Call this before creating the driver:
+///
+/// ```rust
+/// use epd_waveshare::{check_spi_frequency, epd7in5, FrequencyTooHighError, SpiFrequency};
+///
+/// struct Bus(u32);
+/// impl SpiFrequency for Bus {
+///     fn frequency_hz(&self) -> u32 {
+///         self.0
+///     }
+/// }
+///
+/// assert_eq!(check_spi_frequency(&Bus(2_000_000), epd7in5::MAX_SPI_FREQ_HZ), Ok(()));
+/// assert_eq!(
+///     check_spi_frequency(&Bus(8_000_000), epd7in5::MAX_SPI_FREQ_HZ),
+///     Err(FrequencyTooHighError { max_hz: 4_000_000, got_hz: 8_000_000 })
+/// );
+/// ```
+pub fn check_spi_frequency<SPI: SpiFrequency>(
+    spi: &SPI,
+    display_max: u32,
+) -> Result<(), FrequencyTooHighError> {
+    let got_hz = spi.frequency_hz();
+    if got_hz > display_max {
+        return Err(FrequencyTooHighError {
+            max_hz: display_max,
+            got_hz,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timing::{Clock, Operation};
+    use core::convert::Infallible;
+    use core::sync::atomic::{AtomicU32, Ordering};
+
+    static NOW: AtomicU32 = AtomicU32::new(0);
+    static BUSY_READS_LEFT: AtomicU32 = AtomicU32::new(0);
+
+    struct FakeClock;
+    impl Clock for FakeClock {
+        fn now_ms(&self) -> u32 {
+            NOW.load(Ordering::SeqCst)
+        }
+    }
+    static CLOCK: FakeClock = FakeClock;
+
+    struct NoopSpi;
+    impl Write<u8> for NoopSpi {
+        type Error = Infallible;
+        fn write(&mut self, _words: &[u8]) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    struct NoopPin;
+    impl OutputPin for NoopPin {
+        type Error = Infallible;
+        fn set_low(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+        fn set_high(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    /// Busy (high) for a scripted number of reads, every read takes 10ms on the fake clock
+    struct ScriptedBusy;
+    impl InputPin for ScriptedBusy {
+        type Error = Infallible;
+        fn is_high(&self) -> Result<bool, Self::Error> {
+            NOW.fetch_add(10, Ordering::SeqCst);
+            let left = BUSY_READS_LEFT.load(Ordering::SeqCst);
+            if left > 0 {
+                BUSY_READS_LEFT.store(left - 1, Ordering::SeqCst);
+            }
+            Ok(left > 0)
+        }
+        fn is_low(&self) -> Result<bool, Self::Error> {
+            self.is_high().map(|high| !high)
+        }
+    }
+
+    struct NoopDelay;
+    impl DelayMs<u8> for NoopDelay {
+        fn delay_ms(&mut self, _ms: u8) {}
+    }
+
+    #[test]
+    fn timings_are_recorded_per_operation() {
+        let mut interface: DisplayInterface<NoopSpi, _, _, _, _, NoopDelay> =
+            DisplayInterface::new(NoopPin, ScriptedBusy, NoopPin, NoopPin);
+
+        // no clock, no measurements and no wait
+        BUSY_READS_LEFT.store(3, Ordering::SeqCst);
+        interface.start_measurement(Operation::FullRefresh);
+        assert_eq!(
+            interface.measure_busy_period(&mut NoopDelay, false, 100),
+            Ok(())
+        );
+        assert_eq!(BUSY_READS_LEFT.load(Ordering::SeqCst), 3);
+        assert_eq!(interface.timings(), Timings::default());
+        BUSY_READS_LEFT.store(0, Ordering::SeqCst);
+
+        interface.set_clock(Some(&CLOCK));
+
+        // busy for 3 reads and the 4th read reports idle
+        BUSY_READS_LEFT.store(3, Ordering::SeqCst);
+        interface.start_measurement(Operation::FullRefresh);
+        assert_eq!(
+            interface.measure_busy_period(&mut NoopDelay, false, 100),
+            Ok(())
+        );
+        assert_eq!(interface.timings().full_refresh_ms, Some(40));
+
+        // the other waits don't end the measurement
+        interface.start_measurement(Operation::QuickRefresh);
+        BUSY_READS_LEFT.store(2, Ordering::SeqCst);
+        interface.wait_until_idle(false);
+        assert_eq!(interface.timings().quick_refresh_ms, None);
+        BUSY_READS_LEFT.store(1, Ordering::SeqCst);
+        assert_eq!(
+            interface.measure_busy_period(&mut NoopDelay, false, 100),
+            Ok(())
+        );
+        assert_eq!(interface.timings().quick_refresh_ms, Some(50));
+        assert_eq!(interface.timings().full_refresh_ms, Some(40));
+        assert_eq!(interface.timings().clear_ms, None);
+
+        // a timeout drops the measurement
+        BUSY_READS_LEFT.store(5, Ordering::SeqCst);
+        interface.start_measurement(Operation::Clear);
+        assert_eq!(
+            interface.measure_busy_period(&mut NoopDelay, false, 2),
+            Err(Error::Timeout)
+        );
+        BUSY_READS_LEFT.store(0, Ordering::SeqCst);
+        assert_eq!(
+            interface.measure_busy_period(&mut NoopDelay, false, 2),
+            Ok(())
+        );
+        assert_eq!(interface.timings().clear_ms, None);
+    }
+
+    /// Busy (high) for the given number of reads
+    struct BusyFor(core::cell::Cell<u32>);
+    impl InputPin for BusyFor {
+        type Error = Infallible;
+        fn is_high(&self) -> Result<bool, Self::Error> {
+            let left = self.0.get();
+            self.0.set(left.saturating_sub(1));
+            Ok(left > 0)
+        }
+        fn is_low(&self) -> Result<bool, Self::Error> {
+            self.is_high().map(|high| !high)
+        }
+    }
+
+    struct CountingDelay(u32);
+    impl DelayMs<u8> for CountingDelay {
+        fn delay_ms(&mut self, ms: u8) {
+            self.0 += u32::from(ms);
+        }
+    }
+
+    #[test]
+    fn wait_with_timeout() {
+        let mut interface: DisplayInterface<NoopSpi, _, _, _, _, CountingDelay> =
+            DisplayInterface::new(NoopPin, BusyFor(core::cell::Cell::new(5)), NoopPin, NoopPin);
+        let mut delay = CountingDelay(0);
+
+        assert_eq!(
+            interface.wait_until_idle_with_timeout(&mut delay, false, 5),
+            Ok(())
+        );
+        assert_eq!(delay.0, 5);
+
+        interface.busy.0.set(6);
+        delay.0 = 0;
+        assert_eq!(
+            interface.wait_until_idle_with_timeout(&mut delay, false, 5),
+            Err(Error::Timeout)
+        );
+        assert_eq!(delay.0, 5);
+    }
+
+    extern crate std;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::vec;
+    use std::vec::Vec;
+
+    #[derive(Debug, PartialEq)]
+    enum Event {
+        Cs(bool),
+        Dc(bool),
+        Rst(bool),
+        Delay(u8),
+        Write(Vec<u8>),
+    }
+
+    type Log = Rc<RefCell<Vec<Event>>>;
+
+    /// Logs its writes, fails on writes starting with 0xEE
+    struct LoggingSpi(Log);
+    impl Write<u8> for LoggingSpi {
+        type Error = u8;
+        fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+            self.0.borrow_mut().push(Event::Write(words.to_vec()));
+            match words.first() {
+                Some(0xEE) => Err(0xEE),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    struct LoggingPin(Log, fn(bool) -> Event);
+    impl OutputPin for LoggingPin {
+        type Error = Infallible;
+        fn set_low(&mut self) -> Result<(), Self::Error> {
+            self.0.borrow_mut().push((self.1)(false));
+            Ok(())
+        }
+        fn set_high(&mut self) -> Result<(), Self::Error> {
+            self.0.borrow_mut().push((self.1)(true));
+            Ok(())
+        }
+    }
+
+    struct LoggingDelay(Log);
+    impl DelayMs<u8> for LoggingDelay {
+        fn delay_ms(&mut self, ms: u8) {
+            self.0.borrow_mut().push(Event::Delay(ms));
+        }
+    }
+
+    struct TestCommand(u8);
+    impl Command for TestCommand {
+        fn address(self) -> u8 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn pin_and_bus_sequence() {
+        use self::Event::*;
+
+        let log = Log::default();
+        let mut spi = LoggingSpi(log.clone());
+        let mut interface: DisplayInterface<LoggingSpi, _, BusyFor, _, NoopPin, NoopDelay> =
+            DisplayInterface::new(
+                LoggingPin(log.clone(), Cs),
+                BusyFor(core::cell::Cell::new(0)),
+                LoggingPin(log.clone(), Dc),
+                NoopPin,
+            );
+
+        interface
+            .cmd_with_data(&mut spi, TestCommand(0x12), &[0x01, 0x02])
+            .unwrap();
+        interface.data_block(&mut spi, &[0x03, 0x04]).unwrap();
+        interface.data_x_times(&mut spi, 0x05, 2).unwrap();
+        assert_eq!(
+            *log.borrow(),
+            [
+                Dc(false),
+                Cs(false),
+                Write(vec![0x12]),
+                Cs(true),
+                Dc(true),
+                Cs(false),
+                Write(vec![0x01]),
+                Cs(true),
+                Cs(false),
+                Write(vec![0x02]),
+                Cs(true),
+                Dc(true),
+                Cs(false),
+                Write(vec![0x03, 0x04]),
+                Cs(true),
+                Dc(true),
+                Cs(false),
+                Write(vec![0x05, 0x05]),
+                Cs(true),
+            ]
+        );
+    }
+
+    #[test]
+    fn cs_strategies() {
+        use self::Event::*;
+
+        let log = Log::default();
+        let mut spi = LoggingSpi(log.clone());
+        let mut interface: DisplayInterface<LoggingSpi, _, BusyFor, _, NoopPin, NoopDelay> =
+            DisplayInterface::new(
+                crate::ManualCs(LoggingPin(log.clone(), Cs)),
+                BusyFor(core::cell::Cell::new(0)),
+                NoopPin,
+                NoopPin,
+            );
+        interface.data_x_times(&mut spi, 0x05, 2).unwrap();
+        assert_eq!(
+            log.borrow_mut().drain(..).collect::<Vec<_>>(),
+            [Cs(false), Write(vec![0x05, 0x05]), Cs(true)]
+        );
+
+        let mut interface: DisplayInterface<LoggingSpi, _, BusyFor, _, NoopPin, NoopDelay> =
+            DisplayInterface::new(
+                crate::AutoCs,
+                BusyFor(core::cell::Cell::new(0)),
+                NoopPin,
+                NoopPin,
+            );
+        interface
+            .cmd_with_data(&mut spi, TestCommand(0x12), &[0x01])
+            .unwrap();
+        assert_eq!(*log.borrow(), [Write(vec![0x12]), Write(vec![0x01])]);
+    }
+
+    #[test]
+    fn reset_pulse_follows_the_polarity() {
+        use self::Event::*;
+
+        let log = Log::default();
+        let mut delay = LoggingDelay(log.clone());
+        let mut interface: DisplayInterface<LoggingSpi, _, BusyFor, _, _, LoggingDelay> =
+            DisplayInterface::new(
+                NoopPin,
+                BusyFor(core::cell::Cell::new(0)),
+                NoopPin,
+                LoggingPin(log.clone(), Rst),
+            );
+
+        interface.reset(&mut delay, 10, 2).unwrap();
+        assert_eq!(
+            log.borrow_mut().drain(..).collect::<Vec<_>>(),
+            [
+                Rst(true),
+                Delay(10),
+                Rst(false),
+                Delay(2),
+                Rst(true),
+                Delay(200)
+            ]
+        );
+
+        interface.reset_with(&mut delay, 50, 20, false).unwrap();
+        assert_eq!(
+            *log.borrow(),
+            [
+                Rst(false),
+                Delay(50),
+                Rst(true),
+                Delay(20),
+                Rst(false),
+                Delay(200)
+            ]
+        );
+
+        log.borrow_mut().clear();
+        interface.set_reset_timing(Some(ResetTiming {
+            initial_ms: 1,
+            assert_ms: 2,
+            deassert_ms: 3,
+        }));
+        interface.reset(&mut delay, 10, 2).unwrap();
+        assert_eq!(
+            log.borrow_mut().drain(..).collect::<Vec<_>>(),
+            [
+                Rst(true),
+                Delay(1),
+                Rst(false),
+                Delay(2),
+                Rst(true),
+                Delay(3)
+            ]
+        );
+
+        interface.set_reset_timing(None);
+        interface.reset(&mut delay, 10, 2).unwrap();
+        assert_eq!(log.borrow()[5], Delay(200));
+    }
+
+    #[test]
+    fn reset_without_pin() {
+        let log = Log::default();
+        let mut delay = LoggingDelay(log.clone());
+        let mut interface: DisplayInterface<LoggingSpi, _, BusyFor, _, _, LoggingDelay> =
+            DisplayInterface::new(
+                NoopPin,
+                BusyFor(core::cell::Cell::new(0)),
+                NoopPin,
+                crate::NoReset,
+            );
+
+        assert_eq!(interface.check_reset_pin(), Err(Error::NoResetPin));
+        assert_eq!(interface.reset(&mut delay, 10, 2), Ok(false));
+        assert!(log.borrow().is_empty());
+    }
+
+    #[test]
+    fn reset_pin_errors_are_returned() {
+        struct FailingPin;
+        impl OutputPin for FailingPin {
+            type Error = ();
+            fn set_low(&mut self) -> Result<(), Self::Error> {
+                Err(())
+            }
+            fn set_high(&mut self) -> Result<(), Self::Error> {
+                Err(())
+            }
+        }
+
+        let mut interface: DisplayInterface<LoggingSpi, _, BusyFor, _, _, NoopDelay> =
+            DisplayInterface::new(
+                NoopPin,
+                BusyFor(core::cell::Cell::new(0)),
+                NoopPin,
+                FailingPin,
+            );
+        assert_eq!(interface.check_reset_pin(), Ok(()));
+        assert_eq!(interface.reset(&mut NoopDelay, 10, 2), Err(Error::ResetPin));
+    }
+
+    #[test]
+    fn data_x_times_sends_full_chunks() {
+        let log = Log::default();
+        let mut spi = LoggingSpi(log.clone());
+        let mut interface: DisplayInterface<LoggingSpi, _, BusyFor, _, NoopPin, NoopDelay> =
+            DisplayInterface::new(NoopPin, BusyFor(core::cell::Cell::new(0)), NoopPin, NoopPin);
+
+        interface.data_x_times(&mut spi, 0xAB, 600).unwrap();
+        interface.data_x_times(&mut spi, 0xCD, 0).unwrap();
+
+        let writes: Vec<Vec<u8>> = log
+            .borrow()
+            .iter()
+            .map(|event| match event {
+                Event::Write(data) => data.clone(),
+                other => panic!("unexpected {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            writes.iter().map(Vec::len).collect::<Vec<_>>(),
+            [raw::FILL_CHUNK, raw::FILL_CHUNK, 600 - 2 * raw::FILL_CHUNK]
+        );
+        assert!(writes.concat().iter().all(|&b| b == 0xAB));
+    }
+
+    #[test]
+    fn fills_of_a_whole_frame_keep_cs_low() {
+        use self::Event::*;
+
+        let log = Log::default();
+        let mut spi = LoggingSpi(log.clone());
+        let mut interface: DisplayInterface<LoggingSpi, _, BusyFor, _, NoopPin, NoopDelay> =
+            DisplayInterface::new(
+                LoggingPin(log.clone(), Cs),
+                BusyFor(core::cell::Cell::new(0)),
+                LoggingPin(log.clone(), Dc),
+                NoopPin,
+            );
+
+        // one plane of the 5.83" display
+        interface
+            .data_x_times(&mut spi, 0xFF, 648 * 480 / 8)
+            .unwrap();
+        let log_len = log.borrow().len();
+        assert_eq!(log_len, 3 + 152);
+        assert_eq!(log.borrow()[..2], [Dc(true), Cs(false)]);
+        assert_eq!(log.borrow()[log_len - 1], Cs(true));
+        assert_eq!(
+            log.borrow()[log_len - 2],
+            Write([0xFF; 38880 % raw::FILL_CHUNK].to_vec())
+        );
+
+        // no empty write for a multiple of the chunk
+        log.borrow_mut().clear();
+        interface
+            .data_x_times(&mut spi, 0x00, 2 * raw::FILL_CHUNK as u32)
+            .unwrap();
+        assert_eq!(
+            *log.borrow(),
+            [
+                Dc(true),
+                Cs(false),
+                Write([0x00; raw::FILL_CHUNK].to_vec()),
+                Write([0x00; raw::FILL_CHUNK].to_vec()),
+                Cs(true),
+            ]
+        );
+    }
+
+    #[test]
+    fn long_blocks_are_split_with_cs_low_and_dc_high() {
+        use self::Event::*;
+
+        let log = Log::default();
+        let mut spi = LoggingSpi(log.clone());
+        let mut interface: DisplayInterface<LoggingSpi, _, BusyFor, _, NoopPin, NoopDelay> =
+            DisplayInterface::new(
+                LoggingPin(log.clone(), Cs),
+                BusyFor(core::cell::Cell::new(0)),
+                LoggingPin(log.clone(), Dc),
+                NoopPin,
+            );
+
+        let data: Vec<u8> = (0..10_000u32).map(|i| i as u8).collect();
+        interface.data_block(&mut spi, &data).unwrap();
+        assert_eq!(
+            *log.borrow(),
+            [
+                Dc(true),
+                Cs(false),
+                Write(data[..4096].to_vec()),
+                Write(data[4096..8192].to_vec()),
+                Write(data[8192..].to_vec()),
+                Cs(true),
+            ]
+        );
+
+        log.borrow_mut().clear();
+        interface.set_chunk_size(3000);
+        interface.data_block(&mut spi, &data[..6001]).unwrap();
+        assert_eq!(
+            *log.borrow(),
+            [
+                Dc(true),
+                Cs(false),
+                Write(data[..3000].to_vec()),
+                Write(data[3000..6000].to_vec()),
+                Write(data[6000..6001].to_vec()),
+                Cs(true),
+            ]
+        );
+    }
+
+    #[test]
+    fn spi_errors_stop_the_transfer() {
+        use self::Event::*;
+
+        let log = Log::default();
+        let mut spi = LoggingSpi(log.clone());
+        let mut interface: DisplayInterface<LoggingSpi, _, BusyFor, _, NoopPin, NoopDelay> =
+            DisplayInterface::new(
+                LoggingPin(log.clone(), Cs),
+                BusyFor(core::cell::Cell::new(0)),
+                LoggingPin(log.clone(), Dc),
+                NoopPin,
+            );
+
+        assert_eq!(
+            interface.data(&mut spi, &[0x01, 0xEE, 0x02]),
+            Err(Error::Spi(0xEE))
+        );
+        assert_eq!(
+            *log.borrow(),
+            [
+                Dc(true),
+                Cs(false),
+                Write(vec![0x01]),
+                Cs(true),
+                Cs(false),
+                Write(vec![0xEE]),
+                Cs(true),
+            ]
+        );
+        // fills release CS as well
+        log.borrow_mut().clear();
+        assert_eq!(
+            interface.data_x_times(&mut spi, 0xEE, 3),
+            Err(Error::Spi(0xEE))
+        );
+        assert_eq!(
+            *log.borrow(),
+            [Dc(true), Cs(false), Write(vec![0xEE; 3]), Cs(true)]
+        );
+    }
+
+    #[test]
+    fn dirty_rows_span_the_changed_rows() {
+        let old = [0xFF; 4 * 6];
+        let mut new = old;
+        assert_eq!(dirty_rows(&old, &new, 4), None);
+
+        new[4 * 2 + 1] = 0x00;
+        assert_eq!(dirty_rows(&old, &new, 4), Some((2, 2)));
+
+        new[4 * 4 + 3] = 0x00;
+        assert_eq!(dirty_rows(&old, &new, 4), Some((2, 4)));
+
+        new[0] = 0x00;
+        new[4 * 5] = 0x00;
+        assert_eq!(dirty_rows(&old, &new, 4), Some((0, 5)));
+    }
+
+    #[test]
+    fn diff_and_update_rows_sends_the_dirty_rows_only() {
+        let log = Log::default();
+        let mut spi = LoggingSpi(log.clone());
+        let mut interface: DisplayInterface<LoggingSpi, _, BusyFor, _, NoopPin, NoopDelay> =
+            DisplayInterface::new(NoopPin, BusyFor(core::cell::Cell::new(0)), NoopPin, NoopPin);
+        let written = |log: &Log| -> Vec<u8> {
+            log.borrow_mut()
+                .drain(..)
+                .flat_map(|event| match event {
+                    Event::Write(words) => words,
+                    _ => vec![],
+                })
+                .collect()
+        };
+
+        let old = [0xFF; 2 * 300];
+        let mut new = old;
+        assert_eq!(
+            interface.diff_and_update_rows(&mut spi, &old, &new, 2),
+            Ok(0)
+        );
+        assert!(log.borrow().is_empty());
+
+        new[2 * 257] = 0x0F;
+        new[2 * 259 + 1] = 0xF0;
+        assert_eq!(
+            interface.diff_and_update_rows(&mut spi, &old, &new, 2),
+            Ok(6)
+        );
+        assert_eq!(
+            written(&log),
+            [
+                0x45, 0x01, 0x01, 0x03, 0x01, // Y window 257..=259
+                0x4F, 0x01, 0x01, // Y counter 257
+                0x24, 0x0F, 0xFF, 0xFF, 0xFF, 0xFF, 0xF0,
+            ]
+        );
+
+        assert_eq!(
+            interface.diff_and_update_rows(&mut spi, &old, &new[..598], 2),
+            Err(Error::BufferSize {
+                expected: 600,
+                got: 598
+            })
+        );
+        assert_eq!(
+            interface.diff_and_update_rows(&mut spi, &old, &new, 7),
+            Err(Error::InvalidRegion)
+        );
+        assert_eq!(
+            interface.diff_and_update_rows(&mut spi, &old, &new, 0),
+            Err(Error::InvalidRegion)
+        );
+        assert!(log.borrow().is_empty());
+    }
+
+    #[test]
+    fn spi_frequency_up_to_the_maximum() {
+        struct Bus(u32);
+        impl SpiFrequency for Bus {
+            fn frequency_hz(&self) -> u32 {
+                self.0
+            }
+        }
+
+        let max = crate::epd1in54_v2::MAX_SPI_FREQ_HZ;
+        assert_eq!(check_spi_frequency(&Bus(max), max), Ok(()));
+        assert_eq!(
+            check_spi_frequency(&Bus(max + 1), max),
+            Err(FrequencyTooHighError {
+                max_hz: max,
+                got_hz: max + 1
+            })
+        );
     }
 }
@@ -0,0 +1,58 @@
+//! Commands and registers of the IT8951 controller
+
+/// IT8951 host commands
+///
+/// The commands are 16 bit words, unlike the 8 bit commands of the other controllers.
+#[allow(dead_code)]
+#[derive(Copy, Clone)]
+pub(crate) enum Command {
+    /// Wakes the controller up from standby or sleep
+    SysRun = 0x0001,
+    /// Standby, the clocks keep running
+    Standby = 0x0002,
+    /// Sleep, the clocks and the panel power are off
+    Sleep = 0x0003,
+    /// Reads a register: one data word with the address, then one word is read
+    RegRead = 0x0010,
+    /// Writes a register: one data word with the address and one with the value
+    RegWrite = 0x0011,
+    /// Starts loading a full frame into the image buffer
+    LoadImage = 0x0020,
+    /// Starts loading an area into the image buffer: format, x, y, width and height
+    LoadImageArea = 0x0021,
+    /// Ends loading the image data
+    LoadImageEnd = 0x0022,
+    /// Refreshes an area of the panel: x, y, width, height and waveform mode
+    DisplayArea = 0x0034,
+    /// Reads or writes VCOM: `0` and the read word, or `1` and the value in mV
+    Vcom = 0x0039,
+    /// Reads the 20 words of the [DeviceInfo](super::DeviceInfo)
+    GetDeviceInfo = 0x0302,
+}
+
+impl Command {
+    /// Returns the address of the command
+    pub(crate) fn address(self) -> u16 {
+        self as u16
+    }
+}
+
+/// IT8951 registers used by the driver
+#[derive(Copy, Clone)]
+pub(crate) enum Register {
+    /// Host interface control, `1` enables the packed write mode
+    I80Cpcr = 0x0004,
+    /// Low word of the image buffer address for the next load
+    Lisar = 0x0208,
+    /// High word of the image buffer address for the next load
+    LisarHigh = 0x020A,
+    /// LUT engine status, `0` once all refreshes are done
+    Lutafsr = 0x1224,
+}
+
+impl Register {
+    /// Returns the address of the register
+    pub(crate) fn address(self) -> u16 {
+        self as u16
+    }
+}
@@ -0,0 +1,205 @@
+//! SPI protocol of the IT8951
+//!
+//! Every transfer starts with a preamble word telling the controller whether a command, data
+//! or a read follows. All words are sent with the high byte first and the controller pulls
+//! HRDY low while it can't take the next word.
+
+use super::command::{Command, Register};
+use crate::error::Error;
+use crate::CsStrategy;
+use core::marker::PhantomData;
+use embedded_hal::{
+    blocking::{
+        delay::*,
+        spi::{Transfer, Write},
+    },
+    digital::v2::*,
+};
+
+const PREAMBLE_COMMAND: u16 = 0x6000;
+const PREAMBLE_WRITE: u16 = 0x0000;
+const PREAMBLE_READ: u16 = 0x1000;
+
+/// Words sent per SPI write when streaming pixel data
+const CHUNK_WORDS: usize = 128;
+
+/// Connection to the IT8951: CS, HRDY and RST, there is no DC pin
+pub(crate) struct Interface<SPI, CS, HRDY, RST, DELAY> {
+    _spi: PhantomData<SPI>,
+    _delay: PhantomData<DELAY>,
+    /// CS for SPI
+    cs: CS,
+    /// Low while the controller is busy with the last word
+    hrdy: HRDY,
+    /// Pin for Resetting
+    rst: RST,
+}
+
+impl<SPI, CS, HRDY, RST, DELAY> Interface<SPI, CS, HRDY, RST, DELAY>
+where
+    SPI: Write<u8> + Transfer<u8, Error = <SPI as Write<u8>>::Error>,
+    CS: CsStrategy,
+    HRDY: InputPin,
+    RST: OutputPin,
+    DELAY: DelayMs<u8>,
+{
+    pub(crate) fn new(cs: CS, hrdy: HRDY, rst: RST) -> Self {
+        Interface {
+            _spi: PhantomData,
+            _delay: PhantomData,
+            cs,
+            hrdy,
+            rst,
+        }
+    }
+
+    /// Pulls RST low for `duration` ms and waits until the controller is ready
+    pub(crate) fn reset(&mut self, delay: &mut DELAY, duration: u8) {
+        let _ = self.rst.set_high();
+        delay.delay_ms(10);
+        let _ = self.rst.set_low();
+        delay.delay_ms(duration);
+        let _ = self.rst.set_high();
+        delay.delay_ms(200);
+        self.wait_ready();
+    }
+
+    /// Waits until HRDY is high
+    ///
+    /// HRDY only stays low for a few microseconds per word, so it's polled without delay.
+    pub(crate) fn wait_ready(&self) {
+        while self.hrdy.is_low().unwrap_or(false) {}
+    }
+
+    fn write_words(
+        &mut self,
+        spi: &mut SPI,
+        words: &[u16],
+    ) -> Result<(), Error<<SPI as Write<u8>>::Error>> {
+        for word in words {
+            spi.write(&word.to_be_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Runs `f` with CS low, CS is released again if `f` fails
+    fn selected<F>(&mut self, spi: &mut SPI, f: F) -> Result<(), Error<<SPI as Write<u8>>::Error>>
+    where
+        F: FnOnce(&mut Self, &mut SPI) -> Result<(), Error<<SPI as Write<u8>>::Error>>,
+    {
+        self.wait_ready();
+        self.cs.select();
+        let result = f(self, spi);
+        self.cs.deselect();
+        result
+    }
+
+    /// Sends a command
+    pub(crate) fn cmd(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+    ) -> Result<(), Error<<SPI as Write<u8>>::Error>> {
+        self.selected(spi, |interface, spi| {
+            interface.write_words(spi, &[PREAMBLE_COMMAND])?;
+            interface.wait_ready();
+            interface.write_words(spi, &[command.address()])
+        })
+    }
+
+    /// Sends data words, the controller takes one word per transfer
+    pub(crate) fn data(
+        &mut self,
+        spi: &mut SPI,
+        words: &[u16],
+    ) -> Result<(), Error<<SPI as Write<u8>>::Error>> {
+        for &word in words {
+            self.selected(spi, |interface, spi| {
+                interface.write_words(spi, &[PREAMBLE_WRITE])?;
+                interface.wait_ready();
+                interface.write_words(spi, &[word])
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Sends a command followed by its argument words
+    pub(crate) fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        words: &[u16],
+    ) -> Result<(), Error<<SPI as Write<u8>>::Error>> {
+        self.cmd(spi, command)?;
+        self.data(spi, words)
+    }
+
+    /// Streams pixel data in one transfer
+    ///
+    /// `bytes` holds little endian words, like the image buffer of the controller, so the two
+    /// bytes of every word are swapped on the wire. An odd last byte is padded with `0x00`.
+    pub(crate) fn pixels(
+        &mut self,
+        spi: &mut SPI,
+        bytes: &[u8],
+    ) -> Result<(), Error<<SPI as Write<u8>>::Error>> {
+        self.selected(spi, |interface, spi| {
+            interface.write_words(spi, &[PREAMBLE_WRITE])?;
+            interface.wait_ready();
+            let mut chunk = [0u8; 2 * CHUNK_WORDS];
+            for bytes in bytes.chunks(chunk.len()) {
+                let len = bytes.len() + bytes.len() % 2;
+                for (word, pair) in chunk[..len].chunks_mut(2).zip(bytes.chunks(2)) {
+                    word[0] = pair.get(1).copied().unwrap_or(0x00);
+                    word[1] = pair[0];
+                }
+                spi.write(&chunk[..len])?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Reads `words.len()` words after a command
+    ///
+    /// The controller answers a read preamble with one dummy word, which is dropped.
+    pub(crate) fn read(
+        &mut self,
+        spi: &mut SPI,
+        words: &mut [u16],
+    ) -> Result<(), Error<<SPI as Write<u8>>::Error>> {
+        self.selected(spi, |interface, spi| {
+            interface.write_words(spi, &[PREAMBLE_READ])?;
+            interface.wait_ready();
+            spi.transfer(&mut [0x00; 2])?;
+            interface.wait_ready();
+            for word in words.iter_mut() {
+                let mut bytes = [0x00; 2];
+                spi.transfer(&mut bytes)?;
+                *word = u16::from_be_bytes(bytes);
+            }
+            Ok(())
+        })
+    }
+
+    /// Reads a register
+    pub(crate) fn read_register(
+        &mut self,
+        spi: &mut SPI,
+        register: Register,
+    ) -> Result<u16, Error<<SPI as Write<u8>>::Error>> {
+        self.cmd_with_data(spi, Command::RegRead, &[register.address()])?;
+        let mut value = [0];
+        self.read(spi, &mut value)?;
+        Ok(value[0])
+    }
+
+    /// Writes a register
+    pub(crate) fn write_register(
+        &mut self,
+        spi: &mut SPI,
+        register: Register,
+        value: u16,
+    ) -> Result<(), Error<<SPI as Write<u8>>::Error>> {
+        self.cmd_with_data(spi, Command::RegWrite, &[register.address(), value])
+    }
+}
@@ -0,0 +1,576 @@
+//! A driver for the Waveshare HD e-Paper HATs with the IT8951 controller via SPI
+//!
+//! The 6", 7.8", 9.7" and 10.3" HD panels are driven by an IT8951 timing controller with its
+//! own image buffer and waveforms. It speaks a different SPI protocol than the other
+//! controllers: 16 bit commands and data words behind preamble words, register reads and
+//! no DC pin. The driver therefore has its own interface and doesn't implement
+//! [WaveshareDisplay](crate::prelude::WaveshareDisplay), the panel size is read from the
+//! controller in [new()](It8951::new()).
+//!
+//! The buffers hold 16 gray levels with 4 bits per pixel, see [gray_buffer_len()]. Frames are
+//! refreshed with the GC16 waveform by default, [RefreshLut::Quick] selects the fast A2
+//! waveform which only shows black and white.
+//!
+//! # Example
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::*;
+//!# fn main() -> Result<(), epd_waveshare::error::Error<MockError>> {
+//!use epd_waveshare::{it8951::*, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let cs_pin = pin::Mock::new(&[]);
+//!# let hrdy_in = pin::Mock::new(&[]);
+//!# let rst = pin::Mock::new(&[]);
+//!# let mut delay = delay::MockNoop::new();
+//!
+//!// Setup the controller, VCOM is printed on the flex cable of the panel
+//!let mut epd = It8951::new(&mut spi, cs_pin, hrdy_in, rst, &mut delay, 1500)?;
+//!
+//!// Draw a gray square
+//!let buffer = [0x88; gray_buffer_len(64, 64)];
+//!epd.update_partial_frame(&mut spi, &mut delay, &buffer, 100, 100, 64, 64)?;
+//!epd.display_partial_frame(&mut spi, &mut delay, 100, 100, 64, 64)?;
+//!
+//!// Set the controller to sleep
+//!epd.sleep(&mut spi)?;
+//!# Ok(())
+//!# }
+//!```
+//!
+//! # References
+//!
+//! - [IT8951 datasheet](https://www.waveshare.com/w/upload/1/18/IT8951_D_V0.2.4.3_20170728.pdf)
+//! - [Waveshare C driver](https://github.com/waveshare/IT8951-ePaper)
+
+use embedded_hal::{
+    blocking::{
+        delay::*,
+        spi::{Transfer, Write},
+    },
+    digital::v2::*,
+};
+
+use crate::error::Error;
+use crate::traits::RefreshLut;
+use crate::window::WindowLimits;
+use crate::CsStrategy;
+
+pub(crate) mod command;
+use self::command::{Command, Register};
+
+mod interface;
+use self::interface::Interface;
+
+/// Longest time in milliseconds to wait for a refresh before giving up with [Error::Timeout]
+pub const BUSY_TIMEOUT_MS: u32 = 10_000;
+/// Fastest SPI clock of the controller in Hz, see
+/// [check_spi_frequency()](crate::check_spi_frequency())
+pub const MAX_SPI_FREQ_HZ: u32 = 12_000_000;
+
+/// 4 bits per pixel, the first pixel in the high nibble
+const PIXEL_FORMAT_4BPP: u16 = 2 << 4;
+/// Keeps the byte order of the buffer, see [Interface::pixels()]
+const BIG_ENDIAN: u16 = 1 << 8;
+
+/// Waveform clearing the panel to white
+const MODE_INIT: u16 = 0;
+/// Waveform with 16 gray levels
+const MODE_GC16: u16 = 2;
+
+/// Length in bytes of a buffer for a `width` x `height` area with 4 bits per pixel
+///
+/// The controller loads whole 16 bit words, so every row is padded to a multiple of 4 pixels.
+/// `0x0` is black and `0xF` white, the first pixel of a byte is in the high nibble.
+pub const fn gray_buffer_len(width: u32, height: u32) -> usize {
+    width.div_ceil(4) as usize * 2 * height as usize
+}
+
+/// Information read from the controller during [It8951::new()]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeviceInfo {
+    /// Width of the panel in pixels
+    pub width: u16,
+    /// Height of the panel in pixels
+    pub height: u16,
+    /// Address of the image buffer in the memory of the controller
+    pub image_buffer_address: u32,
+    /// Firmware version, an ASCII string padded with `0x00`
+    pub firmware_version: [u8; 16],
+    /// Version of the waveforms, an ASCII string padded with `0x00`, e.g. `M641` for the 6"
+    /// panel
+    pub lut_version: [u8; 16],
+}
+
+impl DeviceInfo {
+    fn from_words(words: &[u16; 20]) -> Self {
+        let string = |words: &[u16]| {
+            let mut string = [0u8; 16];
+            for (bytes, word) in string.chunks_mut(2).zip(words) {
+                bytes.copy_from_slice(&word.to_le_bytes());
+            }
+            string
+        };
+        DeviceInfo {
+            width: words[0],
+            height: words[1],
+            image_buffer_address: u32::from(words[2]) | u32::from(words[3]) << 16,
+            firmware_version: string(&words[4..12]),
+            lut_version: string(&words[12..20]),
+        }
+    }
+
+    /// The 6" panel numbers the A2 waveform differently and needs areas aligned to 32 bits
+    fn is_m641(&self) -> bool {
+        self.lut_version.starts_with(b"M641")
+    }
+
+    fn a2_mode(&self) -> u16 {
+        if self.is_m641() {
+            4
+        } else {
+            6
+        }
+    }
+}
+
+/// IT8951 driver
+pub struct It8951<SPI, CS, HRDY, RST, DELAY> {
+    /// Connection Interface
+    interface: Interface<SPI, CS, HRDY, RST, DELAY>,
+    /// Panel size and image buffer address
+    info: DeviceInfo,
+    /// Waveform used by the refreshes
+    refresh: RefreshLut,
+}
+
+impl<SPI, CS, HRDY, RST, DELAY> It8951<SPI, CS, HRDY, RST, DELAY>
+where
+    SPI: Write<u8> + Transfer<u8, Error = <SPI as Write<u8>>::Error>,
+    CS: CsStrategy,
+    HRDY: InputPin,
+    RST: OutputPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Creates the driver, resets the controller and reads the [DeviceInfo]
+    ///
+    /// `vcom_mv` is the magnitude of the negative VCOM in mV printed on the flex cable of the
+    /// panel, e.g. `1500` for -1.50V. Needs a board which connects MISO, the panel size is read
+    /// from the controller.
+    pub fn new(
+        spi: &mut SPI,
+        cs: CS,
+        hrdy: HRDY,
+        rst: RST,
+        delay: &mut DELAY,
+        vcom_mv: u16,
+    ) -> Result<Self, Error<<SPI as Write<u8>>::Error>> {
+        let mut interface = Interface::new(cs, hrdy, rst);
+        interface.reset(delay, 10);
+        interface.cmd(spi, Command::SysRun)?;
+
+        interface.cmd(spi, Command::GetDeviceInfo)?;
+        let mut words = [0u16; 20];
+        interface.read(spi, &mut words)?;
+        let info = DeviceInfo::from_words(&words);
+
+        // packed write mode, needed to stream the pixel data
+        interface.write_register(spi, Register::I80Cpcr, 0x0001)?;
+        interface.cmd_with_data(spi, Command::Vcom, &[0x0001, vcom_mv])?;
+
+        Ok(It8951 {
+            interface,
+            info,
+            refresh: RefreshLut::Full,
+        })
+    }
+
+    /// Panel size, image buffer address and versions read from the controller
+    pub fn device_info(&self) -> &DeviceInfo {
+        &self.info
+    }
+
+    /// Width of the panel in pixels
+    pub fn width(&self) -> u32 {
+        u32::from(self.info.width)
+    }
+
+    /// Height of the panel in pixels
+    pub fn height(&self) -> u32 {
+        u32::from(self.info.height)
+    }
+
+    /// Selects the waveform of the following refreshes
+    ///
+    /// [RefreshLut::Full] and `None` select GC16 with 16 gray levels, [RefreshLut::Quick] the
+    /// fast A2 waveform with black and white only. The waveforms are stored in the flash of
+    /// the controller, a [RefreshLut::Custom] returns [Error::UnsupportedLut].
+    pub fn set_lut(
+        &mut self,
+        _spi: &mut SPI,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Error<<SPI as Write<u8>>::Error>> {
+        match refresh_rate.unwrap_or_default() {
+            RefreshLut::Custom(_) => Err(Error::UnsupportedLut),
+            refresh => {
+                self.refresh = refresh;
+                Ok(())
+            }
+        }
+    }
+
+    /// Loads a `width` x `height` area at `x`, `y` into the image buffer
+    ///
+    /// `buffer` holds 4 bits per pixel, see [gray_buffer_len()]. Waits until running refreshes
+    /// are done, the controller can't load an area while it's read by a refresh. The 6" panel
+    /// needs `x` and `width` to be multiples of 8, otherwise [Error::InvalidRegion] is
+    /// returned.
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<<SPI as Write<u8>>::Error>> {
+        self.check_area(x, y, width, height)?;
+        let expected = gray_buffer_len(width, height);
+        if buffer.len() != expected {
+            return Err(Error::BufferSize {
+                expected,
+                got: buffer.len(),
+            });
+        }
+
+        self.wait_until_idle(spi, delay)?;
+        let address = self.info.image_buffer_address;
+        self.interface
+            .write_register(spi, Register::LisarHigh, (address >> 16) as u16)?;
+        self.interface
+            .write_register(spi, Register::Lisar, address as u16)?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::LoadImageArea,
+            &[
+                BIG_ENDIAN | PIXEL_FORMAT_4BPP,
+                x as u16,
+                y as u16,
+                width as u16,
+                height as u16,
+            ],
+        )?;
+        self.interface.pixels(spi, buffer)?;
+        self.interface.cmd(spi, Command::LoadImageEnd)
+    }
+
+    /// Loads a full frame into the image buffer
+    pub fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<<SPI as Write<u8>>::Error>> {
+        let (width, height) = (self.width(), self.height());
+        self.update_partial_frame(spi, delay, buffer, 0, 0, width, height)
+    }
+
+    /// Refreshes a `width` x `height` area at `x`, `y` with the selected waveform
+    ///
+    /// Returns once the refresh is started, the next load or [wait_until_idle()] waits for it.
+    ///
+    /// [wait_until_idle()]: It8951::wait_until_idle()
+    pub fn display_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<<SPI as Write<u8>>::Error>> {
+        self.check_area(x, y, width, height)?;
+        let mode = match self.refresh {
+            RefreshLut::Quick => self.info.a2_mode(),
+            _ => MODE_GC16,
+        };
+        self.display_area(spi, x, y, width, height, mode)
+    }
+
+    /// Refreshes the whole panel with the selected waveform
+    pub fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<<SPI as Write<u8>>::Error>> {
+        let (width, height) = (self.width(), self.height());
+        self.display_partial_frame(spi, delay, 0, 0, width, height)
+    }
+
+    /// Loads a full frame and refreshes the panel
+    pub fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<<SPI as Write<u8>>::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)
+    }
+
+    /// Clears the panel to white with the INIT waveform
+    ///
+    /// The INIT waveform doesn't read the image buffer and removes the ghosting left by A2
+    /// refreshes, the image buffer keeps its content.
+    pub fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<<SPI as Write<u8>>::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        let (width, height) = (self.width(), self.height());
+        self.display_area(spi, 0, 0, width, height, MODE_INIT)
+    }
+
+    /// Waits until the controller finished all refreshes
+    ///
+    /// Returns [Error::Timeout] after [BUSY_TIMEOUT_MS].
+    pub fn wait_until_idle(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<<SPI as Write<u8>>::Error>> {
+        for _ in 0..BUSY_TIMEOUT_MS {
+            if self.interface.read_register(spi, Register::Lutafsr)? == 0 {
+                return Ok(());
+            }
+            delay.delay_ms(1);
+        }
+        Err(Error::Timeout)
+    }
+
+    /// Puts the controller to sleep, [wake_up()](It8951::wake_up()) wakes it up again
+    ///
+    /// The image buffer keeps its content.
+    pub fn sleep(&mut self, spi: &mut SPI) -> Result<(), Error<<SPI as Write<u8>>::Error>> {
+        self.interface.cmd(spi, Command::Sleep)
+    }
+
+    /// Wakes the controller up after [sleep()](It8951::sleep())
+    pub fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Error<<SPI as Write<u8>>::Error>> {
+        self.interface.cmd(spi, Command::SysRun)
+    }
+
+    fn check_area(
+        &self,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<<SPI as Write<u8>>::Error>> {
+        WindowLimits::new(self.width(), self.height(), u32::MAX, u32::MAX)
+            .validate(x, y, width, height)?;
+        if self.info.is_m641() && !(x % 8 == 0 && width % 8 == 0) {
+            return Err(Error::InvalidRegion);
+        }
+        Ok(())
+    }
+
+    fn display_area(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        mode: u16,
+    ) -> Result<(), Error<<SPI as Write<u8>>::Error>> {
+        self.interface.cmd_with_data(
+            spi,
+            Command::DisplayArea,
+            &[x as u16, y as u16, width as u16, height as u16, mode],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{HighPin, NoDelay};
+    extern crate std;
+    use core::convert::Infallible;
+    use std::collections::VecDeque;
+    use std::vec::Vec;
+
+    /// Records the written bytes and answers reads from a queue, `0xFF` once it's empty
+    #[derive(Default)]
+    struct FakeSpi {
+        written: Vec<u8>,
+        answers: VecDeque<u8>,
+    }
+    impl Write<u8> for FakeSpi {
+        type Error = Infallible;
+        fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+            self.written.extend_from_slice(words);
+            Ok(())
+        }
+    }
+    impl Transfer<u8> for FakeSpi {
+        type Error = Infallible;
+        fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Self::Error> {
+            for word in words.iter_mut() {
+                *word = self.answers.pop_front().unwrap_or(0xFF);
+            }
+            Ok(words)
+        }
+    }
+    impl FakeSpi {
+        /// Queues the answer to one read: the dummy word and `words`
+        fn answer(&mut self, words: &[u16]) {
+            self.answers.extend([0x00, 0x00]);
+            self.answers
+                .extend(words.iter().flat_map(|w| w.to_be_bytes()));
+        }
+    }
+
+    fn cmd(command: Command) -> Vec<u8> {
+        [0x6000, command.address()]
+            .iter()
+            .flat_map(|w: &u16| w.to_be_bytes())
+            .collect()
+    }
+
+    fn data(words: &[u16]) -> Vec<u8> {
+        words
+            .iter()
+            .flat_map(|w| [0x00, 0x00, (w >> 8) as u8, *w as u8])
+            .collect()
+    }
+
+    fn device_info(lut_version: &[u8; 4]) -> [u16; 20] {
+        let mut words = [0u16; 20];
+        words[0] = 1872;
+        words[1] = 1404;
+        words[2] = 0x36E0;
+        words[3] = 0x0012;
+        words[12] = u16::from_le_bytes([lut_version[0], lut_version[1]]);
+        words[13] = u16::from_le_bytes([lut_version[2], lut_version[3]]);
+        words
+    }
+
+    fn test_epd(
+        lut_version: &[u8; 4],
+    ) -> (FakeSpi, It8951<FakeSpi, HighPin, HighPin, HighPin, NoDelay>) {
+        let mut spi = FakeSpi::default();
+        spi.answer(&device_info(lut_version));
+        let epd = It8951::new(&mut spi, HighPin, HighPin, HighPin, &mut NoDelay, 1530).unwrap();
+        spi.written.clear();
+        (spi, epd)
+    }
+
+    #[test]
+    fn new_reads_the_device_info() {
+        let mut spi = FakeSpi::default();
+        spi.answer(&device_info(b"M841"));
+        let epd = It8951::new(&mut spi, HighPin, HighPin, HighPin, &mut NoDelay, 1530).unwrap();
+
+        assert_eq!((epd.width(), epd.height()), (1872, 1404));
+        assert_eq!(epd.device_info().image_buffer_address, 0x0012_36E0);
+        assert_eq!(&epd.device_info().lut_version[..5], b"M841\0");
+
+        let mut expected = cmd(Command::SysRun);
+        expected.extend(cmd(Command::GetDeviceInfo));
+        expected.extend([0x10, 0x00]);
+        expected.extend(cmd(Command::RegWrite));
+        expected.extend(data(&[0x0004, 0x0001]));
+        expected.extend(cmd(Command::Vcom));
+        expected.extend(data(&[0x0001, 1530]));
+        assert_eq!(spi.written, expected);
+    }
+
+    #[test]
+    fn partial_frame_is_loaded_as_4bpp_words() {
+        let (mut spi, mut epd) = test_epd(b"M841");
+        spi.answer(&[0x0000]);
+        let buffer = [0x01, 0x23, 0x45, 0x67];
+        epd.update_partial_frame(&mut spi, &mut NoDelay, &buffer, 10, 20, 4, 2)
+            .unwrap();
+
+        let mut expected = cmd(Command::RegRead);
+        expected.extend(data(&[0x1224]));
+        expected.extend([0x10, 0x00]);
+        expected.extend(cmd(Command::RegWrite));
+        expected.extend(data(&[0x020A, 0x0012]));
+        expected.extend(cmd(Command::RegWrite));
+        expected.extend(data(&[0x0208, 0x36E0]));
+        expected.extend(cmd(Command::LoadImageArea));
+        expected.extend(data(&[0x0120, 10, 20, 4, 2]));
+        expected.extend([0x00, 0x00, 0x23, 0x01, 0x67, 0x45]);
+        expected.extend(cmd(Command::LoadImageEnd));
+        assert_eq!(spi.written, expected);
+
+        assert_eq!(
+            epd.update_partial_frame(&mut spi, &mut NoDelay, &buffer, 0, 0, 5, 2),
+            Err(Error::BufferSize {
+                expected: 8,
+                got: 4
+            })
+        );
+        assert_eq!(
+            epd.update_partial_frame(&mut spi, &mut NoDelay, &buffer, 1870, 0, 4, 2),
+            Err(Error::WindowOutOfRange)
+        );
+    }
+
+    #[test]
+    fn quick_lut_selects_a2() {
+        let (mut spi, mut epd) = test_epd(b"M841");
+        epd.display_partial_frame(&mut spi, &mut NoDelay, 0, 0, 8, 8)
+            .unwrap();
+        epd.set_lut(&mut spi, Some(RefreshLut::Quick)).unwrap();
+        epd.display_partial_frame(&mut spi, &mut NoDelay, 0, 0, 8, 8)
+            .unwrap();
+
+        let mut expected = cmd(Command::DisplayArea);
+        expected.extend(data(&[0, 0, 8, 8, MODE_GC16]));
+        expected.extend(cmd(Command::DisplayArea));
+        expected.extend(data(&[0, 0, 8, 8, 6]));
+        assert_eq!(spi.written, expected);
+
+        assert_eq!(
+            epd.set_lut(&mut spi, Some(RefreshLut::Custom(&[0x00]))),
+            Err(Error::UnsupportedLut)
+        );
+    }
+
+    #[test]
+    fn six_inch_panel_needs_aligned_areas() {
+        let (mut spi, mut epd) = test_epd(b"M641");
+        epd.set_lut(&mut spi, Some(RefreshLut::Quick)).unwrap();
+        epd.display_partial_frame(&mut spi, &mut NoDelay, 8, 0, 16, 4)
+            .unwrap();
+
+        let mut expected = cmd(Command::DisplayArea);
+        expected.extend(data(&[8, 0, 16, 4, 4]));
+        assert_eq!(spi.written, expected);
+
+        assert_eq!(
+            epd.display_partial_frame(&mut spi, &mut NoDelay, 4, 0, 16, 4),
+            Err(Error::InvalidRegion)
+        );
+    }
+
+    #[test]
+    fn busy_controller_times_out() {
+        let (mut spi, mut epd) = test_epd(b"M841");
+        assert_eq!(
+            epd.wait_until_idle(&mut spi, &mut NoDelay),
+            Err(Error::Timeout)
+        );
+        spi.answer(&[0x0000]);
+        assert_eq!(epd.wait_until_idle(&mut spi, &mut NoDelay), Ok(()));
+    }
+}
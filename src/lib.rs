@@ -12,7 +12,7 @@
 //!
 //!```rust, no_run
 //!# use embedded_hal_mock::*;
-//!# fn main() -> Result<(), MockError> {
+//!# fn main() -> Result<(), epd_waveshare::error::Error<MockError>> {
 //!use embedded_graphics::{
 //!    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
 //!};
@@ -60,50 +60,104 @@
 //!
 //! Maximum speed tested by myself was 8Mhz but more should be possible (Ben Krasnow used 18Mhz with his implemenation)
 //!
+//! Every driver module has a `MAX_SPI_FREQ_HZ` with the fastest clock of its controller,
+//! [check_spi_frequency()] compares it with the clock of a bus implementing [SpiFrequency].
+//!
 #![no_std]
 #![deny(missing_docs)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "graphics")]
+pub mod diff;
+#[cfg(feature = "graphics")]
+pub mod dirty_region;
 #[cfg(feature = "graphics")]
 pub mod graphics;
+#[cfg(feature = "graphics")]
+pub mod tri_color_graphics;
+
+#[cfg(feature = "readout")]
+pub mod readout;
 
 mod traits;
 
+pub mod bitpack;
 pub mod color;
+pub mod dither;
+pub mod error;
+pub(crate) mod external_sensor;
+pub mod it8951;
+pub mod monitor;
+pub mod multi;
+pub mod panel_setting;
+pub mod power;
+
+#[cfg(feature = "test-patterns")]
+mod test_pattern;
+#[cfg(test)]
+mod test_utils;
+pub mod timing;
+pub mod window;
 
 /// Interface for the physical connection between display and the controlling device
 mod interface;
 
+pub use crate::interface::{check_spi_frequency, FrequencyTooHighError, SpiFrequency};
+
+pub mod epd10in2;
+pub mod epd13in3k;
+pub mod epd1in02;
 pub mod epd1in54;
 pub mod epd1in54_v2;
 pub mod epd1in54b;
 pub mod epd1in54c;
 pub mod epd2in13_v2;
 pub mod epd2in13bc;
+pub mod epd2in66;
+pub mod epd2in66b;
 pub mod epd2in7b;
+pub mod epd2in7b_v2;
 pub mod epd2in9;
 pub mod epd2in9_v2;
 pub mod epd2in9bc;
+pub mod epd2in9d;
+pub mod epd3in7;
 pub mod epd4in2;
+pub mod epd4in26;
+pub mod epd4in2_v2;
+pub mod epd4in2b;
 pub mod epd5in65f;
+pub mod epd5in79;
 pub mod epd5in83b_v2;
+pub mod epd6in0f;
+pub mod epd7in3f;
+pub mod epd7in3g;
 pub mod epd7in5;
 pub mod epd7in5_hd;
 pub mod epd7in5_v2;
 pub mod epd7in5_v3;
+pub mod epd7in5b_v2;
 
 pub(crate) mod type_a;
 
 /// Includes everything important besides the chosen Display
 pub mod prelude {
-    pub use crate::color::{Color, OctColor, TriColor};
+    pub use crate::color::{Color, OctColor, QuadColor, TriColor};
     pub use crate::traits::{
-        QuickRefresh, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+        BorderColor, QuickRefresh, RefreshLut, WaveshareDisplay, WaveshareGrayscaleDisplay,
+        WaveshareThreeColorDisplay,
     };
 
-    pub use crate::SPI_MODE;
+    pub use crate::timing::{Clock, ResetTiming, Timings};
+
+    pub use crate::{AutoCs, CsStrategy, DummyOutputPin, ManualCs, NoReset, ResetPin, SPI_MODE};
 
     #[cfg(feature = "graphics")]
-    pub use crate::graphics::{Display, DisplayRotation, OctDisplay, TriDisplay};
+    pub use crate::graphics::{
+        Display, DisplayRotation, GrayDisplay, OctDisplay, QuadDisplay, TriDisplay,
+    };
 }
 
 /// Computes the needed buffer length. Takes care of rounding up in case width
@@ -116,7 +170,24 @@ pub mod prelude {
 /// \[XXXXX210\]\[76543210\]...\[76543210\] | height
 /// \[XXXXX210\]\[76543210\]...\[76543210\] v
 pub const fn buffer_len(width: usize, height: usize) -> usize {
-    (width + 7) / 8 * height
+    width.div_ceil(8) * height
+}
+
+/// Returns [Error::BufferSize](error::Error::BufferSize) if `buffer` doesn't hold exactly one
+/// bit per pixel of a `width` x `height` area
+pub(crate) fn check_buffer_len<E>(
+    buffer: &[u8],
+    width: u32,
+    height: u32,
+) -> Result<(), error::Error<E>> {
+    let expected = buffer_len(width as usize, height as usize);
+    if buffer.len() != expected {
+        return Err(error::Error::BufferSize {
+            expected,
+            got: buffer.len(),
+        });
+    }
+    Ok(())
 }
 
 use embedded_hal::spi::{Mode, Phase, Polarity};
@@ -127,3 +198,120 @@ pub const SPI_MODE: Mode = Mode {
     phase: Phase::CaptureOnFirstTransition,
     polarity: Polarity::IdleLow,
 };
+
+/// Output pin which does nothing, for lines which aren't connected to a GPIO
+///
+/// As CS it works like [AutoCs]. A reset line which isn't connected needs [NoReset] instead,
+/// with this pin the drivers can't tell that the reset pulse has no effect.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DummyOutputPin;
+
+impl embedded_hal::digital::v2::OutputPin for DummyOutputPin {
+    type Error = core::convert::Infallible;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Reset pin of the display
+///
+/// Every [OutputPin](embedded_hal::digital::v2::OutputPin) is one, errors of the pin are
+/// returned as [Error::ResetPin](crate::error::Error::ResetPin). [NoReset] is for boards
+/// without a reset line.
+pub trait ResetPin: sealed::ResetLevel {
+    /// `false` if there is no reset line to pulse
+    const CONNECTED: bool;
+}
+
+impl<P: embedded_hal::digital::v2::OutputPin> ResetPin for P {
+    const CONNECTED: bool = true;
+}
+
+/// Reset pin for boards which don't connect the reset line to a GPIO
+///
+/// The drivers skip the reset pulse on `new` and use the software reset of the controller if
+/// it has one. `wake_up` returns [Error::NoResetPin](crate::error::Error::NoResetPin), as only
+/// the reset pin ends the deep sleep.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NoReset;
+
+impl ResetPin for NoReset {
+    const CONNECTED: bool = false;
+}
+
+mod sealed {
+    /// Sets the level of a [ResetPin](super::ResetPin), `false` if the pin failed
+    pub trait ResetLevel {
+        fn set_level(&mut self, high: bool) -> bool;
+    }
+
+    impl<P: embedded_hal::digital::v2::OutputPin> ResetLevel for P {
+        fn set_level(&mut self, high: bool) -> bool {
+            if high {
+                self.set_high().is_ok()
+            } else {
+                self.set_low().is_ok()
+            }
+        }
+    }
+
+    impl ResetLevel for super::NoReset {
+        fn set_level(&mut self, _high: bool) -> bool {
+            true
+        }
+    }
+}
+
+/// Selects the display on the SPI bus around every transfer
+///
+/// Every [OutputPin](embedded_hal::digital::v2::OutputPin) is one, it's pulled low while the
+/// display is selected. [AutoCs] is for SPI peripherals which drive CS themselves.
+pub trait CsStrategy {
+    /// Called before the bytes of a command or its data are written
+    fn select(&mut self);
+
+    /// Called once they are written
+    fn deselect(&mut self);
+}
+
+impl<P: embedded_hal::digital::v2::OutputPin> CsStrategy for P {
+    fn select(&mut self) {
+        let _ = self.set_low();
+    }
+
+    fn deselect(&mut self) {
+        let _ = self.set_high();
+    }
+}
+
+/// CS driven by a GPIO, low while the display is selected
+///
+/// The same as passing the pin itself, for code which wants to spell it out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ManualCs<CS>(pub CS);
+
+impl<CS: embedded_hal::digital::v2::OutputPin> CsStrategy for ManualCs<CS> {
+    fn select(&mut self) {
+        self.0.select();
+    }
+
+    fn deselect(&mut self) {
+        self.0.deselect();
+    }
+}
+
+/// CS driven by the SPI peripheral or tied low on the board, used by the `new_without_cs`
+/// constructors of the drivers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AutoCs;
+
+impl CsStrategy for AutoCs {
+    fn select(&mut self) {}
+
+    fn deselect(&mut self) {}
+}
@@ -0,0 +1,154 @@
+//! Detection of frames which were never shown
+//!
+//! A refresh can fail while the application believes it succeeded, e.g. when an error is
+//! swallowed further up. Drivers with a `verify_last_frame` method count the frames they
+//! refreshed and remember failed updates and refreshes, so the application can check from
+//! time to time that the panel shows what it thinks it shows:
+//!
+//! - Drivers which can read back their RAM write a small signature of the generation into
+//!   pixels which aren't visible and compare it with the RAM, see [FrameCheck::Verified].
+//!   The driver documents where the signature is placed.
+//! - All other drivers only check their own bookkeeping, see [FrameCheck::Consistent]. This
+//!   finds errors which were ignored, but not a controller which lost its RAM, e.g. after a
+//!   brownout.
+
+/// Result of `verify_last_frame`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameCheck {
+    /// No frame was refreshed since the driver was created
+    NoFrame,
+    /// The last update and refresh succeeded and the RAM still holds the signature of the
+    /// frame
+    Verified {
+        /// Number of the frame, counts the refreshes of new frames and wraps around
+        generation: u32,
+    },
+    /// The last update and refresh succeeded, the driver can't read the RAM to confirm it
+    Consistent {
+        /// Number of the frame, counts the refreshes of new frames and wraps around
+        generation: u32,
+    },
+    /// A new frame was written but not refreshed yet
+    Pending {
+        /// Number of the frame which is still shown
+        generation: u32,
+    },
+    /// An update or refresh failed after the frame `generation` or the RAM doesn't hold its
+    /// signature anymore, the panel may show something else
+    Stale {
+        /// Number of the last frame which was refreshed without an error
+        generation: u32,
+    },
+}
+
+/// Bookkeeping of the drivers for [FrameCheck]
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct FrameMonitor {
+    /// Refreshes of new frames
+    generation: u32,
+    /// A new frame was written since the last refresh
+    written: bool,
+    /// The last attempt to write a frame failed
+    write_failed: bool,
+    /// The last refresh failed
+    refresh_failed: bool,
+}
+
+impl FrameMonitor {
+    /// Generation of the frame which is written now
+    pub(crate) fn next_generation(&self) -> u32 {
+        self.generation.wrapping_add(1)
+    }
+
+    /// Records the result of writing a whole frame
+    pub(crate) fn written<T, E>(&mut self, result: &Result<T, E>) {
+        self.write_failed = result.is_err();
+        self.written = result.is_ok();
+    }
+
+    /// Records the result of a refresh
+    pub(crate) fn displayed<T, E>(&mut self, result: &Result<T, E>) {
+        self.refresh_failed = result.is_err();
+        if result.is_ok() && self.written {
+            self.generation = self.next_generation();
+            self.written = false;
+        }
+    }
+
+    /// Result of the bookkeeping alone, `Consistent` if nothing went wrong
+    pub(crate) fn check(&self) -> FrameCheck {
+        let generation = self.generation;
+        if self.write_failed || self.refresh_failed {
+            FrameCheck::Stale { generation }
+        } else if self.written {
+            FrameCheck::Pending { generation }
+        } else if generation == 0 {
+            FrameCheck::NoFrame
+        } else {
+            FrameCheck::Consistent { generation }
+        }
+    }
+
+    /// Upgrades a consistent result with the comparison of the signature read from the RAM
+    pub(crate) fn verify(&self, signature_matches: bool) -> FrameCheck {
+        match self.check() {
+            FrameCheck::Consistent { generation } if signature_matches => {
+                FrameCheck::Verified { generation }
+            }
+            FrameCheck::Consistent { generation } => FrameCheck::Stale { generation },
+            check => check,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OK: Result<(), ()> = Ok(());
+    const ERR: Result<(), ()> = Err(());
+
+    #[test]
+    fn generations_count_new_frames() {
+        let mut monitor = FrameMonitor::default();
+        assert_eq!(monitor.check(), FrameCheck::NoFrame);
+
+        monitor.written(&OK);
+        assert_eq!(monitor.check(), FrameCheck::Pending { generation: 0 });
+        monitor.displayed(&OK);
+        assert_eq!(monitor.check(), FrameCheck::Consistent { generation: 1 });
+
+        // showing the same frame again doesn't start a new generation
+        monitor.displayed(&OK);
+        assert_eq!(monitor.check(), FrameCheck::Consistent { generation: 1 });
+        assert_eq!(monitor.next_generation(), 2);
+    }
+
+    #[test]
+    fn failures_stay_until_they_are_repaired() {
+        let mut monitor = FrameMonitor::default();
+        monitor.written(&OK);
+        monitor.displayed(&OK);
+
+        // the failed frame is never refreshed, even if the refresh succeeds
+        monitor.written(&ERR);
+        monitor.displayed(&OK);
+        assert_eq!(monitor.check(), FrameCheck::Stale { generation: 1 });
+
+        monitor.written(&OK);
+        monitor.displayed(&ERR);
+        assert_eq!(monitor.check(), FrameCheck::Stale { generation: 1 });
+        monitor.displayed(&OK);
+        assert_eq!(monitor.check(), FrameCheck::Consistent { generation: 2 });
+    }
+
+    #[test]
+    fn signature_upgrades_only_consistent_frames() {
+        let mut monitor = FrameMonitor::default();
+        assert_eq!(monitor.verify(true), FrameCheck::NoFrame);
+        monitor.written(&OK);
+        monitor.displayed(&OK);
+        assert_eq!(monitor.verify(true), FrameCheck::Verified { generation: 1 });
+        assert_eq!(monitor.verify(false), FrameCheck::Stale { generation: 1 });
+    }
+}
@@ -0,0 +1,409 @@
+//! Several displays on one SPI bus
+//!
+//! Small panels are often driven from the same MOSI and CLK lines, each with its own CS, DC,
+//! RST and BUSY pins. The drivers only need the bus while one of their methods runs, so the
+//! displays can simply share the `&mut SPI`. [MultiDisplay] holds two drivers of any type and
+//! [MultiDisplayChain] a fixed number of drivers of the same type; both pass the updates and
+//! refreshes to the addressed display and clear and put all of them to sleep at once.
+//!
+//! The drivers are created as usual and moved into the wrapper:
+//!
+//! ```rust, ignore
+//! let first = Epd2in9::new(&mut spi, cs1, busy1, dc1, rst1, &mut delay)?;
+//! let second = Epd1in54::new(&mut spi, cs2, busy2, dc2, rst2, &mut delay)?;
+//! let mut displays = MultiDisplay::new(first, second);
+//!
+//! displays.clear_frame(&mut spi, &mut delay)?;
+//! displays.update_and_display_frame(DisplayIndex::Second, &mut spi, &buffer, &mut delay)?;
+//! ```
+
+use crate::error::Error;
+use crate::traits::WaveshareDisplay;
+use crate::{CsStrategy, ResetPin};
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::*,
+};
+
+/// Addresses one of the displays of a [MultiDisplay]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisplayIndex {
+    /// The first display, `D1`
+    First,
+    /// The second display, `D2`
+    Second,
+}
+
+/// Two displays of possibly different types on the same SPI bus
+///
+/// The pin types of both drivers are inferred from `D1` and `D2` in every call.
+pub struct MultiDisplay<D1, D2> {
+    first: D1,
+    second: D2,
+}
+
+impl<D1, D2> MultiDisplay<D1, D2> {
+    /// Combines two initialised drivers
+    pub fn new(first: D1, second: D2) -> Self {
+        MultiDisplay { first, second }
+    }
+
+    /// The first driver, e.g. for settings which only it has
+    pub fn first(&mut self) -> &mut D1 {
+        &mut self.first
+    }
+
+    /// The second driver, e.g. for settings which only it has
+    pub fn second(&mut self) -> &mut D2 {
+        &mut self.second
+    }
+
+    /// Returns both drivers
+    pub fn into_inner(self) -> (D1, D2) {
+        (self.first, self.second)
+    }
+
+    /// Transmits a full frame to the SRAM of the addressed display
+    pub fn update_frame<SPI, CS1, BUSY1, DC1, RST1, CS2, BUSY2, DC2, RST2, DELAY>(
+        &mut self,
+        index: DisplayIndex,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>>
+    where
+        SPI: Write<u8>,
+        CS1: OutputPin,
+        BUSY1: InputPin,
+        DC1: OutputPin,
+        RST1: OutputPin,
+        CS2: OutputPin,
+        BUSY2: InputPin,
+        DC2: OutputPin,
+        RST2: OutputPin,
+        DELAY: DelayMs<u8>,
+        D1: WaveshareDisplay<SPI, CS1, BUSY1, DC1, RST1, DELAY>,
+        D2: WaveshareDisplay<SPI, CS2, BUSY2, DC2, RST2, DELAY>,
+    {
+        match index {
+            DisplayIndex::First => self.first.update_frame(spi, buffer, delay),
+            DisplayIndex::Second => self.second.update_frame(spi, buffer, delay),
+        }
+    }
+
+    /// Refreshes the addressed display with the frame in its SRAM
+    pub fn display_frame<SPI, CS1, BUSY1, DC1, RST1, CS2, BUSY2, DC2, RST2, DELAY>(
+        &mut self,
+        index: DisplayIndex,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>>
+    where
+        SPI: Write<u8>,
+        CS1: OutputPin,
+        BUSY1: InputPin,
+        DC1: OutputPin,
+        RST1: OutputPin,
+        CS2: OutputPin,
+        BUSY2: InputPin,
+        DC2: OutputPin,
+        RST2: OutputPin,
+        DELAY: DelayMs<u8>,
+        D1: WaveshareDisplay<SPI, CS1, BUSY1, DC1, RST1, DELAY>,
+        D2: WaveshareDisplay<SPI, CS2, BUSY2, DC2, RST2, DELAY>,
+    {
+        match index {
+            DisplayIndex::First => self.first.display_frame(spi, delay),
+            DisplayIndex::Second => self.second.display_frame(spi, delay),
+        }
+    }
+
+    /// Transmits a full frame to the addressed display and refreshes it
+    pub fn update_and_display_frame<SPI, CS1, BUSY1, DC1, RST1, CS2, BUSY2, DC2, RST2, DELAY>(
+        &mut self,
+        index: DisplayIndex,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>>
+    where
+        SPI: Write<u8>,
+        CS1: OutputPin,
+        BUSY1: InputPin,
+        DC1: OutputPin,
+        RST1: OutputPin,
+        CS2: OutputPin,
+        BUSY2: InputPin,
+        DC2: OutputPin,
+        RST2: OutputPin,
+        DELAY: DelayMs<u8>,
+        D1: WaveshareDisplay<SPI, CS1, BUSY1, DC1, RST1, DELAY>,
+        D2: WaveshareDisplay<SPI, CS2, BUSY2, DC2, RST2, DELAY>,
+    {
+        match index {
+            DisplayIndex::First => self.first.update_and_display_frame(spi, buffer, delay),
+            DisplayIndex::Second => self.second.update_and_display_frame(spi, buffer, delay),
+        }
+    }
+
+    /// Clears the frames of both displays to their background colors
+    ///
+    /// Stops at the first error, the second display isn't cleared if the first one fails.
+    pub fn clear_frame<SPI, CS1, BUSY1, DC1, RST1, CS2, BUSY2, DC2, RST2, DELAY>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>>
+    where
+        SPI: Write<u8>,
+        CS1: OutputPin,
+        BUSY1: InputPin,
+        DC1: OutputPin,
+        RST1: OutputPin,
+        CS2: OutputPin,
+        BUSY2: InputPin,
+        DC2: OutputPin,
+        RST2: OutputPin,
+        DELAY: DelayMs<u8>,
+        D1: WaveshareDisplay<SPI, CS1, BUSY1, DC1, RST1, DELAY>,
+        D2: WaveshareDisplay<SPI, CS2, BUSY2, DC2, RST2, DELAY>,
+    {
+        self.first.clear_frame(spi, delay)?;
+        self.second.clear_frame(spi, delay)
+    }
+
+    /// Puts both displays to sleep
+    pub fn sleep<SPI, CS1, BUSY1, DC1, RST1, CS2, BUSY2, DC2, RST2, DELAY>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>>
+    where
+        SPI: Write<u8>,
+        CS1: OutputPin,
+        BUSY1: InputPin,
+        DC1: OutputPin,
+        RST1: OutputPin,
+        CS2: OutputPin,
+        BUSY2: InputPin,
+        DC2: OutputPin,
+        RST2: OutputPin,
+        DELAY: DelayMs<u8>,
+        D1: WaveshareDisplay<SPI, CS1, BUSY1, DC1, RST1, DELAY>,
+        D2: WaveshareDisplay<SPI, CS2, BUSY2, DC2, RST2, DELAY>,
+    {
+        self.first.sleep(spi, delay)?;
+        self.second.sleep(spi, delay)
+    }
+}
+
+/// `N` displays of the same type on the same SPI bus
+///
+/// The displays are addressed by their position in the array given to
+/// [new()](MultiDisplayChain::new()). The methods panic if the index is out of range, like
+/// indexing a slice.
+pub struct MultiDisplayChain<D, const N: usize> {
+    displays: [D; N],
+}
+
+impl<D, const N: usize> MultiDisplayChain<D, N> {
+    /// Combines `N` initialised drivers
+    pub fn new(displays: [D; N]) -> Self {
+        MultiDisplayChain { displays }
+    }
+
+    /// The driver at `index`, e.g. for settings of a single display
+    pub fn get(&mut self, index: usize) -> &mut D {
+        &mut self.displays[index]
+    }
+
+    /// Returns the drivers
+    pub fn into_inner(self) -> [D; N] {
+        self.displays
+    }
+
+    /// Transmits a full frame to the SRAM of the display at `index`
+    pub fn update_frame<SPI, CS, BUSY, DC, RST, DELAY>(
+        &mut self,
+        index: usize,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>>
+    where
+        SPI: Write<u8>,
+        CS: CsStrategy,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: ResetPin,
+        DELAY: DelayMs<u8>,
+        D: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    {
+        self.displays[index].update_frame(spi, buffer, delay)
+    }
+
+    /// Refreshes the display at `index` with the frame in its SRAM
+    pub fn display_frame<SPI, CS, BUSY, DC, RST, DELAY>(
+        &mut self,
+        index: usize,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>>
+    where
+        SPI: Write<u8>,
+        CS: CsStrategy,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: ResetPin,
+        DELAY: DelayMs<u8>,
+        D: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    {
+        self.displays[index].display_frame(spi, delay)
+    }
+
+    /// Transmits a full frame to the display at `index` and refreshes it
+    pub fn update_and_display_frame<SPI, CS, BUSY, DC, RST, DELAY>(
+        &mut self,
+        index: usize,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>>
+    where
+        SPI: Write<u8>,
+        CS: CsStrategy,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: ResetPin,
+        DELAY: DelayMs<u8>,
+        D: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    {
+        self.displays[index].update_and_display_frame(spi, buffer, delay)
+    }
+
+    /// Clears the frames of all displays to their background colors, stops at the first error
+    pub fn clear_frame<SPI, CS, BUSY, DC, RST, DELAY>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>>
+    where
+        SPI: Write<u8>,
+        CS: CsStrategy,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: ResetPin,
+        DELAY: DelayMs<u8>,
+        D: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    {
+        self.displays
+            .iter_mut()
+            .try_for_each(|display| display.clear_frame(spi, delay))
+    }
+
+    /// Puts all displays to sleep, stops at the first error
+    pub fn sleep<SPI, CS, BUSY, DC, RST, DELAY>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>>
+    where
+        SPI: Write<u8>,
+        CS: CsStrategy,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: ResetPin,
+        DELAY: DelayMs<u8>,
+        D: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    {
+        self.displays
+            .iter_mut()
+            .try_for_each(|display| display.sleep(spi, delay))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epd1in54_v2::Epd1in54;
+    use crate::epd2in9_v2::{self, Epd2in9};
+    use crate::test_utils::{CsPin, LowPin, NoDelay, RecordingSpi};
+
+    #[test]
+    fn only_the_addressed_display_is_updated() {
+        let (mut spi, _) = RecordingSpi::new();
+        let selected = spi.selected.clone();
+        let first = Epd2in9::new(
+            &mut spi,
+            CsPin(1, selected.clone()),
+            LowPin,
+            LowPin,
+            LowPin,
+            &mut NoDelay,
+        )
+        .unwrap();
+        let second = Epd1in54::new(
+            &mut spi,
+            CsPin(2, selected),
+            LowPin,
+            LowPin,
+            LowPin,
+            &mut NoDelay,
+        )
+        .unwrap();
+        let mut displays = MultiDisplay::new(first, second);
+
+        spi.clear();
+        let buffer = [0xFF; epd2in9_v2::WIDTH as usize / 8 * epd2in9_v2::HEIGHT as usize];
+        displays
+            .update_and_display_frame(DisplayIndex::First, &mut spi, &buffer, &mut NoDelay)
+            .unwrap();
+        assert_eq!(spi.receivers(), [1]);
+
+        spi.clear();
+        displays
+            .display_frame(DisplayIndex::Second, &mut spi, &mut NoDelay)
+            .unwrap();
+        assert_eq!(spi.receivers(), [2]);
+
+        // the buffer of the 2.9" display doesn't fit the 1.54" display
+        assert!(displays
+            .update_frame(DisplayIndex::Second, &mut spi, &buffer, &mut NoDelay)
+            .is_err());
+
+        spi.clear();
+        displays.clear_frame(&mut spi, &mut NoDelay).unwrap();
+        assert_eq!(spi.receivers(), [1, 2]);
+    }
+
+    #[test]
+    fn chain_addresses_the_displays_by_position() {
+        let (mut spi, _) = RecordingSpi::new();
+        let selected = spi.selected.clone();
+        let mut new = |id| {
+            Epd2in9::new(
+                &mut spi,
+                CsPin(id, selected.clone()),
+                LowPin,
+                LowPin,
+                LowPin,
+                &mut NoDelay,
+            )
+            .unwrap()
+        };
+        let mut displays = MultiDisplayChain::new([new(1), new(2), new(3)]);
+
+        spi.clear();
+        displays.display_frame(2, &mut spi, &mut NoDelay).unwrap();
+        assert_eq!(spi.receivers(), [3]);
+
+        spi.clear();
+        displays.sleep(&mut spi, &mut NoDelay).unwrap();
+        assert_eq!(spi.receivers(), [1, 2, 3]);
+        // a sleeping display has to be woken up first
+        assert!(displays
+            .get(1)
+            .update_frame(&mut spi, &[0x00; 4736], &mut NoDelay)
+            .is_err());
+    }
+}
@@ -0,0 +1,214 @@
+//! Panel setting register of the UC81xx and IL03xx controllers
+//!
+//! The panel setting (`0x00`) packs the LUT source, the color mode, the scan directions, the
+//! booster switch and the soft reset into one byte. The drivers write a fixed value that
+//! fits their panel, which is available as `PANEL_SETTING` in the driver module.
+//! [PanelSettingConfig] names the bits, so single bits can be changed without magic numbers:
+//!
+//! ```
+//! use epd_waveshare::{epd7in5_v2, panel_setting::PanelSettingConfig};
+//!
+//! // the default of the 7.5" V2 with the gates scanned down, which flips the image vertically
+//! let flipped = epd7in5_v2::PANEL_SETTING.with_scan_up(false);
+//! assert_eq!(flipped.bits(), 0x17);
+//! assert_eq!(PanelSettingConfig::from_bits(0x1F).scan_up(), true);
+//! ```
+//!
+//! The drivers with a `set_panel_setting` method accept a changed setting, after checking it
+//! with [PanelSettingConfig::validate()].
+
+use crate::error::Error;
+
+/// Content of the panel setting register
+///
+/// ```text
+/// [7|6|5|4|3|2|1|0]
+///  | | | | | | | `--- RST_N: 0 starts a soft reset
+///  | | | | | | `----- SHD_N: 1 switches the booster on
+///  | | | | | `------- SHL: 1 shifts the source outputs right
+///  | | | | `--------- UD: 1 scans the gates up
+///  | | | `----------- KW/R: 1 for black/white, 0 for black/white/red
+///  | | `------------- REG: 1 for the LUTs from the registers, 0 for the LUTs from the OTP
+///  `-`--------------- RES[1:0]: resolution, the values depend on the controller
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PanelSettingConfig(u8);
+
+const RST_N: u8 = 0;
+const SHD_N: u8 = 1;
+const SHL: u8 = 2;
+const UD: u8 = 3;
+const KW_R: u8 = 4;
+const REG: u8 = 5;
+const RES: u8 = 6;
+
+impl PanelSettingConfig {
+    /// Creates the setting from the raw register value
+    pub const fn from_bits(bits: u8) -> Self {
+        PanelSettingConfig(bits)
+    }
+
+    /// Raw register value
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    const fn with_bit(self, bit: u8, value: bool) -> Self {
+        if value {
+            PanelSettingConfig(self.0 | (1 << bit))
+        } else {
+            PanelSettingConfig(self.0 & !(1 << bit))
+        }
+    }
+
+    const fn bit(self, bit: u8) -> bool {
+        self.0 & (1 << bit) != 0
+    }
+
+    /// Sets the resolution bits, only the lower two bits of `resolution` are used
+    pub const fn with_resolution(self, resolution: u8) -> Self {
+        PanelSettingConfig((self.0 & 0x3F) | ((resolution & 0x03) << RES))
+    }
+
+    /// Resolution bits
+    pub const fn resolution(self) -> u8 {
+        self.0 >> RES
+    }
+
+    /// Selects the LUTs from the registers (`true`) or from the OTP (`false`)
+    pub const fn with_lut_from_register(self, from_register: bool) -> Self {
+        self.with_bit(REG, from_register)
+    }
+
+    /// `true` if the LUTs are taken from the registers
+    pub const fn lut_from_register(self) -> bool {
+        self.bit(REG)
+    }
+
+    /// Selects the black/white mode (`true`) or the black/white/red mode (`false`)
+    ///
+    /// In the black/white mode the controller only refreshes the black and white particles,
+    /// which is a lot faster on tri-color panels.
+    pub const fn with_black_white_mode(self, black_white: bool) -> Self {
+        self.with_bit(KW_R, black_white)
+    }
+
+    /// `true` for the black/white mode
+    pub const fn black_white_mode(self) -> bool {
+        self.bit(KW_R)
+    }
+
+    /// Scans the gates up (`true`) or down (`false`), flips the image vertically
+    pub const fn with_scan_up(self, up: bool) -> Self {
+        self.with_bit(UD, up)
+    }
+
+    /// `true` if the gates are scanned up
+    pub const fn scan_up(self) -> bool {
+        self.bit(UD)
+    }
+
+    /// Shifts the sources right (`true`) or left (`false`), flips the image horizontally
+    pub const fn with_shift_right(self, right: bool) -> Self {
+        self.with_bit(SHL, right)
+    }
+
+    /// `true` if the sources are shifted right
+    pub const fn shift_right(self) -> bool {
+        self.bit(SHL)
+    }
+
+    /// Switches the booster on (`true`) or off (`false`)
+    pub const fn with_booster(self, on: bool) -> Self {
+        self.with_bit(SHD_N, on)
+    }
+
+    /// `true` if the booster is on
+    pub const fn booster(self) -> bool {
+        self.bit(SHD_N)
+    }
+
+    /// Starts a soft reset of the controller when the setting is written
+    pub const fn with_soft_reset(self, reset: bool) -> Self {
+        self.with_bit(RST_N, !reset)
+    }
+
+    /// `true` if writing the setting starts a soft reset
+    pub const fn soft_reset(self) -> bool {
+        !self.bit(RST_N)
+    }
+
+    /// Checks for settings which can't work with any driver
+    ///
+    /// Returns [Error::InvalidPanelSetting] if the booster is off, the panel can't be driven
+    /// without it, or if the setting starts a soft reset, which also resets all other
+    /// registers the driver has written.
+    pub fn validate<E>(self) -> Result<(), Error<E>> {
+        if !self.booster() || self.soft_reset() {
+            return Err(Error::InvalidPanelSetting);
+        }
+        Ok(())
+    }
+
+    /// [validate()](Self::validate()) for drivers which don't write LUTs into the registers
+    pub(crate) fn validate_otp<E>(self) -> Result<(), Error<E>> {
+        self.validate()?;
+        if self.lut_from_register() {
+            return Err(Error::InvalidPanelSetting);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn driver_defaults_are_unchanged() {
+        assert_eq!(crate::epd7in5_v2::PANEL_SETTING.bits(), 0x1F);
+        assert_eq!(crate::epd7in5_v3::PANEL_SETTING.bits(), 0x0F);
+        assert_eq!(crate::epd7in5b_v2::PANEL_SETTING.bits(), 0x0F);
+        assert_eq!(crate::epd5in83b_v2::PANEL_SETTING.bits(), 0x0F);
+    }
+
+    #[test]
+    fn named_bits() {
+        let config = PanelSettingConfig::from_bits(0x00)
+            .with_scan_up(true)
+            .with_shift_right(true)
+            .with_booster(true)
+            .with_soft_reset(false);
+        assert_eq!(config.bits(), 0x0F);
+        assert_eq!(config.with_black_white_mode(true).bits(), 0x1F);
+        assert_eq!(config.with_lut_from_register(true).bits(), 0x2F);
+        assert_eq!(config.with_resolution(0b10).bits(), 0x8F);
+        assert_eq!(config.with_resolution(0b10).resolution(), 0b10);
+
+        let config = PanelSettingConfig::from_bits(0xBF);
+        assert!(config.lut_from_register());
+        assert!(config.black_white_mode());
+        assert!(config.scan_up());
+        assert!(config.shift_right());
+        assert!(config.booster());
+        assert!(!config.soft_reset());
+    }
+
+    #[test]
+    fn validation() {
+        let config = PanelSettingConfig::from_bits(0x0F);
+        assert_eq!(config.validate::<()>(), Ok(()));
+        assert_eq!(
+            config.with_booster(false).validate::<()>(),
+            Err(Error::InvalidPanelSetting)
+        );
+        assert_eq!(
+            config.with_soft_reset(true).validate::<()>(),
+            Err(Error::InvalidPanelSetting)
+        );
+        assert_eq!(
+            config.with_lut_from_register(true).validate_otp::<()>(),
+            Err(Error::InvalidPanelSetting)
+        );
+    }
+}
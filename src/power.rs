@@ -0,0 +1,158 @@
+//! Power and mode state machine of the controllers
+//!
+//! The controllers ignore or misinterpret commands in some of their modes: after a deep
+//! sleep only a hardware reset wakes them up again, and the UC81xx controllers have to leave
+//! the partial mode (`PartialOut`) before they are put to sleep, otherwise they need a hard
+//! reset. Drivers which track their [PowerState] check every [Transition] before any SPI
+//! traffic is sent and return [Error::IllegalTransition] instead.
+//!
+//! | State        | Init   | Update / Refresh    | PartialIn | PartialOut | Sleep     | PowerOff   | PowerOn |
+//! | ------------ | ------ | ------------------- | --------- | ---------- | --------- | ---------- | ------- |
+//! | `Active`     | Active | Active              | Partial   | ✕          | DeepSleep | PoweredOff | ✕       |
+//! | `Partial`    | Active | Partial             | ✕         | Active     | ✕         | ✕          | ✕       |
+//! | `PoweredOff` | Active | PoweredOff / ✕      | ✕         | ✕          | DeepSleep | ✕          | Active  |
+//! | `DeepSleep`  | Active | ✕                   | ✕         | ✕          | ✕         | ✕          | ✕       |
+//!
+//! `Init` resets the controller and is always allowed. Controllers without a partial mode
+//! (e.g. the SSD16xx based type A displays) never use `PartialIn` and `PartialOut`. With the
+//! booster powered off the registers and the SRAM can still be written, but the panel can't
+//! be refreshed before `PowerOn`.
+
+use crate::error::Error;
+
+/// Mode the controller is currently in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PowerState {
+    /// Initialised and ready for commands
+    Active,
+    /// Inside of the partial mode of the UC81xx controllers
+    Partial,
+    /// Booster and regulators powered off, the registers are kept
+    PoweredOff,
+    /// In deep sleep, only a hardware reset wakes the controller up again
+    DeepSleep,
+}
+
+/// Operations that change or depend on the [PowerState]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Transition {
+    /// Hardware reset and initialisation: `new` and `wake_up`
+    Init,
+    /// Writing frame data, LUTs or settings
+    Update,
+    /// Refreshing the panel
+    Refresh,
+    /// Entering the partial mode
+    PartialIn,
+    /// Leaving the partial mode
+    PartialOut,
+    /// Entering deep sleep
+    Sleep,
+    /// Powering off the booster and regulators
+    PowerOff,
+    /// Powering the booster and regulators on again
+    PowerOn,
+}
+
+impl PowerState {
+    /// State after `transition`, `None` if the transition is illegal in this state
+    pub fn next(self, transition: Transition) -> Option<PowerState> {
+        use self::PowerState::*;
+        use self::Transition::*;
+
+        match (self, transition) {
+            (_, Init) => Some(Active),
+            (Active, Update) | (Active, Refresh) => Some(Active),
+            (Active, PartialIn) => Some(Partial),
+            (Active, Sleep) => Some(DeepSleep),
+            (Active, PowerOff) => Some(PoweredOff),
+            (Partial, Update) | (Partial, Refresh) => Some(Partial),
+            (Partial, PartialOut) => Some(Active),
+            (PoweredOff, Update) => Some(PoweredOff),
+            (PoweredOff, Sleep) => Some(DeepSleep),
+            (PoweredOff, PowerOn) => Some(Active),
+            (Active, PartialOut) | (Partial, PartialIn) | (Partial, Sleep) => None,
+            (Active, PowerOn) | (Partial, PowerOff) | (Partial, PowerOn) => None,
+            (PoweredOff, Refresh)
+            | (PoweredOff, PartialIn)
+            | (PoweredOff, PartialOut)
+            | (PoweredOff, PowerOff) => None,
+            (DeepSleep, _) => None,
+        }
+    }
+
+    /// Like [next()](Self::next()) but with the error the drivers return
+    pub(crate) fn check<E>(self, transition: Transition) -> Result<PowerState, Error<E>> {
+        self.next(transition).ok_or(Error::IllegalTransition {
+            state: self,
+            transition,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PowerState::*;
+    use super::Transition::*;
+    use super::*;
+
+    fn run(transitions: &[Transition]) -> Option<PowerState> {
+        transitions
+            .iter()
+            .try_fold(Active, |state, &transition| state.next(transition))
+    }
+
+    // type A (SSD16xx): init, updates, refreshes and deep sleep
+    #[test]
+    fn type_a_sequences() {
+        assert_eq!(run(&[Update, Refresh, Update, Refresh]), Some(Active));
+        assert_eq!(run(&[Update, Sleep]), Some(DeepSleep));
+        assert_eq!(run(&[Sleep, Init, Update, Refresh]), Some(Active));
+
+        assert_eq!(run(&[Sleep, Update]), None);
+        assert_eq!(run(&[Sleep, Refresh]), None);
+        assert_eq!(run(&[Sleep, Sleep]), None);
+    }
+
+    // UC81xx: additionally the partial mode
+    #[test]
+    fn uc81xx_sequences() {
+        assert_eq!(
+            run(&[PartialIn, Update, Refresh, PartialOut, Sleep]),
+            Some(DeepSleep)
+        );
+        assert_eq!(run(&[PartialIn, Init, Sleep]), Some(DeepSleep));
+        assert_eq!(run(&[PartialIn, PartialOut, Update, Refresh]), Some(Active));
+
+        assert_eq!(run(&[PartialIn, Sleep]), None);
+        assert_eq!(run(&[PartialIn, PartialIn]), None);
+        assert_eq!(run(&[PartialOut]), None);
+        assert_eq!(run(&[Sleep, PartialIn]), None);
+        assert_eq!(run(&[Sleep, PartialOut]), None);
+    }
+
+    // UC81xx: power off and on without losing the registers
+    #[test]
+    fn power_off_sequences() {
+        assert_eq!(run(&[PowerOff, PowerOn, Update, Refresh]), Some(Active));
+        assert_eq!(run(&[PowerOff, Update, PowerOn, Refresh]), Some(Active));
+        assert_eq!(run(&[PowerOff, Sleep]), Some(DeepSleep));
+        assert_eq!(run(&[PowerOff, Init]), Some(Active));
+
+        assert_eq!(run(&[PowerOff, Refresh]), None);
+        assert_eq!(run(&[PowerOff, PowerOff]), None);
+        assert_eq!(run(&[PowerOff, PartialIn]), None);
+        assert_eq!(run(&[PowerOn]), None);
+        assert_eq!(run(&[PartialIn, PowerOff]), None);
+        assert_eq!(run(&[Sleep, PowerOn]), None);
+    }
+
+    #[test]
+    fn init_is_always_legal() {
+        for state in [Active, Partial, PoweredOff, DeepSleep] {
+            assert_eq!(state.next(Init), Some(Active));
+        }
+    }
+}
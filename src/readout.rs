@@ -0,0 +1,329 @@
+//! Formatting and alignment helpers for numeric readouts
+//!
+//! Dashboards mostly show numbers with a unit (`23.4 °C`, `1013 hPa`, `87%`). The helpers in
+//! here format such values into a caller provided [heapless::String] without allocating and
+//! compute where the text has to be drawn inside a fixed region, so that columns of values
+//! stay aligned when their magnitude changes.
+//!
+//! Only monospaced fonts are supported, their metrics are passed in as [MonoMetrics].
+//!
+//! ```rust
+//! use epd_waveshare::readout::{self, MonoMetrics, ReadoutAlign, ReadoutStyle};
+//! use embedded_graphics_core::{geometry::{Point, Size}, primitives::Rectangle};
+//! use heapless::String;
+//!
+//! let style = ReadoutStyle::new(1, " °C", 8);
+//! let metrics = MonoMetrics::new(10, 0);
+//! let region = Rectangle::new(Point::new(0, 0), Size::new(100, 20));
+//!
+//! let mut text: String<16> = String::new();
+//! readout::format_f32(&mut text, 23.44, &style).unwrap();
+//! assert_eq!(text, " 23.4 °C");
+//!
+//! let layout = readout::layout(region, &text, ReadoutAlign::Right, metrics);
+//! assert_eq!(layout.bounding_box.bottom_right(), region.bottom_right());
+//! ```
+
+use core::fmt::{self, Write};
+use embedded_graphics_core::geometry::{Point, Size};
+use embedded_graphics_core::primitives::Rectangle;
+use heapless::String;
+
+/// Formatting options of a readout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadoutStyle<'a> {
+    /// Number of digits after the decimal point, ignored for integers
+    pub precision: usize,
+    /// Unit appended directly after the number, e.g. `" hPa"` or `"%"`
+    pub unit: &'a str,
+    /// Minimum width in characters of number and unit together, shorter readouts are
+    /// padded with spaces on the left
+    pub width: usize,
+}
+
+impl<'a> ReadoutStyle<'a> {
+    /// Creates a new style
+    pub const fn new(precision: usize, unit: &'a str, width: usize) -> Self {
+        ReadoutStyle {
+            precision,
+            unit,
+            width,
+        }
+    }
+}
+
+/// Character metrics of a monospaced font
+///
+/// For a `MonoFont` of embedded-graphics these are `character_size.width` and
+/// `character_spacing`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MonoMetrics {
+    /// Width of a single glyph in pixels
+    pub char_width: u32,
+    /// Space between two glyphs in pixels
+    pub spacing: u32,
+}
+
+impl MonoMetrics {
+    /// Creates new font metrics
+    pub const fn new(char_width: u32, spacing: u32) -> Self {
+        MonoMetrics {
+            char_width,
+            spacing,
+        }
+    }
+
+    /// Width in pixels of `chars` characters
+    pub const fn width_of(&self, chars: u32) -> u32 {
+        if chars == 0 {
+            0
+        } else {
+            chars * self.char_width + (chars - 1) * self.spacing
+        }
+    }
+
+    /// Width in pixels of the rendered text
+    pub fn text_width(&self, text: &str) -> u32 {
+        self.width_of(text.chars().count() as u32)
+    }
+
+    /// Horizontal advance of a single character, glyph and spacing
+    const fn advance(&self) -> u32 {
+        self.char_width + self.spacing
+    }
+}
+
+/// Horizontal alignment of a readout inside its region
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadoutAlign {
+    /// The last character touches the right edge of the region
+    Right,
+    /// The decimal point (or the end of the integer part if there is none) is placed
+    /// `trailing` characters left of the right edge of the region
+    ///
+    /// `trailing` has to cover the decimal point, the fraction digits and the unit of the
+    /// longest readout of the column, e.g. `5` for `.25 °C`.
+    DecimalPoint {
+        /// Characters reserved right of the integer part
+        trailing: u32,
+    },
+}
+
+/// Where a readout has to be drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadoutLayout {
+    /// Top left position of the first character
+    pub position: Point,
+    /// Area covered by the text, clipped to the region
+    pub bounding_box: Rectangle,
+}
+
+impl ReadoutLayout {
+    /// Area which has to be flushed when this layout replaces `previous`
+    ///
+    /// A readout that got shorter has to clear the pixels the old one covered, so the
+    /// result is the smallest rectangle around both bounding boxes.
+    pub fn dirty_area(&self, previous: &ReadoutLayout) -> Rectangle {
+        let a = self.bounding_box;
+        let b = previous.bounding_box;
+        if a.size.width == 0 || a.size.height == 0 {
+            return b;
+        }
+        if b.size.width == 0 || b.size.height == 0 {
+            return a;
+        }
+        let top_left = Point::new(
+            a.top_left.x.min(b.top_left.x),
+            a.top_left.y.min(b.top_left.y),
+        );
+        let right = (a.top_left.x + a.size.width as i32).max(b.top_left.x + b.size.width as i32);
+        let bottom = (a.top_left.y + a.size.height as i32).max(b.top_left.y + b.size.height as i32);
+        Rectangle::new(
+            top_left,
+            Size::new((right - top_left.x) as u32, (bottom - top_left.y) as u32),
+        )
+    }
+}
+
+/// Counts the characters that would be written
+struct CharCounter(usize);
+
+impl Write for CharCounter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.chars().count();
+        Ok(())
+    }
+}
+
+fn write_padded<const N: usize>(
+    out: &mut String<N>,
+    style: &ReadoutStyle,
+    args: fmt::Arguments,
+) -> fmt::Result {
+    let mut counter = CharCounter(0);
+    counter.write_fmt(args)?;
+    counter.write_str(style.unit)?;
+
+    out.clear();
+    for _ in counter.0..style.width {
+        out.push(' ').map_err(|_| fmt::Error)?;
+    }
+    out.write_fmt(args)?;
+    out.write_str(style.unit)
+}
+
+/// Formats `value` with `style.precision` fraction digits and the unit into `out`
+///
+/// The previous content of `out` is replaced. Returns an error if `out` is too small.
+pub fn format_f32<const N: usize>(
+    out: &mut String<N>,
+    value: f32,
+    style: &ReadoutStyle,
+) -> fmt::Result {
+    write_padded(out, style, format_args!("{:.*}", style.precision, value))
+}
+
+/// Formats `value` with the unit into `out`
+///
+/// The previous content of `out` is replaced. Returns an error if `out` is too small.
+pub fn format_i32<const N: usize>(
+    out: &mut String<N>,
+    value: i32,
+    style: &ReadoutStyle,
+) -> fmt::Result {
+    write_padded(out, style, format_args!("{}", value))
+}
+
+/// Computes where `text` has to be drawn inside `region`
+///
+/// Leading spaces are ignored for the bounding box, they don't put any ink on the display.
+pub fn layout(
+    region: Rectangle,
+    text: &str,
+    align: ReadoutAlign,
+    metrics: MonoMetrics,
+) -> ReadoutLayout {
+    let chars = text.chars().count() as i32;
+    let right = region.top_left.x + region.size.width as i32;
+    let advance = metrics.advance() as i32;
+
+    let x = match align {
+        ReadoutAlign::Right => right - metrics.width_of(chars as u32) as i32,
+        ReadoutAlign::DecimalPoint { trailing } => {
+            // index of the first character after the padding, sign and integer digits
+            let blanks = text.chars().take_while(|&c| c == ' ').count();
+            let integer_end = blanks
+                + text
+                    .chars()
+                    .skip(blanks)
+                    .take_while(|&c| c.is_ascii_digit() || c == '-')
+                    .count();
+            let integer_end = integer_end as i32;
+            right - (trailing as i32 + integer_end) * advance + metrics.spacing as i32
+        }
+    };
+    let position = Point::new(x, region.top_left.y);
+
+    let blanks = text.chars().take_while(|&c| c == ' ').count() as i32;
+    let ink_start = x + blanks * advance;
+    let ink_end = x + metrics.width_of(chars as u32) as i32;
+    let start = ink_start.max(region.top_left.x);
+    let end = ink_end.min(right);
+    let bounding_box = Rectangle::new(
+        Point::new(start, region.top_left.y),
+        Size::new((end - start).max(0) as u32, region.size.height),
+    );
+
+    ReadoutLayout {
+        position,
+        bounding_box,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const METRICS: MonoMetrics = MonoMetrics::new(6, 1);
+
+    fn region() -> Rectangle {
+        Rectangle::new(Point::new(10, 5), Size::new(120, 10))
+    }
+
+    #[test]
+    fn formatting() {
+        let mut text: String<16> = String::new();
+
+        format_f32(&mut text, 23.44, &ReadoutStyle::new(1, " °C", 0)).unwrap();
+        assert_eq!(text, "23.4 °C");
+
+        format_f32(&mut text, -3.0, &ReadoutStyle::new(2, "", 6)).unwrap();
+        assert_eq!(text, " -3.00");
+
+        format_i32(&mut text, 87, &ReadoutStyle::new(0, "%", 5)).unwrap();
+        assert_eq!(text, "  87%");
+
+        let mut small: String<4> = String::new();
+        assert!(format_i32(&mut small, 1013, &ReadoutStyle::new(0, " hPa", 0)).is_err());
+    }
+
+    #[test]
+    fn right_alignment_is_stable() {
+        let style = ReadoutStyle::new(1, "%", 0);
+        let mut text: String<16> = String::new();
+        let mut right_edges = [0; 3];
+
+        for (edge, value) in right_edges.iter_mut().zip([9.5, 10.5, 100.5]) {
+            format_f32(&mut text, value, &style).unwrap();
+            let layout = layout(region(), &text, ReadoutAlign::Right, METRICS);
+            *edge = layout.position.x + METRICS.text_width(&text) as i32;
+        }
+
+        assert_eq!(right_edges, [130; 3]);
+    }
+
+    #[test]
+    fn decimal_alignment_is_stable() {
+        let mut text: String<16> = String::new();
+        let align = ReadoutAlign::DecimalPoint { trailing: 5 };
+        let mut points = [0; 4];
+
+        let values = [(1.25, 2), (-12.5, 1), (100.0, 0), (1013.0, 2)];
+        for (point, (value, precision)) in points.iter_mut().zip(values) {
+            format_f32(&mut text, value, &ReadoutStyle::new(precision, " V", 0)).unwrap();
+            let layout = layout(region(), &text, align, METRICS);
+            let integer_digits = text.split(['.', ' ']).next().unwrap();
+            *point = layout.position.x
+                + integer_digits.chars().count() as i32 * METRICS.advance() as i32;
+        }
+
+        // the decimal point starts 5 characters left of the right edge
+        assert_eq!(points, [130 - 5 * 7 + 1; 4]);
+    }
+
+    #[test]
+    fn dirty_area_covers_shrinking_readout() {
+        let style = ReadoutStyle::new(0, "", 0);
+        let mut text: String<16> = String::new();
+
+        format_i32(&mut text, 1000, &style).unwrap();
+        let long = layout(region(), &text, ReadoutAlign::Right, METRICS);
+        format_i32(&mut text, 7, &style).unwrap();
+        let short = layout(region(), &text, ReadoutAlign::Right, METRICS);
+
+        assert_eq!(short.dirty_area(&long), long.bounding_box);
+        assert_eq!(long.bounding_box.size, Size::new(METRICS.width_of(4), 10));
+        assert_eq!(short.bounding_box.size, Size::new(METRICS.width_of(1), 10));
+    }
+
+    #[test]
+    fn padding_is_not_ink() {
+        let mut text: String<16> = String::new();
+        format_i32(&mut text, 5, &ReadoutStyle::new(0, "", 4)).unwrap();
+        let layout = layout(region(), &text, ReadoutAlign::Right, METRICS);
+
+        assert_eq!(layout.position.x, 130 - METRICS.width_of(4) as i32);
+        assert_eq!(layout.bounding_box.top_left.x, 130 - 6);
+        assert_eq!(layout.bounding_box.size.width, 6);
+    }
+}
@@ -0,0 +1,75 @@
+//! Checkerboard for bringing up a display
+//!
+//! [display_test_pattern()](crate::prelude::WaveshareDisplay::display_test_pattern()) lets the
+//! driver clear its frame with this pattern instead of the background color, so no buffer is
+//! needed to check the wiring.
+
+/// Edge length of the squares in pixels
+const SQUARE: u32 = 16;
+
+/// Checkerboard of 16x16 pixel squares in the background color and its inverse
+///
+/// Every fill after the pattern was set is shifted by half a square, so the second RAM plane
+/// of a tri-color or gray display overlaps the first one only partially and all colors show
+/// up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FillPattern {
+    row_bytes: u32,
+    pixels_per_byte: u32,
+    ink: u8,
+}
+
+impl FillPattern {
+    /// Pattern for a frame `width` pixels wide with 1, 2 or 4 bits per pixel
+    pub(crate) fn new(width: u32, bits_per_pixel: u8) -> Self {
+        FillPattern {
+            row_bytes: (width * u32::from(bits_per_pixel)).div_ceil(8),
+            pixels_per_byte: 8 / u32::from(bits_per_pixel),
+            // flips the lowest bit of every pixel, e.g. white to black for all color indices
+            ink: 0xFF / ((1 << bits_per_pixel) - 1),
+        }
+    }
+
+    /// Byte `index` of the `fill`th fill with `val`
+    pub(crate) fn byte(&self, val: u8, index: u32, fill: u32) -> u8 {
+        let row = index / self.row_bytes;
+        let x = (index % self.row_bytes) * self.pixels_per_byte + fill * SQUARE / 2;
+        if (x / SQUARE + row / SQUARE) % 2 == 1 {
+            val ^ self.ink
+        } else {
+            val
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn squares_alternate_in_both_directions() {
+        let pattern = FillPattern::new(200, 1);
+        // 25 bytes per row, a square is 2 bytes wide and 16 rows high
+        let first_row: [u8; 6] = core::array::from_fn(|i| pattern.byte(0xFF, i as u32, 0));
+        assert_eq!(first_row, [0xFF, 0xFF, 0x00, 0x00, 0xFF, 0xFF]);
+        assert_eq!(pattern.byte(0xFF, 15 * 25, 0), 0xFF);
+        assert_eq!(pattern.byte(0xFF, 16 * 25, 0), 0x00);
+
+        // the second plane is shifted by half a square
+        let first_row: [u8; 6] = core::array::from_fn(|i| pattern.byte(0x00, i as u32, 1));
+        assert_eq!(first_row, [0x00, 0xFF, 0xFF, 0x00, 0x00, 0xFF]);
+    }
+
+    #[test]
+    fn color_indices_are_flipped_per_pixel() {
+        // 4 bits per pixel, white 0x1 turns black 0x0
+        let pattern = FillPattern::new(600, 4);
+        assert_eq!(pattern.byte(0x11, 7, 0), 0x11);
+        assert_eq!(pattern.byte(0x11, 8, 0), 0x00);
+        assert_eq!(pattern.byte(0x11, 300, 0), 0x11);
+
+        // 2 bits per pixel
+        let pattern = FillPattern::new(800, 2);
+        assert_eq!(pattern.byte(0x55, 4, 0), 0x00);
+    }
+}
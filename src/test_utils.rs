@@ -0,0 +1,275 @@
+//! Fake peripherals shared by the driver tests
+extern crate std;
+
+use crate::timing::Clock;
+use crate::traits::WaveshareDisplay;
+use core::cell::Cell;
+use core::convert::Infallible;
+use embedded_hal::{
+    blocking::{
+        delay::DelayMs,
+        spi::{Transfer, Write},
+    },
+    digital::v2::{InputPin, OutputPin},
+};
+use std::rc::Rc;
+use std::thread_local;
+use std::vec;
+use std::vec::Vec;
+
+/// A byte on the bus, `true` for a command
+pub(crate) type Sent = (bool, u8);
+
+/// Records every byte together with the level of the shared DC pin, answers transfers
+/// with the scripted bytes
+pub(crate) struct RecordingSpi {
+    pub(crate) dc: Rc<Cell<bool>>,
+    pub(crate) sent: Vec<Sent>,
+    pub(crate) answers: Vec<u8>,
+    /// Length of every write
+    pub(crate) writes: Vec<usize>,
+    /// Display selected by a [CsPin], `0` if none
+    pub(crate) selected: Rc<Cell<u8>>,
+    /// The selected display of every byte in `sent`
+    pub(crate) sent_to: Vec<u8>,
+}
+
+impl RecordingSpi {
+    /// A bus together with the DC pin whose level it records
+    pub(crate) fn new() -> (Self, DcPin) {
+        let dc = Rc::new(Cell::new(false));
+        let spi = RecordingSpi {
+            dc: dc.clone(),
+            sent: Vec::new(),
+            answers: Vec::new(),
+            writes: Vec::new(),
+            selected: Rc::default(),
+            sent_to: Vec::new(),
+        };
+        (spi, DcPin(dc))
+    }
+
+    /// The recorded bytes without their DC level
+    pub(crate) fn bytes(&self) -> Vec<u8> {
+        self.sent.iter().map(|&(_, b)| b).collect()
+    }
+
+    /// Forgets the recorded bytes and writes
+    pub(crate) fn clear(&mut self) {
+        self.sent.clear();
+        self.writes.clear();
+        self.sent_to.clear();
+    }
+
+    /// The displays which received bytes, in order
+    pub(crate) fn receivers(&self) -> Vec<u8> {
+        let mut receivers = self.sent_to.clone();
+        receivers.dedup();
+        receivers
+    }
+}
+
+impl Write<u8> for RecordingSpi {
+    type Error = Infallible;
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        let is_command = !self.dc.get();
+        if is_command {
+            if let Some((command, reads)) = BUSY_AFTER.with(Cell::get) {
+                if words.contains(&command) {
+                    BUSY_AFTER.with(|after| after.set(None));
+                    busy_for(reads);
+                }
+            }
+        }
+        self.sent.extend(words.iter().map(|&b| (is_command, b)));
+        self.writes.push(words.len());
+        let selected = self.selected.get();
+        self.sent_to.extend(words.iter().map(|_| selected));
+        Ok(())
+    }
+}
+
+impl Transfer<u8> for RecordingSpi {
+    type Error = Infallible;
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Self::Error> {
+        for word in words.iter_mut() {
+            *word = self.answers.remove(0);
+        }
+        Ok(words)
+    }
+}
+
+/// The DC pin of a [`RecordingSpi`]
+pub(crate) struct DcPin(Rc<Cell<bool>>);
+
+impl OutputPin for DcPin {
+    type Error = Infallible;
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.0.set(false);
+        Ok(())
+    }
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.0.set(true);
+        Ok(())
+    }
+}
+
+/// CS pin of display `.0`, selects it in the `selected` cell of a [RecordingSpi]
+pub(crate) struct CsPin(pub(crate) u8, pub(crate) Rc<Cell<u8>>);
+
+impl OutputPin for CsPin {
+    type Error = Infallible;
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.1.set(self.0);
+        Ok(())
+    }
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.1.set(0);
+        Ok(())
+    }
+}
+
+/// A pin which ignores writes and always reads low
+///
+/// As busy pin it keeps drivers whose busy level is high from waiting.
+#[derive(Default)]
+pub(crate) struct LowPin;
+
+impl OutputPin for LowPin {
+    type Error = Infallible;
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl InputPin for LowPin {
+    type Error = Infallible;
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+}
+
+/// A pin which ignores writes and always reads high
+///
+/// As busy pin it keeps drivers whose busy level is low from waiting.
+#[derive(Default)]
+pub(crate) struct HighPin;
+
+impl OutputPin for HighPin {
+    type Error = Infallible;
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl InputPin for HighPin {
+    type Error = Infallible;
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+}
+
+thread_local! {
+    static NOW_MS: Cell<u32> = const { Cell::new(0) };
+    static BUSY_READS: Cell<u32> = const { Cell::new(0) };
+    static BUSY_AFTER: Cell<Option<(u8, u32)>> = const { Cell::new(None) };
+}
+
+/// Clock of the current test thread, every read of a [BusyPin] advances it by 1 ms
+pub(crate) struct TestClock;
+
+impl Clock for TestClock {
+    fn now_ms(&self) -> u32 {
+        NOW_MS.with(Cell::get)
+    }
+}
+
+pub(crate) static TEST_CLOCK: TestClock = TestClock;
+
+/// Lets the [BusyPin]s of the current test thread read high for the next `reads` reads
+pub(crate) fn busy_for(reads: u32) {
+    BUSY_READS.with(|busy| busy.set(reads));
+}
+
+/// Calls [busy_for()] once a [RecordingSpi] of the current test thread sends `command`,
+/// like a controller which starts working on it
+pub(crate) fn busy_after(command: u8, reads: u32) {
+    BUSY_AFTER.with(|after| after.set(Some((command, reads))));
+}
+
+fn busy_reads_left() -> u32 {
+    BUSY_READS.with(Cell::get)
+}
+
+/// A pin which ignores writes and reads high as set with [busy_for()]
+///
+/// Every read takes 1 ms of the [TestClock].
+#[derive(Default)]
+pub(crate) struct BusyPin;
+
+impl OutputPin for BusyPin {
+    type Error = Infallible;
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl InputPin for BusyPin {
+    type Error = Infallible;
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        NOW_MS.with(|now| now.set(now.get().wrapping_add(1)));
+        let left = busy_reads_left();
+        busy_for(left.saturating_sub(1));
+        Ok(left > 0)
+    }
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        self.is_high().map(|high| !high)
+    }
+}
+
+pub(crate) struct NoDelay;
+
+impl DelayMs<u8> for NoDelay {
+    fn delay_ms(&mut self, _ms: u8) {}
+}
+
+/// Creates a driver on a [`RecordingSpi`], `P` is used for the CS, busy and reset pins
+pub(crate) fn test_epd<EPD, P>() -> (RecordingSpi, EPD)
+where
+    EPD: WaveshareDisplay<RecordingSpi, P, P, DcPin, P, NoDelay>,
+    P: InputPin + OutputPin + Default,
+{
+    let (mut spi, dc) = RecordingSpi::new();
+    let epd = EPD::new(
+        &mut spi,
+        P::default(),
+        P::default(),
+        dc,
+        P::default(),
+        &mut NoDelay,
+    )
+    .unwrap();
+    (spi, epd)
+}
+
+/// The bytes of `command` followed by `data`
+pub(crate) fn cmd(command: u8, data: &[u8]) -> Vec<Sent> {
+    let mut sent = vec![(true, command)];
+    sent.extend(data.iter().map(|&b| (false, b)));
+    sent
+}
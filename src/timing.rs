@@ -0,0 +1,84 @@
+//! Optional timing measurements of the busy periods of an EPD
+//!
+//! Refresh times depend on the panel and its temperature. The drivers of Epd 1in54, Epd 1in54
+//! V2, Epd 2in13 V2, Epd 2in9 and Epd 2in9 V2 can be given a [Clock] with their `set_clock`
+//! method. It is read whenever a refresh or clear is started and again when the busy wait
+//! afterwards has finished. The last measured durations can then be queried as [Timings]
+//! with `last_timings`, the other drivers don't measure anything.
+//!
+//! The pulse on the reset pin can be tuned with [ResetTiming].
+
+use crate::traits::RefreshLut;
+
+/// Monotonic millisecond clock provided by the user
+///
+/// The counter is allowed to wrap around, durations are computed with wrapping arithmetic.
+pub trait Clock {
+    /// Current time in milliseconds
+    fn now_ms(&self) -> u32;
+}
+
+/// The kinds of operations whose duration is measured
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// A refresh with the full LUT
+    FullRefresh,
+    /// A refresh with the quick LUT
+    QuickRefresh,
+    /// A refresh with a custom LUT
+    CustomRefresh,
+    /// Writing the background color into the RAM with `clear_frame`
+    Clear,
+}
+
+impl From<RefreshLut> for Operation {
+    fn from(refresh: RefreshLut) -> Self {
+        match refresh {
+            RefreshLut::Full => Operation::FullRefresh,
+            RefreshLut::Quick => Operation::QuickRefresh,
+            RefreshLut::Custom(_) => Operation::CustomRefresh,
+        }
+    }
+}
+
+/// Last measured durations in milliseconds per [Operation]
+///
+/// `None` until the operation has been measured at least once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Timings {
+    /// Duration of the last full refresh
+    pub full_refresh_ms: Option<u32>,
+    /// Duration of the last quick refresh
+    pub quick_refresh_ms: Option<u32>,
+    /// Duration of the last refresh with a custom LUT
+    pub custom_refresh_ms: Option<u32>,
+    /// Duration of the last clear
+    pub clear_ms: Option<u32>,
+}
+
+/// Timing of the pulse on the reset pin, which wakes the controller up
+///
+/// Every driver has a default which follows the example code of Waveshare, some panels
+/// tolerate much shorter pulses and waits. Set with `WaveshareDisplay::set_reset_timing`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ResetTiming {
+    /// Time the released pin is held before the pulse
+    pub initial_ms: u8,
+    /// Duration of the pulse itself
+    pub assert_ms: u8,
+    /// Time the controller gets to start after the pulse
+    pub deassert_ms: u8,
+}
+
+impl Timings {
+    pub(crate) fn record(&mut self, operation: Operation, duration_ms: u32) {
+        let slot = match operation {
+            Operation::FullRefresh => &mut self.full_refresh_ms,
+            Operation::QuickRefresh => &mut self.quick_refresh_ms,
+            Operation::CustomRefresh => &mut self.custom_refresh_ms,
+            Operation::Clear => &mut self.clear_ms,
+        };
+        *slot = Some(duration_ms);
+    }
+}
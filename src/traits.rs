@@ -1,3 +1,6 @@
+use crate::error::Error;
+use crate::timing::ResetTiming;
+use crate::{CsStrategy, ResetPin};
 use core::marker::Sized;
 use embedded_hal::{
     blocking::{delay::*, spi::Write},
@@ -10,29 +13,56 @@ pub(crate) trait Command {
     fn address(self) -> u8;
 }
 
+/// Raw command addresses, used by [send_command_raw()](WaveshareDisplay::send_command_raw())
+impl Command for u8 {
+    fn address(self) -> u8 {
+        self
+    }
+}
+
 /// Seperates the different LUT for the Display Refresh process
-#[derive(Debug, Clone, PartialEq, Eq, Copy)]
+///
+/// The LUTs are ordered `Full`, `Quick`, then the custom LUTs by their bytes, so pending
+/// refreshes can be kept in sorted or hashed collections.
+#[derive(Debug, Clone, PartialEq, Eq, Copy, Default, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum RefreshLut {
     /// The "normal" full Lookuptable for the Refresh-Sequence
+    #[default]
     Full,
     /// The quick LUT where not the full refresh sequence is followed.
     /// This might lead to some
     Quick,
+    /// A LUT provided by the user, in the layout the driver writes into the LUT register
+    ///
+    /// Only Epd 1in54, Epd 1in54 V2 and Epd 2in9 load custom LUTs. A LUT of the wrong length
+    /// returns [Error::BufferSize], the other drivers return [Error::UnsupportedLut].
+    Custom(&'static [u8]),
 }
 
-impl Default for RefreshLut {
-    fn default() -> Self {
-        RefreshLut::Full
-    }
+/// Color of the border around the active area of the panel
+///
+/// Only the drivers with a `set_border_color()` method drive the border, see their
+/// documentation for the defaults.
+#[derive(Debug, Clone, PartialEq, Eq, Copy)]
+pub enum BorderColor {
+    /// The border is refreshed to white together with the frame
+    White,
+    /// The border is refreshed to black together with the frame
+    Black,
+    /// The border isn't driven and keeps its color, it slowly fades to gray
+    Float,
+    /// The border is held at the VCOM level, it doesn't flash during quick refreshes
+    Vcom,
 }
 
 pub(crate) trait InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
     /// This initialises the EPD and powers it up
@@ -45,7 +75,7 @@ where
     /// This function calls [reset](WaveshareDisplay::reset),
     /// so you don't need to call reset your self when trying to wake your device up
     /// after setting it to sleep.
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>>;
 }
 
 /// Functions to interact with three color panels
@@ -53,10 +83,10 @@ pub trait WaveshareThreeColorDisplay<SPI, CS, BUSY, DC, RST, DELAY>:
     WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
     /// Transmit data to the SRAM of the EPD
@@ -67,19 +97,76 @@ where
         spi: &mut SPI,
         black: &[u8],
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), Error<SPI::Error>>;
 
     /// Update only the black/white data of the display.
     ///
-    /// This must be finished by calling `update_chromatic_frame`.
-    fn update_achromatic_frame(&mut self, spi: &mut SPI, black: &[u8]) -> Result<(), SPI::Error>;
+    /// The controller keeps the two planes in separate RAMs, the chromatic plane isn't touched.
+    /// Follow up with `update_chromatic_frame` unless the chromatic data is still up to date.
+    fn update_achromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        black: &[u8],
+    ) -> Result<(), Error<SPI::Error>>;
 
     /// Update only the chromatic data of the display.
     ///
-    /// This should be preceded by a call to `update_achromatic_frame`.
-    /// This data takes precedence over the black/white data.
-    fn update_chromatic_frame(&mut self, spi: &mut SPI, chromatic: &[u8])
-        -> Result<(), SPI::Error>;
+    /// This data takes precedence over the black/white data. The black/white plane written
+    /// before stays in the RAM of the controller, so a change of the chromatic pixels alone is
+    /// this call followed by [display_frame()](WaveshareDisplay::display_frame()), e.g. for a
+    /// blinking red indicator. The refresh still takes as long as a full one.
+    ///
+    /// Most controllers lose their RAM in the deep sleep, after
+    /// [wake_up()](WaveshareDisplay::wake_up()) both planes have to be written again.
+    fn update_chromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        chromatic: &[u8],
+    ) -> Result<(), Error<SPI::Error>>;
+
+    /// Number of bytes [update_chromatic_frame()](Self::update_chromatic_frame()) takes, one
+    /// bit per pixel
+    fn chromatic_buffer_len(&self) -> usize {
+        crate::buffer_len(self.width() as usize, self.height() as usize)
+    }
+}
+
+/// Functions to interact with panels which can show gray levels
+pub trait WaveshareGrayscaleDisplay<SPI, CS, BUSY, DC, RST, DELAY>:
+    WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: CsStrategy,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: ResetPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Transmit a frame with 4 gray levels to the SRAM of the EPD
+    ///
+    /// The buffer has 2 bits per pixel, 4 pixels per byte with the first pixel in the highest
+    /// bits: `0b00` is black, `0b01` dark gray, `0b10` light gray and `0b11` white. The gray
+    /// LUT is loaded as well, so the frame is shown by the next
+    /// [display_frame()](WaveshareDisplay::display_frame()).
+    fn update_gray_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Error<SPI::Error>>;
+
+    /// Transmits a frame with 4 gray levels and shows it, see
+    /// [update_gray_frame()](WaveshareGrayscaleDisplay::update_gray_frame())
+    fn update_and_display_gray_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.update_gray_frame(spi, buffer)?;
+        self.display_frame(spi, delay)
+    }
+
+    /// Number of bytes [update_gray_frame()](Self::update_gray_frame()) takes, two bits per
+    /// pixel
+    fn gray_buffer_len(&self) -> usize {
+        crate::buffer_len(2 * self.width() as usize, self.height() as usize)
+    }
 }
 
 /// All the functions to interact with the EPDs
@@ -90,7 +177,7 @@ where
 ///
 ///```rust, no_run
 ///# use embedded_hal_mock::*;
-///# fn main() -> Result<(), MockError> {
+///# fn main() -> Result<(), epd_waveshare::error::Error<MockError>> {
 ///use embedded_graphics::{
 ///    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
 ///};
@@ -129,17 +216,19 @@ where
 pub trait WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
     /// The Color Type used by the Display
     type DisplayColor;
     /// Creates a new driver from a SPI peripheral, CS Pin, Busy InputPin, DC
     ///
-    /// This already initialises the device.
+    /// This already initialises the device. The background color is the
+    /// driver's default, see [`new_with_background`](Self::new_with_background)
+    /// to pick another one.
     fn new(
         spi: &mut SPI,
         cs: CS,
@@ -147,19 +236,36 @@ where
         dc: DC,
         rst: RST,
         delay: &mut DELAY,
-    ) -> Result<Self, SPI::Error>
+    ) -> Result<Self, Error<SPI::Error>>
+    where
+        Self: Sized;
+
+    /// Creates a new driver like [`new`](Self::new), but with `color` as
+    /// background color
+    ///
+    /// The color is in place before the device is initialised, so it is also
+    /// used by the first `clear_frame`.
+    fn new_with_background(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        color: Self::DisplayColor,
+    ) -> Result<Self, Error<SPI::Error>>
     where
         Self: Sized;
 
     /// Let the device enter deep-sleep mode to save power.
     ///
     /// The deep sleep mode returns to standby with a hardware reset.
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>>;
 
     /// Wakes the device up from sleep
     ///
     /// Also reintialises the device if necessary.
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>>;
 
     /// Sets the backgroundcolor for various commands like [clear_frame](WaveshareDisplay::clear_frame)
     fn set_background_color(&mut self, color: Self::DisplayColor);
@@ -173,19 +279,50 @@ where
     /// Get the height of the display
     fn height(&self) -> u32;
 
+    /// Typical duration of a refresh with the full LUT in milliseconds
+    ///
+    /// A hint from the datasheets and vendor drivers for planning work around
+    /// [display_frame()](Self::display_frame()), the panel is slower in the cold. The
+    /// `BUSY_TIMEOUT_MS` of the driver module is the upper bound.
+    fn full_refresh_ms(&self) -> u32;
+
+    /// Typical duration of a refresh with [RefreshLut::Quick] in milliseconds
+    ///
+    /// The same as [full_refresh_ms()](Self::full_refresh_ms()) for panels without a quick
+    /// LUT.
+    fn quick_refresh_ms(&self) -> u32 {
+        self.full_refresh_ms()
+    }
+
+    /// Number of bytes [update_frame()](Self::update_frame()) takes
+    ///
+    /// One bit per pixel unless the driver says otherwise, the multi color panels pack several
+    /// bits into a pixel. Meant for allocating the frame, tri-color panels take a second plane
+    /// of [chromatic_buffer_len()](WaveshareThreeColorDisplay::chromatic_buffer_len()) bytes.
+    fn buffer_len(&self) -> usize {
+        crate::buffer_len(self.width() as usize, self.height() as usize)
+    }
+
     /// Transmit a full frame to the SRAM of the EPD
     fn update_frame(
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), Error<SPI::Error>>;
 
     /// Transmits partial data to the SRAM of the EPD
     ///
     /// (x,y) is the top left corner
     ///
     /// BUFFER needs to be of size: width / 8 * height !
+    ///
+    /// Empty windows are rejected with [Error::InvalidRegion], windows which don't fit into the
+    /// `WINDOW_LIMITS` of the driver with [Error::WindowTooLarge] or [Error::WindowOutOfRange].
+    /// Most controllers address x in bytes, their drivers also return [Error::InvalidRegion]
+    /// if `x` or `width` aren't multiples of 8, see
+    /// [WindowLimits::validate_aligned()](crate::window::WindowLimits::validate_aligned()).
+    #[allow(clippy::too_many_arguments)]
     fn update_partial_frame(
         &mut self,
         spi: &mut SPI,
@@ -194,12 +331,13 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error>;
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>>;
 
     /// Displays the frame data from SRAM
     ///
     /// This function waits until the device isn`t busy anymore
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>>;
 
     /// Provide a combined update&display and save some time (skipping a busy check in between)
     fn update_and_display_frame(
@@ -207,12 +345,12 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), Error<SPI::Error>>;
 
     /// Clears the frame buffer on the EPD with the declared background color
     ///
     /// The background color can be changed with [`WaveshareDisplay::set_background_color`]
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Error<SPI::Error>>;
 
     /// Trait for using various Waveforms from different LUTs
     /// E.g. for partial refreshes
@@ -226,7 +364,7 @@ where
         &mut self,
         spi: &mut SPI,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), Error<SPI::Error>>;
 
     /// Checks if the display is busy transmitting data
     ///
@@ -234,6 +372,58 @@ where
     /// but in the case you send data and commands directly you might need to check
     /// if the device is still busy
     fn is_busy(&self) -> bool;
+
+    /// Sends a single command byte to the controller
+    ///
+    /// An escape hatch for commands of the controller which aren't covered by the driver, e.g.
+    /// custom waveforms or the temperature registers. The driver doesn't know about the effects
+    /// of the command: neither the busy pin nor the [power state](crate::power) are checked or
+    /// updated. See the datasheet of the controller for the commands.
+    fn send_command_raw(&mut self, spi: &mut SPI, command: u8) -> Result<(), Error<SPI::Error>>;
+
+    /// Sends data bytes to the controller, usually the parameters of a
+    /// [raw command](WaveshareDisplay::send_command_raw())
+    fn send_data_raw(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Error<SPI::Error>>;
+
+    /// Shows a checkerboard of 16x16 pixel squares, e.g. to check the wiring of a new display
+    ///
+    /// The pattern is written straight into the RAM by [clear_frame()](Self::clear_frame())
+    /// and refreshed, no buffer is needed. Tri-color and gray displays get a shifted
+    /// checkerboard on their second RAM plane, so all colors show up. Needs the
+    /// `test-patterns` feature.
+    #[cfg(feature = "test-patterns")]
+    fn display_test_pattern(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.set_test_pattern(true);
+        let result = self.clear_frame(spi, delay);
+        self.set_test_pattern(false);
+        result?;
+        self.display_frame(spi, delay)
+    }
+
+    /// Sets the largest number of bytes handed to a single `SPI::write`, 4096 by default
+    ///
+    /// Longer frames are split into several writes while CS stays low, for HALs whose DMA or
+    /// transaction size is limited. Values below 1 are treated as 1.
+    fn set_spi_chunk_size(&mut self, _chunk_size: usize) {}
+
+    /// Replaces the reset pulse used by [wake_up()](Self::wake_up()), `None` restores the
+    /// driver default
+    ///
+    /// Mostly to shorten the wake up of panels which tolerate shorter pulses than the
+    /// Waveshare example code uses, see [ResetTiming]. `new` always uses the default.
+    fn set_reset_timing(&mut self, _timing: Option<ResetTiming>) {}
+
+    /// Lets [clear_frame()](Self::clear_frame()) fill the RAM with the test pattern
+    ///
+    /// Used by [display_test_pattern()](Self::display_test_pattern()), drivers which don't
+    /// override it clear to the background color instead.
+    #[cfg(feature = "test-patterns")]
+    #[doc(hidden)]
+    fn set_test_pattern(&mut self, _enable: bool) {}
 }
 
 /// Allows quick refresh support for displays that support it; lets you send both
@@ -247,7 +437,7 @@ where
 /// Example:
 ///```rust, no_run
 ///# use embedded_hal_mock::*;
-///# fn main() -> Result<(), MockError> {
+///# fn main() -> Result<(), epd_waveshare::error::Error<MockError>> {
 ///# use embedded_graphics::{
 ///#   pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
 ///# };
@@ -270,13 +460,13 @@ where
 ///let mut buffer = [DEFAULT_BACKGROUND_COLOR.get_byte_value(); 80 / 8 * 80];
 ///let mut display = VarDisplay::new(frame_width, frame_height, &mut buffer);
 ///
-///epd.update_partial_old_frame(&mut spi, display.buffer(), x, y, frame_width, frame_height)
+///epd.update_partial_old_frame(&mut spi, display.buffer(), x, y, frame_width, frame_height, &mut delay)
 ///  .ok();
 ///
 ///display.clear_buffer(Color::White);
 ///// Execute drawing commands here.
 ///
-///epd.update_partial_new_frame(&mut spi, display.buffer(), x, y, frame_width, frame_height)
+///epd.update_partial_new_frame(&mut spi, display.buffer(), x, y, frame_width, frame_height, &mut delay)
 ///  .ok();
 ///# Ok(())
 ///# }
@@ -284,10 +474,10 @@ where
 pub trait QuickRefresh<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
-    CS: OutputPin,
+    CS: CsStrategy,
     BUSY: InputPin,
     DC: OutputPin,
-    RST: OutputPin,
+    RST: ResetPin,
     DELAY: DelayMs<u8>,
 {
     /// Updates the old frame.
@@ -296,7 +486,7 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), Error<SPI::Error>>;
 
     /// Updates the new frame.
     fn update_new_frame(
@@ -304,10 +494,14 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), Error<SPI::Error>>;
 
     /// Displays the new frame
-    fn display_new_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>>;
 
     /// Updates and displays the new frame.
     fn update_and_display_new_frame(
@@ -315,9 +509,10 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), Error<SPI::Error>>;
 
     /// Updates the old frame for a portion of the display.
+    #[allow(clippy::too_many_arguments)]
     fn update_partial_old_frame(
         &mut self,
         spi: &mut SPI,
@@ -326,9 +521,11 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error>;
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>>;
 
     /// Updates the new frame for a portion of the display.
+    #[allow(clippy::too_many_arguments)]
     fn update_partial_new_frame(
         &mut self,
         spi: &mut SPI,
@@ -337,7 +534,8 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error>;
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>>;
 
     /// Clears the partial frame buffer on the EPD with the declared background color
     /// The background color can be changed with [`WaveshareDisplay::set_background_color`]
@@ -348,5 +546,78 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error>;
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SPI::Error>>;
+}
+
+/// Raw access to the bytes of a full size display buffer
+///
+/// Lets driver agnostic code checksum, compress or log a frame without knowing the buffer type,
+/// the bytes are in the layout the driver sends to the display. Implemented by all `DisplayXXX`
+/// buffers and [VarDisplay](crate::graphics::VarDisplay).
+///
+/// `clear` has the same name as `DrawTarget::clear`, call it as `FrameBuffer::clear(&mut display,
+/// color)` when both traits are in scope.
+///
+/// ```rust
+/// use epd_waveshare::{color::Color, epd1in54::Display1in54, graphics::FrameBuffer};
+///
+/// fn checksum<F: FrameBuffer>(frame: &F) -> u32 {
+///     frame.as_bytes().iter().map(|&b| u32::from(b)).sum()
+/// }
+///
+/// let mut display = Display1in54::default();
+/// display.clear(Color::Black);
+/// assert_eq!(checksum(&display), 0);
+/// ```
+#[cfg(feature = "graphics")]
+pub trait FrameBuffer {
+    /// Color the buffer is cleared with
+    type Color;
+
+    /// Bytes of the frame
+    fn as_bytes(&self) -> &[u8];
+
+    /// Mutable bytes of the frame
+    fn as_bytes_mut(&mut self) -> &mut [u8];
+
+    /// Fills the whole frame with `color`
+    fn clear(&mut self, color: Self::Color);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    extern crate std;
+    use std::collections::{BTreeMap, HashSet};
+    use std::vec::Vec;
+
+    #[test]
+    fn refresh_luts_as_keys() {
+        static CUSTOM: [u8; 2] = [1, 2];
+        static OTHER: [u8; 2] = [1, 3];
+        let luts = [
+            RefreshLut::Custom(&OTHER),
+            RefreshLut::Quick,
+            RefreshLut::Custom(&CUSTOM),
+            RefreshLut::Full,
+        ];
+
+        let map: BTreeMap<RefreshLut, u8> = luts.iter().zip(0..).map(|(&l, i)| (l, i)).collect();
+        assert_eq!(
+            map.keys().copied().collect::<Vec<_>>(),
+            [
+                RefreshLut::Full,
+                RefreshLut::Quick,
+                RefreshLut::Custom(&CUSTOM),
+                RefreshLut::Custom(&OTHER),
+            ]
+        );
+        assert_eq!(map[&RefreshLut::Custom(&[1, 2])], 2);
+
+        // equal LUTs hash equal, a copy of a custom LUT is the same key
+        let set: HashSet<RefreshLut> = luts.iter().chain(&luts).copied().collect();
+        assert_eq!(set.len(), luts.len());
+        assert!(set.contains(&RefreshLut::Custom(&[1, 3])));
+    }
 }
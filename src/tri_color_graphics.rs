@@ -0,0 +1,255 @@
+//! Generic full size buffer for tri-color displays
+//!
+//! [TriColorDisplay] is sized at compile time through const generics instead of having a
+//! hand written buffer struct per display.
+
+use crate::buffer_len;
+use crate::color::TriColor;
+use crate::graphics::{
+    find_position, outside_display, set_tri_color_bit, DisplayColorRendering, DisplayRotation,
+    Positions, TriPixelIter,
+};
+use embedded_graphics_core::prelude::*;
+
+/// Full size tri-color buffer with separate b/w and chromatic planes
+///
+/// `BYTES` is the size of a single plane and has to be `buffer_len(WIDTH, HEIGHT)`,
+/// anything else is rejected at compile time.
+///
+/// ```rust
+/// use epd_waveshare::{color::TriColor, tri_color_graphics::TriColorDisplay};
+/// use embedded_graphics::{prelude::*, primitives::{Line, PrimitiveStyle}};
+///
+/// let mut display: TriColorDisplay<104, 212, { 104 / 8 * 212 }> = TriColorDisplay::default();
+/// let _ = Line::new(Point::new(0, 0), Point::new(20, 0))
+///     .into_styled(PrimitiveStyle::with_stroke(TriColor::Chromatic, 1))
+///     .draw(&mut display);
+///
+/// assert_eq!(display.bw_buffer().len(), 104 / 8 * 212);
+/// assert_eq!(display.chromatic_buffer().len(), 104 / 8 * 212);
+/// ```
+pub struct TriColorDisplay<const WIDTH: u32, const HEIGHT: u32, const BYTES: usize> {
+    bw: [u8; BYTES],
+    chromatic: [u8; BYTES],
+    rotation: DisplayRotation,
+    rendering: DisplayColorRendering,
+}
+
+impl<const WIDTH: u32, const HEIGHT: u32, const BYTES: usize>
+    TriColorDisplay<WIDTH, HEIGHT, BYTES>
+{
+    const SIZE_CHECK: () = assert!(
+        BYTES == buffer_len(WIDTH as usize, HEIGHT as usize),
+        "BYTES has to be buffer_len(WIDTH, HEIGHT)"
+    );
+
+    /// Creates a white buffer which uses the given rendering mode for the chromatic plane
+    ///
+    /// Check [DisplayColorRendering] and the datasheet of the display for the right mode.
+    pub fn new(rendering: DisplayColorRendering) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let _ = Self::SIZE_CHECK;
+
+        let mut display = TriColorDisplay {
+            bw: [0; BYTES],
+            chromatic: [0; BYTES],
+            rotation: DisplayRotation::default(),
+            rendering,
+        };
+        display.clear_buffer(TriColor::White);
+        display
+    }
+
+    /// Fills both planes with the given color
+    pub fn clear_buffer(&mut self, background_color: TriColor) {
+        for (bw, chromatic) in self.bw.iter_mut().zip(self.chromatic.iter_mut()) {
+            set_tri_color_bit(bw, chromatic, 0xFF, background_color, self.rendering);
+        }
+    }
+
+    /// Returns the b/w plane
+    pub fn bw_buffer(&self) -> &[u8] {
+        &self.bw
+    }
+
+    /// Returns the chromatic plane
+    pub fn chromatic_buffer(&self) -> &[u8] {
+        &self.chromatic
+    }
+
+    /// Sets the rotation of the display
+    pub fn set_rotation(&mut self, rotation: DisplayRotation) {
+        self.rotation = rotation;
+    }
+
+    /// Get the current rotation of the display
+    pub fn rotation(&self) -> DisplayRotation {
+        self.rotation
+    }
+
+    /// Iterates over the pixels of both planes, row by row in the rotated coordinates
+    pub fn pixels(&self) -> TriPixelIter<'_> {
+        TriPixelIter::new(
+            &self.bw,
+            &self.chromatic,
+            self.rendering,
+            Positions::new(self.size(), self.rotation),
+        )
+    }
+}
+
+impl<const WIDTH: u32, const HEIGHT: u32, const BYTES: usize> Default
+    for TriColorDisplay<WIDTH, HEIGHT, BYTES>
+{
+    fn default() -> Self {
+        TriColorDisplay::new(DisplayColorRendering::Positive)
+    }
+}
+
+impl<const WIDTH: u32, const HEIGHT: u32, const BYTES: usize> DrawTarget
+    for TriColorDisplay<WIDTH, HEIGHT, BYTES>
+{
+    type Color = TriColor;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            if outside_display(point, WIDTH, HEIGHT, self.rotation) {
+                continue;
+            }
+            let (index, bit) =
+                find_position(point.x as u32, point.y as u32, WIDTH, HEIGHT, self.rotation);
+            let index = index as usize;
+            set_tri_color_bit(
+                &mut self.bw[index],
+                &mut self.chromatic[index],
+                bit,
+                color,
+                self.rendering,
+            );
+        }
+        Ok(())
+    }
+}
+
+impl<const WIDTH: u32, const HEIGHT: u32, const BYTES: usize> OriginDimensions
+    for TriColorDisplay<WIDTH, HEIGHT, BYTES>
+{
+    fn size(&self) -> Size {
+        match self.rotation {
+            DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => Size::new(WIDTH, HEIGHT),
+            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => Size::new(HEIGHT, WIDTH),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epd2in13bc::{self, Display2in13bc};
+    use crate::graphics::TriDisplay;
+    use embedded_graphics::{
+        prelude::*,
+        primitives::{Line, PrimitiveStyle},
+    };
+
+    type Display = TriColorDisplay<
+        { epd2in13bc::WIDTH },
+        { epd2in13bc::HEIGHT },
+        { buffer_len(epd2in13bc::WIDTH as usize, epd2in13bc::HEIGHT as usize) },
+    >;
+
+    #[test]
+    fn matches_hand_written_buffer() {
+        let mut generic = Display::default();
+        let mut fixed = Display2in13bc::default();
+
+        for rotation in [
+            DisplayRotation::Rotate0,
+            DisplayRotation::Rotate90,
+            DisplayRotation::Rotate180,
+            DisplayRotation::Rotate270,
+        ] {
+            generic.set_rotation(rotation);
+            fixed.set_rotation(rotation);
+            for (color, y) in [(TriColor::Black, 3), (TriColor::Chromatic, 7)] {
+                let line = Line::new(Point::new(0, y), Point::new(40, y))
+                    .into_styled(PrimitiveStyle::with_stroke(color, 1));
+                line.draw(&mut generic).unwrap();
+                line.draw(&mut fixed).unwrap();
+            }
+        }
+
+        assert_eq!(generic.bw_buffer(), fixed.bw_buffer());
+        assert_eq!(generic.chromatic_buffer(), fixed.chromatic_buffer());
+    }
+
+    #[test]
+    fn clear_buffer() {
+        let mut display = TriColorDisplay::<8, 2, 2>::new(DisplayColorRendering::Negative);
+        assert_eq!(display.bw_buffer(), &[0xFF; 2]);
+        assert_eq!(display.chromatic_buffer(), &[0x00; 2]);
+
+        display.clear_buffer(TriColor::Chromatic);
+        assert_eq!(display.bw_buffer(), &[0xFF; 2]);
+        assert_eq!(display.chromatic_buffer(), &[0xFF; 2]);
+
+        display.clear_buffer(TriColor::Black);
+        assert_eq!(display.bw_buffer(), &[0x00; 2]);
+        assert_eq!(display.chromatic_buffer(), &[0x00; 2]);
+    }
+
+    #[test]
+    fn rotated_size() {
+        let mut display = TriColorDisplay::<16, 4, 8>::default();
+        assert_eq!(display.size(), Size::new(16, 4));
+        display.set_rotation(DisplayRotation::Rotate90);
+        assert_eq!(display.size(), Size::new(4, 16));
+    }
+
+    #[test]
+    fn pixels_round_trip() {
+        for rendering in [
+            DisplayColorRendering::Positive,
+            DisplayColorRendering::Negative,
+        ] {
+            let mut display = TriColorDisplay::<16, 2, 4>::new(rendering);
+            display.set_rotation(DisplayRotation::Rotate90);
+            Pixel(Point::new(0, 0), TriColor::Black)
+                .draw(&mut display)
+                .unwrap();
+            Pixel(Point::new(1, 8), TriColor::Chromatic)
+                .draw(&mut display)
+                .unwrap();
+
+            assert_eq!(display.pixels().len(), 32);
+            for (point, color) in display.pixels() {
+                let expected = match (point.x, point.y) {
+                    (0, 0) => TriColor::Black,
+                    (1, 8) => TriColor::Chromatic,
+                    _ => TriColor::White,
+                };
+                assert_eq!(color, expected, "{:?}", point);
+            }
+        }
+    }
+
+    #[test]
+    fn pixels_of_the_hand_written_buffer() {
+        let mut display = Display2in13bc::default();
+        Pixel(Point::new(7, 0), TriColor::Chromatic)
+            .draw(&mut display)
+            .unwrap();
+        Pixel(Point::new(8, 0), TriColor::Black)
+            .draw(&mut display)
+            .unwrap();
+
+        let mut pixels = TriDisplay::pixels(&display).filter(|(_, c)| *c != TriColor::White);
+        assert_eq!(pixels.next(), Some((Point::new(7, 0), TriColor::Chromatic)));
+        assert_eq!(pixels.next(), Some((Point::new(8, 0), TriColor::Black)));
+        assert_eq!(pixels.next(), None);
+    }
+}
@@ -31,10 +31,11 @@ pub(crate) enum Command {
     // GateScanStartPosition = 0x0F,
     /// Deep Sleep Mode Control
     ///     1 Databyte:
-    ///     0.. A[0]
+    ///     0.. A[1:0]
     ///     Values:
-    ///         A[0] = 0: Normal Mode (POR)
-    ///         A[0] = 1: Enter Deep Sleep Mode
+    ///         A[1:0] = 00: Normal Mode (POR)
+    ///         A[1:0] = 01: Enter Deep Sleep Mode 1 (RAM is retained)
+    ///         A[1:0] = 11: Enter Deep Sleep Mode 2 (RAM is not retained)
     DeepSleepMode = 0x10,
     // /// Data Entry mode setting
     DataEntryModeSetting = 0x11,
@@ -44,6 +45,8 @@ pub(crate) enum Command {
     TemperatureSensorSelection = 0x18,
 
     TemperatureSensorControl = 0x1A,
+    /// Reads the temperature register, 2 bytes: A[11:4], then A[3:0] in the upper nibble
+    TemperatureSensorRead = 0x1B,
 
     MasterActivation = 0x20,
 
@@ -1,2 +1,141 @@
+use crate::traits::BorderColor;
+use core::convert::TryFrom;
+
 pub(crate) mod command;
 pub(crate) mod constants;
+
+/// Value of the BorderWaveformControl register for a [BorderColor]
+///
+/// White and black follow the waveforms of LUT1 and LUT0, a floating border isn't driven.
+pub(crate) fn border_waveform(color: BorderColor) -> u8 {
+    match color {
+        BorderColor::White => 0x01,
+        BorderColor::Black => 0x00,
+        BorderColor::Vcom => 0x80,
+        BorderColor::Float => 0xC0,
+    }
+}
+
+/// Value of the WriteVcomRegister register for a VCOM of `mv` millivolts
+///
+/// The controllers take -200 mV to -3000 mV in steps of 100 mV, other values are `None`.
+pub(crate) fn vcom_register(mv: i16) -> Option<u8> {
+    const STEPS: [u8; 29] = [
+        0x08, 0x0B, 0x10, 0x14, 0x17, 0x1B, 0x20, 0x24, 0x28, 0x2C, 0x2F, 0x34, 0x37, 0x3C, 0x40,
+        0x44, 0x48, 0x4B, 0x50, 0x54, 0x58, 0x5B, 0x5F, 0x64, 0x68, 0x6C, 0x6F, 0x73, 0x78,
+    ];
+    if mv % 100 != 0 {
+        return None;
+    }
+    let index = usize::try_from(-(mv / 100) - 2).ok()?;
+    STEPS.get(index).copied()
+}
+
+/// Mirroring of the frame by the address counter directions of the data entry mode
+///
+/// A mirrored axis is written with a decrementing counter from the opposite edge of the RAM.
+/// The X counter moves in bytes, so the bits of every frame byte are reversed as well when
+/// mirroring horizontally.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct Mirror {
+    pub(crate) horizontal: bool,
+    pub(crate) vertical: bool,
+}
+
+impl Mirror {
+    /// Value of the DataEntryModeSetting register, the counter moves in X direction
+    pub(crate) fn data_entry_mode(self) -> u8 {
+        u8::from(!self.horizontal) | u8::from(!self.vertical) << 1
+    }
+
+    /// RAM column of the column `x` of a panel `width` pixels wide
+    pub(crate) fn x(self, x: u32, width: u32) -> u32 {
+        if self.horizontal {
+            (width - 1).saturating_sub(x)
+        } else {
+            x
+        }
+    }
+
+    /// RAM row of the row `y` of a panel `height` pixels high
+    pub(crate) fn y(self, y: u32, height: u32) -> u32 {
+        if self.vertical {
+            (height - 1).saturating_sub(y)
+        } else {
+            y
+        }
+    }
+
+    /// Frame byte as it has to be written to the RAM
+    pub(crate) fn byte(self, byte: u8) -> u8 {
+        if self.horizontal {
+            byte.reverse_bits()
+        } else {
+            byte
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vcom_steps() {
+        assert_eq!(vcom_register(-200), Some(0x08));
+        assert_eq!(vcom_register(-2100), Some(0x54));
+        assert_eq!(vcom_register(-3000), Some(0x78));
+        for mv in [-100, -250, 0, 200, -3100, i16::MIN] {
+            assert_eq!(vcom_register(mv), None, "{}", mv);
+        }
+    }
+
+    #[test]
+    fn mirror_reverses_the_counter_directions() {
+        let mirror = Mirror::default();
+        assert_eq!(mirror.data_entry_mode(), 0x03);
+        assert_eq!((mirror.x(8, 200), mirror.y(10, 200)), (8, 10));
+        assert_eq!(mirror.byte(0x80), 0x80);
+
+        let mirror = Mirror {
+            horizontal: true,
+            vertical: false,
+        };
+        assert_eq!(mirror.data_entry_mode(), 0x02);
+        assert_eq!((mirror.x(8, 200), mirror.y(10, 200)), (191, 10));
+        assert_eq!(mirror.byte(0x80), 0x01);
+
+        let mirror = Mirror {
+            horizontal: false,
+            vertical: true,
+        };
+        assert_eq!(mirror.data_entry_mode(), 0x01);
+        assert_eq!((mirror.x(8, 200), mirror.y(10, 200)), (8, 189));
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn mirroring_both_axes_rotates_by_180_degrees() {
+        use crate::epd1in54::{Display1in54, HEIGHT, WIDTH};
+        use crate::graphics::{Display, DisplayRotation};
+        use embedded_graphics_core::pixelcolor::BinaryColor;
+        use embedded_graphics_core::prelude::*;
+
+        let mirror = Mirror {
+            horizontal: true,
+            vertical: true,
+        };
+        assert_eq!(mirror.data_entry_mode(), 0x00);
+
+        let (x, y) = (21, 7);
+        let mut display = Display1in54::default();
+        display.set_rotation(DisplayRotation::Rotate180);
+        let _ = Pixel(Point::new(x, y), BinaryColor::On).draw(&mut display);
+
+        // the pixel lands where the mirrored counters put the unrotated one
+        let (ram_x, ram_y) = (mirror.x(x as u32, WIDTH), mirror.y(y as u32, HEIGHT));
+        let index = (ram_y * WIDTH / 8 + ram_x / 8) as usize;
+        assert_eq!(display.buffer()[index], !(0x80 >> (ram_x % 8)));
+        assert_eq!(mirror.byte(0x80 >> (x % 8)), 0x80 >> (ram_x % 8));
+    }
+}
@@ -0,0 +1,201 @@
+//! Limits of the partial window registers
+//!
+//! Partial windows are written to start and end registers with a fixed number of bits. On
+//! most panels these registers cover the whole panel, but large panels can be taller than the
+//! registers can address. Writing such a window would silently wrap around in the registers,
+//! so the drivers check every window against their [WindowLimits] before any register is
+//! written and return an [Error] instead.
+//!
+//! Each driver module provides its limits as `WINDOW_LIMITS`.
+
+use crate::error::Error;
+
+/// Area of a panel which can be addressed by the partial window registers of its controller
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowLimits {
+    /// Width of the panel
+    pub width: u32,
+    /// Height of the panel
+    pub height: u32,
+    /// Largest x coordinate the window registers can hold
+    pub max_x: u32,
+    /// Largest y coordinate the window registers can hold
+    pub max_y: u32,
+}
+
+impl WindowLimits {
+    /// Creates the limits of a panel with the given size and largest register values
+    pub const fn new(width: u32, height: u32, max_x: u32, max_y: u32) -> Self {
+        WindowLimits {
+            width,
+            height,
+            max_x,
+            max_y,
+        }
+    }
+
+    /// Width which can be used for partial windows
+    ///
+    /// Smaller than the panel width if the registers can't address the whole panel.
+    pub const fn usable_width(&self) -> u32 {
+        if self.max_x < self.width {
+            self.max_x + 1
+        } else {
+            self.width
+        }
+    }
+
+    /// Height which can be used for partial windows
+    ///
+    /// Smaller than the panel height if the registers can't address the whole panel.
+    pub const fn usable_height(&self) -> u32 {
+        if self.max_y < self.height {
+            self.max_y + 1
+        } else {
+            self.height
+        }
+    }
+
+    /// Checks that a window fits into the usable area
+    ///
+    /// Returns [Error::InvalidRegion] for an empty window, [Error::WindowTooLarge] if the
+    /// window wouldn't fit anywhere and [Error::WindowOutOfRange] if it doesn't fit at the
+    /// given position.
+    pub fn validate<E>(&self, x: u32, y: u32, width: u32, height: u32) -> Result<(), Error<E>> {
+        if width == 0 || height == 0 {
+            return Err(Error::InvalidRegion);
+        }
+
+        let usable_width = self.usable_width();
+        let usable_height = self.usable_height();
+
+        if width > usable_width || height > usable_height {
+            return Err(Error::WindowTooLarge);
+        }
+
+        match (x.checked_add(width), y.checked_add(height)) {
+            (Some(end_x), Some(end_y)) if end_x <= usable_width && end_y <= usable_height => Ok(()),
+            _ => Err(Error::WindowOutOfRange),
+        }
+    }
+
+    /// Checks a window like [validate()](Self::validate()) and that its columns are whole bytes
+    ///
+    /// The controllers of the b/w and tri-color panels address x in bytes of 8 pixels, so `x`
+    /// has to be a multiple of 8 and so does `width`, unless the window ends at the right edge
+    /// of the panel. Other windows return [Error::InvalidRegion], the controller would shift
+    /// them to the start of the byte.
+    pub fn validate_aligned<E>(
+        &self,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<E>> {
+        self.validate(x, y, width, height)?;
+        if x % 8 != 0 || !(width % 8 == 0 || x + width == self.width) {
+            return Err(Error::InvalidRegion);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(
+        limits: &WindowLimits,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error<()>> {
+        limits.validate(x, y, width, height)
+    }
+
+    #[test]
+    fn small_panel_fits_into_registers() {
+        let limits = crate::epd1in54::WINDOW_LIMITS;
+        assert_eq!(limits.usable_width(), 200);
+        assert_eq!(limits.usable_height(), 200);
+
+        assert_eq!(check(&limits, 0, 0, 200, 200), Ok(()));
+        assert_eq!(check(&limits, 192, 192, 8, 8), Ok(()));
+        assert_eq!(check(&limits, 0, 0, 208, 200), Err(Error::WindowTooLarge));
+        assert_eq!(check(&limits, 0, 0, 200, 201), Err(Error::WindowTooLarge));
+        assert_eq!(check(&limits, 8, 0, 200, 200), Err(Error::WindowOutOfRange));
+        assert_eq!(check(&limits, 0, 193, 8, 8), Err(Error::WindowOutOfRange));
+        assert_eq!(
+            check(&limits, u32::MAX, 0, 8, 8),
+            Err(Error::WindowOutOfRange)
+        );
+        assert_eq!(check(&limits, 0, 0, 0, 8), Err(Error::InvalidRegion));
+        assert_eq!(check(&limits, 0, 0, 8, 0), Err(Error::InvalidRegion));
+    }
+
+    #[test]
+    fn aligned_windows_cover_whole_bytes() {
+        // 122 pixels wide, the last byte of a row has 2 pixels
+        let limits = WindowLimits::new(122, 250, 255, 511);
+        let aligned = |x, width| limits.validate_aligned::<()>(x, 0, width, 8);
+
+        assert_eq!(aligned(0, 8), Ok(()));
+        assert_eq!(aligned(112, 10), Ok(()));
+        assert_eq!(aligned(0, 122), Ok(()));
+        assert_eq!(aligned(4, 8), Err(Error::InvalidRegion));
+        assert_eq!(aligned(8, 12), Err(Error::InvalidRegion));
+        assert_eq!(aligned(112, 11), Err(Error::WindowOutOfRange));
+    }
+
+    #[test]
+    fn large_panel_exceeds_registers() {
+        // 10 bit y registers on a panel with 1600 lines
+        let limits = WindowLimits::new(1200, 1600, 1199, 1023);
+        assert_eq!(limits.usable_width(), 1200);
+        assert_eq!(limits.usable_height(), 1024);
+
+        assert_eq!(check(&limits, 0, 0, 1200, 1024), Ok(()));
+        assert_eq!(check(&limits, 0, 1000, 8, 24), Ok(()));
+        assert_eq!(check(&limits, 0, 0, 8, 1025), Err(Error::WindowTooLarge));
+        assert_eq!(check(&limits, 0, 0, 1208, 8), Err(Error::WindowTooLarge));
+        assert_eq!(check(&limits, 0, 1000, 8, 25), Err(Error::WindowOutOfRange));
+        assert_eq!(check(&limits, 0, 1024, 8, 8), Err(Error::WindowOutOfRange));
+        assert_eq!(check(&limits, 1192, 0, 16, 8), Err(Error::WindowOutOfRange));
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn accepted_windows_fit_into_registers(
+            panel_width in 1u32..2048,
+            panel_height in 1u32..2048,
+            max_x in 0u32..2048,
+            max_y in 0u32..2048,
+            x in proptest::num::u32::ANY,
+            y in proptest::num::u32::ANY,
+            width in 0u32..4096,
+            height in 0u32..4096,
+        ) {
+            let limits = WindowLimits::new(panel_width, panel_height, max_x, max_y);
+            match check(&limits, x, y, width, height) {
+                Ok(()) => {
+                    proptest::prop_assert!(x + width <= panel_width && y + height <= panel_height);
+                    proptest::prop_assert!(x + width <= max_x + 1 && y + height <= max_y + 1);
+                }
+                Err(Error::WindowTooLarge) => {
+                    proptest::prop_assert!(
+                        width > limits.usable_width() || height > limits.usable_height()
+                    );
+                }
+                Err(Error::InvalidRegion) => {
+                    proptest::prop_assert!(width == 0 || height == 0);
+                }
+                Err(Error::WindowOutOfRange) => {
+                    // the same window fits at the origin
+                    proptest::prop_assert_eq!(check(&limits, 0, 0, width, height), Ok(()));
+                }
+                Err(e) => proptest::prop_assert!(false, "unexpected error {:?}", e),
+            }
+        }
+    }
+}